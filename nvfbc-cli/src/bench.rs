@@ -0,0 +1,175 @@
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use clap::Args;
+use nvfbc::system::CaptureMethod;
+use serde::Serialize;
+
+use crate::{open_source, CaptureArgs, Cli};
+
+#[derive(Args)]
+pub struct BenchArgs {
+	#[command(flatten)]
+	capture: CaptureArgs,
+
+	/// Number of seconds to run every benchmark.
+	#[arg(long, default_value_t = 3.0)]
+	seconds: f64,
+
+	/// Print the results as JSON.
+	#[arg(long)]
+	json: bool,
+}
+
+/// Grab latencies in milliseconds.
+#[derive(Serialize)]
+struct Latency {
+	min: f64,
+	p50: f64,
+	p99: f64,
+	max: f64,
+}
+
+#[derive(Serialize)]
+struct BenchResult {
+	capture_type: &'static str,
+	method: &'static str,
+	grabs: usize,
+	new_frames: usize,
+	/// Number of new frames per second.
+	fps: f64,
+	latency_ms: Latency,
+}
+
+impl BenchResult {
+	fn new(capture_type: &'static str, method: &'static str, elapsed: Duration, mut latencies: Vec<Duration>, new_frames: usize) -> Self {
+		latencies.sort();
+		let percentile = |p: usize| {
+			latencies.get((latencies.len() * p / 100).min(latencies.len().saturating_sub(1)))
+				.map_or(0.0, |latency| latency.as_secs_f64() * 1000.0)
+		};
+
+		Self {
+			capture_type,
+			method,
+			grabs: latencies.len(),
+			new_frames,
+			fps: new_frames as f64 / elapsed.as_secs_f64(),
+			latency_ms: Latency { min: percentile(0), p50: percentile(50), p99: percentile(99), max: percentile(100) },
+		}
+	}
+}
+
+/// Capture methods, named the same for every capture type.
+const METHODS: [&str; 3] = ["no-wait", "no-wait-if-new-frame", "blocking"];
+
+fn system_method(name: &str) -> CaptureMethod {
+	match name {
+		"no-wait" => CaptureMethod::NoWait,
+		"no-wait-if-new-frame" => CaptureMethod::NoWaitIfNewFrame,
+		_ => CaptureMethod::Blocking,
+	}
+}
+
+pub fn run(cli: &Cli, args: &BenchArgs) -> Result<(), Box<dyn Error>> {
+	let duration = Duration::from_secs_f64(args.seconds);
+	let timeout = Some(Duration::from_secs(1));
+	let mut results = Vec::new();
+
+	let mut source = open_source(cli)?;
+	let status = source.status()?;
+	let options = args.capture.session_options(&status)?;
+	source.start_with_options(&options)?;
+	for method in METHODS {
+		let mut latencies = Vec::new();
+		let mut new_frames = 0;
+		let started = Instant::now();
+		while started.elapsed() < duration {
+			let grab_started = Instant::now();
+			let frame = source.next_frame(system_method(method), timeout)?;
+			latencies.push(grab_started.elapsed());
+			new_frames += frame.is_new_frame as usize;
+		}
+		results.push(BenchResult::new("system", method, started.elapsed(), latencies, new_frames));
+	}
+	source.stop()?;
+
+	#[cfg(feature = "cuda")]
+	if !cli.synthetic {
+		results.extend(cuda::run(&options, duration, timeout)?);
+	}
+
+	if args.json {
+		println!("{}", serde_json::to_string_pretty(&results)?);
+		return Ok(());
+	}
+
+	println!(
+		"{:<8} {:<22} {:>7} {:>7} {:>8} {:>9} {:>9} {:>9} {:>9}",
+		"type", "method", "grabs", "new", "fps", "min ms", "p50 ms", "p99 ms", "max ms",
+	);
+	for result in &results {
+		println!(
+			"{:<8} {:<22} {:>7} {:>7} {:>8.2} {:>9.3} {:>9.3} {:>9.3} {:>9.3}",
+			result.capture_type,
+			result.method,
+			result.grabs,
+			result.new_frames,
+			result.fps,
+			result.latency_ms.min,
+			result.latency_ms.p50,
+			result.latency_ms.p99,
+			result.latency_ms.max,
+		);
+	}
+
+	Ok(())
+}
+
+#[cfg(feature = "cuda")]
+mod cuda {
+	use std::error::Error;
+	use std::time::{Duration, Instant};
+
+	use nvfbc::cuda::CaptureMethod;
+	use nvfbc::{CudaCapturer, SessionOptions};
+	use rustacuda::context::{Context, ContextFlags};
+	use rustacuda::device::Device;
+	use rustacuda::CudaFlags;
+
+	use super::{BenchResult, METHODS};
+
+	fn method(name: &str) -> CaptureMethod {
+		match name {
+			"no-wait" => CaptureMethod::NoWait,
+			"no-wait-if-new-frame" => CaptureMethod::NoWaitIfNewFrame,
+			_ => CaptureMethod::Blocking,
+		}
+	}
+
+	pub fn run(options: &SessionOptions, duration: Duration, timeout: Option<Duration>) -> Result<Vec<BenchResult>, Box<dyn Error>> {
+		rustacuda::init(CudaFlags::empty())?;
+		let device = Device::get_device(0)?;
+		let _context = Context::create_and_push(ContextFlags::MAP_HOST | ContextFlags::SCHED_AUTO, device)?;
+
+		let mut capturer = CudaCapturer::new()?;
+		capturer.start_with_options(options)?;
+
+		let mut results = Vec::new();
+		for name in METHODS {
+			let mut latencies = Vec::new();
+			let mut new_frames = 0;
+			let started = Instant::now();
+			while started.elapsed() < duration {
+				let grab_started = Instant::now();
+				let frame = capturer.next_frame(method(name), timeout)?;
+				latencies.push(grab_started.elapsed());
+				new_frames += frame.is_new_frame as usize;
+			}
+			results.push(BenchResult::new("cuda", name, started.elapsed(), latencies, new_frames));
+		}
+		capturer.stop()?;
+
+		Ok(results)
+	}
+}
@@ -0,0 +1,146 @@
+//! Command-line tool to inspect NVFBC and to capture, record and benchmark frames.
+//!
+//! Every subcommand accepts `--synthetic`, which replaces NVFBC with a generated test pattern.
+//! This makes it possible to use the tool on machines without an NVIDIA GPU or driver, for example in CI,
+//! because the NVFBC library is only loaded when it is used.
+
+mod bench;
+mod record;
+mod screenshot;
+mod status;
+
+use std::error::Error;
+
+use clap::{Args, Parser, Subcommand};
+use nvfbc::synthetic::SyntheticCapturer;
+use nvfbc::{Box, BufferFormat, Capture, SessionOptions, Size, Status, SystemCapturer, Tracking};
+
+#[derive(Parser)]
+#[command(name = "nvfbc", version, about)]
+struct Cli {
+	/// Generate test pattern frames instead of capturing with NVFBC.
+	#[arg(long, global = true)]
+	synthetic: bool,
+
+	/// Size of the simulated screen when using --synthetic.
+	#[arg(long, global = true, value_name = "WxH", default_value = "1920x1080", value_parser = parse_size)]
+	synthetic_size: Size,
+
+	#[command(subcommand)]
+	command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+	/// Print the status of NVFBC.
+	Status(status::StatusArgs),
+	/// Capture a single frame to a PNG or PPM file.
+	Screenshot(screenshot::ScreenshotArgs),
+	/// Record raw frames and a timestamp index.
+	Record(record::RecordArgs),
+	/// Measure grab latency and frame rate of every capture method.
+	Bench(bench::BenchArgs),
+}
+
+/// Options selecting what to capture and how.
+#[derive(Args)]
+struct CaptureArgs {
+	/// RandR output to track, by id or name. Defaults to the primary output or the entire screen.
+	#[arg(long)]
+	output: Option<String>,
+
+	/// Capture only this region of the tracked output.
+	#[arg(long = "box", value_name = "WxH+X+Y", value_parser = parse_box)]
+	capture_box: Option<Box>,
+
+	/// Format of the captured frames.
+	#[arg(long, default_value = "rgb", value_parser = parse_format)]
+	format: BufferFormat,
+
+	/// Rate at which frames are generated.
+	#[arg(long, default_value_t = 30)]
+	fps: u32,
+
+	/// Do not composite the mouse cursor into the frames.
+	#[arg(long)]
+	no_cursor: bool,
+}
+
+impl CaptureArgs {
+	/// Session options for these arguments, resolving output names using `status`.
+	fn session_options(&self, status: &Status) -> Result<SessionOptions, std::boxed::Box<dyn Error>> {
+		let tracking = match &self.output {
+			None => Tracking::Default,
+			Some(output) => {
+				let found = status.outputs.iter().find(|o| o.name == *output || o.id.to_string() == *output);
+				match found {
+					Some(found) => Tracking::Output(found.id),
+					None => return Err(format!("Unknown output '{}'", output).into()),
+				}
+			},
+		};
+
+		Ok(SessionOptions {
+			tracking,
+			capture_box: self.capture_box,
+			with_cursor: !self.no_cursor,
+			..SessionOptions::new(self.format, self.fps)
+		})
+	}
+}
+
+/// Open the source of frames selected on the command line.
+fn open_source(cli: &Cli) -> Result<std::boxed::Box<dyn Capture>, nvfbc::Error> {
+	if cli.synthetic {
+		Ok(std::boxed::Box::new(SyntheticCapturer::new(cli.synthetic_size.w, cli.synthetic_size.h)))
+	} else {
+		Ok(std::boxed::Box::new(SystemCapturer::new()?))
+	}
+}
+
+fn parse_format(value: &str) -> Result<BufferFormat, String> {
+	BufferFormat::ALL.into_iter()
+		.find(|format| format_name(*format) == value.to_ascii_lowercase())
+		.ok_or_else(|| format!("expected one of: {}", BufferFormat::ALL.map(format_name).join(", ")))
+}
+
+fn format_name(format: BufferFormat) -> &'static str {
+	match format {
+		BufferFormat::Argb => "argb",
+		BufferFormat::Rgb => "rgb",
+		BufferFormat::Nv12 => "nv12",
+		BufferFormat::Yuv444p => "yuv444p",
+		BufferFormat::Rgba => "rgba",
+		BufferFormat::Bgra => "bgra",
+	}
+}
+
+fn parse_size(value: &str) -> Result<Size, String> {
+	let (w, h) = value.split_once('x').ok_or("expected WxH")?;
+	Ok(Size {
+		w: w.parse().map_err(|_| "invalid width")?,
+		h: h.parse().map_err(|_| "invalid height")?,
+	})
+}
+
+fn parse_box(value: &str) -> Result<Box, String> {
+	let (size, offset) = value.split_once('+').ok_or("expected WxH+X+Y")?;
+	let size = parse_size(size)?;
+	let (x, y) = offset.split_once('+').ok_or("expected WxH+X+Y")?;
+	Ok(Box {
+		x: x.parse().map_err(|_| "invalid x offset")?,
+		y: y.parse().map_err(|_| "invalid y offset")?,
+		w: size.w,
+		h: size.h,
+	})
+}
+
+fn main() -> Result<(), std::boxed::Box<dyn Error>> {
+	let cli = Cli::parse();
+	match &cli.command {
+		Command::Status(args) => status::run(&cli, args),
+		Command::Screenshot(args) => screenshot::run(&cli, args),
+		Command::Record(args) => record::run(&cli, args),
+		Command::Bench(args) => bench::run(&cli, args),
+	}
+}
@@ -0,0 +1,100 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use clap::Args;
+use nvfbc::dedup::{DedupOptions, Deduplicator};
+use nvfbc::system::CaptureMethod;
+
+use crate::{format_name, open_source, CaptureArgs, Cli};
+
+#[derive(Args)]
+pub struct RecordArgs {
+	#[command(flatten)]
+	capture: CaptureArgs,
+
+	/// Number of seconds to record.
+	#[arg(long, default_value_t = 5.0)]
+	seconds: f64,
+
+	/// Skip frames with the same pixels as the last written frame, even if they are reported as new frames.
+	#[arg(long)]
+	skip_duplicates: bool,
+
+	/// Also skip frames in which at most this many pixels changed since the last written frame.
+	#[arg(long, value_name = "PIXELS", requires = "skip_duplicates")]
+	near_duplicate_pixels: Option<u64>,
+
+	/// File to write the raw frames to.
+	///
+	/// The frames are written back to back without any header.
+	/// An index with the offset, size and grab info of every frame is written to the same path with `.csv` appended.
+	#[arg(default_value = "frames.raw")]
+	path: PathBuf,
+}
+
+pub fn run(cli: &Cli, args: &RecordArgs) -> Result<(), Box<dyn Error>> {
+	let mut index_path = args.path.clone().into_os_string();
+	index_path.push(".csv");
+
+	let mut frames = BufWriter::new(File::create(&args.path)?);
+	let mut index = BufWriter::new(File::create(&index_path)?);
+	writeln!(index, "frame,offset,byte_size,width,height,format,current_frame,timestamp_us,is_new_frame,missed_frames")?;
+
+	let mut source = open_source(cli)?;
+	let status = source.status()?;
+	source.start_with_options(&args.capture.session_options(&status)?)?;
+
+	let duration = Duration::from_secs_f64(args.seconds);
+	let started = Instant::now();
+	let mut offset = 0;
+	let mut count = 0;
+	let mut skipped = 0;
+	let mut deduplicator = args.skip_duplicates.then(|| Deduplicator::new(DedupOptions {
+		near_duplicate_pixels: args.near_duplicate_pixels,
+		..DedupOptions::default()
+	}));
+	while started.elapsed() < duration {
+		let remaining = duration.saturating_sub(started.elapsed());
+		let frame = source.next_frame(CaptureMethod::Blocking, Some(remaining.max(Duration::from_millis(1))))?;
+		if !frame.is_new_frame {
+			continue;
+		}
+		if let Some(deduplicator) = &mut deduplicator {
+			if deduplicator.check(&frame)?.is_duplicate() {
+				skipped += 1;
+				continue;
+			}
+		}
+
+		frames.write_all(frame.buffer)?;
+		writeln!(
+			index,
+			"{},{},{},{},{},{},{},{},{},{}",
+			count,
+			offset,
+			frame.buffer.len(),
+			frame.width,
+			frame.height,
+			format_name(frame.buffer_format),
+			frame.current_frame,
+			frame.timestamp_us,
+			frame.is_new_frame,
+			frame.missed_frames,
+		)?;
+		offset += frame.buffer.len();
+		count += 1;
+	}
+	source.stop()?;
+
+	frames.flush()?;
+	index.flush()?;
+	eprintln!("Recorded {} frames to '{}'.", count, args.path.display());
+	if args.skip_duplicates {
+		eprintln!("Skipped {} duplicate frames.", skipped);
+	}
+
+	Ok(())
+}
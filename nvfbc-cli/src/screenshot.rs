@@ -0,0 +1,53 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::Args;
+use nvfbc::convert;
+use nvfbc::system::CaptureMethod;
+
+use crate::{open_source, CaptureArgs, Cli};
+
+#[derive(Args)]
+pub struct ScreenshotArgs {
+	#[command(flatten)]
+	capture: CaptureArgs,
+
+	/// File to write the frame to. The extension selects the file format, either `.png` or `.ppm`.
+	#[arg(default_value = "frame.png")]
+	path: PathBuf,
+}
+
+pub fn run(cli: &Cli, args: &ScreenshotArgs) -> Result<(), Box<dyn Error>> {
+	let extension = args.path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+	if !matches!(extension.as_deref(), Some("png") | Some("ppm")) {
+		return Err(format!("Unsupported file format for '{}', use .png or .ppm", args.path.display()).into());
+	}
+
+	let mut source = open_source(cli)?;
+	let status = source.status()?;
+	source.start_with_options(&args.capture.session_options(&status)?)?;
+
+	let frame = source.next_frame(CaptureMethod::NoWaitIfNewFrame, Some(Duration::from_secs(1)))?;
+	let rgb = convert::to_rgb(frame.buffer, frame.width, frame.height, frame.buffer_format)?;
+	let (width, height) = (frame.width, frame.height);
+	source.stop()?;
+
+	match extension.as_deref() {
+		Some("ppm") => write_ppm(&args.path, width, height, &rgb)?,
+		_ => image::save_buffer(&args.path, &rgb, width, height, image::ColorType::Rgb8)?,
+	}
+	eprintln!("Saved {}x{} frame to '{}'.", width, height, args.path.display());
+
+	Ok(())
+}
+
+/// Write packed RGB888 data as a binary PPM (P6) image.
+fn write_ppm(path: &Path, width: u32, height: u32, rgb: &[u8]) -> std::io::Result<()> {
+	let mut file = BufWriter::new(File::create(path)?);
+	write!(file, "P6\n{} {}\n255\n", width, height)?;
+	file.write_all(rgb)?;
+	file.flush()
+}
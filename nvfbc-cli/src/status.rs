@@ -0,0 +1,41 @@
+use std::error::Error;
+
+use clap::Args;
+
+use crate::{open_source, Cli};
+
+#[derive(Args)]
+pub struct StatusArgs {
+	/// Print the status as JSON.
+	#[arg(long)]
+	json: bool,
+}
+
+pub fn run(cli: &Cli, args: &StatusArgs) -> Result<(), Box<dyn Error>> {
+	let status = open_source(cli)?.status()?;
+
+	if args.json {
+		println!("{}", serde_json::to_string_pretty(&status)?);
+		return Ok(());
+	}
+
+	println!("NvFBC version:       {}", status.nvfbc_version);
+	if let Some(diagnostic) = status.nvfbc_version.diagnostic() {
+		println!("                     {}", diagnostic);
+	}
+	println!("Capture possible:    {}", status.is_capture_possible);
+	println!("Currently capturing: {}", status.currently_capturing);
+	println!("Can create now:      {}", status.can_create_now);
+	println!("In modeset:          {}", status.in_modeset);
+	println!("Screen size:         {}x{}", status.screen_size.w, status.screen_size.h);
+	println!("XRandR available:    {}", status.xrandr_available);
+	for output in &status.outputs {
+		let tracked = output.tracked_box;
+		println!(
+			"Output {:<3}          {} {}x{}+{}+{}",
+			output.id, output.name, tracked.w, tracked.h, tracked.x, tracked.y,
+		);
+	}
+
+	Ok(())
+}
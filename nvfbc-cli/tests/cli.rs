@@ -0,0 +1,111 @@
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn nvfbc(args: &[&str]) -> Output {
+	let output = Command::new(env!("CARGO_BIN_EXE_nvfbc"))
+		.args(["--synthetic", "--synthetic-size", "64x48"])
+		.args(args)
+		.output()
+		.expect("failed to run nvfbc");
+	assert!(output.status.success(), "nvfbc {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+	output
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("nvfbc-cli-{}-{}", name, std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	dir
+}
+
+#[test]
+fn status_json() {
+	let output = nvfbc(&["status", "--json"]);
+	let status: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+	assert_eq!(status["screen_size"]["w"], 64);
+	assert_eq!(status["screen_size"]["h"], 48);
+	assert_eq!(status["outputs"][0]["name"], "SYNTHETIC-0");
+}
+
+#[test]
+fn synthetic_does_not_need_the_nvfbc_library() {
+	let output = Command::new(env!("CARGO_BIN_EXE_nvfbc"))
+		.env_remove("LD_LIBRARY_PATH")
+		.args(["--synthetic", "status", "--json"])
+		.output()
+		.expect("failed to run nvfbc");
+	assert!(output.status.success(), "nvfbc failed: {}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn screenshot_ppm() {
+	let dir = temp_dir("screenshot-ppm");
+	let path = dir.join("frame.ppm");
+	nvfbc(&["screenshot", "--format", "nv12", "--box", "32x16+8+8", path.to_str().unwrap()]);
+
+	let data = std::fs::read(&path).unwrap();
+	let header = b"P6\n32 16\n255\n";
+	assert!(data.starts_with(header));
+	assert_eq!(data.len(), header.len() + 32 * 16 * 3);
+	std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn screenshot_png() {
+	let dir = temp_dir("screenshot-png");
+	let path = dir.join("frame.png");
+	nvfbc(&["screenshot", path.to_str().unwrap()]);
+
+	let data = std::fs::read(&path).unwrap();
+	assert!(data.starts_with(b"\x89PNG\r\n\x1a\n"));
+	std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn record() {
+	let dir = temp_dir("record");
+	let path = dir.join("frames.raw");
+	nvfbc(&["record", "--seconds", "0.3", "--fps", "30", path.to_str().unwrap()]);
+
+	let index = std::fs::read_to_string(dir.join("frames.raw.csv")).unwrap();
+	let rows: Vec<&str> = index.lines().skip(1).collect();
+	assert!(!rows.is_empty());
+
+	let frame_size = 64 * 48 * 3;
+	for (i, row) in rows.iter().enumerate() {
+		let columns: Vec<&str> = row.split(',').collect();
+		assert_eq!(columns[0], i.to_string());
+		assert_eq!(columns[1], (i * frame_size).to_string());
+		assert_eq!(columns[2], frame_size.to_string());
+		assert_eq!(columns[5], "rgb");
+	}
+	assert_eq!(std::fs::metadata(&path).unwrap().len() as usize, rows.len() * frame_size);
+	std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn record_skips_duplicates() {
+	let dir = temp_dir("record-duplicates");
+	let path = dir.join("frames.raw");
+	// Every frame of the test pattern is a near-duplicate of the first one.
+	let output = nvfbc(&[
+		"record", "--seconds", "0.3", "--fps", "30", "--skip-duplicates", "--near-duplicate-pixels", "3072",
+		path.to_str().unwrap(),
+	]);
+
+	let index = std::fs::read_to_string(dir.join("frames.raw.csv")).unwrap();
+	assert_eq!(index.lines().skip(1).count(), 1);
+	assert!(String::from_utf8_lossy(&output.stderr).contains("Skipped"));
+	std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn bench_json() {
+	let output = nvfbc(&["bench", "--seconds", "0.1", "--json"]);
+	let results: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+	let results = results.as_array().unwrap();
+	assert_eq!(results.len(), 3);
+	for result in results {
+		assert_eq!(result["capture_type"], "system");
+		assert!(result["grabs"].as_u64().unwrap() > 0);
+	}
+}
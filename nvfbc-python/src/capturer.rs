@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use nvfbc::synthetic::SyntheticCapturer;
+use nvfbc::{Capture, SystemCapturer};
+
+use crate::{nvfbc_error, CaptureMethod, Frame, SessionOptions, Status};
+
+/// Lets a capturer be used while the GIL is released.
+///
+/// Capturers are not `Send`, because NvFBC binds their context to the thread that created them.
+/// [`Python::detach`] runs its closure on the calling thread, so the capturer never actually moves to another thread.
+struct Detached<T>(T);
+
+// SAFETY: only used to pass a capturer to `Python::detach`, which runs on the current thread.
+unsafe impl<T> Send for Detached<T> {}
+
+impl<T> Detached<T> {
+	/// Unwrap the value, taking the whole wrapper into a closure instead of only its field.
+	fn into_inner(self) -> T {
+		self.0
+	}
+}
+
+/// Captures frames with NvFBC, or generates a test pattern without it.
+///
+/// A capturer can only be used by the thread that created it.
+#[pyclass(unsendable, module = "nvfbc")]
+pub struct Capturer {
+	inner: std::boxed::Box<dyn Capture>,
+}
+
+#[pymethods]
+impl Capturer {
+	/// Create a capturer that captures frames to system memory with NvFBC.
+	#[new]
+	fn new() -> PyResult<Self> {
+		let inner = SystemCapturer::new().map_err(nvfbc_error)?;
+		Ok(Self { inner: std::boxed::Box::new(inner) })
+	}
+
+	/// Create a capturer that generates a test pattern of `width` x `height` pixels, without NvFBC or a GPU.
+	#[staticmethod]
+	fn synthetic(width: u32, height: u32) -> Self {
+		Self { inner: std::boxed::Box::new(SyntheticCapturer::new(width, height)) }
+	}
+
+	/// Retrieve the status of the capturer.
+	fn status(&self) -> PyResult<Status> {
+		self.inner.status().map(Status::from).map_err(nvfbc_error)
+	}
+
+	/// Start a capture session configured by `options`, or with the default options if `None`.
+	#[pyo3(signature = (options = None))]
+	fn start(&mut self, options: Option<PyRef<'_, SessionOptions>>) -> PyResult<()> {
+		let status = self.inner.status().map_err(nvfbc_error)?;
+		let options = match options {
+			Some(options) => options.to_options(&status)?,
+			None => SessionOptions::default().to_options(&status)?,
+		};
+		self.inner.start_with_options(&options).map_err(nvfbc_error)
+	}
+
+	/// Stop the capture session.
+	fn stop(&mut self) -> PyResult<()> {
+		self.inner.stop().map_err(nvfbc_error)
+	}
+
+	/// Grab the next frame, waiting as specified by `method` for at most `timeout` seconds.
+	///
+	/// The GIL is released while waiting for and copying the frame, so other Python threads keep running.
+	#[pyo3(signature = (method = CaptureMethod::Blocking, timeout = None))]
+	fn next_frame(&mut self, py: Python<'_>, method: CaptureMethod, timeout: Option<f64>) -> PyResult<Frame> {
+		let timeout = timeout
+			.map(Duration::try_from_secs_f64)
+			.transpose()
+			.map_err(|e| PyValueError::new_err(format!("Invalid timeout: {}", e)))?;
+
+		let inner = Detached(&mut self.inner);
+		py.detach(move || {
+			let inner = inner.into_inner();
+			inner.next_frame(method.into(), timeout).map(|frame| Frame::new(&frame))
+		}).map_err(nvfbc_error)
+	}
+}
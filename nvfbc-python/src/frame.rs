@@ -0,0 +1,171 @@
+use numpy::ndarray::{ArrayView2, ArrayView3, ArrayViewD, IxDyn};
+use numpy::{PyArray2, PyArray3, PyArrayDyn, ToPyArray};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use nvfbc::convert::{check_size, to_rgb};
+use nvfbc::system::SystemFrameInfo;
+use nvfbc::Plane;
+
+use crate::{buffer_size_error, BufferFormat};
+
+/// Diff map of a frame, copied out of the capturer.
+struct DiffMap {
+	data: Vec<u8>,
+	width: u32,
+	height: u32,
+	scaling_factor: u32,
+}
+
+/// A captured frame and the information NvFBC reported about it.
+///
+/// The frame data is copied out of the capturer, so frames remain valid after grabbing the next one.
+/// Every array returned by a frame is a new copy of its data.
+#[pyclass(frozen, module = "nvfbc")]
+pub struct Frame {
+	data: Vec<u8>,
+	/// Width of the captured frame.
+	#[pyo3(get)]
+	width: u32,
+	/// Height of the captured frame.
+	#[pyo3(get)]
+	height: u32,
+	/// Format of the frame data.
+	#[pyo3(get)]
+	buffer_format: BufferFormat,
+	/// Incremental ID of the frame.
+	#[pyo3(get)]
+	current_frame: u32,
+	/// Whether this frame is a new frame.
+	#[pyo3(get)]
+	is_new_frame: bool,
+	/// Time in microseconds when the display server started rendering the frame.
+	#[pyo3(get)]
+	timestamp_us: u64,
+	/// Number of frames the display server rendered since the previous grab that were not captured.
+	#[pyo3(get)]
+	missed_frames: u32,
+	diff_map: Option<DiffMap>,
+}
+
+impl Frame {
+	/// Copy `frame` out of the capturer.
+	pub fn new(frame: &SystemFrameInfo) -> Self {
+		Self {
+			data: frame.buffer.to_vec(),
+			width: frame.width,
+			height: frame.height,
+			buffer_format: frame.buffer_format.into(),
+			current_frame: frame.current_frame,
+			is_new_frame: frame.is_new_frame,
+			timestamp_us: frame.timestamp_us,
+			missed_frames: frame.missed_frames,
+			diff_map: frame.diff_map.map(|diff_map| DiffMap {
+				data: diff_map.data.to_vec(),
+				width: diff_map.width,
+				height: diff_map.height,
+				scaling_factor: diff_map.scaling_factor,
+			}),
+		}
+	}
+
+	/// The planes of the frame, after checking that the data has the size they require.
+	fn checked_planes(&self) -> PyResult<Vec<Plane>> {
+		let format = self.buffer_format.into();
+		check_size(&self.data, self.width, self.height, format).map_err(buffer_size_error)?;
+		Ok(format.planes(self.width, self.height))
+	}
+}
+
+/// Raise an `ImportError` if NumPy is not installed, instead of panicking when creating an array.
+fn require_numpy(py: Python<'_>) -> PyResult<()> {
+	py.import("numpy").map(drop)
+}
+
+/// An array of shape (height, width), or (height, width, bytes) for planes with multiple bytes per sample.
+fn plane_array<'py>(py: Python<'py>, data: &[u8], plane: &Plane) -> PyResult<Bound<'py, PyArrayDyn<u8>>> {
+	let mut shape = vec![plane.height as usize, plane.width as usize];
+	if plane.bytes_per_sample > 1 {
+		shape.push(plane.bytes_per_sample);
+	}
+	let view = ArrayViewD::from_shape(IxDyn(&shape), &data[plane.range()]).map_err(|e| PyValueError::new_err(e.to_string()))?;
+	Ok(view.to_pyarray(py))
+}
+
+#[pymethods]
+impl Frame {
+	/// Size of the frame data in bytes.
+	#[getter]
+	fn byte_size(&self) -> usize {
+		self.data.len()
+	}
+
+	/// The raw frame data.
+	#[getter]
+	fn data<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+		PyBytes::new(py, &self.data)
+	}
+
+	/// Width and height in pixels of the blocks of the diff map, or `None` if the frame has no diff map.
+	#[getter]
+	fn diff_map_scaling_factor(&self) -> Option<u32> {
+		self.diff_map.as_ref().map(|diff_map| diff_map.scaling_factor)
+	}
+
+	/// The frame as an array of shape (height, width, channels), with the channels in the order of the buffer format.
+	///
+	/// Raises `ValueError` for the planar formats NV12 and YUV444P, use `planes()` for those.
+	fn array<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArrayDyn<u8>>> {
+		require_numpy(py)?;
+		match self.checked_planes()?.as_slice() {
+			[plane] => plane_array(py, &self.data, plane),
+			_ => Err(PyValueError::new_err(format!("{} frames have multiple planes, use planes() instead", self.buffer_format.name()))),
+		}
+	}
+
+	/// Every plane of the frame as an array.
+	///
+	/// NV12 frames have a Y plane of shape (height, width) and a UV plane of shape (height / 2, width / 2, 2),
+	/// YUV444P frames have Y, U and V planes of shape (height, width),
+	/// and frames in other formats have a single plane of shape (height, width, channels).
+	fn planes<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyArrayDyn<u8>>>> {
+		require_numpy(py)?;
+		self.checked_planes()?.iter().map(|plane| plane_array(py, &self.data, plane)).collect()
+	}
+
+	/// The frame converted to RGB, as an array of shape (height, width, 3).
+	fn to_rgb<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray3<u8>>> {
+		require_numpy(py)?;
+		let rgb = to_rgb(&self.data, self.width, self.height, self.buffer_format.into()).map_err(buffer_size_error)?;
+		let view = ArrayView3::from_shape((self.height as usize, self.width as usize, 3), &rgb).map_err(|e| PyValueError::new_err(e.to_string()))?;
+		Ok(view.to_pyarray(py))
+	}
+
+	/// The blocks of the frame that changed since the previously captured frame, as an array of shape (rows, columns).
+	///
+	/// A block changed if its value is non-zero.
+	/// Returns `None` unless the session was started with a `diff_map_scaling_factor`.
+	fn diff_map<'py>(&self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyArray2<u8>>>> {
+		let Some(diff_map) = &self.diff_map else {
+			return Ok(None);
+		};
+		require_numpy(py)?;
+		let shape = (diff_map.height as usize, diff_map.width as usize);
+		let view = ArrayView2::from_shape(shape, &diff_map.data).map_err(|e| PyValueError::new_err(e.to_string()))?;
+		Ok(Some(view.to_pyarray(py)))
+	}
+
+	fn __repr__(&self) -> String {
+		format!(
+			"Frame(width={}, height={}, buffer_format=BufferFormat.{}, current_frame={}, is_new_frame={}, timestamp_us={}, missed_frames={})",
+			self.width,
+			self.height,
+			self.buffer_format.name(),
+			self.current_frame,
+			if self.is_new_frame { "True" } else { "False" },
+			self.timestamp_us,
+			self.missed_frames,
+		)
+	}
+}
@@ -0,0 +1,61 @@
+//! Python bindings for NVFBC.
+//!
+//! This crate builds the `nvfbc` Python module, which captures frames with NvFBC and returns them as NumPy arrays.
+//! Build and install it in the active Python environment with [maturin](https://www.maturin.rs):
+//!
+//! ```sh
+//! cd nvfbc-python
+//! maturin develop --release
+//! ```
+//!
+//! The module mirrors the [`nvfbc`] crate:
+//!
+//! ```python
+//! import nvfbc
+//!
+//! capturer = nvfbc.Capturer()
+//! print([output.name for output in capturer.status().outputs])
+//! capturer.start(nvfbc.SessionOptions(nvfbc.BufferFormat.RGB, 30, tracking="DP-0"))
+//! frame = capturer.next_frame()
+//! print(frame.current_frame, frame.timestamp_us, frame.array().shape)  # (1080, 1920, 3)
+//! capturer.stop()
+//! ```
+//!
+//! Use `nvfbc.Capturer.synthetic(width, height)` to capture a generated test pattern on machines without an NVIDIA GPU.
+
+mod capturer;
+mod frame;
+mod types;
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+
+pub use capturer::Capturer;
+pub use frame::Frame;
+pub use types::{BufferFormat, CaptureMethod, Output, SessionOptions, Status};
+
+create_exception!(nvfbc, NvfbcError, PyException, "An error reported by NvFBC.");
+
+fn nvfbc_error(error: nvfbc::Error) -> PyErr {
+	NvfbcError::new_err(error.to_string())
+}
+
+fn buffer_size_error(error: nvfbc::BufferSizeError) -> PyErr {
+	PyValueError::new_err(error.to_string())
+}
+
+/// Capture frames with NvFBC as NumPy arrays.
+#[pymodule]
+#[pyo3(name = "nvfbc")]
+pub fn nvfbc_python(module: &Bound<'_, PyModule>) -> PyResult<()> {
+	module.add_class::<BufferFormat>()?;
+	module.add_class::<CaptureMethod>()?;
+	module.add_class::<Capturer>()?;
+	module.add_class::<Frame>()?;
+	module.add_class::<Output>()?;
+	module.add_class::<SessionOptions>()?;
+	module.add_class::<Status>()?;
+	module.add("NvfbcError", module.py().get_type::<NvfbcError>())?;
+	Ok(())
+}
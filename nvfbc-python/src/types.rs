@@ -0,0 +1,236 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// Format of the captured frames.
+#[pyclass(eq, eq_int, hash, frozen, module = "nvfbc")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BufferFormat {
+	/// ARGB8888 byte order, arrays of shape (height, width, 4).
+	#[pyo3(name = "ARGB")]
+	Argb,
+	/// RGB888 byte order, arrays of shape (height, width, 3).
+	#[pyo3(name = "RGB")]
+	Rgb,
+	/// A Y plane of shape (height, width) followed by an interleaved UV plane of shape (height / 2, width / 2, 2).
+	#[pyo3(name = "NV12")]
+	Nv12,
+	/// Y, U and V planes of shape (height, width).
+	#[pyo3(name = "YUV444P")]
+	Yuv444p,
+	/// RGBA8888 byte order, arrays of shape (height, width, 4).
+	#[pyo3(name = "RGBA")]
+	Rgba,
+	/// BGRA8888 byte order, arrays of shape (height, width, 4).
+	#[pyo3(name = "BGRA")]
+	Bgra,
+}
+
+impl BufferFormat {
+	/// Name of the format in Python.
+	pub fn name(&self) -> &'static str {
+		match self {
+			BufferFormat::Argb => "ARGB",
+			BufferFormat::Rgb => "RGB",
+			BufferFormat::Nv12 => "NV12",
+			BufferFormat::Yuv444p => "YUV444P",
+			BufferFormat::Rgba => "RGBA",
+			BufferFormat::Bgra => "BGRA",
+		}
+	}
+}
+
+impl From<BufferFormat> for nvfbc::BufferFormat {
+	fn from(format: BufferFormat) -> Self {
+		match format {
+			BufferFormat::Argb => nvfbc::BufferFormat::Argb,
+			BufferFormat::Rgb => nvfbc::BufferFormat::Rgb,
+			BufferFormat::Nv12 => nvfbc::BufferFormat::Nv12,
+			BufferFormat::Yuv444p => nvfbc::BufferFormat::Yuv444p,
+			BufferFormat::Rgba => nvfbc::BufferFormat::Rgba,
+			BufferFormat::Bgra => nvfbc::BufferFormat::Bgra,
+		}
+	}
+}
+
+impl From<nvfbc::BufferFormat> for BufferFormat {
+	fn from(format: nvfbc::BufferFormat) -> Self {
+		match format {
+			nvfbc::BufferFormat::Argb => BufferFormat::Argb,
+			nvfbc::BufferFormat::Rgb => BufferFormat::Rgb,
+			nvfbc::BufferFormat::Nv12 => BufferFormat::Nv12,
+			nvfbc::BufferFormat::Yuv444p => BufferFormat::Yuv444p,
+			nvfbc::BufferFormat::Rgba => BufferFormat::Rgba,
+			nvfbc::BufferFormat::Bgra => BufferFormat::Bgra,
+		}
+	}
+}
+
+/// How to wait for a frame, see [`nvfbc::system::CaptureMethod`].
+#[pyclass(eq, eq_int, hash, frozen, module = "nvfbc")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CaptureMethod {
+	/// Return the most recent frame immediately, even if it was grabbed before.
+	#[pyo3(name = "NO_WAIT")]
+	NoWait,
+	/// Return immediately if a new frame is ready, otherwise wait for the next frame.
+	#[pyo3(name = "NO_WAIT_IF_NEW_FRAME")]
+	NoWaitIfNewFrame,
+	/// Wait for the next frame.
+	#[pyo3(name = "BLOCKING")]
+	Blocking,
+}
+
+impl From<CaptureMethod> for nvfbc::system::CaptureMethod {
+	fn from(method: CaptureMethod) -> Self {
+		match method {
+			CaptureMethod::NoWait => nvfbc::system::CaptureMethod::NoWait,
+			CaptureMethod::NoWaitIfNewFrame => nvfbc::system::CaptureMethod::NoWaitIfNewFrame,
+			CaptureMethod::Blocking => nvfbc::system::CaptureMethod::Blocking,
+		}
+	}
+}
+
+/// A RandR output that can be tracked.
+#[pyclass(frozen, get_all, module = "nvfbc")]
+#[derive(Debug, Clone)]
+pub struct Output {
+	/// Identifier of the RandR output.
+	pub id: u32,
+	/// Name of the RandR output, for example "DP-0".
+	pub name: String,
+	/// Region of the X screen tracked by the output, as (x, y, width, height).
+	pub tracked_box: (u32, u32, u32, u32),
+}
+
+#[pymethods]
+impl Output {
+	fn __repr__(&self) -> String {
+		let (x, y, w, h) = self.tracked_box;
+		format!("Output(id={}, name={:?}, tracked_box=({}, {}, {}, {}))", self.id, self.name, x, y, w, h)
+	}
+}
+
+/// Status of NvFBC, see [`nvfbc::Status`].
+#[pyclass(frozen, get_all, module = "nvfbc")]
+#[derive(Debug, Clone)]
+pub struct Status {
+	/// Whether or not framebuffer capture is supported by the graphics driver.
+	pub is_capture_possible: bool,
+	/// Whether or not there is already a capture session on this system.
+	pub currently_capturing: bool,
+	/// Whether or not it is possible to create a capture session on this system.
+	pub can_create_now: bool,
+	/// Size of the X screen, as (width, height).
+	pub screen_size: (u32, u32),
+	/// Whether the XRandR extension is available.
+	pub xrandr_available: bool,
+	/// Outputs connected to the X screen, only if XRandR is available.
+	pub outputs: Vec<Output>,
+	/// Version of the NvFBC library running on this system, as (major, minor).
+	pub nvfbc_version: (u32, u32),
+	/// Whether the X server is currently in modeset.
+	pub in_modeset: bool,
+}
+
+impl From<nvfbc::Status> for Status {
+	fn from(status: nvfbc::Status) -> Self {
+		Self {
+			is_capture_possible: status.is_capture_possible,
+			currently_capturing: status.currently_capturing,
+			can_create_now: status.can_create_now,
+			screen_size: (status.screen_size.w, status.screen_size.h),
+			xrandr_available: status.xrandr_available,
+			outputs: status.outputs.into_iter().map(|output| Output {
+				id: output.id,
+				name: output.name,
+				tracked_box: (output.tracked_box.x, output.tracked_box.y, output.tracked_box.w, output.tracked_box.h),
+			}).collect(),
+			nvfbc_version: (status.nvfbc_version.major, status.nvfbc_version.minor),
+			in_modeset: status.in_modeset,
+		}
+	}
+}
+
+/// Region of the framebuffer to track: a RandR output id, an output name, `"screen"` or `"default"`.
+#[derive(Debug, Clone, FromPyObject, IntoPyObject)]
+pub enum Tracking {
+	Id(u32),
+	Name(String),
+}
+
+/// Options used to start a capture session, see [`nvfbc::SessionOptions`].
+#[pyclass(get_all, set_all, module = "nvfbc")]
+#[derive(Debug, Clone)]
+pub struct SessionOptions {
+	/// Format of the captured frames.
+	pub buffer_format: BufferFormat,
+	/// Rate at which the display server generates new frames.
+	pub fps: u32,
+	/// Region of the framebuffer to track, or `None` for the default region.
+	pub tracking: Option<Tracking>,
+	/// Crop the tracked region to this box, as (x, y, width, height).
+	pub capture_box: Option<(u32, u32, u32, u32)>,
+	/// Scale the captured frames to this size, as (width, height).
+	pub frame_size: Option<(u32, u32)>,
+	/// Whether the mouse cursor should be composited to the frame.
+	pub with_cursor: bool,
+	/// Generate a diff map for every frame with blocks of this many pixels.
+	pub diff_map_scaling_factor: Option<u32>,
+}
+
+#[pymethods]
+impl SessionOptions {
+	#[new]
+	#[pyo3(signature = (
+		buffer_format = BufferFormat::Rgb,
+		fps = 30,
+		*,
+		tracking = None,
+		capture_box = None,
+		frame_size = None,
+		with_cursor = true,
+		diff_map_scaling_factor = None,
+	))]
+	fn new(
+		buffer_format: BufferFormat,
+		fps: u32,
+		tracking: Option<Tracking>,
+		capture_box: Option<(u32, u32, u32, u32)>,
+		frame_size: Option<(u32, u32)>,
+		with_cursor: bool,
+		diff_map_scaling_factor: Option<u32>,
+	) -> Self {
+		Self { buffer_format, fps, tracking, capture_box, frame_size, with_cursor, diff_map_scaling_factor }
+	}
+}
+
+impl Default for SessionOptions {
+	fn default() -> Self {
+		Self::new(BufferFormat::Rgb, 30, None, None, None, true, None)
+	}
+}
+
+impl SessionOptions {
+	/// The options of the `nvfbc` crate, resolving output names using `status`.
+	pub fn to_options(&self, status: &nvfbc::Status) -> PyResult<nvfbc::SessionOptions> {
+		let tracking = match &self.tracking {
+			None => nvfbc::Tracking::Default,
+			Some(Tracking::Id(id)) => nvfbc::Tracking::Output(*id),
+			Some(Tracking::Name(name)) if name == "default" => nvfbc::Tracking::Default,
+			Some(Tracking::Name(name)) if name == "screen" => nvfbc::Tracking::Screen,
+			Some(Tracking::Name(name)) => match status.outputs.iter().find(|output| output.name == *name) {
+				Some(output) => nvfbc::Tracking::Output(output.id),
+				None => return Err(PyValueError::new_err(format!("Unknown output '{}'", name))),
+			},
+		};
+
+		Ok(nvfbc::SessionOptions {
+			tracking,
+			capture_box: self.capture_box.map(|(x, y, w, h)| nvfbc::Box { x, y, w, h }),
+			frame_size: self.frame_size.map(|(w, h)| nvfbc::Size { w, h }),
+			with_cursor: self.with_cursor,
+			diff_map_scaling_factor: self.diff_map_scaling_factor,
+			..nvfbc::SessionOptions::new(self.buffer_format.into(), self.fps)
+		})
+	}
+}
@@ -0,0 +1,105 @@
+use std::ffi::CStr;
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// Run `code` with the `nvfbc` module imported, failing the test on any Python exception.
+fn run(code: &CStr) {
+	Python::attach(|py| {
+		let module = pyo3::wrap_pymodule!(nvfbc_python::nvfbc_python)(py);
+		let globals = PyDict::new(py);
+		globals.set_item("nvfbc", module).unwrap();
+		if let Err(error) = py.run(code, Some(&globals), None) {
+			error.display(py);
+			panic!("{}", error);
+		}
+	});
+}
+
+#[test]
+fn status() {
+	run(c"
+status = nvfbc.Capturer.synthetic(64, 48).status()
+assert status.is_capture_possible
+assert status.screen_size == (64, 48)
+assert [(output.name, output.tracked_box) for output in status.outputs] == [('SYNTHETIC-0', (0, 0, 64, 48))]
+");
+}
+
+#[test]
+fn grab_info() {
+	run(c"
+capturer = nvfbc.Capturer.synthetic(64, 48)
+options = nvfbc.SessionOptions(nvfbc.BufferFormat.NV12, 30, tracking='SYNTHETIC-0', capture_box=(8, 8, 32, 16), diff_map_scaling_factor=16)
+capturer.start(options)
+
+first = capturer.next_frame(nvfbc.CaptureMethod.NO_WAIT)
+assert (first.width, first.height, first.buffer_format) == (32, 16, nvfbc.BufferFormat.NV12)
+assert first.byte_size == len(first.data) == 32 * 16 * 3 // 2
+assert first.is_new_frame and first.missed_frames == 0
+assert first.diff_map_scaling_factor == 16
+assert repr(first).startswith('Frame(width=32, height=16, buffer_format=BufferFormat.NV12')
+
+second = capturer.next_frame()
+assert second.is_new_frame
+assert second.current_frame > first.current_frame
+assert second.timestamp_us > first.timestamp_us
+capturer.stop()
+");
+}
+
+#[test]
+fn blocking_grabs_release_the_gil() {
+	run(c"
+import threading
+import time
+
+capturer = nvfbc.Capturer.synthetic(64, 48)
+capturer.start(nvfbc.SessionOptions(fps=4))
+capturer.next_frame(nvfbc.CaptureMethod.NO_WAIT)
+
+# Another thread can only record ticks while the grab waits for the next frame if the GIL is released.
+ticks = []
+done = threading.Event()
+def tick():
+	while not done.is_set():
+		ticks.append(time.monotonic())
+		time.sleep(0.001)
+
+thread = threading.Thread(target=tick)
+thread.start()
+start = time.monotonic()
+capturer.next_frame(nvfbc.CaptureMethod.BLOCKING)
+end = time.monotonic()
+done.set()
+thread.join()
+during = [t for t in ticks if start + 0.05 < t < end - 0.05]
+assert end - start > 0.15, end - start
+assert len(during) > 10, len(during)
+");
+}
+
+#[test]
+fn errors() {
+	run(c"
+capturer = nvfbc.Capturer.synthetic(64, 48)
+try:
+	capturer.next_frame()
+	raise AssertionError('grabbed a frame without a session')
+except nvfbc.NvfbcError:
+	pass
+
+try:
+	capturer.start(nvfbc.SessionOptions(tracking='DP-9'))
+	raise AssertionError('started tracking an unknown output')
+except ValueError as error:
+	assert 'DP-9' in str(error)
+
+capturer.start(nvfbc.SessionOptions(tracking='screen'))
+try:
+	capturer.next_frame(timeout=-1.0)
+	raise AssertionError('accepted a negative timeout')
+except ValueError:
+	pass
+");
+}
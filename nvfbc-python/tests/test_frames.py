@@ -0,0 +1,97 @@
+"""Frames of the synthetic capturer as NumPy arrays. Run with `maturin develop && pytest tests`."""
+
+import numpy as np
+import pytest
+
+import nvfbc
+
+WIDTH = 64
+HEIGHT = 48
+
+
+def grab(buffer_format, **options):
+	capturer = nvfbc.Capturer.synthetic(WIDTH, HEIGHT)
+	capturer.start(nvfbc.SessionOptions(buffer_format, 1, **options))
+	frame = capturer.next_frame(nvfbc.CaptureMethod.NO_WAIT)
+	capturer.stop()
+	return frame
+
+
+@pytest.mark.parametrize("buffer_format, channels", [
+	(nvfbc.BufferFormat.RGB, 3),
+	(nvfbc.BufferFormat.ARGB, 4),
+	(nvfbc.BufferFormat.RGBA, 4),
+	(nvfbc.BufferFormat.BGRA, 4),
+])
+def test_packed_formats(buffer_format, channels):
+	frame = grab(buffer_format)
+	array = frame.array()
+	assert array.shape == (HEIGHT, WIDTH, channels)
+	assert array.dtype == np.uint8
+	assert array.tobytes() == frame.data
+	assert [plane.shape for plane in frame.planes()] == [array.shape]
+
+
+def test_channel_order():
+	rgb = grab(nvfbc.BufferFormat.RGB).array()
+	bgra = grab(nvfbc.BufferFormat.BGRA).array()
+	np.testing.assert_array_equal(bgra[..., [2, 1, 0]], rgb)
+	assert (bgra[..., 3] == 0xff).all()
+
+
+def test_nv12_planes():
+	frame = grab(nvfbc.BufferFormat.NV12)
+	y, uv = frame.planes()
+	assert y.shape == (HEIGHT, WIDTH)
+	assert uv.shape == (HEIGHT // 2, WIDTH // 2, 2)
+	assert y.tobytes() + uv.tobytes() == frame.data
+	with pytest.raises(ValueError):
+		frame.array()
+
+
+def test_yuv444p_planes():
+	frame = grab(nvfbc.BufferFormat.YUV444P)
+	planes = frame.planes()
+	assert [plane.shape for plane in planes] == [(HEIGHT, WIDTH)] * 3
+	assert b"".join(plane.tobytes() for plane in planes) == frame.data
+	with pytest.raises(ValueError):
+		frame.array()
+
+
+@pytest.mark.parametrize("buffer_format", [
+	nvfbc.BufferFormat.ARGB,
+	nvfbc.BufferFormat.RGB,
+	nvfbc.BufferFormat.NV12,
+	nvfbc.BufferFormat.YUV444P,
+	nvfbc.BufferFormat.RGBA,
+	nvfbc.BufferFormat.BGRA,
+])
+def test_to_rgb(buffer_format):
+	rgb = grab(buffer_format).to_rgb()
+	assert rgb.shape == (HEIGHT, WIDTH, 3)
+	# The color bars survive the conversion to YUV and back.
+	expected = grab(nvfbc.BufferFormat.RGB).array()
+	assert np.abs(rgb.astype(int) - expected).max() <= 8
+
+
+def test_scaled_frames():
+	frame = grab(nvfbc.BufferFormat.RGB, frame_size=(32, 24))
+	assert (frame.width, frame.height) == (32, 24)
+	assert frame.array().shape == (24, 32, 3)
+
+
+def test_diff_map():
+	assert grab(nvfbc.BufferFormat.RGB).diff_map() is None
+
+	frame = grab(nvfbc.BufferFormat.RGB, diff_map_scaling_factor=16)
+	diff_map = frame.diff_map()
+	assert diff_map.shape == (HEIGHT // 16, WIDTH // 16)
+	assert diff_map.dtype == np.uint8
+
+
+def test_arrays_are_copies():
+	frame = grab(nvfbc.BufferFormat.RGB)
+	array = frame.array()
+	array[...] = 0
+	assert frame.array().tobytes() == frame.data
+	assert frame.data != bytes(len(frame.data))
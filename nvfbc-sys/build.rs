@@ -1,3 +1,31 @@
 fn main() {
-	println!("cargo:rustc-link-lib=dylib=nvidia-fbc");
+	// The NvFBC library is not linked, it is loaded when it is first used.
+	#[cfg(feature = "bindgen")]
+	generate_bindings();
+}
+
+/// Only NvFBC items end up in the bindings, keeping system headers out of the public API.
+/// Functions are left out, they are loaded at runtime by the `library` module.
+///
+/// Keep this in sync with the `bindgen` script.
+#[cfg(feature = "bindgen")]
+const ALLOWLIST: &str = "(P|_)?NVFBC.*|NvFBC.*";
+
+/// Generate the bindings from the header in `NVFBC_HEADER`, or the bundled header if it is not set.
+#[cfg(feature = "bindgen")]
+fn generate_bindings() {
+	println!("cargo:rerun-if-env-changed=NVFBC_HEADER");
+	let header = std::env::var("NVFBC_HEADER").unwrap_or_else(|_| "NvFBC.h".to_string());
+	println!("cargo:rerun-if-changed={}", header);
+
+	let bindings = bindgen::Builder::default()
+		.header(&header)
+		.allowlist_type(ALLOWLIST)
+		.allowlist_var(ALLOWLIST)
+		.generate()
+		.unwrap_or_else(|e| panic!("Failed to generate bindings from {}: {}", header, e));
+
+	let out_path = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap()).join("generated.rs");
+	bindings.write_to_file(&out_path)
+		.unwrap_or_else(|e| panic!("Failed to write bindings to {}: {}", out_path.display(), e));
 }
@@ -1,216 +1,11 @@
 /* automatically generated by rust-bindgen 0.60.1 */
 
-pub const _STDINT_H: u32 = 1;
-pub const _FEATURES_H: u32 = 1;
-pub const _DEFAULT_SOURCE: u32 = 1;
-pub const __GLIBC_USE_ISOC2X: u32 = 0;
-pub const __USE_ISOC11: u32 = 1;
-pub const __USE_ISOC99: u32 = 1;
-pub const __USE_ISOC95: u32 = 1;
-pub const __USE_POSIX_IMPLICITLY: u32 = 1;
-pub const _POSIX_SOURCE: u32 = 1;
-pub const _POSIX_C_SOURCE: u32 = 200809;
-pub const __USE_POSIX: u32 = 1;
-pub const __USE_POSIX2: u32 = 1;
-pub const __USE_POSIX199309: u32 = 1;
-pub const __USE_POSIX199506: u32 = 1;
-pub const __USE_XOPEN2K: u32 = 1;
-pub const __USE_XOPEN2K8: u32 = 1;
-pub const _ATFILE_SOURCE: u32 = 1;
-pub const __WORDSIZE: u32 = 64;
-pub const __WORDSIZE_TIME64_COMPAT32: u32 = 1;
-pub const __SYSCALL_WORDSIZE: u32 = 64;
-pub const __TIMESIZE: u32 = 64;
-pub const __USE_MISC: u32 = 1;
-pub const __USE_ATFILE: u32 = 1;
-pub const __USE_FORTIFY_LEVEL: u32 = 0;
-pub const __GLIBC_USE_DEPRECATED_GETS: u32 = 0;
-pub const __GLIBC_USE_DEPRECATED_SCANF: u32 = 0;
-pub const _STDC_PREDEF_H: u32 = 1;
-pub const __STDC_IEC_559__: u32 = 1;
-pub const __STDC_IEC_60559_BFP__: u32 = 201404;
-pub const __STDC_IEC_559_COMPLEX__: u32 = 1;
-pub const __STDC_IEC_60559_COMPLEX__: u32 = 201404;
-pub const __STDC_ISO_10646__: u32 = 201706;
-pub const __GNU_LIBRARY__: u32 = 6;
-pub const __GLIBC__: u32 = 2;
-pub const __GLIBC_MINOR__: u32 = 35;
-pub const _SYS_CDEFS_H: u32 = 1;
-pub const __glibc_c99_flexarr_available: u32 = 1;
-pub const __LDOUBLE_REDIRECTS_TO_FLOAT128_ABI: u32 = 0;
-pub const __HAVE_GENERIC_SELECTION: u32 = 1;
-pub const __GLIBC_USE_LIB_EXT2: u32 = 0;
-pub const __GLIBC_USE_IEC_60559_BFP_EXT: u32 = 0;
-pub const __GLIBC_USE_IEC_60559_BFP_EXT_C2X: u32 = 0;
-pub const __GLIBC_USE_IEC_60559_EXT: u32 = 0;
-pub const __GLIBC_USE_IEC_60559_FUNCS_EXT: u32 = 0;
-pub const __GLIBC_USE_IEC_60559_FUNCS_EXT_C2X: u32 = 0;
-pub const __GLIBC_USE_IEC_60559_TYPES_EXT: u32 = 0;
-pub const _BITS_TYPES_H: u32 = 1;
-pub const _BITS_TYPESIZES_H: u32 = 1;
-pub const __OFF_T_MATCHES_OFF64_T: u32 = 1;
-pub const __INO_T_MATCHES_INO64_T: u32 = 1;
-pub const __RLIM_T_MATCHES_RLIM64_T: u32 = 1;
-pub const __STATFS_MATCHES_STATFS64: u32 = 1;
-pub const __KERNEL_OLD_TIMEVAL_MATCHES_TIMEVAL64: u32 = 1;
-pub const __FD_SETSIZE: u32 = 1024;
-pub const _BITS_TIME64_H: u32 = 1;
-pub const _BITS_WCHAR_H: u32 = 1;
-pub const _BITS_STDINT_INTN_H: u32 = 1;
-pub const _BITS_STDINT_UINTN_H: u32 = 1;
-pub const INT8_MIN: i32 = -128;
-pub const INT16_MIN: i32 = -32768;
-pub const INT32_MIN: i32 = -2147483648;
-pub const INT8_MAX: u32 = 127;
-pub const INT16_MAX: u32 = 32767;
-pub const INT32_MAX: u32 = 2147483647;
-pub const UINT8_MAX: u32 = 255;
-pub const UINT16_MAX: u32 = 65535;
-pub const UINT32_MAX: u32 = 4294967295;
-pub const INT_LEAST8_MIN: i32 = -128;
-pub const INT_LEAST16_MIN: i32 = -32768;
-pub const INT_LEAST32_MIN: i32 = -2147483648;
-pub const INT_LEAST8_MAX: u32 = 127;
-pub const INT_LEAST16_MAX: u32 = 32767;
-pub const INT_LEAST32_MAX: u32 = 2147483647;
-pub const UINT_LEAST8_MAX: u32 = 255;
-pub const UINT_LEAST16_MAX: u32 = 65535;
-pub const UINT_LEAST32_MAX: u32 = 4294967295;
-pub const INT_FAST8_MIN: i32 = -128;
-pub const INT_FAST16_MIN: i64 = -9223372036854775808;
-pub const INT_FAST32_MIN: i64 = -9223372036854775808;
-pub const INT_FAST8_MAX: u32 = 127;
-pub const INT_FAST16_MAX: u64 = 9223372036854775807;
-pub const INT_FAST32_MAX: u64 = 9223372036854775807;
-pub const UINT_FAST8_MAX: u32 = 255;
-pub const UINT_FAST16_MAX: i32 = -1;
-pub const UINT_FAST32_MAX: i32 = -1;
-pub const INTPTR_MIN: i64 = -9223372036854775808;
-pub const INTPTR_MAX: u64 = 9223372036854775807;
-pub const UINTPTR_MAX: i32 = -1;
-pub const PTRDIFF_MIN: i64 = -9223372036854775808;
-pub const PTRDIFF_MAX: u64 = 9223372036854775807;
-pub const SIG_ATOMIC_MIN: i32 = -2147483648;
-pub const SIG_ATOMIC_MAX: u32 = 2147483647;
-pub const SIZE_MAX: i32 = -1;
-pub const WINT_MIN: u32 = 0;
-pub const WINT_MAX: u32 = 4294967295;
 pub const NVFBC_VERSION_MAJOR: u32 = 1;
 pub const NVFBC_VERSION_MINOR: u32 = 8;
 pub const NVFBC_ERR_STR_LEN: u32 = 512;
 pub const NVFBC_OUTPUT_MAX: u32 = 5;
 pub const NVFBC_OUTPUT_NAME_LEN: u32 = 128;
 pub const NVFBC_TOGL_TEXTURES_MAX: u32 = 2;
-pub type __u_char = ::std::os::raw::c_uchar;
-pub type __u_short = ::std::os::raw::c_ushort;
-pub type __u_int = ::std::os::raw::c_uint;
-pub type __u_long = ::std::os::raw::c_ulong;
-pub type __int8_t = ::std::os::raw::c_schar;
-pub type __uint8_t = ::std::os::raw::c_uchar;
-pub type __int16_t = ::std::os::raw::c_short;
-pub type __uint16_t = ::std::os::raw::c_ushort;
-pub type __int32_t = ::std::os::raw::c_int;
-pub type __uint32_t = ::std::os::raw::c_uint;
-pub type __int64_t = ::std::os::raw::c_long;
-pub type __uint64_t = ::std::os::raw::c_ulong;
-pub type __int_least8_t = __int8_t;
-pub type __uint_least8_t = __uint8_t;
-pub type __int_least16_t = __int16_t;
-pub type __uint_least16_t = __uint16_t;
-pub type __int_least32_t = __int32_t;
-pub type __uint_least32_t = __uint32_t;
-pub type __int_least64_t = __int64_t;
-pub type __uint_least64_t = __uint64_t;
-pub type __quad_t = ::std::os::raw::c_long;
-pub type __u_quad_t = ::std::os::raw::c_ulong;
-pub type __intmax_t = ::std::os::raw::c_long;
-pub type __uintmax_t = ::std::os::raw::c_ulong;
-pub type __dev_t = ::std::os::raw::c_ulong;
-pub type __uid_t = ::std::os::raw::c_uint;
-pub type __gid_t = ::std::os::raw::c_uint;
-pub type __ino_t = ::std::os::raw::c_ulong;
-pub type __ino64_t = ::std::os::raw::c_ulong;
-pub type __mode_t = ::std::os::raw::c_uint;
-pub type __nlink_t = ::std::os::raw::c_ulong;
-pub type __off_t = ::std::os::raw::c_long;
-pub type __off64_t = ::std::os::raw::c_long;
-pub type __pid_t = ::std::os::raw::c_int;
-#[repr(C)]
-#[derive(Debug, Copy, Clone)]
-pub struct __fsid_t {
-	pub __val: [::std::os::raw::c_int; 2usize],
-}
-#[test]
-fn bindgen_test_layout___fsid_t() {
-	assert_eq!(
-		::std::mem::size_of::<__fsid_t>(),
-		8usize,
-		concat!("Size of: ", stringify!(__fsid_t))
-	);
-	assert_eq!(
-		::std::mem::align_of::<__fsid_t>(),
-		4usize,
-		concat!("Alignment of ", stringify!(__fsid_t))
-	);
-	fn test_field___val() {
-		assert_eq!(
-			unsafe {
-				let uninit = ::std::mem::MaybeUninit::<__fsid_t>::uninit();
-				let ptr = uninit.as_ptr();
-				::std::ptr::addr_of!((*ptr).__val) as usize - ptr as usize
-			},
-			0usize,
-			concat!("Offset of field: ", stringify!(__fsid_t), "::", stringify!(__val))
-		);
-	}
-	test_field___val();
-}
-pub type __clock_t = ::std::os::raw::c_long;
-pub type __rlim_t = ::std::os::raw::c_ulong;
-pub type __rlim64_t = ::std::os::raw::c_ulong;
-pub type __id_t = ::std::os::raw::c_uint;
-pub type __time_t = ::std::os::raw::c_long;
-pub type __useconds_t = ::std::os::raw::c_uint;
-pub type __suseconds_t = ::std::os::raw::c_long;
-pub type __suseconds64_t = ::std::os::raw::c_long;
-pub type __daddr_t = ::std::os::raw::c_int;
-pub type __key_t = ::std::os::raw::c_int;
-pub type __clockid_t = ::std::os::raw::c_int;
-pub type __timer_t = *mut ::std::os::raw::c_void;
-pub type __blksize_t = ::std::os::raw::c_long;
-pub type __blkcnt_t = ::std::os::raw::c_long;
-pub type __blkcnt64_t = ::std::os::raw::c_long;
-pub type __fsblkcnt_t = ::std::os::raw::c_ulong;
-pub type __fsblkcnt64_t = ::std::os::raw::c_ulong;
-pub type __fsfilcnt_t = ::std::os::raw::c_ulong;
-pub type __fsfilcnt64_t = ::std::os::raw::c_ulong;
-pub type __fsword_t = ::std::os::raw::c_long;
-pub type __ssize_t = ::std::os::raw::c_long;
-pub type __syscall_slong_t = ::std::os::raw::c_long;
-pub type __syscall_ulong_t = ::std::os::raw::c_ulong;
-pub type __loff_t = __off64_t;
-pub type __caddr_t = *mut ::std::os::raw::c_char;
-pub type __intptr_t = ::std::os::raw::c_long;
-pub type __socklen_t = ::std::os::raw::c_uint;
-pub type __sig_atomic_t = ::std::os::raw::c_int;
-pub type int_least8_t = __int_least8_t;
-pub type int_least16_t = __int_least16_t;
-pub type int_least32_t = __int_least32_t;
-pub type int_least64_t = __int_least64_t;
-pub type uint_least8_t = __uint_least8_t;
-pub type uint_least16_t = __uint_least16_t;
-pub type uint_least32_t = __uint_least32_t;
-pub type uint_least64_t = __uint_least64_t;
-pub type int_fast8_t = ::std::os::raw::c_schar;
-pub type int_fast16_t = ::std::os::raw::c_long;
-pub type int_fast32_t = ::std::os::raw::c_long;
-pub type int_fast64_t = ::std::os::raw::c_long;
-pub type uint_fast8_t = ::std::os::raw::c_uchar;
-pub type uint_fast16_t = ::std::os::raw::c_ulong;
-pub type uint_fast32_t = ::std::os::raw::c_ulong;
-pub type uint_fast64_t = ::std::os::raw::c_ulong;
-pub type intmax_t = __intmax_t;
-pub type uintmax_t = __uintmax_t;
 #[doc = " This indicates that the API call returned with no errors."]
 pub const _NVFBCSTATUS_NVFBC_SUCCESS: _NVFBCSTATUS = 0;
 #[doc = " This indicates that the API version between the client and the library"]
@@ -2667,406 +2462,6 @@ fn bindgen_test_layout__NVFBC_TOGL_GRAB_FRAME_PARAMS() {
 }
 #[doc = " Defines parameters for the ::NvFBCToGLGrabFrame() API call."]
 pub type NVFBC_TOGL_GRAB_FRAME_PARAMS = _NVFBC_TOGL_GRAB_FRAME_PARAMS;
-extern "C" {
-	#[doc = " Gets the last error message that got recorded for a client."]
-	#[doc = ""]
-	#[doc = " When NvFBC returns an error, it will save an error message that can be"]
-	#[doc = " queried through this API call.  Only the last message is saved."]
-	#[doc = " The message and the return code should give enough information about"]
-	#[doc = " what went wrong."]
-	#[doc = ""]
-	#[doc = " \\param [in] sessionHandle"]
-	#[doc = "   Handle to the NvFBC client."]
-	#[doc = " \\return"]
-	#[doc = "   A NULL terminated error message, or an empty string.  Its maximum length"]
-	#[doc = "   is NVFBC_ERROR_STR_LEN."]
-	pub fn NvFBCGetLastErrorStr(sessionHandle: NVFBC_SESSION_HANDLE) -> *const ::std::os::raw::c_char;
-}
-extern "C" {
-	#[doc = " \\brief Allocates a new handle for an NvFBC client."]
-	#[doc = ""]
-	#[doc = " This function allocates a session handle used to identify an FBC client."]
-	#[doc = ""]
-	#[doc = " This function implicitly calls NvFBCBindContext()."]
-	#[doc = ""]
-	#[doc = " \\param [out] pSessionHandle"]
-	#[doc = "   Pointer that will hold the allocated session handle."]
-	#[doc = " \\param [in] pParams"]
-	#[doc = "   ::NVFBC_CREATE_HANDLE_PARAMS"]
-	#[doc = ""]
-	#[doc = " \\return"]
-	#[doc = "   ::NVFBC_SUCCESS \\n"]
-	#[doc = "   ::NVFBC_ERR_INVALID_PTR \\n"]
-	#[doc = "   ::NVFBC_ERR_API_VERSION \\n"]
-	#[doc = "   ::NVFBC_ERR_INTERNAL \\n"]
-	#[doc = "   ::NVFBC_ERR_OUT_OF_MEMORY \\n"]
-	#[doc = "   ::NVFBC_ERR_MAX_CLIENTS \\n"]
-	#[doc = "   ::NVFBC_ERR_X \\n"]
-	#[doc = "   ::NVFBC_ERR_GLX \\n"]
-	#[doc = "   ::NVFBC_ERR_GL"]
-	#[doc = ""]
-	pub fn NvFBCCreateHandle(
-		pSessionHandle: *mut NVFBC_SESSION_HANDLE,
-		pParams: *mut NVFBC_CREATE_HANDLE_PARAMS,
-	) -> NVFBCSTATUS;
-}
-extern "C" {
-	#[doc = " \\brief Destroys the handle of an NvFBC client."]
-	#[doc = ""]
-	#[doc = " This function uninitializes an FBC client."]
-	#[doc = ""]
-	#[doc = " This function implicitly calls NvFBCReleaseContext()."]
-	#[doc = ""]
-	#[doc = " After this fucntion returns, it is not possible to use this session handle"]
-	#[doc = " for any further API call."]
-	#[doc = ""]
-	#[doc = " \\param [in] sessionHandle"]
-	#[doc = "   FBC session handle."]
-	#[doc = " \\param [in] pParams"]
-	#[doc = "   ::NVFBC_DESTROY_HANDLE_PARAMS"]
-	#[doc = ""]
-	#[doc = " \\return"]
-	#[doc = "   ::NVFBC_SUCCESS \\n"]
-	#[doc = "   ::NVFBC_ERR_INVALID_HANDLE \\n"]
-	#[doc = "   ::NVFBC_ERR_API_VERSION \\n"]
-	#[doc = "   ::NVFBC_ERR_BAD_REQUEST \\n"]
-	#[doc = "   ::NVFBC_ERR_INTERNAL \\n"]
-	#[doc = "   ::NVFBC_ERR_CONTEXT \\n"]
-	#[doc = "   ::NVFBC_ERR_X"]
-	pub fn NvFBCDestroyHandle(
-		sessionHandle: NVFBC_SESSION_HANDLE,
-		pParams: *mut NVFBC_DESTROY_HANDLE_PARAMS,
-	) -> NVFBCSTATUS;
-}
-extern "C" {
-	#[doc = " \\brief Gets the current status of the display driver."]
-	#[doc = ""]
-	#[doc = " This function queries the display driver for various information."]
-	#[doc = ""]
-	#[doc = " \\param [in] sessionHandle"]
-	#[doc = "   FBC session handle."]
-	#[doc = " \\param [in] pParams"]
-	#[doc = "   ::NVFBC_GET_STATUS_PARAMS"]
-	#[doc = ""]
-	#[doc = " \\return"]
-	#[doc = "   ::NVFBC_SUCCESS \\n"]
-	#[doc = "   ::NVFBC_ERR_INVALID_HANDLE \\n"]
-	#[doc = "   ::NVFBC_ERR_API_VERSION \\n"]
-	#[doc = "   ::NVFBC_ERR_INTERNAL \\n"]
-	#[doc = "   ::NVFBC_ERR_X"]
-	pub fn NvFBCGetStatus(sessionHandle: NVFBC_SESSION_HANDLE, pParams: *mut NVFBC_GET_STATUS_PARAMS) -> NVFBCSTATUS;
-}
-extern "C" {
-	#[doc = " \\brief Binds the FBC context to the calling thread."]
-	#[doc = ""]
-	#[doc = " The NvFBC library internally relies on objects that must be bound to a"]
-	#[doc = " thread.  Such objects are OpenGL contexts and CUDA contexts."]
-	#[doc = ""]
-	#[doc = " This function binds these objects to the calling thread."]
-	#[doc = ""]
-	#[doc = " The FBC context must be bound to the calling thread for most NvFBC entry"]
-	#[doc = " points, otherwise ::NVFBC_ERR_CONTEXT is returned."]
-	#[doc = ""]
-	#[doc = " If the FBC context is already bound to a different thread,"]
-	#[doc = " ::NVFBC_ERR_CONTEXT is returned.  The other thread must release the context"]
-	#[doc = " first by calling the ReleaseContext() entry point."]
-	#[doc = ""]
-	#[doc = " If the FBC context is already bound to the current thread, this function has"]
-	#[doc = " no effects."]
-	#[doc = ""]
-	#[doc = " \\param [in] sessionHandle"]
-	#[doc = "   FBC session handle."]
-	#[doc = " \\param [in] pParams"]
-	#[doc = "   ::NVFBC_DESTROY_CAPTURE_SESSION_PARAMS"]
-	#[doc = ""]
-	#[doc = " \\return"]
-	#[doc = "   ::NVFBC_SUCCESS \\n"]
-	#[doc = "   ::NVFBC_ERR_INVALID_HANDLE \\n"]
-	#[doc = "   ::NVFBC_ERR_API_VERSION \\n"]
-	#[doc = "   ::NVFBC_ERR_BAD_REQUEST \\n"]
-	#[doc = "   ::NVFBC_ERR_CONTEXT \\n"]
-	#[doc = "   ::NVFBC_ERR_INTERNAL \\n"]
-	#[doc = "   ::NVFBC_ERR_X"]
-	pub fn NvFBCBindContext(
-		sessionHandle: NVFBC_SESSION_HANDLE,
-		pParams: *mut NVFBC_BIND_CONTEXT_PARAMS,
-	) -> NVFBCSTATUS;
-}
-extern "C" {
-	#[doc = " \\brief Releases the FBC context from the calling thread."]
-	#[doc = ""]
-	#[doc = " If the FBC context is bound to a different thread, ::NVFBC_ERR_CONTEXT is"]
-	#[doc = " returned."]
-	#[doc = ""]
-	#[doc = " If the FBC context is already released, this functino has no effects."]
-	#[doc = ""]
-	#[doc = " \\param [in] sessionHandle"]
-	#[doc = "   FBC session handle."]
-	#[doc = " \\param [in] pParams"]
-	#[doc = "   ::NVFBC_SUCCESS \\n"]
-	#[doc = "   ::NVFBC_ERR_INVALID_HANDLE \\n"]
-	#[doc = "   ::NVFBC_ERR_API_VERSION \\n"]
-	#[doc = "   ::NVFBC_ERR_BAD_REQUEST \\n"]
-	#[doc = "   ::NVFBC_ERR_CONTEXT \\n"]
-	#[doc = "   ::NVFBC_ERR_INTERNAL \\n"]
-	#[doc = "   ::NVFBC_ERR_X"]
-	pub fn NvFBCReleaseContext(
-		sessionHandle: NVFBC_SESSION_HANDLE,
-		pParams: *mut NVFBC_RELEASE_CONTEXT_PARAMS,
-	) -> NVFBCSTATUS;
-}
-extern "C" {
-	#[doc = " \\brief Creates a capture session for an FBC client."]
-	#[doc = ""]
-	#[doc = " This function starts a capture session of the desired type (system memory,"]
-	#[doc = " video memory with CUDA interop, or H.264 compressed frames in system memory)."]
-	#[doc = ""]
-	#[doc = " Not all types are supported on all systems.  Also, it is possible to use"]
-	#[doc = " NvFBC without having the CUDA library.  In this case, requesting a capture"]
-	#[doc = " session of the concerned type will return an error."]
-	#[doc = ""]
-	#[doc = " After this function returns, the display driver will start generating frames"]
-	#[doc = " that can be captured using the corresponding API call."]
-	#[doc = ""]
-	#[doc = " \\param [in] sessionHandle"]
-	#[doc = "   FBC session handle."]
-	#[doc = " \\param [in] pParams"]
-	#[doc = "   ::NVFBC_CREATE_CAPTURE_SESSION_PARAMS"]
-	#[doc = ""]
-	#[doc = " \\return"]
-	#[doc = "   ::NVFBC_SUCCESS \\n"]
-	#[doc = "   ::NVFBC_ERR_INVALID_HANDLE \\n"]
-	#[doc = "   ::NVFBC_ERR_API_VERSION \\n"]
-	#[doc = "   ::NVFBC_ERR_BAD_REQUEST \\n"]
-	#[doc = "   ::NVFBC_ERR_CONTEXT \\n"]
-	#[doc = "   ::NVFBC_ERR_INVALID_PARAM \\n"]
-	#[doc = "   ::NVFBC_ERR_OUT_OF_MEMORY \\n"]
-	#[doc = "   ::NVFBC_ERR_X \\n"]
-	#[doc = "   ::NVFBC_ERR_GLX \\n"]
-	#[doc = "   ::NVFBC_ERR_GL \\n"]
-	#[doc = "   ::NVFBC_ERR_CUDA \\n"]
-	#[doc = "   ::NVFBC_ERR_MUST_RECREATE \\n"]
-	#[doc = "   ::NVFBC_ERR_INTERNAL"]
-	pub fn NvFBCCreateCaptureSession(
-		sessionHandle: NVFBC_SESSION_HANDLE,
-		pParams: *mut NVFBC_CREATE_CAPTURE_SESSION_PARAMS,
-	) -> NVFBCSTATUS;
-}
-extern "C" {
-	#[doc = " \\brief Destroys a capture session for an FBC client."]
-	#[doc = ""]
-	#[doc = " This function stops a capture session and frees allocated objects."]
-	#[doc = ""]
-	#[doc = " After this function returns, it is possible to create another capture"]
-	#[doc = " session using the corresponding API call."]
-	#[doc = ""]
-	#[doc = " \\param [in] sessionHandle"]
-	#[doc = "   FBC session handle."]
-	#[doc = " \\param [in] pParams"]
-	#[doc = "   ::NVFBC_DESTROY_CAPTURE_SESSION_PARAMS"]
-	#[doc = ""]
-	#[doc = " \\return"]
-	#[doc = "   ::NVFBC_SUCCESS \\n"]
-	#[doc = "   ::NVFBC_ERR_INVALID_HANDLE \\n"]
-	#[doc = "   ::NVFBC_ERR_API_VERSION \\n"]
-	#[doc = "   ::NVFBC_ERR_BAD_REQUEST \\n"]
-	#[doc = "   ::NVFBC_ERR_CONTEXT \\n"]
-	#[doc = "   ::NVFBC_ERR_INTERNAL \\n"]
-	#[doc = "   ::NVFBC_ERR_X"]
-	pub fn NvFBCDestroyCaptureSession(
-		sessionHandle: NVFBC_SESSION_HANDLE,
-		pParams: *mut NVFBC_DESTROY_CAPTURE_SESSION_PARAMS,
-	) -> NVFBCSTATUS;
-}
-extern "C" {
-	#[doc = " \\brief Sets up a capture to system memory session."]
-	#[doc = ""]
-	#[doc = " This function configures how the capture to system memory should behave. It"]
-	#[doc = " can be called anytime and several times after the capture session has been"]
-	#[doc = " created.  However, it must be called at least once prior to start capturing"]
-	#[doc = " frames."]
-	#[doc = ""]
-	#[doc = " This function allocates the buffer that will contain the captured frame."]
-	#[doc = " The application does not need to free this buffer.  The size of this buffer"]
-	#[doc = " is returned in the ::NVFBC_FRAME_GRAB_INFO structure."]
-	#[doc = ""]
-	#[doc = " \\param [in] sessionHandle"]
-	#[doc = "   FBC session handle."]
-	#[doc = " \\param [in] pParams"]
-	#[doc = "   ::NVFBC_TOSYS_SETUP_PARAMS"]
-	#[doc = ""]
-	#[doc = " \\return"]
-	#[doc = "   ::NVFBC_SUCCESS \\n"]
-	#[doc = "   ::NVFBC_ERR_INVALID_HANDLE \\n"]
-	#[doc = "   ::NVFBC_ERR_API_VERSION \\n"]
-	#[doc = "   ::NVFBC_ERR_BAD_REQUEST \\n"]
-	#[doc = "   ::NVFBC_ERR_INTERNAL \\n"]
-	#[doc = "   ::NVFBC_ERR_CONTEXT \\n"]
-	#[doc = "   ::NVFBC_ERR_UNSUPPORTED \\n"]
-	#[doc = "   ::NVFBC_ERR_INVALID_PTR \\n"]
-	#[doc = "   ::NVFBC_ERR_INVALID_PARAM \\n"]
-	#[doc = "   ::NVFBC_ERR_OUT_OF_MEMORY \\n"]
-	#[doc = "   ::NVFBC_ERR_X"]
-	pub fn NvFBCToSysSetUp(sessionHandle: NVFBC_SESSION_HANDLE, pParams: *mut NVFBC_TOSYS_SETUP_PARAMS) -> NVFBCSTATUS;
-}
-extern "C" {
-	#[doc = " \\brief Captures a frame to a buffer in system memory."]
-	#[doc = ""]
-	#[doc = " This function triggers a frame capture to a buffer in system memory that was"]
-	#[doc = " registered with the ToSysSetUp() API call."]
-	#[doc = ""]
-	#[doc = " Note that it is possible that the resolution of the desktop changes while"]
-	#[doc = " capturing frames. This should be transparent for the application."]
-	#[doc = ""]
-	#[doc = " When the resolution changes, the capture session is recreated using the same"]
-	#[doc = " parameters, and necessary buffers are re-allocated. The frame counter is not"]
-	#[doc = " reset."]
-	#[doc = ""]
-	#[doc = " An application can detect that the resolution changed by comparing the"]
-	#[doc = " dwByteSize member of the ::NVFBC_FRAME_GRAB_INFO against a previous"]
-	#[doc = " frame and/or dwWidth and dwHeight."]
-	#[doc = ""]
-	#[doc = " During a change of resolution the capture is paused even in asynchronous"]
-	#[doc = " mode."]
-	#[doc = ""]
-	#[doc = " \\param [in] sessionHandle"]
-	#[doc = "   FBC session handle."]
-	#[doc = " \\param [in] pParams"]
-	#[doc = "   ::NVFBC_TOSYS_GRAB_FRAME_PARAMS"]
-	#[doc = ""]
-	#[doc = " \\return"]
-	#[doc = "   ::NVFBC_SUCCESS \\n"]
-	#[doc = "   ::NVFBC_ERR_INVALID_HANDLE \\n"]
-	#[doc = "   ::NVFBC_ERR_API_VERSION \\n"]
-	#[doc = "   ::NVFBC_ERR_BAD_REQUEST \\n"]
-	#[doc = "   ::NVFBC_ERR_CONTEXT \\n"]
-	#[doc = "   ::NVFBC_ERR_INVALID_PTR \\n"]
-	#[doc = "   ::NVFBC_ERR_INTERNAL \\n"]
-	#[doc = "   ::NVFBC_ERR_X \\n"]
-	#[doc = "   ::NVFBC_ERR_MUST_RECREATE \\n"]
-	#[doc = "   \\see NvFBCCreateCaptureSession \\n"]
-	#[doc = "   \\see NvFBCToSysSetUp"]
-	pub fn NvFBCToSysGrabFrame(
-		sessionHandle: NVFBC_SESSION_HANDLE,
-		pParams: *mut NVFBC_TOSYS_GRAB_FRAME_PARAMS,
-	) -> NVFBCSTATUS;
-}
-extern "C" {
-	#[doc = " \\brief Sets up a capture to video memory session."]
-	#[doc = ""]
-	#[doc = " This function configures how the capture to video memory with CUDA interop"]
-	#[doc = " should behave.  It can be called anytime and several times after the capture"]
-	#[doc = " session has been created.  However, it must be called at least once prior"]
-	#[doc = " to start capturing frames."]
-	#[doc = ""]
-	#[doc = " \\param [in] sessionHandle"]
-	#[doc = "   FBC session handle."]
-	#[doc = ""]
-	#[doc = " \\param [in] pParams"]
-	#[doc = "   ::NVFBC_TOCUDA_SETUP_PARAMS"]
-	#[doc = ""]
-	#[doc = " \\return"]
-	#[doc = "   ::NVFBC_SUCCESS \\n"]
-	#[doc = "   ::NVFBC_ERR_INVALID_HANDLE \\n"]
-	#[doc = "   ::NVFBC_ERR_API_VERSION \\n"]
-	#[doc = "   ::NVFBC_ERR_BAD_REQUEST \\n"]
-	#[doc = "   ::NVFBC_ERR_INTERNAL \\n"]
-	#[doc = "   ::NVFBC_ERR_CONTEXT \\n"]
-	#[doc = "   ::NVFBC_ERR_UNSUPPORTED \\n"]
-	#[doc = "   ::NVFBC_ERR_GL \\n"]
-	#[doc = "   ::NVFBC_ERR_X"]
-	pub fn NvFBCToCudaSetUp(
-		sessionHandle: NVFBC_SESSION_HANDLE,
-		pParams: *mut NVFBC_TOCUDA_SETUP_PARAMS,
-	) -> NVFBCSTATUS;
-}
-extern "C" {
-	#[doc = " \\brief Captures a frame to a CUDA device in video memory."]
-	#[doc = ""]
-	#[doc = " This function triggers a frame capture to a CUDA device in video memory."]
-	#[doc = ""]
-	#[doc = " Note about changes of resolution: \\see NvFBCToSysGrabFrame"]
-	#[doc = ""]
-	#[doc = " \\param [in] sessionHandle"]
-	#[doc = "   FBC session handle."]
-	#[doc = ""]
-	#[doc = " \\param [in] pParams"]
-	#[doc = "   ::NVFBC_TOCUDA_GRAB_FRAME_PARAMS"]
-	#[doc = ""]
-	#[doc = " \\return"]
-	#[doc = "   ::NVFBC_SUCCESS \\n"]
-	#[doc = "   ::NVFBC_ERR_INVALID_HANDLE \\n"]
-	#[doc = "   ::NVFBC_ERR_API_VERSION \\n"]
-	#[doc = "   ::NVFBC_ERR_BAD_REQUEST \\n"]
-	#[doc = "   ::NVFBC_ERR_CONTEXT \\n"]
-	#[doc = "   ::NVFBC_ERR_INVALID_PTR \\n"]
-	#[doc = "   ::NVFBC_ERR_CUDA \\n"]
-	#[doc = "   ::NVFBC_ERR_INTERNAL \\n"]
-	#[doc = "   ::NVFBC_ERR_X \\n"]
-	#[doc = "   ::NVFBC_ERR_MUST_RECREATE \\n"]
-	#[doc = "   \\see NvFBCCreateCaptureSession \\n"]
-	#[doc = "   \\see NvFBCToCudaSetUp"]
-	pub fn NvFBCToCudaGrabFrame(
-		sessionHandle: NVFBC_SESSION_HANDLE,
-		pParams: *mut NVFBC_TOCUDA_GRAB_FRAME_PARAMS,
-	) -> NVFBCSTATUS;
-}
-extern "C" {
-	#[doc = " \\brief Sets up a capture to OpenGL buffer in video memory session."]
-	#[doc = ""]
-	#[doc = " This function configures how the capture to video memory should behave."]
-	#[doc = " It can be called anytime and several times after the capture session has been"]
-	#[doc = " created.  However, it must be called at least once prior to start capturing"]
-	#[doc = " frames."]
-	#[doc = ""]
-	#[doc = " \\param [in] sessionHandle"]
-	#[doc = "   FBC session handle."]
-	#[doc = ""]
-	#[doc = " \\param [in] pParams"]
-	#[doc = "   ::NVFBC_TOGL_SETUP_PARAMS"]
-	#[doc = ""]
-	#[doc = " \\return"]
-	#[doc = "   ::NVFBC_SUCCESS \\n"]
-	#[doc = "   ::NVFBC_ERR_INVALID_HANDLE \\n"]
-	#[doc = "   ::NVFBC_ERR_API_VERSION \\n"]
-	#[doc = "   ::NVFBC_ERR_BAD_REQUEST \\n"]
-	#[doc = "   ::NVFBC_ERR_INTERNAL \\n"]
-	#[doc = "   ::NVFBC_ERR_CONTEXT \\n"]
-	#[doc = "   ::NVFBC_ERR_UNSUPPORTED \\n"]
-	#[doc = "   ::NVFBC_ERR_GL \\n"]
-	#[doc = "   ::NVFBC_ERR_X"]
-	pub fn NvFBCToGLSetUp(sessionHandle: NVFBC_SESSION_HANDLE, pParams: *mut NVFBC_TOGL_SETUP_PARAMS) -> NVFBCSTATUS;
-}
-extern "C" {
-	#[doc = " \\brief Captures a frame to an OpenGL buffer in video memory."]
-	#[doc = ""]
-	#[doc = " This function triggers a frame capture to a selected resource in video memory."]
-	#[doc = ""]
-	#[doc = " Note about changes of resolution: \\see NvFBCToSysGrabFrame"]
-	#[doc = ""]
-	#[doc = " \\param [in] sessionHandle"]
-	#[doc = "   FBC session handle."]
-	#[doc = ""]
-	#[doc = " \\param [in] pParams"]
-	#[doc = "   ::NVFBC_TOGL_GRAB_FRAME_PARAMS"]
-	#[doc = ""]
-	#[doc = " \\return"]
-	#[doc = "   ::NVFBC_SUCCESS \\n"]
-	#[doc = "   ::NVFBC_ERR_INVALID_HANDLE \\n"]
-	#[doc = "   ::NVFBC_ERR_API_VERSION \\n"]
-	#[doc = "   ::NVFBC_ERR_BAD_REQUEST \\n"]
-	#[doc = "   ::NVFBC_ERR_CONTEXT \\n"]
-	#[doc = "   ::NVFBC_ERR_INVALID_PTR \\n"]
-	#[doc = "   ::NVFBC_ERR_INTERNAL \\n"]
-	#[doc = "   ::NVFBC_ERR_X \\n"]
-	#[doc = "   ::NVFBC_ERR_MUST_RECREATE \\n"]
-	#[doc = "   \\see NvFBCCreateCaptureSession \\n"]
-	#[doc = "   \\see NvFBCToCudaSetUp"]
-	pub fn NvFBCToGLGrabFrame(
-		sessionHandle: NVFBC_SESSION_HANDLE,
-		pParams: *mut NVFBC_TOGL_GRAB_FRAME_PARAMS,
-	) -> NVFBCSTATUS;
-}
 #[doc = " \\cond FBC_PFN"]
 #[doc = ""]
 #[doc = " Defines API function pointers"]
@@ -3570,23 +2965,6 @@ fn bindgen_test_layout_NVFBC_API_FUNCTION_LIST() {
 	}
 	test_field_nvFBCToGLGrabFrame();
 }
-extern "C" {
-	#[doc = " \\ingroup FBC_FUNC"]
-	#[doc = ""]
-	#[doc = " \\brief Entry Points to the NvFBC interface."]
-	#[doc = ""]
-	#[doc = " Creates an instance of the NvFBC interface, and populates the"]
-	#[doc = " pFunctionList with function pointers to the API routines implemented by"]
-	#[doc = " the NvFBC interface."]
-	#[doc = ""]
-	#[doc = " \\param [out] pFunctionList"]
-	#[doc = ""]
-	#[doc = " \\return"]
-	#[doc = "   ::NVFBC_SUCCESS \\n"]
-	#[doc = "   ::NVFBC_ERR_INVALID_PTR \\n"]
-	#[doc = "   ::NVFBC_ERR_API_VERSION"]
-	pub fn NvFBCCreateInstance(pFunctionList: *mut NVFBC_API_FUNCTION_LIST) -> NVFBCSTATUS;
-}
 #[doc = " \\ingroup FBC_FUNC"]
 #[doc = ""]
 #[doc = " Defines function pointer for the ::NvFBCCreateInstance() API call."]
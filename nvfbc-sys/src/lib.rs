@@ -2,10 +2,18 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 
+#[cfg(not(feature = "bindgen"))]
 mod generated;
+#[cfg(feature = "bindgen")]
+mod generated {
+	include!(concat!(env!("OUT_DIR"), "/generated.rs"));
+}
 pub use generated::*;
 
-pub const NVFBC_VERSION: u32 = NVFBC_VERSION_MINOR | (NVFBC_VERSION_MAJOR << 8);
+mod library;
+pub use library::*;
+
+pub const NVFBC_VERSION: u32 = nvfbc_api_version(NVFBC_VERSION_MAJOR, NVFBC_VERSION_MINOR);
 
 pub const NVFBC_CREATE_HANDLE_PARAMS_VER: u32 = nvfbc_struct_version::<NVFBC_CREATE_HANDLE_PARAMS>(2);
 pub const NVFBC_DESTROY_HANDLE_PARAMS_VER: u32 = nvfbc_struct_version::<NVFBC_DESTROY_HANDLE_PARAMS>(1);
@@ -22,5 +30,94 @@ pub const NVFBC_RELEASE_CONTEXT_PARAMS_VER: u32 = nvfbc_struct_version::<NVFBC_R
 pub const NVFBC_BIND_CONTEXT_PARAMS_VER: u32 = nvfbc_struct_version::<NVFBC_BIND_CONTEXT_PARAMS>(1);
 
 pub const fn nvfbc_struct_version<T>(version: u32) -> u32 {
-	std::mem::size_of::<T>() as u32 | ((version) << 16 | NVFBC_VERSION << 24)
+	nvfbc_struct_version_for_api(std::mem::size_of::<T>(), version, NVFBC_VERSION)
+}
+
+/// Creates a struct version for a struct of `size` bytes, for a library implementing `api_version`.
+pub const fn nvfbc_struct_version_for_api(size: usize, version: u32, api_version: u32) -> u32 {
+	size as u32 | ((version) << 16 | api_version << 24)
+}
+
+/// Creates an API version number from its major and minor version, similar to `NVFBC_VERSION`.
+pub const fn nvfbc_api_version(major: u32, minor: u32) -> u32 {
+	minor | (major << 8)
+}
+
+/// The `dwVersion` values of every parameter struct for a specific NvFBC API version.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StructVersions {
+	pub create_handle: u32,
+	pub destroy_handle: u32,
+	pub get_status: u32,
+	pub create_capture_session: u32,
+	pub destroy_capture_session: u32,
+	pub togl_setup: u32,
+	pub togl_grab_frame: u32,
+	pub tocuda_setup: u32,
+	pub tocuda_grab_frame: u32,
+	pub tosys_setup: u32,
+	pub tosys_grab_frame: u32,
+	pub release_context: u32,
+	pub bind_context: u32,
+}
+
+impl StructVersions {
+	/// Struct versions of the API version these bindings were generated for.
+	pub const CURRENT: Self = Self {
+		create_handle: NVFBC_CREATE_HANDLE_PARAMS_VER,
+		destroy_handle: NVFBC_DESTROY_HANDLE_PARAMS_VER,
+		get_status: NVFBC_GET_STATUS_PARAMS_VER,
+		create_capture_session: NVFBC_CREATE_CAPTURE_SESSION_PARAMS_VER,
+		destroy_capture_session: NVFBC_DESTROY_CAPTURE_SESSION_PARAMS_VER,
+		togl_setup: NVFBC_TOGL_SETUP_PARAMS_VER,
+		togl_grab_frame: NVFBC_TOGL_GRAB_FRAME_PARAMS_VER,
+		tocuda_setup: NVFBC_TOCUDA_SETUP_PARAMS_VER,
+		tocuda_grab_frame: NVFBC_TOCUDA_GRAB_FRAME_PARAMS_VER,
+		tosys_setup: NVFBC_TOSYS_SETUP_PARAMS_VER,
+		tosys_grab_frame: NVFBC_TOSYS_GRAB_FRAME_PARAMS_VER,
+		release_context: NVFBC_RELEASE_CONTEXT_PARAMS_VER,
+		bind_context: NVFBC_BIND_CONTEXT_PARAMS_VER,
+	};
+
+	/// Oldest API version whose struct layouts are compatible with these bindings.
+	///
+	/// NvFBC 1.6 added `ulTimestampUs` to `NVFBC_FRAME_GRAB_INFO`, which these bindings rely on.
+	pub const OLDEST_COMPATIBLE: u32 = nvfbc_api_version(1, 6);
+
+	/// Struct versions to use with a library implementing `api_version`.
+	///
+	/// Since NvFBC 1.6, the only layout changes are the fields that NvFBC 1.8 appended to
+	/// `NVFBC_GET_STATUS_PARAMS` (`bInModeset`) and `NVFBC_CREATE_CAPTURE_SESSION_PARAMS` (`bAllowDirectCapture`).
+	/// Older libraries are passed the size of these structs without the appended fields,
+	/// which means those fields are ignored.
+	///
+	/// Returns `None` if the layouts of `api_version` are not compatible with these bindings.
+	pub const fn for_api_version(api_version: u32) -> Option<Self> {
+		if api_version >= NVFBC_VERSION {
+			return Some(Self::CURRENT);
+		}
+		if api_version < Self::OLDEST_COMPATIBLE {
+			return None;
+		}
+
+		Some(Self {
+			create_handle: nvfbc_struct_version_for_api(std::mem::size_of::<NVFBC_CREATE_HANDLE_PARAMS>(), 2, api_version),
+			destroy_handle: nvfbc_struct_version_for_api(std::mem::size_of::<NVFBC_DESTROY_HANDLE_PARAMS>(), 1, api_version),
+			get_status: nvfbc_struct_version_for_api(std::mem::offset_of!(NVFBC_GET_STATUS_PARAMS, bInModeset), 2, api_version),
+			create_capture_session: nvfbc_struct_version_for_api(
+				std::mem::offset_of!(NVFBC_CREATE_CAPTURE_SESSION_PARAMS, bAllowDirectCapture),
+				6,
+				api_version,
+			),
+			destroy_capture_session: nvfbc_struct_version_for_api(std::mem::size_of::<NVFBC_DESTROY_CAPTURE_SESSION_PARAMS>(), 1, api_version),
+			togl_setup: nvfbc_struct_version_for_api(std::mem::size_of::<NVFBC_TOGL_SETUP_PARAMS>(), 2, api_version),
+			togl_grab_frame: nvfbc_struct_version_for_api(std::mem::size_of::<NVFBC_TOGL_GRAB_FRAME_PARAMS>(), 2, api_version),
+			tocuda_setup: nvfbc_struct_version_for_api(std::mem::size_of::<NVFBC_TOCUDA_SETUP_PARAMS>(), 1, api_version),
+			tocuda_grab_frame: nvfbc_struct_version_for_api(std::mem::size_of::<NVFBC_TOCUDA_GRAB_FRAME_PARAMS>(), 2, api_version),
+			tosys_setup: nvfbc_struct_version_for_api(std::mem::size_of::<NVFBC_TOSYS_SETUP_PARAMS>(), 3, api_version),
+			tosys_grab_frame: nvfbc_struct_version_for_api(std::mem::size_of::<NVFBC_TOSYS_GRAB_FRAME_PARAMS>(), 2, api_version),
+			release_context: nvfbc_struct_version_for_api(std::mem::size_of::<NVFBC_RELEASE_CONTEXT_PARAMS>(), 1, api_version),
+			bind_context: nvfbc_struct_version_for_api(std::mem::size_of::<NVFBC_BIND_CONTEXT_PARAMS>(), 1, api_version),
+		})
+	}
 }
@@ -0,0 +1,153 @@
+//! The functions of the NvFBC library, which is loaded when one of them is called for the first time.
+//!
+//! Programs using these bindings start without the NVIDIA driver installed, for example to use other capture sources.
+//! When the library cannot be loaded, every function returns `NVFBC_ERR_INTERNAL`
+//! and [`NvFBCGetLastErrorStr`] describes why it could not be loaded.
+
+use std::ffi::{c_void, CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::sync::OnceLock;
+
+use crate::*;
+
+/// Names of the library to load, in order of preference. The NVIDIA driver installs the first one.
+const LIBRARY_NAMES: [&CStr; 2] = [c"libnvidia-fbc.so.1", c"libnvidia-fbc.so"];
+
+const RTLD_NOW: c_int = 2;
+
+extern "C" {
+	fn dlopen(filename: *const c_char, flags: c_int) -> *mut c_void;
+	fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+	fn dlerror() -> *mut c_char;
+}
+
+/// The message of the last `dl*` error.
+fn last_dl_error() -> String {
+	// SAFETY: dlerror returns null or a valid C string.
+	let error = unsafe { dlerror() };
+	if error.is_null() {
+		return "unknown error".to_string();
+	}
+	// SAFETY: checked that it is not null.
+	unsafe { CStr::from_ptr(error) }.to_string_lossy().into_owned()
+}
+
+macro_rules! functions {
+	($($(#[$doc:meta])* fn $name:ident($($arg:ident: $ty:ty),*) -> $ret:ty, $fallback:expr;)*) => {
+		/// The functions exported by the library.
+		struct Functions {
+			$($name: unsafe extern "C" fn($($ty),*) -> $ret,)*
+		}
+
+		impl Functions {
+			/// Look up every function in the library opened as `handle`.
+			///
+			/// # Safety
+			/// `handle` must be a handle returned by `dlopen` for the NvFBC library.
+			unsafe fn load(handle: *mut c_void) -> Result<Self, String> {
+				Ok(Self {
+					$($name: {
+						let symbol = dlsym(handle, concat!(stringify!($name), "\0").as_ptr().cast());
+						if symbol.is_null() {
+							return Err(format!("the NvFBC library does not export {}", stringify!($name)));
+						}
+						// SAFETY: the symbol is the function with this signature declared in NvFBC.h.
+						std::mem::transmute::<*mut c_void, unsafe extern "C" fn($($ty),*) -> $ret>(symbol)
+					},)*
+				})
+			}
+		}
+
+		$(
+			$(#[$doc])*
+			///
+			/// # Safety
+			/// See the documentation of this function in `NvFBC.h`.
+			pub unsafe fn $name($($arg: $ty),*) -> $ret {
+				match library() {
+					Ok(functions) => (functions.$name)($($arg),*),
+					Err(_) => $fallback,
+				}
+			}
+		)*
+	};
+}
+
+functions! {
+	/// Get the last error message that was recorded for a client, or why the library could not be loaded.
+	fn NvFBCGetLastErrorStr(sessionHandle: NVFBC_SESSION_HANDLE) -> *const c_char,
+		load_error().map_or(std::ptr::null(), CStr::as_ptr);
+	/// Allocate a new handle for an NvFBC client.
+	fn NvFBCCreateHandle(pSessionHandle: *mut NVFBC_SESSION_HANDLE, pParams: *mut NVFBC_CREATE_HANDLE_PARAMS) -> NVFBCSTATUS,
+		_NVFBCSTATUS_NVFBC_ERR_INTERNAL;
+	/// Release the handle of an NvFBC client.
+	fn NvFBCDestroyHandle(sessionHandle: NVFBC_SESSION_HANDLE, pParams: *mut NVFBC_DESTROY_HANDLE_PARAMS) -> NVFBCSTATUS,
+		_NVFBCSTATUS_NVFBC_ERR_INTERNAL;
+	/// Get the current status of the display driver.
+	fn NvFBCGetStatus(sessionHandle: NVFBC_SESSION_HANDLE, pParams: *mut NVFBC_GET_STATUS_PARAMS) -> NVFBCSTATUS,
+		_NVFBCSTATUS_NVFBC_ERR_INTERNAL;
+	/// Bind the OpenGL context of the client to the calling thread.
+	fn NvFBCBindContext(sessionHandle: NVFBC_SESSION_HANDLE, pParams: *mut NVFBC_BIND_CONTEXT_PARAMS) -> NVFBCSTATUS,
+		_NVFBCSTATUS_NVFBC_ERR_INTERNAL;
+	/// Release the OpenGL context of the client from the calling thread.
+	fn NvFBCReleaseContext(sessionHandle: NVFBC_SESSION_HANDLE, pParams: *mut NVFBC_RELEASE_CONTEXT_PARAMS) -> NVFBCSTATUS,
+		_NVFBCSTATUS_NVFBC_ERR_INTERNAL;
+	/// Create a capture session for a client.
+	fn NvFBCCreateCaptureSession(sessionHandle: NVFBC_SESSION_HANDLE, pParams: *mut NVFBC_CREATE_CAPTURE_SESSION_PARAMS) -> NVFBCSTATUS,
+		_NVFBCSTATUS_NVFBC_ERR_INTERNAL;
+	/// Destroy the capture session of a client.
+	fn NvFBCDestroyCaptureSession(sessionHandle: NVFBC_SESSION_HANDLE, pParams: *mut NVFBC_DESTROY_CAPTURE_SESSION_PARAMS) -> NVFBCSTATUS,
+		_NVFBCSTATUS_NVFBC_ERR_INTERNAL;
+	/// Set up a capture to system memory.
+	fn NvFBCToSysSetUp(sessionHandle: NVFBC_SESSION_HANDLE, pParams: *mut NVFBC_TOSYS_SETUP_PARAMS) -> NVFBCSTATUS,
+		_NVFBCSTATUS_NVFBC_ERR_INTERNAL;
+	/// Capture a frame to system memory.
+	fn NvFBCToSysGrabFrame(sessionHandle: NVFBC_SESSION_HANDLE, pParams: *mut NVFBC_TOSYS_GRAB_FRAME_PARAMS) -> NVFBCSTATUS,
+		_NVFBCSTATUS_NVFBC_ERR_INTERNAL;
+	/// Set up a capture to a CUDA device.
+	fn NvFBCToCudaSetUp(sessionHandle: NVFBC_SESSION_HANDLE, pParams: *mut NVFBC_TOCUDA_SETUP_PARAMS) -> NVFBCSTATUS,
+		_NVFBCSTATUS_NVFBC_ERR_INTERNAL;
+	/// Capture a frame to a CUDA device.
+	fn NvFBCToCudaGrabFrame(sessionHandle: NVFBC_SESSION_HANDLE, pParams: *mut NVFBC_TOCUDA_GRAB_FRAME_PARAMS) -> NVFBCSTATUS,
+		_NVFBCSTATUS_NVFBC_ERR_INTERNAL;
+	/// Set up a capture to OpenGL textures.
+	fn NvFBCToGLSetUp(sessionHandle: NVFBC_SESSION_HANDLE, pParams: *mut NVFBC_TOGL_SETUP_PARAMS) -> NVFBCSTATUS,
+		_NVFBCSTATUS_NVFBC_ERR_INTERNAL;
+	/// Capture a frame to an OpenGL texture.
+	fn NvFBCToGLGrabFrame(sessionHandle: NVFBC_SESSION_HANDLE, pParams: *mut NVFBC_TOGL_GRAB_FRAME_PARAMS) -> NVFBCSTATUS,
+		_NVFBCSTATUS_NVFBC_ERR_INTERNAL;
+	/// Fill a list with the entry points of the library.
+	fn NvFBCCreateInstance(pFunctionList: *mut NVFBC_API_FUNCTION_LIST) -> NVFBCSTATUS,
+		_NVFBCSTATUS_NVFBC_ERR_INTERNAL;
+}
+
+/// The functions of the library, or why it could not be loaded.
+fn library() -> &'static Result<Functions, CString> {
+	static LIBRARY: OnceLock<Result<Functions, CString>> = OnceLock::new();
+	LIBRARY.get_or_init(|| {
+		load().map_err(|error| CString::new(error).unwrap_or_default())
+	})
+}
+
+/// Load the library, which stays loaded until the process exits.
+fn load() -> Result<Functions, String> {
+	let mut errors = Vec::new();
+	for name in LIBRARY_NAMES {
+		// SAFETY: the name is a valid C string.
+		let handle = unsafe { dlopen(name.as_ptr(), RTLD_NOW) };
+		if handle.is_null() {
+			errors.push(last_dl_error());
+			continue;
+		}
+		// SAFETY: the handle was returned by dlopen for the NvFBC library.
+		return unsafe { Functions::load(handle) };
+	}
+	Err(format!("failed to load the NvFBC library: {}", errors.join(", ")))
+}
+
+/// Why the NvFBC library could not be loaded, or `None` if it was loaded.
+///
+/// This loads the library if it was not loaded yet.
+pub fn load_error() -> Option<&'static CStr> {
+	library().as_ref().err().map(CString::as_c_str)
+}
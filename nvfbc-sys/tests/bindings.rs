@@ -0,0 +1,13 @@
+#![cfg(feature = "bindgen")]
+
+/// The checked-in bindings must be the output of the `bindgen` script for the bundled header.
+#[test]
+fn checked_in_bindings_are_up_to_date() {
+	if option_env!("NVFBC_HEADER").is_some() {
+		// The generated bindings are for another header.
+		return;
+	}
+	let generated = include_str!(concat!(env!("OUT_DIR"), "/generated.rs"));
+	let checked_in = include_str!("../src/generated.rs");
+	assert!(generated == checked_in, "src/generated.rs is out of date, run nvfbc-sys/bindgen to update it");
+}
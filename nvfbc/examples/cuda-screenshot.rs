@@ -1,6 +1,5 @@
 use std::{error::Error, mem::ManuallyDrop};
 
-use image::{Rgb, ImageBuffer};
 use nvfbc::cuda::CaptureMethod;
 use nvfbc::{BufferFormat, CudaCapturer};
 use rustacuda::{
@@ -47,11 +46,9 @@ fn main() -> Result<(), Box<dyn Error>> {
 	// See https://docs.rs/rustacuda/latest/rustacuda/memory/index.html#page-locked-host-memory for more information.
 	let mut data: LockedBuffer<u8> = unsafe { LockedBuffer::uninitialized(frame_info.device_buffer_len as usize) }?;
 
-	// Copy device memory to host memory and wrap it as an image.
+	// Copy device memory to host memory and convert it to an image.
 	device_buffer.copy_to(&mut data)?;
-	let slice = data.as_slice();
-	let frame = ImageBuffer::<Rgb<u8>, &[u8]>::from_raw(frame_info.width, frame_info.height, slice).unwrap();
-	frame.save("frame.png")?;
+	nvfbc::to_image(data.as_slice(), frame_info.width, frame_info.height, BufferFormat::Rgb)?.save("frame.png")?;
 
 	capturer.stop()?;
 
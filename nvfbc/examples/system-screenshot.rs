@@ -1,5 +1,4 @@
 use std::error::Error;
-use image::Rgb;
 use nvfbc::{SystemCapturer, BufferFormat, system::CaptureMethod};
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -16,12 +15,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 	let frame_info = capturer.next_frame(CaptureMethod::NoWaitIfNewFrame, None)?;
 	println!("{:#?}", frame_info);
 
-	let image = image::ImageBuffer::<Rgb<u8>, &[u8]>::from_raw(
-		frame_info.width,
-		frame_info.height,
-		frame_info.buffer,
-	).unwrap();
-	image.save("frame.png")?;
+	frame_info.to_image()?.save("frame.png")?;
 	println!("Saved frame to 'frame.png'.");
 
 	capturer.stop()?;
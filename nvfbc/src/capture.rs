@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use crate::system::{CaptureMethod, SystemFrameInfo};
+use crate::{Error, SessionOptions, Status};
+
+/// A source of frames in system memory.
+///
+/// This is implemented by [`SystemCapturer`](crate::SystemCapturer) and by sources that do not need NvFBC,
+/// such as [`SyntheticCapturer`](crate::synthetic::SyntheticCapturer).
+/// Code that only consumes frames can be written against this trait to be testable without a GPU.
+pub trait Capture {
+	/// Retrieve the status of the source.
+	fn status(&self) -> Result<Status, Error>;
+
+	/// Start a capture session configured by `options`.
+	fn start_with_options(&mut self, options: &SessionOptions) -> Result<(), Error>;
+
+	/// Stop the capture session.
+	fn stop(&mut self) -> Result<(), Error>;
+
+	/// Retrieve the next frame.
+	///
+	/// Only one frame can exist at the same time, see [`SystemCapturer::next_frame`](crate::SystemCapturer::next_frame).
+	fn next_frame(&mut self, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<SystemFrameInfo<'_>, Error>;
+}
+
+impl<C: Capture + ?Sized> Capture for std::boxed::Box<C> {
+	fn status(&self) -> Result<Status, Error> {
+		(**self).status()
+	}
+
+	fn start_with_options(&mut self, options: &SessionOptions) -> Result<(), Error> {
+		(**self).start_with_options(options)
+	}
+
+	fn stop(&mut self) -> Result<(), Error> {
+		(**self).stop()
+	}
+
+	fn next_frame(&mut self, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<SystemFrameInfo<'_>, Error> {
+		(**self).next_frame(capture_method, timeout)
+	}
+}
@@ -1,4 +1,6 @@
 use std::os::raw::c_uint;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{LockResult, Mutex, MutexGuard, PoisonError};
 use std::{mem::MaybeUninit, ffi::CStr};
 
 use nvfbc_sys::_NVFBCSTATUS_NVFBC_SUCCESS as SUCCESS;
@@ -6,69 +8,228 @@ use nvfbc_sys::NVFBC_SESSION_HANDLE;
 
 use crate::CaptureType;
 use crate::Error;
+use crate::SessionOptions;
 use crate::Status;
+use crate::Tracking;
+use crate::Version;
 
 pub type Handle = NVFBC_SESSION_HANDLE;
 
+/// Enter a span for an NvFBC call with the given fields, when the `tracing` feature is enabled.
+///
+/// The span is exited at the end of the enclosing block.
+/// The returned status code and error message are recorded on it by [`check_ret`].
+macro_rules! nvfbc_span {
+	($name:literal $(, $($field:tt)*)?) => {
+		#[cfg(feature = "tracing")]
+		let _span = tracing::debug_span!(
+			$name,
+			status = tracing::field::Empty,
+			error = tracing::field::Empty,
+			$($($field)*)?
+		).entered();
+	};
+}
+pub(crate) use nvfbc_span;
+
+/// Enter a span for an NvFBC grab call, which also records the grab info on success.
+macro_rules! nvfbc_grab_span {
+	($name:literal $(, $($field:tt)*)?) => {
+		crate::common::nvfbc_span!(
+			$name,
+			width = tracing::field::Empty,
+			height = tracing::field::Empty,
+			byte_size = tracing::field::Empty,
+			current_frame = tracing::field::Empty,
+			is_new_frame = tracing::field::Empty,
+			timestamp_us = tracing::field::Empty,
+			missed_frames = tracing::field::Empty,
+			$($($field)*)?
+		);
+	};
+}
+pub(crate) use nvfbc_grab_span;
+
+/// Record the status code returned by an NvFBC call on the current span.
+#[cfg(feature = "tracing")]
+fn record_status(ret: nvfbc_sys::_NVFBCSTATUS, error: Option<&Error>) {
+	let span = tracing::Span::current();
+	span.record("status", ret);
+	if let Some(error) = error {
+		span.record("error", error.message().unwrap_or_default());
+		tracing::warn!(status = ret, error = error.message().unwrap_or_default(), "NvFBC call failed");
+	}
+}
+
+/// Record the grab info of a successful grab on the current span.
+#[cfg(feature = "tracing")]
+pub(crate) fn record_grab_info(frame_info: &nvfbc_sys::NVFBC_FRAME_GRAB_INFO) {
+	let span = tracing::Span::current();
+	span.record("width", frame_info.dwWidth);
+	span.record("height", frame_info.dwHeight);
+	span.record("byte_size", frame_info.dwByteSize);
+	span.record("current_frame", frame_info.dwCurrentFrame);
+	span.record("is_new_frame", frame_info.bIsNewFrame != 0);
+	span.record("timestamp_us", frame_info.ulTimestampUs);
+	span.record("missed_frames", frame_info.dwMissedFrames);
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn record_grab_info(_frame_info: &nvfbc_sys::NVFBC_FRAME_GRAB_INFO) {}
+
 pub(crate) fn check_ret(handle: Handle, ret: nvfbc_sys::_NVFBCSTATUS) -> Result<(), Error> {
 	if ret != SUCCESS {
-		return Err(Error::new(ret, get_last_error(handle)));
+		let error = Error::new(ret, get_last_error(handle));
+		#[cfg(feature = "tracing")]
+		record_status(ret, Some(&error));
+		return Err(error);
 	}
+	#[cfg(feature = "tracing")]
+	record_status(ret, None);
 	Ok(())
 }
 
+/// Take the guard out of a lock result, even if another thread panicked while holding the lock.
+///
+/// Every lock in this crate guards state that is replaced or updated as a whole, so it stays consistent after a panic.
+pub(crate) fn ignore_poison<G>(result: LockResult<G>) -> G {
+	result.unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Lock `mutex`, even if another thread panicked while holding it, see [`ignore_poison`].
+pub(crate) fn lock_ignoring_poison<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+	ignore_poison(mutex.lock())
+}
+
+/// Interval at which the display server should generate frames to reach `fps` frames per second.
+pub(crate) fn sampling_rate(fps: u32) -> std::time::Duration {
+	std::time::Duration::from_millis(1000 / fps.max(1) as u64)
+}
+
+/// Older API versions to try when the library rejects the API version these bindings were generated for.
+const FALLBACK_API_VERSIONS: [u32; 2] = [nvfbc_sys::nvfbc_api_version(1, 7), nvfbc_sys::nvfbc_api_version(1, 6)];
+
+/// The API version negotiated with the NvFBC library.
+static API_VERSION: AtomicU32 = AtomicU32::new(nvfbc_sys::NVFBC_VERSION);
+
+/// The API version negotiated with the NvFBC library.
+///
+/// This is the compiled version until a handle is created with an older version.
+pub(crate) fn api_version() -> u32 {
+	API_VERSION.load(Ordering::Relaxed)
+}
+
+/// Struct versions matching the negotiated API version.
+pub(crate) fn struct_versions() -> nvfbc_sys::StructVersions {
+	nvfbc_sys::StructVersions::for_api_version(api_version()).unwrap_or(nvfbc_sys::StructVersions::CURRENT)
+}
+
+/// API versions to try in order when creating a handle, with their struct versions.
+///
+/// Starts with the `negotiated` version, then falls back to older versions with compatible struct layouts.
+pub(crate) fn candidate_api_versions(negotiated: u32) -> impl Iterator<Item = (u32, nvfbc_sys::StructVersions)> {
+	std::iter::once(negotiated)
+		.chain(FALLBACK_API_VERSIONS.into_iter().filter(move |&version| version < negotiated))
+		.filter_map(|api_version| Some((api_version, nvfbc_sys::StructVersions::for_api_version(api_version)?)))
+}
+
 pub(crate) fn create_handle() -> Result<nvfbc_sys::NVFBC_SESSION_HANDLE, Error> {
 	const MAGIC_PRIVATE_DATA: [u32; 4] = [0xAEF57AC5, 0x401D1A39, 0x1B856BBE, 0x9ED0CEBA];
 
-	let mut params: nvfbc_sys::_NVFBC_CREATE_HANDLE_PARAMS = unsafe { MaybeUninit::zeroed().assume_init() };
-	params.dwVersion = nvfbc_sys::NVFBC_CREATE_HANDLE_PARAMS_VER;
-	params.privateData = MAGIC_PRIVATE_DATA.as_ptr() as _;
-	params.privateDataSize = std::mem::size_of_val(&MAGIC_PRIVATE_DATA) as u32;
+	for (api_version, versions) in candidate_api_versions(api_version()) {
+		let mut params: nvfbc_sys::_NVFBC_CREATE_HANDLE_PARAMS = unsafe { MaybeUninit::zeroed().assume_init() };
+		params.dwVersion = versions.create_handle;
+		params.privateData = MAGIC_PRIVATE_DATA.as_ptr() as _;
+		params.privateDataSize = std::mem::size_of_val(&MAGIC_PRIVATE_DATA) as u32;
 
-	let mut handle = 0;
-	let ret = unsafe { nvfbc_sys::NvFBCCreateHandle(
-		&mut handle,
-		&mut params
-	)};
-	if ret != SUCCESS {
-		return Err(Error::new(ret, None));
+		nvfbc_span!("NvFBCCreateHandle", api_version = %Version::from_raw(api_version));
+		let mut handle = 0;
+		let ret = unsafe { nvfbc_sys::NvFBCCreateHandle(
+			&mut handle,
+			&mut params
+		)};
+		#[cfg(feature = "tracing")]
+		tracing::Span::current().record("status", ret);
+		match ret {
+			SUCCESS => {
+				API_VERSION.store(api_version, Ordering::Relaxed);
+				return Ok(handle);
+			},
+			nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_API_VERSION => continue,
+			ret => {
+				// There is no handle to get the error message of, unless the library could not be loaded.
+				let message = nvfbc_sys::load_error().map(|error| error.to_string_lossy().into_owned());
+				let error = Error::new(ret, message);
+				#[cfg(feature = "tracing")]
+				record_status(ret, Some(&error));
+				return Err(error);
+			},
+		}
 	}
 
-	Ok(handle)
+	Err(Error::new(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_API_VERSION, Some(Version::COMPILED.requirement())))
 }
 
 pub(crate) fn destroy_handle(handle: Handle) -> Result<(), Error> {
+	nvfbc_span!("NvFBCDestroyHandle", handle);
 	let mut params: nvfbc_sys::_NVFBC_DESTROY_HANDLE_PARAMS = unsafe { MaybeUninit::zeroed().assume_init() };
-	params.dwVersion = nvfbc_sys::NVFBC_DESTROY_HANDLE_PARAMS_VER;
+	params.dwVersion = struct_versions().destroy_handle;
 	check_ret(handle, unsafe { nvfbc_sys::NvFBCDestroyHandle(handle, &mut params) })
 }
 
 pub(crate) fn get_last_error(handle: Handle) -> Option<String> {
 	let error = unsafe { nvfbc_sys::NvFBCGetLastErrorStr(handle) };
+	if error.is_null() {
+		return None;
+	}
 	let error = unsafe { CStr::from_ptr(error) };
 	error.to_str().ok().map(|e| e.to_string())
 }
 
 pub(crate) fn status(handle: Handle) -> Result<Status, Error> {
+	nvfbc_span!("NvFBCGetStatus", handle);
 	let mut params: nvfbc_sys::_NVFBC_GET_STATUS_PARAMS = unsafe { MaybeUninit::zeroed().assume_init() };
-	params.dwVersion = nvfbc_sys::NVFBC_GET_STATUS_PARAMS_VER;
+	params.dwVersion = struct_versions().get_status;
 	check_ret(handle, unsafe { nvfbc_sys::NvFBCGetStatus(handle, &mut params) })?;
-	Ok(params.into())
+	Ok(params.try_into()?)
 }
 
-pub(crate) fn create_capture_session(handle: Handle, capture_type: CaptureType, sampling_rate: std::time::Duration) -> Result<(), Error> {
+pub(crate) fn create_capture_session(handle: Handle, capture_type: CaptureType, options: &SessionOptions) -> Result<(), Error> {
+	nvfbc_span!(
+		"NvFBCCreateCaptureSession",
+		handle,
+		capture_type = ?capture_type,
+		buffer_format = ?options.buffer_format,
+		fps = options.fps,
+		tracking = ?options.tracking,
+		capture_box = ?options.capture_box,
+		frame_size = ?options.frame_size,
+		with_cursor = options.with_cursor,
+	);
 	let mut params: nvfbc_sys::_NVFBC_CREATE_CAPTURE_SESSION_PARAMS = unsafe { MaybeUninit::zeroed().assume_init() };
-	params.dwVersion = nvfbc_sys::NVFBC_CREATE_CAPTURE_SESSION_PARAMS_VER;
+	params.dwVersion = struct_versions().create_capture_session;
 	params.eCaptureType = capture_type as c_uint;
-	params.bWithCursor = nvfbc_sys::_NVFBC_BOOL_NVFBC_TRUE;
-	params.frameSize = nvfbc_sys::NVFBC_SIZE { w: 0, h: 0 };
-	params.eTrackingType = nvfbc_sys::NVFBC_TRACKING_TYPE_NVFBC_TRACKING_DEFAULT;
-	params.dwSamplingRateMs = sampling_rate.as_millis() as u32;
+	params.bWithCursor = if options.with_cursor { nvfbc_sys::_NVFBC_BOOL_NVFBC_TRUE } else { nvfbc_sys::_NVFBC_BOOL_NVFBC_FALSE };
+	params.frameSize = options.frame_size.map_or(nvfbc_sys::NVFBC_SIZE { w: 0, h: 0 }, |size| nvfbc_sys::NVFBC_SIZE { w: size.w, h: size.h });
+	params.captureBox = options.capture_box.map_or(
+		nvfbc_sys::NVFBC_BOX { x: 0, y: 0, w: 0, h: 0 },
+		|capture_box| nvfbc_sys::NVFBC_BOX { x: capture_box.x, y: capture_box.y, w: capture_box.w, h: capture_box.h },
+	);
+	match options.tracking {
+		Tracking::Default => params.eTrackingType = nvfbc_sys::NVFBC_TRACKING_TYPE_NVFBC_TRACKING_DEFAULT,
+		Tracking::Output(output_id) => {
+			params.eTrackingType = nvfbc_sys::NVFBC_TRACKING_TYPE_NVFBC_TRACKING_OUTPUT;
+			params.dwOutputId = output_id;
+		},
+		Tracking::Screen => params.eTrackingType = nvfbc_sys::NVFBC_TRACKING_TYPE_NVFBC_TRACKING_SCREEN,
+	}
+	params.dwSamplingRateMs = sampling_rate(options.fps).as_millis() as u32;
 	check_ret(handle, unsafe { nvfbc_sys::NvFBCCreateCaptureSession(handle, &mut params) })
 }
 
 pub(crate) fn destroy_capture_session(handle: Handle) -> Result<(), Error> {
+	nvfbc_span!("NvFBCDestroyCaptureSession", handle);
 	let mut params: nvfbc_sys::_NVFBC_DESTROY_CAPTURE_SESSION_PARAMS = unsafe { MaybeUninit::zeroed().assume_init() };
-	params.dwVersion = nvfbc_sys::NVFBC_DESTROY_CAPTURE_SESSION_PARAMS_VER;
+	params.dwVersion = struct_versions().destroy_capture_session;
 	check_ret(handle, unsafe { nvfbc_sys::NvFBCDestroyCaptureSession(handle, &mut params) })
 }
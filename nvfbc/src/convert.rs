@@ -0,0 +1,149 @@
+//! Conversions between packed RGB and the buffer formats produced by NvFBC.
+//!
+//! YUV formats use the ITU-R BT.709 weights that NvFBC applies, with limited (16-235) range.
+
+use crate::{BufferFormat, BufferSizeError};
+
+/// Convert an RGB pixel to BT.709 limited range YUV.
+pub fn rgb_to_yuv([r, g, b]: [u8; 3]) -> [u8; 3] {
+	let (r, g, b) = (r as i32, g as i32, b as i32);
+	let y = ((47 * r + 157 * g + 16 * b + 128) >> 8) + 16;
+	let u = ((-26 * r - 87 * g + 112 * b + 128) >> 8) + 128;
+	let v = ((112 * r - 102 * g - 10 * b + 128) >> 8) + 128;
+	[y.clamp(0, 255) as u8, u.clamp(0, 255) as u8, v.clamp(0, 255) as u8]
+}
+
+/// Convert a BT.709 limited range YUV pixel to RGB.
+pub fn yuv_to_rgb([y, u, v]: [u8; 3]) -> [u8; 3] {
+	let c = 298 * (y as i32 - 16);
+	let d = u as i32 - 128;
+	let e = v as i32 - 128;
+	let r = (c + 459 * e + 128) >> 8;
+	let g = (c - 55 * d - 136 * e + 128) >> 8;
+	let b = (c + 541 * d + 128) >> 8;
+	[r.clamp(0, 255) as u8, g.clamp(0, 255) as u8, b.clamp(0, 255) as u8]
+}
+
+/// Check that `buffer` holds exactly a frame of `width` x `height` pixels in `format`.
+pub fn check_size(buffer: &[u8], width: u32, height: u32, format: BufferFormat) -> Result<(), BufferSizeError> {
+	let expected = format.frame_size(width, height);
+	if buffer.len() != expected {
+		return Err(BufferSizeError { expected, actual: buffer.len() });
+	}
+	Ok(())
+}
+
+/// Convert a frame in `format` to packed RGB888.
+pub fn to_rgb(buffer: &[u8], width: u32, height: u32, format: BufferFormat) -> Result<Vec<u8>, BufferSizeError> {
+	let mut rgb = vec![0; BufferFormat::Rgb.frame_size(width, height)];
+	to_rgb_into(buffer, width, height, format, &mut rgb)?;
+	Ok(rgb)
+}
+
+/// Convert a frame in `format` to packed RGB888, writing into `rgb`.
+pub fn to_rgb_into(buffer: &[u8], width: u32, height: u32, format: BufferFormat, rgb: &mut [u8]) -> Result<(), BufferSizeError> {
+	check_size(buffer, width, height, format)?;
+	check_size(rgb, width, height, BufferFormat::Rgb)?;
+
+	if let (Some(bytes_per_pixel), Some([r, g, b])) = (format.bytes_per_pixel(), format.rgb_offsets()) {
+		for (src, dst) in buffer.chunks_exact(bytes_per_pixel).zip(rgb.chunks_exact_mut(3)) {
+			dst.copy_from_slice(&[src[r], src[g], src[b]]);
+		}
+		return Ok(());
+	}
+
+	let planes = format.planes(width, height);
+	let (width, height) = (width as usize, height as usize);
+	for y in 0..height {
+		for x in 0..width {
+			let luma = buffer[y * width + x];
+			let (u, v) = match format {
+				BufferFormat::Nv12 => {
+					let chroma = &planes[1];
+					let index = chroma.offset + (y / 2) * chroma.stride() + (x / 2) * 2;
+					(buffer[index], buffer[index + 1])
+				},
+				_ => (buffer[planes[1].offset + y * width + x], buffer[planes[2].offset + y * width + x]),
+			};
+			let index = (y * width + x) * 3;
+			rgb[index..index + 3].copy_from_slice(&yuv_to_rgb([luma, u, v]));
+		}
+	}
+
+	Ok(())
+}
+
+/// Convert a packed RGB888 frame to `format`.
+pub fn from_rgb(rgb: &[u8], width: u32, height: u32, format: BufferFormat) -> Result<Vec<u8>, BufferSizeError> {
+	let mut buffer = vec![0; format.frame_size(width, height)];
+	from_rgb_into(rgb, width, height, format, &mut buffer)?;
+	Ok(buffer)
+}
+
+/// Convert a packed RGB888 frame to `format`, writing into `buffer`.
+///
+/// Chroma of NV12 frames is the average of every 2x2 block of pixels.
+/// Alpha channels are set to fully opaque.
+pub fn from_rgb_into(rgb: &[u8], width: u32, height: u32, format: BufferFormat, buffer: &mut [u8]) -> Result<(), BufferSizeError> {
+	check_size(rgb, width, height, BufferFormat::Rgb)?;
+	check_size(buffer, width, height, format)?;
+
+	if let (Some(bytes_per_pixel), Some([r, g, b])) = (format.bytes_per_pixel(), format.rgb_offsets()) {
+		for (src, dst) in rgb.chunks_exact(3).zip(buffer.chunks_exact_mut(bytes_per_pixel)) {
+			dst[r] = src[0];
+			dst[g] = src[1];
+			dst[b] = src[2];
+			if let Some(a) = format.alpha_offset() {
+				dst[a] = 0xff;
+			}
+		}
+		return Ok(());
+	}
+
+	let planes = format.planes(width, height);
+	let (width, height) = (width as usize, height as usize);
+	let pixel = |x: usize, y: usize| -> [u8; 3] {
+		let index = (y * width + x) * 3;
+		rgb_to_yuv([rgb[index], rgb[index + 1], rgb[index + 2]])
+	};
+
+	match format {
+		BufferFormat::Nv12 => {
+			for y in 0..height {
+				for x in 0..width {
+					buffer[y * width + x] = pixel(x, y)[0];
+				}
+			}
+
+			let chroma = &planes[1];
+			for cy in 0..chroma.height as usize {
+				for cx in 0..chroma.width as usize {
+					let (mut u, mut v, mut count) = (0u32, 0u32, 0u32);
+					for y in (cy * 2)..(cy * 2 + 2).min(height) {
+						for x in (cx * 2)..(cx * 2 + 2).min(width) {
+							let [_, pu, pv] = pixel(x, y);
+							u += pu as u32;
+							v += pv as u32;
+							count += 1;
+						}
+					}
+					let index = chroma.offset + cy * chroma.stride() + cx * 2;
+					buffer[index] = ((u + count / 2) / count) as u8;
+					buffer[index + 1] = ((v + count / 2) / count) as u8;
+				}
+			}
+		},
+		_ => {
+			for y in 0..height {
+				for x in 0..width {
+					let [luma, u, v] = pixel(x, y);
+					buffer[y * width + x] = luma;
+					buffer[planes[1].offset + y * width + x] = u;
+					buffer[planes[2].offset + y * width + x] = v;
+				}
+			}
+		},
+	}
+
+	Ok(())
+}
@@ -13,19 +13,26 @@ use crate::{
 	BufferFormat,
 	CaptureType,
 	Error,
+	FrameGrabInfo,
+	SessionOptions,
 	Status,
 };
 
 use crate::common::{
 	Handle,
 	check_ret,
+	nvfbc_grab_span,
+	nvfbc_span,
+	record_grab_info,
 	create_capture_session,
 	create_handle,
 	destroy_capture_session,
 	destroy_handle,
 	status,
+	struct_versions,
 };
 
+#[derive(Debug, Copy, Clone)]
 pub enum CaptureMethod {
 	/// Capturing does not wait for a new frame nor a mouse move.
 	///
@@ -61,6 +68,25 @@ pub struct CudaFrameInfo {
 	pub current_frame: u32,
 	/// Whether this frame is a new frame.
 	pub is_new_frame: bool,
+	/// Time in microseconds when the display server started rendering the frame.
+	pub timestamp_us: u64,
+	/// Number of frames the display server rendered since the previous grab that were not captured.
+	pub missed_frames: u32,
+}
+
+impl CudaFrameInfo {
+	/// Information about this frame, without the frame data.
+	pub fn grab_info(&self) -> FrameGrabInfo {
+		FrameGrabInfo {
+			width: self.width,
+			height: self.height,
+			byte_size: self.device_buffer_len,
+			current_frame: self.current_frame,
+			is_new_frame: self.is_new_frame,
+			timestamp_us: self.timestamp_us,
+			missed_frames: self.missed_frames,
+		}
+	}
 }
 
 impl std::fmt::Debug for CudaFrameInfo {
@@ -71,6 +97,9 @@ impl std::fmt::Debug for CudaFrameInfo {
 			.field("width", &self.width)
 			.field("height", &self.height)
 			.field("current_frame", &self.current_frame)
+			.field("is_new_frame", &self.is_new_frame)
+			.field("timestamp_us", &self.timestamp_us)
+			.field("missed_frames", &self.missed_frames)
 			.finish()
 	}
 }
@@ -96,16 +125,23 @@ impl CudaCapturer {
 
 	/// Start a capture session with the desired buffer format.
 	pub fn start(&self, buffer_format: BufferFormat, fps: u32) -> Result<(), Error> {
-		create_capture_session(
-			self.handle,
-			CaptureType::SharedCuda,
-			std::time::Duration::from_millis(1000 / fps as u64),
-		)?;
+		self.start_with_options(&SessionOptions::new(buffer_format, fps))
+	}
 
+	/// Start a capture session configured by `options`.
+	pub fn start_with_options(&self, options: &SessionOptions) -> Result<(), Error> {
+		create_capture_session(self.handle, CaptureType::SharedCuda, options)?;
+
+		nvfbc_span!("NvFBCToCudaSetUp", handle = self.handle, buffer_format = ?options.buffer_format);
 		let mut params: nvfbc_sys::NVFBC_TOCUDA_SETUP_PARAMS = unsafe { MaybeUninit::zeroed().assume_init() };
-		params.dwVersion = nvfbc_sys::NVFBC_TOCUDA_SETUP_PARAMS_VER;
-		params.eBufferFormat = buffer_format as u32;
-		check_ret(self.handle, unsafe { nvfbc_sys::NvFBCToCudaSetUp(self.handle, &mut params) })
+		params.dwVersion = struct_versions().tocuda_setup;
+		params.eBufferFormat = options.buffer_format as u32;
+		let result = check_ret(self.handle, unsafe { nvfbc_sys::NvFBCToCudaSetUp(self.handle, &mut params) });
+		if result.is_err() {
+			// Don't leave a half initialized session behind on this handle.
+			destroy_capture_session(self.handle).ok();
+		}
+		result
 	}
 
 	/// Stop a capture session.
@@ -115,10 +151,16 @@ impl CudaCapturer {
 
 	/// Retrieve the next frame from the GPU.
 	pub fn next_frame(&mut self, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<CudaFrameInfo, Error> {
+		nvfbc_grab_span!(
+			"NvFBCToCudaGrabFrame",
+			handle = self.handle,
+			flags = capture_method as u32,
+			timeout_ms = timeout.map(|timeout| timeout.as_millis() as u64),
+		);
 		let mut device_buffer: *mut c_void =  null_mut();
 		let mut frame_info: nvfbc_sys::NVFBC_FRAME_GRAB_INFO = unsafe { MaybeUninit::zeroed().assume_init() };
 		let mut params: nvfbc_sys::NVFBC_TOCUDA_GRAB_FRAME_PARAMS = unsafe { MaybeUninit::zeroed().assume_init() };
-		params.dwVersion = nvfbc_sys::NVFBC_TOCUDA_GRAB_FRAME_PARAMS_VER;
+		params.dwVersion = struct_versions().tocuda_grab_frame;
 		params.dwFlags = capture_method as u32;
 		params.pFrameGrabInfo = &mut frame_info;
 		params.pCUDADeviceBuffer = &mut device_buffer as *mut _ as *mut c_void;
@@ -126,6 +168,7 @@ impl CudaCapturer {
 			params.dwTimeoutMs = timeout.as_millis() as u32;
 		}
 		check_ret(self.handle, unsafe { nvfbc_sys::NvFBCToCudaGrabFrame(self.handle, &mut params) })?;
+		record_grab_info(&frame_info);
 
 		Ok(CudaFrameInfo {
 			device_buffer: device_buffer as usize,
@@ -134,6 +177,8 @@ impl CudaCapturer {
 			height: frame_info.dwHeight,
 			current_frame: frame_info.dwCurrentFrame,
 			is_new_frame: frame_info.bIsNewFrame != 0,
+			timestamp_us: frame_info.ulTimestampUs,
+			missed_frames: frame_info.dwMissedFrames,
 		})
 	}
 
@@ -144,8 +189,9 @@ impl CudaCapturer {
 	///
 	/// If the FBC context is already released, this function has no effect.
 	pub fn release_context(&self) -> Result<(), Error> {
+		nvfbc_span!("NvFBCReleaseContext", handle = self.handle);
 		let mut params: nvfbc_sys::NVFBC_RELEASE_CONTEXT_PARAMS = unsafe { MaybeUninit::zeroed().assume_init() };
-		params.dwVersion = nvfbc_sys::NVFBC_RELEASE_CONTEXT_PARAMS_VER;
+		params.dwVersion = struct_versions().release_context;
 		check_ret(
 			self.handle,
 			unsafe { nvfbc_sys::NvFBCReleaseContext(self.handle, &mut params) }
@@ -169,8 +215,9 @@ impl CudaCapturer {
 	/// If the FBC context is already bound to the current thread, this function has
 	/// no effects.
 	pub fn bind_context(&self) -> Result<(), Error> {
+		nvfbc_span!("NvFBCBindContext", handle = self.handle);
 		let mut params: nvfbc_sys::NVFBC_BIND_CONTEXT_PARAMS = unsafe { MaybeUninit::zeroed().assume_init() };
-		params.dwVersion = nvfbc_sys::NVFBC_BIND_CONTEXT_PARAMS_VER;
+		params.dwVersion = struct_versions().bind_context;
 		check_ret(
 			self.handle,
 			unsafe { nvfbc_sys::NvFBCBindContext(self.handle, &mut params) }
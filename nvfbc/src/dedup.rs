@@ -0,0 +1,273 @@
+//! Detecting duplicate frames by their content.
+//!
+//! [`SystemFrameInfo::is_new_frame`] and [`SystemFrameInfo::current_frame`] do not tell whether the pixels changed
+//! for grabs with [`CaptureMethod::NoWait`](crate::system::CaptureMethod::NoWait) or after a session was recreated.
+//! A [`Deduplicator`] hashes frames in tiles and compares them with the last distinct frame,
+//! reporting exact duplicates and, optionally, near-duplicates in which only a few pixels changed.
+//!
+//! ```
+//! use nvfbc::dedup::{DedupOptions, Deduplicator, Similarity};
+//! use nvfbc::BufferFormat;
+//!
+//! let mut deduplicator = Deduplicator::new(DedupOptions { near_duplicate_pixels: Some(4), ..DedupOptions::default() });
+//! let mut frame = vec![0; 64 * 48 * 3];
+//! assert_eq!(deduplicator.check_buffer(&frame, 64, 48, BufferFormat::Rgb).unwrap().similarity, Similarity::Distinct);
+//! assert_eq!(deduplicator.check_buffer(&frame, 64, 48, BufferFormat::Rgb).unwrap().similarity, Similarity::Duplicate);
+//! frame[0] = 0xff;
+//! assert_eq!(deduplicator.check_buffer(&frame, 64, 48, BufferFormat::Rgb).unwrap().similarity, Similarity::NearDuplicate);
+//! ```
+
+use crate::convert::check_size;
+use crate::system::SystemFrameInfo;
+use crate::{BufferFormat, BufferSizeError};
+
+/// Default width and height of a tile in pixels.
+pub const DEFAULT_TILE_SIZE: u32 = 64;
+
+/// Options of a [`Deduplicator`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DedupOptions {
+	/// Width and height of the tiles that are hashed and compared separately, in pixels.
+	pub tile_size: u32,
+	/// Report frames in which at most this many pixels changed as near-duplicates, or only exact duplicates if `None`.
+	///
+	/// Comparing pixels requires a copy of the last distinct frame.
+	pub near_duplicate_pixels: Option<u64>,
+	/// Largest difference of a sample that does not count as a change, when comparing pixels.
+	pub sample_tolerance: u8,
+}
+
+impl Default for DedupOptions {
+	fn default() -> Self {
+		Self { tile_size: DEFAULT_TILE_SIZE, near_duplicate_pixels: None, sample_tolerance: 0 }
+	}
+}
+
+/// How a frame compares to the last distinct frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Similarity {
+	/// The frame differs, or is the first frame of its size and format. It becomes the new reference.
+	Distinct,
+	/// At most [`DedupOptions::near_duplicate_pixels`] pixels changed.
+	NearDuplicate,
+	/// The frame has the same pixels.
+	Duplicate,
+}
+
+/// The result of comparing a frame with the last distinct frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Comparison {
+	/// How the frame compares.
+	pub similarity: Similarity,
+	/// Hash of the frame content and layout.
+	pub hash: u64,
+	/// Number of tiles with a different hash, or all tiles if the size or format changed.
+	pub changed_tiles: u32,
+	/// Number of changed pixels, if pixels were compared.
+	///
+	/// Pixels are only compared with [`DedupOptions::near_duplicate_pixels`] set, and when the size and format did not change.
+	pub changed_pixels: Option<u64>,
+}
+
+impl Comparison {
+	/// Whether the frame is a duplicate or a near-duplicate and can be skipped.
+	pub fn is_duplicate(&self) -> bool {
+		self.similarity != Similarity::Distinct
+	}
+}
+
+/// The last distinct frame.
+struct Reference {
+	width: u32,
+	height: u32,
+	format: BufferFormat,
+	tiles: Vec<u64>,
+	/// Copy of the frame, only if pixels are compared.
+	buffer: Vec<u8>,
+}
+
+/// Compares frames with the last distinct frame by their content.
+///
+/// Frames are compared with the last frame that was reported as [`Similarity::Distinct`], not with the previous frame,
+/// so that slow changes add up until they exceed the near-duplicate threshold.
+pub struct Deduplicator {
+	options: DedupOptions,
+	reference: Option<Reference>,
+	/// Tile hashes of the frame being checked.
+	tiles: Vec<u64>,
+}
+
+impl Deduplicator {
+	/// Create a deduplicator without a reference frame.
+	pub fn new(options: DedupOptions) -> Self {
+		Self { options, reference: None, tiles: Vec::new() }
+	}
+
+	/// The options of the deduplicator.
+	pub fn options(&self) -> &DedupOptions {
+		&self.options
+	}
+
+	/// Forget the reference frame, so that the next frame is distinct.
+	pub fn reset(&mut self) {
+		self.reference = None;
+	}
+
+	/// Compare `frame` with the last distinct frame.
+	pub fn check(&mut self, frame: &SystemFrameInfo) -> Result<Comparison, BufferSizeError> {
+		self.check_buffer(frame.buffer, frame.width, frame.height, frame.buffer_format)
+	}
+
+	/// Compare a frame of `width` x `height` pixels in `format` with the last distinct frame.
+	pub fn check_buffer(&mut self, buffer: &[u8], width: u32, height: u32, format: BufferFormat) -> Result<Comparison, BufferSizeError> {
+		check_size(buffer, width, height, format)?;
+		let tile_size = self.options.tile_size.max(1);
+		let tiles = Tiles::new(width, height, format, tile_size);
+		self.tiles.clear();
+		self.tiles.extend((0..tiles.count()).map(|tile| tiles.hash(buffer, tile)));
+		let hash = frame_hash_of_tiles(width, height, format, &self.tiles);
+
+		let same_layout = self.reference.as_ref().is_some_and(|reference| {
+			(reference.width, reference.height, reference.format) == (width, height, format)
+		});
+		let (similarity, changed_tiles, changed_pixels) = match &self.reference {
+			Some(reference) if same_layout => {
+				let changed: Vec<usize> = (0..self.tiles.len()).filter(|&tile| self.tiles[tile] != reference.tiles[tile]).collect();
+				if changed.is_empty() {
+					(Similarity::Duplicate, 0, self.options.near_duplicate_pixels.map(|_| 0))
+				} else if let Some(threshold) = self.options.near_duplicate_pixels {
+					let pixels: u64 = changed.iter()
+						.map(|&tile| tiles.changed_pixels(buffer, &reference.buffer, tile, self.options.sample_tolerance))
+						.sum();
+					let similarity = if pixels <= threshold { Similarity::NearDuplicate } else { Similarity::Distinct };
+					(similarity, changed.len() as u32, Some(pixels))
+				} else {
+					(Similarity::Distinct, changed.len() as u32, None)
+				}
+			},
+			_ => (Similarity::Distinct, self.tiles.len() as u32, None),
+		};
+
+		if similarity == Similarity::Distinct {
+			let reference = self.reference.get_or_insert_with(|| Reference {
+				width,
+				height,
+				format,
+				tiles: Vec::new(),
+				buffer: Vec::new(),
+			});
+			(reference.width, reference.height, reference.format) = (width, height, format);
+			std::mem::swap(&mut reference.tiles, &mut self.tiles);
+			reference.buffer.clear();
+			if self.options.near_duplicate_pixels.is_some() {
+				reference.buffer.extend_from_slice(buffer);
+			}
+		}
+
+		Ok(Comparison { similarity, hash, changed_tiles, changed_pixels })
+	}
+}
+
+/// Hash of the content and layout of a frame of `width` x `height` pixels in `format`.
+///
+/// Frames with the same hash are duplicates, hashes of the same frame may differ between versions of this crate.
+pub fn frame_hash(buffer: &[u8], width: u32, height: u32, format: BufferFormat) -> Result<u64, BufferSizeError> {
+	check_size(buffer, width, height, format)?;
+	let tiles = Tiles::new(width, height, format, DEFAULT_TILE_SIZE);
+	let hashes: Vec<u64> = (0..tiles.count()).map(|tile| tiles.hash(buffer, tile)).collect();
+	Ok(frame_hash_of_tiles(width, height, format, &hashes))
+}
+
+fn frame_hash_of_tiles(width: u32, height: u32, format: BufferFormat, tiles: &[u64]) -> u64 {
+	let layout = mix(mix(width as u64) ^ height as u64) ^ format as u64;
+	tiles.iter().fold(mix(layout), |hash, &tile| mix(hash ^ tile))
+}
+
+/// The split of a frame into square tiles of pixels, covering the samples of every plane.
+struct Tiles {
+	width: u32,
+	height: u32,
+	tile_size: u32,
+	columns: u32,
+	planes: Vec<crate::Plane>,
+}
+
+impl Tiles {
+	fn new(width: u32, height: u32, format: BufferFormat, tile_size: u32) -> Self {
+		Self {
+			width,
+			height,
+			tile_size,
+			columns: width.div_ceil(tile_size),
+			planes: format.planes(width, height),
+		}
+	}
+
+	fn count(&self) -> usize {
+		(self.columns * self.height.div_ceil(self.tile_size)) as usize
+	}
+
+	/// The pixel columns and rows of `tile`.
+	fn pixels(&self, tile: usize) -> (std::ops::Range<u32>, std::ops::Range<u32>) {
+		let (column, row) = (tile as u32 % self.columns, tile as u32 / self.columns);
+		let x = column * self.tile_size..((column + 1) * self.tile_size).min(self.width);
+		let y = row * self.tile_size..((row + 1) * self.tile_size).min(self.height);
+		(x, y)
+	}
+
+	/// The byte ranges of the rows of `tile` in `plane`.
+	fn rows<'a>(&'a self, plane: &'a crate::Plane, tile: usize) -> impl Iterator<Item = std::ops::Range<usize>> + 'a {
+		let (x, y) = self.pixels(tile);
+		let (columns, rows) = (plane.samples(x), plane.samples(y));
+		rows.map(move |row| {
+			let start = plane.offset + row as usize * plane.stride() + columns.start as usize * plane.bytes_per_sample;
+			start..start + columns.len() * plane.bytes_per_sample
+		})
+	}
+
+	fn hash(&self, buffer: &[u8], tile: usize) -> u64 {
+		self.planes.iter()
+			.flat_map(|plane| self.rows(plane, tile))
+			.fold(mix(tile as u64), |hash, row| hash_bytes(hash, &buffer[row]))
+	}
+
+	/// Number of pixels of `tile` with a sample in any plane that differs by more than `tolerance`.
+	fn changed_pixels(&self, buffer: &[u8], reference: &[u8], tile: usize, tolerance: u8) -> u64 {
+		let (x, y) = self.pixels(tile);
+		let mut changed = vec![false; x.len() * y.len()];
+		for plane in &self.planes {
+			for row in y.clone() {
+				let plane_row = (row / plane.subsampling) as usize;
+				for column in x.clone() {
+					let plane_column = (column / plane.subsampling) as usize;
+					let start = plane.offset + plane_row * plane.stride() + plane_column * plane.bytes_per_sample;
+					let sample = start..start + plane.bytes_per_sample;
+					if buffer[sample.clone()].iter().zip(&reference[sample]).any(|(&a, &b)| a.abs_diff(b) > tolerance) {
+						changed[((row - y.start) * x.len() as u32 + column - x.start) as usize] = true;
+					}
+				}
+			}
+		}
+		changed.iter().filter(|&&changed| changed).count() as u64
+	}
+}
+
+fn hash_bytes(hash: u64, bytes: &[u8]) -> u64 {
+	let mut chunks = bytes.chunks_exact(8);
+	let mut hash = (&mut chunks).fold(hash, |hash, chunk| mix(hash ^ u64::from_le_bytes(chunk.try_into().unwrap())));
+	let mut remainder = [0; 8];
+	remainder[..chunks.remainder().len()].copy_from_slice(chunks.remainder());
+	hash = mix(hash ^ u64::from_le_bytes(remainder));
+	mix(hash ^ bytes.len() as u64)
+}
+
+/// The finalizer of SplitMix64, a cheap mix of all bits of `x`.
+fn mix(x: u64) -> u64 {
+	let x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+	let x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+	x ^ (x >> 31)
+}
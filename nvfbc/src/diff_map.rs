@@ -0,0 +1,268 @@
+//! Maps of the regions of a frame that changed since the previous frame.
+//!
+//! NvFBC can generate a diff map for every frame captured to system memory,
+//! see [`SessionOptions::diff_map_scaling_factor`](crate::SessionOptions::diff_map_scaling_factor).
+//! Every byte of the map describes a square block of pixels and is non-zero if any of those pixels changed.
+//!
+//! For frames without a diff map, such as frames copied back from CUDA, a [`TileDiffer`] computes the same map on the CPU
+//! by comparing every frame with the previous one. [`DiffMapCapturer`] adds these maps to the frames of another capturer.
+
+use std::ops::Range;
+use std::time::Duration;
+
+use crate::convert::check_size;
+use crate::system::{CaptureMethod, SystemFrameInfo};
+use crate::{Box, BufferFormat, BufferSizeError, Capture, Error, SessionOptions, Size, Status};
+
+/// Map of the blocks of a frame that changed since the previously captured frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DiffMap<'a> {
+	/// One byte per block, row by row. A block changed if its byte is non-zero.
+	pub data: &'a [u8],
+	/// Number of blocks in a row.
+	pub width: u32,
+	/// Number of rows of blocks.
+	pub height: u32,
+	/// Width and height in pixels of a block.
+	pub scaling_factor: u32,
+}
+
+impl<'a> DiffMap<'a> {
+	/// Size in blocks of the diff map of a frame of `width` x `height` pixels.
+	///
+	/// Blocks at the right and bottom edges are partially outside of the frame if its size is not a multiple of `scaling_factor`.
+	pub fn size_for(width: u32, height: u32, scaling_factor: u32) -> Size {
+		let scaling_factor = scaling_factor.max(1);
+		Size { w: width.div_ceil(scaling_factor), h: height.div_ceil(scaling_factor) }
+	}
+
+	/// Whether the block in column `x` and row `y` changed.
+	///
+	/// Blocks outside of the map are unchanged.
+	pub fn is_changed(&self, x: u32, y: u32) -> bool {
+		if x >= self.width || y >= self.height {
+			return false;
+		}
+		self.data.get(y as usize * self.width as usize + x as usize).is_some_and(|&block| block != 0)
+	}
+
+	/// Whether any block changed.
+	pub fn any_changed(&self) -> bool {
+		self.blocks().iter().any(|&block| block != 0)
+	}
+
+	/// Number of blocks that changed.
+	pub fn changed_blocks(&self) -> usize {
+		self.blocks().iter().filter(|&&block| block != 0).count()
+	}
+
+	/// Regions in pixels that changed in a frame of `frame_width` x `frame_height` pixels.
+	///
+	/// Horizontally adjacent changed blocks are merged, and so are rows of blocks with the same horizontal extent.
+	/// The boxes are clipped to the frame and do not overlap.
+	pub fn changed_boxes(&self, frame_width: u32, frame_height: u32) -> Vec<Box> {
+		// Boxes in blocks, and the indices of the boxes that end in the previous row.
+		let mut boxes: Vec<Box> = Vec::new();
+		let mut open: Vec<usize> = Vec::new();
+
+		for y in 0..self.height {
+			let mut still_open = Vec::new();
+			let mut x = 0;
+			while x < self.width {
+				if !self.is_changed(x, y) {
+					x += 1;
+					continue;
+				}
+				let start = x;
+				while x < self.width && self.is_changed(x, y) {
+					x += 1;
+				}
+
+				let extends = open.iter().copied().find(|&i| boxes[i].x == start && boxes[i].w == x - start);
+				match extends {
+					Some(i) => {
+						boxes[i].h += 1;
+						still_open.push(i);
+					},
+					None => {
+						still_open.push(boxes.len());
+						boxes.push(Box { x: start, y, w: x - start, h: 1 });
+					},
+				}
+			}
+			open = still_open;
+		}
+
+		let scaling_factor = self.scaling_factor.max(1);
+		boxes.into_iter()
+			.filter_map(|block| {
+				let x = block.x.saturating_mul(scaling_factor);
+				let y = block.y.saturating_mul(scaling_factor);
+				let right = (block.x + block.w).saturating_mul(scaling_factor).min(frame_width);
+				let bottom = (block.y + block.h).saturating_mul(scaling_factor).min(frame_height);
+				(right > x && bottom > y).then_some(Box { x, y, w: right - x, h: bottom - y })
+			})
+			.collect()
+	}
+
+	/// Whether any block covering `region` of the frame, in pixels, changed.
+	pub fn any_changed_in(&self, region: Box) -> bool {
+		let (columns, rows) = self.blocks_covering(region);
+		rows.into_iter().any(|y| columns.clone().any(|x| self.is_changed(x, y)))
+	}
+
+	/// Copy the map into `data`, with every block covering one of `regions` of the frame, in pixels, marked as changed.
+	///
+	/// This is used by capturers that modify frames, so that consumers of the diff map see the modified pixels.
+	pub fn with_changed<'b>(&self, regions: &[Box], data: &'b mut Vec<u8>) -> DiffMap<'b> {
+		data.clear();
+		data.extend_from_slice(self.blocks());
+		data.resize(self.width as usize * self.height as usize, 0);
+		for &region in regions {
+			let (columns, rows) = self.blocks_covering(region);
+			for y in rows {
+				data[y as usize * self.width as usize..][columns.start as usize..columns.end as usize].fill(1);
+			}
+		}
+		DiffMap { data, ..*self }
+	}
+
+	/// The columns and rows of the blocks covering `region` of the frame, in pixels.
+	fn blocks_covering(&self, region: Box) -> (Range<u32>, Range<u32>) {
+		if region.w == 0 || region.h == 0 {
+			return (0..0, 0..0);
+		}
+		let scaling_factor = self.scaling_factor.max(1);
+		let blocks = |start: u32, len: u32, count: u32| {
+			(start / scaling_factor).min(count)..start.saturating_add(len).div_ceil(scaling_factor).min(count)
+		};
+		(blocks(region.x, region.w, self.width), blocks(region.y, region.h, self.height))
+	}
+
+	/// The bytes of the blocks within the map.
+	fn blocks(&self) -> &'a [u8] {
+		let len = (self.width as usize * self.height as usize).min(self.data.len());
+		&self.data[..len]
+	}
+}
+
+/// Computes diff maps on the CPU by comparing every frame with the previous one.
+///
+/// The maps have the same layout as the diff maps generated by NvFBC with the same scaling factor,
+/// and cover the samples of every plane, so they can be computed for every [`BufferFormat`].
+pub struct TileDiffer {
+	scaling_factor: u32,
+	/// Width, height and format of the previous frame.
+	layout: Option<(u32, u32, BufferFormat)>,
+	previous: Vec<u8>,
+	map: Vec<u8>,
+}
+
+impl TileDiffer {
+	/// Create a differ with blocks of `scaling_factor` x `scaling_factor` pixels.
+	pub fn new(scaling_factor: u32) -> Self {
+		Self { scaling_factor: scaling_factor.max(1), layout: None, previous: Vec::new(), map: Vec::new() }
+	}
+
+	/// Width and height in pixels of a block.
+	pub fn scaling_factor(&self) -> u32 {
+		self.scaling_factor
+	}
+
+	/// Forget the previous frame, so that every block of the next frame changed.
+	pub fn reset(&mut self) {
+		self.layout = None;
+		self.previous.clear();
+	}
+
+	/// Compute the diff map of `frame`.
+	pub fn diff_frame(&mut self, frame: &SystemFrameInfo) -> Result<DiffMap<'_>, BufferSizeError> {
+		self.diff(frame.buffer, frame.width, frame.height, frame.buffer_format)
+	}
+
+	/// Compute the diff map of a frame of `width` x `height` pixels in `format` since the previous frame.
+	///
+	/// Every block changed in the first frame and when the size or format of the frames changes.
+	/// Subsampled chroma samples belong to the block of their first pixel, see [`Plane::samples`](crate::Plane::samples).
+	pub fn diff(&mut self, buffer: &[u8], width: u32, height: u32, format: BufferFormat) -> Result<DiffMap<'_>, BufferSizeError> {
+		check_size(buffer, width, height, format)?;
+		let scaling_factor = self.scaling_factor;
+		let size = DiffMap::size_for(width, height, scaling_factor);
+		let count = size.w as usize * size.h as usize;
+
+		self.map.clear();
+		if self.layout != Some((width, height, format)) {
+			self.map.resize(count, 1);
+		} else {
+			self.map.resize(count, 0);
+			let planes = format.planes(width, height);
+			for block_y in 0..size.h {
+				let pixel_rows = block_y * scaling_factor..((block_y + 1) * scaling_factor).min(height);
+				for block_x in 0..size.w {
+					let pixel_columns = block_x * scaling_factor..((block_x + 1) * scaling_factor).min(width);
+					let changed = planes.iter().any(|plane| {
+						let rows = plane.samples(pixel_rows.clone());
+						let columns = plane.samples(pixel_columns.clone());
+						rows.into_iter().any(|row| {
+							let start = plane.offset + row as usize * plane.stride() + columns.start as usize * plane.bytes_per_sample;
+							let bytes = start..start + columns.len() * plane.bytes_per_sample;
+							buffer[bytes.clone()] != self.previous[bytes]
+						})
+					});
+					self.map[(block_y * size.w + block_x) as usize] = changed as u8;
+				}
+			}
+		}
+
+		self.layout = Some((width, height, format));
+		self.previous.clear();
+		self.previous.extend_from_slice(buffer);
+		Ok(DiffMap { data: &self.map, width: size.w, height: size.h, scaling_factor })
+	}
+}
+
+/// Adds diff maps computed by a [`TileDiffer`] to the frames of another capturer.
+///
+/// Frames that already have a diff map from NvFBC are returned unchanged.
+pub struct DiffMapCapturer<C> {
+	inner: C,
+	differ: TileDiffer,
+}
+
+impl<C: Capture> DiffMapCapturer<C> {
+	/// Add diff maps with blocks of `scaling_factor` x `scaling_factor` pixels to the frames of `inner`.
+	pub fn new(inner: C, scaling_factor: u32) -> Self {
+		Self { inner, differ: TileDiffer::new(scaling_factor) }
+	}
+
+	/// The wrapped capturer.
+	pub fn into_inner(self) -> C {
+		self.inner
+	}
+}
+
+impl<C: Capture> Capture for DiffMapCapturer<C> {
+	fn status(&self) -> Result<Status, Error> {
+		self.inner.status()
+	}
+
+	fn start_with_options(&mut self, options: &SessionOptions) -> Result<(), Error> {
+		self.differ.reset();
+		self.inner.start_with_options(options)
+	}
+
+	fn stop(&mut self) -> Result<(), Error> {
+		self.inner.stop()
+	}
+
+	fn next_frame(&mut self, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<SystemFrameInfo<'_>, Error> {
+		let frame = self.inner.next_frame(capture_method, timeout)?;
+		if frame.diff_map.is_some() {
+			self.differ.reset();
+			return Ok(frame);
+		}
+		let diff_map = self.differ.diff_frame(&frame)
+			.map_err(|e| Error::new(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_INTERNAL, Some(e.to_string())))?;
+		Ok(SystemFrameInfo { diff_map: Some(diff_map), ..frame })
+	}
+}
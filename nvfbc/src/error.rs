@@ -1,5 +1,7 @@
 use std::fmt;
 
+use crate::{Box, Size};
+
 #[derive(Debug)]
 pub struct Error {
 	code: u32,
@@ -10,6 +12,16 @@ impl Error {
 	pub fn new(code: u32, message: Option<String>) -> Self {
 		Error { code, message }
 	}
+
+	/// The NvFBC status code of this error.
+	pub fn code(&self) -> u32 {
+		self.code
+	}
+
+	/// The last error message reported by NvFBC, if any.
+	pub fn message(&self) -> Option<&str> {
+		self.message.as_deref()
+	}
 }
 
 impl fmt::Display for Error {
@@ -46,3 +58,64 @@ impl fmt::Display for Error {
 }
 
 impl std::error::Error for Error {}
+
+/// Reasons why the status reported by NvFBC is rejected as invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatusError {
+	/// More outputs are reported than NvFBC supports.
+	TooManyOutputs {
+		/// The number of outputs that was reported.
+		count: u32,
+		/// The maximum number of outputs, `NVFBC_OUTPUT_MAX`.
+		max: u32,
+	},
+	/// The region tracked by an output does not fit within the X screen.
+	TrackedBoxOutOfBounds {
+		/// Identifier of the RandR output.
+		output_id: u32,
+		/// The region tracked by the output.
+		tracked_box: Box,
+		/// Size of the X screen.
+		screen_size: Size,
+	},
+}
+
+impl fmt::Display for StatusError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			StatusError::TooManyOutputs { count, max } => {
+				write!(f, "NvFBC reported {} outputs, but at most {} are supported", count, max)
+			},
+			StatusError::TrackedBoxOutOfBounds { output_id, tracked_box, screen_size } => write!(
+				f,
+				"Output {} tracks {}x{}+{}+{}, which does not fit within the {}x{} screen",
+				output_id, tracked_box.w, tracked_box.h, tracked_box.x, tracked_box.y, screen_size.w, screen_size.h,
+			),
+		}
+	}
+}
+
+impl std::error::Error for StatusError {}
+
+impl From<StatusError> for Error {
+	fn from(error: StatusError) -> Self {
+		Error::new(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_INTERNAL, Some(error.to_string()))
+	}
+}
+
+/// A frame buffer does not have the size its dimensions and format require.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BufferSizeError {
+	/// Size in bytes required by the dimensions and format.
+	pub expected: usize,
+	/// Actual size of the buffer in bytes.
+	pub actual: usize,
+}
+
+impl fmt::Display for BufferSizeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "Expected a buffer of {} bytes, but got {} bytes", self.expected, self.actual)
+	}
+}
+
+impl std::error::Error for BufferSizeError {}
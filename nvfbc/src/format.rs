@@ -0,0 +1,131 @@
+use crate::{Box, BufferFormat};
+
+/// Describes where one plane of a frame is stored in its buffer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Plane {
+	/// Offset of the first byte of the plane in the buffer.
+	pub offset: usize,
+	/// Number of samples in a row.
+	pub width: u32,
+	/// Number of rows.
+	pub height: u32,
+	/// Number of bytes of a single sample, e.g. 2 for the interleaved UV plane of NV12.
+	pub bytes_per_sample: usize,
+	/// Number of pixels in a row and in a column covered by a single sample, e.g. 2 for the UV plane of NV12.
+	pub subsampling: u32,
+}
+
+impl Plane {
+	/// Number of bytes in a row of this plane.
+	pub fn stride(&self) -> usize {
+		self.width as usize * self.bytes_per_sample
+	}
+
+	/// Number of bytes in this plane.
+	pub fn len(&self) -> usize {
+		self.stride() * self.height as usize
+	}
+
+	/// Whether this plane contains no samples.
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Range of bytes in the buffer covered by this plane.
+	pub fn range(&self) -> std::ops::Range<usize> {
+		self.offset..self.offset + self.len()
+	}
+
+	/// The samples in a row or column of this plane whose first pixel is in `pixels`.
+	///
+	/// Splitting the pixels of a frame in ranges splits the samples of every plane in the same way,
+	/// without samples that are in several ranges or in none.
+	pub fn samples(&self, pixels: std::ops::Range<u32>) -> std::ops::Range<u32> {
+		pixels.start.div_ceil(self.subsampling)..pixels.end.div_ceil(self.subsampling)
+	}
+}
+
+impl BufferFormat {
+	/// All buffer formats.
+	pub const ALL: [BufferFormat; 6] = [
+		BufferFormat::Argb,
+		BufferFormat::Rgb,
+		BufferFormat::Nv12,
+		BufferFormat::Yuv444p,
+		BufferFormat::Rgba,
+		BufferFormat::Bgra,
+	];
+
+	/// Number of bytes per pixel for packed formats, or `None` for planar formats.
+	pub fn bytes_per_pixel(&self) -> Option<usize> {
+		match self {
+			BufferFormat::Argb | BufferFormat::Rgba | BufferFormat::Bgra => Some(4),
+			BufferFormat::Rgb => Some(3),
+			BufferFormat::Nv12 | BufferFormat::Yuv444p => None,
+		}
+	}
+
+	/// Whether this format stores luma and chroma instead of RGB values.
+	pub fn is_yuv(&self) -> bool {
+		matches!(self, BufferFormat::Nv12 | BufferFormat::Yuv444p)
+	}
+
+	/// The planes of a frame of `width` x `height` pixels in this format.
+	///
+	/// Packed formats have a single plane. NV12 has a luma plane followed by an interleaved chroma plane
+	/// subsampled by two in both directions. YUV444P has a Y, U and V plane of the same size.
+	pub fn planes(&self, width: u32, height: u32) -> Vec<Plane> {
+		let luma = Plane { offset: 0, width, height, bytes_per_sample: 1, subsampling: 1 };
+		match self {
+			BufferFormat::Nv12 => vec![
+				luma,
+				Plane { offset: luma.len(), width: width.div_ceil(2), height: height.div_ceil(2), bytes_per_sample: 2, subsampling: 2 },
+			],
+			BufferFormat::Yuv444p => vec![
+				luma,
+				Plane { offset: luma.len(), ..luma },
+				Plane { offset: 2 * luma.len(), ..luma },
+			],
+			format => vec![Plane { bytes_per_sample: format.bytes_per_pixel().unwrap_or(1), ..luma }],
+		}
+	}
+
+	/// `region` of a frame of `width` x `height` pixels, extended to the pixels of every sample that covers it, and clipped to the frame.
+	///
+	/// Changing the pixels of `region` may change every pixel of the returned region, through their shared chroma samples.
+	pub fn sample_region(&self, region: Box, width: u32, height: u32) -> Box {
+		let subsampling = self.planes(width, height).iter().map(|plane| plane.subsampling).max().unwrap_or(1);
+		let align = |start: u32, len: u32, size: u32| {
+			let end = start.saturating_add(len).div_ceil(subsampling).saturating_mul(subsampling).min(size);
+			let start = (start / subsampling * subsampling).min(end);
+			(start, end - start)
+		};
+		let (x, w) = align(region.x, region.w, width);
+		let (y, h) = align(region.y, region.h, height);
+		Box { x, y, w, h }
+	}
+
+	/// Number of bytes of a frame of `width` x `height` pixels in this format.
+	pub fn frame_size(&self, width: u32, height: u32) -> usize {
+		self.planes(width, height).iter().map(Plane::len).sum()
+	}
+
+	/// Byte offsets of the red, green and blue channel within a pixel, for packed formats.
+	pub fn rgb_offsets(&self) -> Option<[usize; 3]> {
+		match self {
+			BufferFormat::Argb => Some([1, 2, 3]),
+			BufferFormat::Rgb | BufferFormat::Rgba => Some([0, 1, 2]),
+			BufferFormat::Bgra => Some([2, 1, 0]),
+			BufferFormat::Nv12 | BufferFormat::Yuv444p => None,
+		}
+	}
+
+	/// Byte offset of the alpha channel within a pixel, for packed formats that have one.
+	pub fn alpha_offset(&self) -> Option<usize> {
+		match self {
+			BufferFormat::Argb => Some(0),
+			BufferFormat::Rgba | BufferFormat::Bgra => Some(3),
+			_ => None,
+		}
+	}
+}
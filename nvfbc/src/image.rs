@@ -0,0 +1,88 @@
+//! Conversions of frames to images of the `image` crate.
+//!
+//! This module requires the `image` feature.
+
+use ::image::{DynamicImage, ImageBuffer, Pixel, Rgb, Rgba};
+
+use crate::convert::{check_size, to_rgb};
+use crate::recording::RecordedFrame;
+use crate::system::SystemFrameInfo;
+use crate::{BufferFormat, BufferSizeError};
+
+/// Wrap `data` in an image of `width` x `height` pixels, or fail if it is too small.
+fn image_buffer<P: Pixel<Subpixel = u8>, C: std::ops::Deref<Target = [u8]>>(width: u32, height: u32, data: C) -> Result<ImageBuffer<P, C>, BufferSizeError> {
+	let expected = width as usize * height as usize * P::CHANNEL_COUNT as usize;
+	let actual = data.len();
+	ImageBuffer::from_raw(width, height, data).ok_or(BufferSizeError { expected, actual })
+}
+
+/// Convert a frame of `width` x `height` pixels in `format` to an RGB image, or an RGBA image for formats with an alpha channel.
+///
+/// This converts frames that were copied out of a capturer, such as frames copied from CUDA to host memory.
+pub fn to_image(buffer: &[u8], width: u32, height: u32, format: BufferFormat) -> Result<DynamicImage, BufferSizeError> {
+	check_size(buffer, width, height, format)?;
+	match (format.bytes_per_pixel(), format.rgb_offsets(), format.alpha_offset()) {
+		(Some(bytes_per_pixel), Some([r, g, b]), Some(a)) => {
+			let rgba = buffer.chunks_exact(bytes_per_pixel).flat_map(|pixel| [pixel[r], pixel[g], pixel[b], pixel[a]]).collect();
+			Ok(DynamicImage::ImageRgba8(image_buffer(width, height, rgba)?))
+		},
+		_ => Ok(DynamicImage::ImageRgb8(image_buffer(width, height, to_rgb(buffer, width, height, format)?)?)),
+	}
+}
+
+impl<'a> SystemFrameInfo<'a> {
+	/// Convert the frame to an RGB image, or an RGBA image for formats with an alpha channel.
+	///
+	/// ```no_run
+	/// use nvfbc::system::CaptureMethod;
+	/// use nvfbc::{BufferFormat, Capture, SessionOptions, SystemCapturer};
+	///
+	/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+	///     let mut capturer = SystemCapturer::new()?;
+	///     capturer.start_with_options(&SessionOptions::new(BufferFormat::Nv12, 30))?;
+	///     let frame = capturer.next_frame(CaptureMethod::Blocking, None)?;
+	///     frame.to_image()?.save("frame.png")?;
+	///     Ok(())
+	/// }
+	/// ```
+	pub fn to_image(&self) -> Result<DynamicImage, BufferSizeError> {
+		to_image(self.buffer, self.width, self.height, self.buffer_format)
+	}
+
+	/// The frame as an RGB image without copying it, or `None` if the format is not [`BufferFormat::Rgb`].
+	#[allow(clippy::type_complexity)]
+	pub fn as_rgb_image(&self) -> Result<Option<ImageBuffer<Rgb<u8>, &'a [u8]>>, BufferSizeError> {
+		self.as_image(BufferFormat::Rgb)
+	}
+
+	/// The frame as an RGBA image without copying it, or `None` if the format is not [`BufferFormat::Rgba`].
+	#[allow(clippy::type_complexity)]
+	pub fn as_rgba_image(&self) -> Result<Option<ImageBuffer<Rgba<u8>, &'a [u8]>>, BufferSizeError> {
+		self.as_image(BufferFormat::Rgba)
+	}
+
+	fn as_image<P: Pixel<Subpixel = u8>>(&self, format: BufferFormat) -> Result<Option<ImageBuffer<P, &'a [u8]>>, BufferSizeError> {
+		if self.buffer_format != format {
+			return Ok(None);
+		}
+		check_size(self.buffer, self.width, self.height, format)?;
+		image_buffer(self.width, self.height, self.buffer).map(Some)
+	}
+}
+
+impl RecordedFrame {
+	/// Convert the frame to an RGB image, or an RGBA image for formats with an alpha channel.
+	///
+	/// The frame data is reused for frames in [`BufferFormat::Rgb`] and [`BufferFormat::Rgba`].
+	pub fn into_image(self) -> Result<DynamicImage, BufferSizeError> {
+		let (width, height) = (self.info.width, self.info.height);
+		match self.buffer_format {
+			BufferFormat::Rgb | BufferFormat::Rgba => check_size(&self.data, width, height, self.buffer_format)?,
+			_ => return self.frame_info().to_image(),
+		}
+		match self.buffer_format {
+			BufferFormat::Rgba => Ok(DynamicImage::ImageRgba8(image_buffer(width, height, self.data)?)),
+			_ => Ok(DynamicImage::ImageRgb8(image_buffer(width, height, self.data)?)),
+		}
+	}
+}
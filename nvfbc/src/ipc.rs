@@ -0,0 +1,437 @@
+//! Serving capture sessions to other processes over a Unix domain socket.
+//!
+//! This module requires the `ipc` feature.
+//!
+//! [`IpcServer`] speaks a small binary protocol, so that programs that cannot link Rust can capture frames
+//! without loading NvFBC themselves. Every client can query the status and subscribe to its own capture session.
+//! Pixel data is either sent inline after every frame header, or in a sealed memfd passed along with the header,
+//! which the client can map without copying the frame through the socket.
+//!
+//! # Protocol
+//! All integers are little endian. Every message in either direction starts with its type as `u8`
+//! and the size of its body in bytes as `u32`, followed by the body.
+//! The status and the session options are encoded as in [recordings](crate::recording#file-format).
+//! Buffer formats are encoded as `u32`: 0 for ARGB, 1 for RGB, 2 for NV12, 3 for YUV444P, 4 for RGBA and 5 for BGRA.
+//!
+//! Requests sent by the client:
+//!
+//! | Type | Request | Body |
+//! | --- | --- | --- |
+//! | 1 | Get status | empty |
+//! | 2 | Subscribe | session options, followed by the transfer as `u8`: 0 for inline and 1 for memfd |
+//! | 3 | Unsubscribe | empty |
+//!
+//! Messages sent by the server:
+//!
+//! | Type | Message | Body |
+//! | --- | --- | --- |
+//! | 129 | Status | status |
+//! | 130 | Subscribed | empty |
+//! | 131 | Unsubscribed | empty |
+//! | 132 | Frame | frame header, followed by the pixel data if it is sent inline |
+//! | 255 | Error | NvFBC status code as `u32`, followed by a UTF-8 message |
+//!
+//! The frame header contains the buffer format, width, height and current frame as `u32`, `is_new_frame` as `u8`,
+//! the timestamp in microseconds as `u64`, the missed frames and the size of the pixel data as `u32`,
+//! and the transfer as `u8`. With the memfd transfer, the file descriptor is attached to the first byte of the message
+//! as `SCM_RIGHTS` ancillary data. It is sealed against writing and resizing and the client must close it.
+//!
+//! Every request is answered with a single message, except that frames are sent after the subscribed message
+//! until the client unsubscribes. Subscribing again replaces the session. If capturing fails,
+//! an error is sent instead of a frame and the client has to subscribe again.
+//! Only new frames are sent, so no frames are sent while the screen does not change.
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::fs::FileExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, TryRecvError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::recording::{decode_options, encode_status};
+use crate::system::{CaptureMethod, SystemFrameInfo};
+use crate::{Capture, Error, SessionOptions};
+
+/// Request for the status.
+pub const GET_STATUS: u8 = 1;
+/// Request to subscribe to a capture session.
+pub const SUBSCRIBE: u8 = 2;
+/// Request to stop the capture session.
+pub const UNSUBSCRIBE: u8 = 3;
+/// Message with the status.
+pub const STATUS: u8 = 129;
+/// Message confirming that the capture session started.
+pub const SUBSCRIBED: u8 = 130;
+/// Message confirming that the capture session stopped.
+pub const UNSUBSCRIBED: u8 = 131;
+/// Message with a frame.
+pub const FRAME: u8 = 132;
+/// Message with an error.
+pub const ERROR: u8 = 255;
+
+/// Size of the type and body size of a message.
+pub const MESSAGE_HEADER_SIZE: usize = 5;
+
+/// Size of the body of a frame message without pixel data.
+pub const FRAME_HEADER_SIZE: usize = 34;
+
+/// Maximum size of the body of a request.
+const MAX_REQUEST_SIZE: u32 = 4096;
+
+/// How often idle connections check whether the server is stopping.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How pixel data is sent to a client.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Transfer {
+	/// In the frame message, after the frame header.
+	Inline = 0,
+	/// In a sealed memfd passed with the frame message.
+	Memfd = 1,
+}
+
+enum Request {
+	GetStatus,
+	Subscribe(SessionOptions, Transfer),
+	Unsubscribe,
+	/// A request that could not be decoded, with the reason.
+	Invalid(String),
+}
+
+/// Serves capture sessions over a Unix domain socket.
+///
+/// Dropping the server stops all capture sessions and removes the socket.
+pub struct IpcServer {
+	path: PathBuf,
+	shared: Arc<Shared>,
+	accept_thread: Option<JoinHandle<()>>,
+}
+
+#[derive(Default)]
+struct Shared {
+	stop: AtomicBool,
+	clients: AtomicUsize,
+}
+
+impl IpcServer {
+	/// Listen on a Unix domain socket at `path` and serve capture sessions from capturers created by `create`.
+	///
+	/// Every client gets its own capturer, which is created on the thread of the connection because capturers such as
+	/// [`SystemCapturer`](crate::SystemCapturer) cannot be sent to other threads.
+	/// Fails if a file already exists at `path`.
+	pub fn bind<C, F>(path: impl AsRef<Path>, create: F) -> io::Result<Self>
+	where
+		C: Capture,
+		F: Fn() -> Result<C, Error> + Send + Sync + 'static,
+	{
+		let path = path.as_ref().to_path_buf();
+		let listener = UnixListener::bind(&path)?;
+		let shared = Arc::new(Shared::default());
+		let create = Arc::new(create);
+
+		let accept_thread = std::thread::Builder::new()
+			.name("nvfbc-ipc".to_string())
+			.spawn({
+				let shared = shared.clone();
+				move || {
+					for stream in listener.incoming() {
+						if shared.stop.load(Ordering::Relaxed) {
+							break;
+						}
+						let Ok(stream) = stream else {
+							continue;
+						};
+						let (shared, create) = (shared.clone(), create.clone());
+						let _ = std::thread::Builder::new()
+							.name("nvfbc-ipc-client".to_string())
+							.spawn(move || {
+								shared.clients.fetch_add(1, Ordering::Relaxed);
+								// Errors only affect this client.
+								let _ = serve(stream, &*create, &shared);
+								shared.clients.fetch_sub(1, Ordering::Relaxed);
+							});
+					}
+				}
+			});
+		let accept_thread = match accept_thread {
+			Ok(accept_thread) => accept_thread,
+			Err(e) => {
+				let _ = std::fs::remove_file(&path);
+				return Err(e);
+			},
+		};
+
+		Ok(Self { path, shared, accept_thread: Some(accept_thread) })
+	}
+
+	/// The path of the socket.
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Number of connected clients.
+	pub fn clients(&self) -> usize {
+		self.shared.clients.load(Ordering::Relaxed)
+	}
+}
+
+impl Drop for IpcServer {
+	fn drop(&mut self) {
+		self.shared.stop.store(true, Ordering::Relaxed);
+		// Wake up the accepting thread, which is blocked accepting connections.
+		let _ = UnixStream::connect(&self.path);
+		if let Some(thread) = self.accept_thread.take() {
+			let _ = thread.join();
+		}
+		let _ = std::fs::remove_file(&self.path);
+	}
+}
+
+/// Answer the requests of a single client and send its frames until it disconnects or the server stops.
+fn serve<C: Capture>(mut stream: UnixStream, create: &dyn Fn() -> Result<C, Error>, shared: &Shared) -> io::Result<()> {
+	// Requests are read on a separate thread, so that they can arrive while frames are sent.
+	let mut reader = stream.try_clone()?;
+	let (sender, requests) = mpsc::channel();
+	std::thread::Builder::new()
+		.name("nvfbc-ipc-reader".to_string())
+		.spawn(move || {
+			while let Ok(Some(request)) = read_request(&mut reader) {
+				if sender.send(request).is_err() {
+					break;
+				}
+			}
+		})?;
+
+	let mut capturer = None;
+	// Interval between frames and transfer of the subscribed session.
+	let mut subscription: Option<(Duration, Transfer)> = None;
+	let result = loop {
+		if shared.stop.load(Ordering::Relaxed) {
+			break Ok(());
+		}
+		// Only wait for requests while no frames have to be sent.
+		let request = match subscription {
+			Some(_) => match requests.try_recv() {
+				Ok(request) => Some(request),
+				Err(TryRecvError::Empty) => None,
+				Err(TryRecvError::Disconnected) => break Ok(()),
+			},
+			None => match requests.recv_timeout(POLL_INTERVAL) {
+				Ok(request) => Some(request),
+				Err(RecvTimeoutError::Timeout) => None,
+				Err(RecvTimeoutError::Disconnected) => break Ok(()),
+			},
+		};
+
+		let result = match (request, subscription, capturer.as_mut()) {
+			(Some(request), _, _) => {
+				let (kind, body) = handle(request, &mut capturer, &mut subscription, create);
+				write_message(&mut stream, kind, &body)
+			},
+			(None, Some((interval, transfer)), Some(capturer)) => match capturer.next_frame(CaptureMethod::Blocking, Some(interval)) {
+				Ok(frame) if frame.is_new_frame => send_frame(&mut stream, &frame, transfer),
+				Ok(_) => Ok(()),
+				Err(e) => {
+					subscription = None;
+					let _ = capturer.stop();
+					let (kind, body) = error_message(&e);
+					write_message(&mut stream, kind, &body)
+				},
+			},
+			_ => Ok(()),
+		};
+		if let Err(e) = result {
+			break Err(e);
+		}
+	};
+
+	if let (Some(_), Some(capturer)) = (subscription, capturer.as_mut()) {
+		let _ = capturer.stop();
+	}
+	// Unblock the reading thread.
+	let _ = stream.shutdown(std::net::Shutdown::Both);
+	result
+}
+
+/// Handle a request, returning the type and body of the reply.
+fn handle<C: Capture>(
+	request: Request,
+	capturer: &mut Option<C>,
+	subscription: &mut Option<(Duration, Transfer)>,
+	create: &dyn Fn() -> Result<C, Error>,
+) -> (u8, Vec<u8>) {
+	match request {
+		Request::GetStatus => match get_or_create(capturer, create).and_then(|capturer| capturer.status()) {
+			Ok(status) => {
+				let mut body = Vec::new();
+				encode_status(&mut body, &status);
+				(STATUS, body)
+			},
+			Err(e) => error_message(&e),
+		},
+		Request::Subscribe(options, transfer) => {
+			let capturer = match get_or_create(capturer, create) {
+				Ok(capturer) => capturer,
+				Err(e) => return error_message(&e),
+			};
+			if subscription.take().is_some() {
+				let _ = capturer.stop();
+			}
+			match capturer.start_with_options(&options) {
+				Ok(()) => {
+					*subscription = Some((Duration::from_secs(1) / options.fps.max(1), transfer));
+					(SUBSCRIBED, Vec::new())
+				},
+				Err(e) => error_message(&e),
+			}
+		},
+		Request::Unsubscribe => {
+			if let (Some(_), Some(capturer)) = (subscription.take(), capturer.as_mut()) {
+				let _ = capturer.stop();
+			}
+			(UNSUBSCRIBED, Vec::new())
+		},
+		Request::Invalid(message) => error_message(&Error::new(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_INVALID_PARAM, Some(message))),
+	}
+}
+
+/// The capturer of a client, which is created when it is first needed.
+fn get_or_create<'a, C>(capturer: &'a mut Option<C>, create: &dyn Fn() -> Result<C, Error>) -> Result<&'a mut C, Error> {
+	if capturer.is_none() {
+		*capturer = Some(create()?);
+	}
+	Ok(capturer.as_mut().expect("capturer was created"))
+}
+
+/// Read a request, or return `None` when the client closed the connection.
+fn read_request(reader: &mut impl Read) -> io::Result<Option<Request>> {
+	let mut header = [0; MESSAGE_HEADER_SIZE];
+	match reader.read_exact(&mut header) {
+		Ok(()) => {},
+		Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+		Err(e) => return Err(e),
+	}
+	let len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]);
+	if len > MAX_REQUEST_SIZE {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, format!("request of {} bytes", len)));
+	}
+	let mut body = vec![0; len as usize];
+	reader.read_exact(&mut body)?;
+
+	let request = match header[0] {
+		GET_STATUS => Request::GetStatus,
+		UNSUBSCRIBE => Request::Unsubscribe,
+		SUBSCRIBE => {
+			let mut body = &body[..];
+			let transfer = decode_options(&mut body).and_then(|options| {
+				let mut transfer = [0];
+				body.read_exact(&mut transfer)?;
+				Ok((options, transfer[0]))
+			});
+			match transfer {
+				Ok((options, 0)) => Request::Subscribe(options, Transfer::Inline),
+				Ok((options, 1)) => Request::Subscribe(options, Transfer::Memfd),
+				Ok((_, transfer)) => Request::Invalid(format!("unknown transfer {}", transfer)),
+				Err(e) => Request::Invalid(format!("invalid subscribe request: {}", e)),
+			}
+		},
+		kind => Request::Invalid(format!("unknown request {}", kind)),
+	};
+	Ok(Some(request))
+}
+
+/// The type and body of an error message.
+fn error_message(error: &Error) -> (u8, Vec<u8>) {
+	let mut body = error.code().to_le_bytes().to_vec();
+	body.extend_from_slice(error.to_string().as_bytes());
+	(ERROR, body)
+}
+
+fn message_header(kind: u8, len: usize) -> io::Result<Vec<u8>> {
+	let len = u32::try_from(len).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "message is too large"))?;
+	let mut message = Vec::with_capacity(MESSAGE_HEADER_SIZE + len as usize);
+	message.push(kind);
+	message.extend_from_slice(&len.to_le_bytes());
+	Ok(message)
+}
+
+fn write_message(stream: &mut UnixStream, kind: u8, body: &[u8]) -> io::Result<()> {
+	let mut message = message_header(kind, body.len())?;
+	message.extend_from_slice(body);
+	stream.write_all(&message)
+}
+
+/// Send a frame header, followed by the pixel data or with the pixel data in a memfd.
+fn send_frame(stream: &mut UnixStream, frame: &SystemFrameInfo, transfer: Transfer) -> io::Result<()> {
+	let inline = match transfer {
+		Transfer::Inline => frame.buffer.len(),
+		Transfer::Memfd => 0,
+	};
+	let byte_size = u32::try_from(frame.buffer.len()).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame is too large"))?;
+	let mut message = message_header(FRAME, FRAME_HEADER_SIZE + inline)?;
+	for value in [frame.buffer_format as u32, frame.width, frame.height, frame.current_frame] {
+		message.extend_from_slice(&value.to_le_bytes());
+	}
+	message.push(frame.is_new_frame as u8);
+	message.extend_from_slice(&frame.timestamp_us.to_le_bytes());
+	message.extend_from_slice(&frame.missed_frames.to_le_bytes());
+	message.extend_from_slice(&byte_size.to_le_bytes());
+	message.push(transfer as u8);
+
+	match transfer {
+		Transfer::Inline => {
+			message.extend_from_slice(frame.buffer);
+			stream.write_all(&message)
+		},
+		Transfer::Memfd => send_with_fd(stream, &message, &sealed_memfd(frame.buffer)?),
+	}
+}
+
+/// A memfd holding `data` that can no longer be modified.
+fn sealed_memfd(data: &[u8]) -> io::Result<File> {
+	let fd = unsafe { libc::memfd_create(c"nvfbc-frame".as_ptr(), libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING) };
+	if fd < 0 {
+		return Err(io::Error::last_os_error());
+	}
+	let file = File::from(unsafe { OwnedFd::from_raw_fd(fd) });
+	// Keep the file offset, which is shared with the client, at the start of the data.
+	file.write_all_at(data, 0)?;
+	let seals = libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE | libc::F_SEAL_SEAL;
+	if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_ADD_SEALS, seals) } < 0 {
+		return Err(io::Error::last_os_error());
+	}
+	Ok(file)
+}
+
+/// Write `data` with `file` attached as `SCM_RIGHTS` ancillary data.
+fn send_with_fd(stream: &mut UnixStream, data: &[u8], file: &File) -> io::Result<()> {
+	let fd = file.as_raw_fd();
+	let mut iov = libc::iovec { iov_base: data.as_ptr() as *mut libc::c_void, iov_len: data.len() };
+	let space = unsafe { libc::CMSG_SPACE(std::mem::size_of_val(&fd) as u32) } as usize;
+	// Aligned for the `cmsghdr`.
+	let mut control = vec![0u64; space.div_ceil(8)];
+
+	let mut header: libc::msghdr = unsafe { std::mem::zeroed() };
+	header.msg_iov = &mut iov;
+	header.msg_iovlen = 1;
+	header.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+	header.msg_controllen = space as _;
+	let sent = unsafe {
+		let cmsg = libc::CMSG_FIRSTHDR(&header);
+		(*cmsg).cmsg_level = libc::SOL_SOCKET;
+		(*cmsg).cmsg_type = libc::SCM_RIGHTS;
+		(*cmsg).cmsg_len = libc::CMSG_LEN(std::mem::size_of_val(&fd) as u32) as _;
+		std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut libc::c_int, fd);
+		libc::sendmsg(stream.as_raw_fd(), &header, libc::MSG_NOSIGNAL)
+	};
+	if sent < 0 {
+		return Err(io::Error::last_os_error());
+	}
+	// The file descriptor was sent with the first byte, the rest is written as usual.
+	stream.write_all(&data[sent as usize..])
+}
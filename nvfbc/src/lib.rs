@@ -9,11 +9,113 @@
 //! # Supported capture types
 //! Currently only CUDA and system (RAM) capture types are supported.
 //!
+//! # Multiple sessions
+//! A process can have at most ten NvFBC clients at the same time.
+//! [`SessionManager`] owns multiple system capture sessions, for example one per RandR output,
+//! and reuses their handles so that this limit is not exceeded.
+//!
+//! # Watching for changes
+//! [`StatusWatcher`] polls the status of NvFBC and reports changes such as outputs being
+//! connected or disconnected, screen resizes and modesets as [`watcher::StatusEvent`]s.
+//!
+//! # Serialization
+//! With the `serde` feature enabled, [`Status`], [`Output`], [`Box`], [`Size`], [`BufferFormat`], [`CaptureType`],
+//! [`FrameGrabInfo`], [`Tracking`] and [`SessionOptions`] implement `Serialize` and `Deserialize`.
+//! Fields are named exactly like their Rust counterparts and enum variants are written in snake_case
+//! (e.g. `"yuv444p"`, `"to_system"`, `{ "output": 442 }`).
+//! These names are part of the stable API of this crate.
+//! When deserializing [`SessionOptions`] only `buffer_format` and `fps` are required,
+//! which allows loading session configurations from config files.
+//!
+//! # Writing video
+//! [`video::VideoWriter`] writes NV12 or YUV444P frames as a Y4M or raw planar YUV stream,
+//! which can be piped into tools such as ffmpeg and x264. Frames in RGB formats are converted.
+//!
+//! # Statistics
+//! [`stats::StatsCapturer`] wraps any [`Capture`] source and tracks grab latency, delivered fps,
+//! the ratio of new frames, missed frames, timeouts and errors, which can be polled as a [`stats::StatsSnapshot`].
+//!
+//! # Tracing
+//! With the `tracing` feature enabled, every NvFBC call is wrapped in a debug span named after the NvFBC function,
+//! such as `NvFBCToSysGrabFrame`. Spans record the parameters of the call, the returned status code,
+//! the error message of NvFBC and, for grabs, the grab info. Failed calls also emit a warning event.
+//!
+//! # Metrics
+//! With the `metrics` feature enabled, [`metrics::MetricsServer`] serves Prometheus metrics of capture sessions,
+//! such as captured and missed frames, grab latency and session recreations, on a `/metrics` HTTP endpoint.
+//! It uses a plain thread and does not depend on an async runtime.
+//!
+//! # Preview
+//! With the `preview` feature enabled, [`preview::PreviewServer`] captures RGB frames in the background and serves them
+//! as an MJPEG stream that can be opened in a browser, together with a PNG snapshot and the current status as JSON.
+//! Frames are encoded on the CPU.
+//!
+//! # VNC server
+//! With the `rfb` feature enabled, [`rfb::RfbServer`] serves frames to VNC viewers over RFB 3.8.
+//! Updates only contain the regions that changed according to the NvFBC diff map, see [`diff_map::DiffMap`],
+//! and are sent with the Raw or ZRLE encoding. Viewers are view-only.
+//!
+//! # RTP streaming
+//! With the `rtp` feature enabled, [`rtp::Packetizer`] splits RGB, RGBA and YUV444P frames into RFC 4175 RTP packets
+//! timestamped with the 90 kHz clock derived from the grab timestamps.
+//! [`rtp::RtspServer`] streams them over UDP to clients that set up and play the stream over RTSP.
+//!
+//! # Sharing frames between processes
+//! With the `shm` feature enabled, [`shm::FramePublisher`] copies frames into a ring of slots in a memfd,
+//! so that several processes can consume them through [`shm::FrameSubscriber`] while only one NvFBC session is open.
+//! Subscribers map the ring read-only and read consistent frames without locks.
+//!
+//! # Serving other processes
+//! With the `ipc` feature enabled, [`ipc::IpcServer`] serves capture sessions over a Unix domain socket with a small,
+//! documented binary protocol, for clients that cannot link Rust. Clients query the status, subscribe to a session
+//! and receive frames with their pixel data inline or in a passed memfd.
+//!
+//! # Privacy masking
+//! [`mask::MaskedCapturer`] blacks out, pixelates or blurs regions of the screen in every frame of another capturer,
+//! before the frames reach recordings or servers. Regions are given in screen coordinates and follow the tracked
+//! output, capture box and frame size of the session.
+//!
+//! # Text overlay
+//! [`overlay::OverlayCapturer`] burns the wall-clock time, frame id, grab timestamp, missed frames and free text into
+//! every frame of another capturer with a built-in bitmap font, with a configurable position, color and background box.
+//!
+//! # Deduplication
+//! [`dedup::Deduplicator`] hashes frames in tiles to detect frames with the same pixels, independent of `is_new_frame` and
+//! `current_frame`. Optionally, frames in which at most a given number of pixels changed are reported as near-duplicates.
+//!
+//! # Diff maps on the CPU
+//! [`diff_map::TileDiffer`] compares frames with the previous frame in blocks, producing diff maps laid out like the ones
+//! generated by NvFBC, for example for frames copied back from CUDA. [`diff_map::DiffMapCapturer`] adds them to the frames
+//! of capturers that do not generate diff maps, so consumers handle both the same way.
+//!
+//! # Resizing
+//! [`resample`] resizes frames of every buffer format on the CPU with box, bilinear or Lanczos3 filters,
+//! for example to create previews and thumbnails next to a full size recording. Large frames are resized on multiple threads.
+//!
+//! # Images
+//! With the `image` feature enabled, `SystemFrameInfo::to_image` converts frames of every buffer format to an `image::DynamicImage`,
+//! and `as_rgb_image` and `as_rgba_image` borrow RGB and RGBA frames as `image::ImageBuffer`s without copying them.
+//! `to_image` converts frames that were copied out of a capturer, such as CUDA frames copied to host memory.
+//!
+//! # Constant frame rate
+//! [`pacer::Pacer`] maps captured frames onto a fixed clock using their grab timestamps,
+//! duplicating frames when the screen does not change and keeping only the newest of the frames that arrive in bursts.
+//!
+//! # Recording and replay
+//! [`recording::RecordingWriter`] stores frames with their grab info and the session configuration,
+//! optionally compressed with zstd when the `zstd` feature is enabled.
+//! [`recording::ReplayCapturer`] replays a recording through [`Capture`], in real time or as fast as possible,
+//! to reproduce capture issues on machines without an NVIDIA GPU.
+//!
 //! # Example: Saving an image.
+//! This example requires the `image` feature.
 //! ```no_run
 //! use nvfbc::{SystemCapturer, BufferFormat};
 //! use nvfbc::system::CaptureMethod;
 //!
+//! # #[cfg(not(feature = "image"))]
+//! # fn main() {}
+//! # #[cfg(feature = "image")]
 //! fn main() -> Result<(), Box<dyn std::error::Error>> {
 //!     let mut capturer = SystemCapturer::new()?;
 //!
@@ -25,15 +127,10 @@
 //!
 //!     capturer.start(BufferFormat::Rgb, 30)?;
 //!
-//!     let frame_info = capturer.next_frame(CaptureMethod::Blocking)?;
+//!     let frame_info = capturer.next_frame(CaptureMethod::Blocking, None)?;
 //!     println!("{:#?}", frame_info);
 //!
-//!     let image = image::ImageBuffer::<image::Rgb<u8>, &[u8]>::from_raw(
-//!         frame_info.width,
-//!         frame_info.height,
-//!         frame_info.buffer,
-//!     ).unwrap();
-//!     image.save("frame.png")?;
+//!     frame_info.to_image()?.save("frame.png")?;
 //!     println!("Saved frame to 'frame.png'.");
 //!
 //!     capturer.stop()?;
@@ -46,13 +143,50 @@
 //! Support for configuration is currently limited, to keep the code simple and concise.
 //! Future releases will add more configuration options.
 
+mod capture;
 mod common;
+pub mod convert;
 pub mod cuda;
+pub mod dedup;
+pub mod diff_map;
 mod error;
+mod format;
+#[cfg(feature = "image")]
+mod image;
+#[cfg(feature = "ipc")]
+pub mod ipc;
+pub mod manager;
+pub mod mask;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod overlay;
+pub mod pacer;
+#[cfg(feature = "preview")]
+pub mod preview;
+pub mod recording;
+pub mod resample;
+#[cfg(feature = "rfb")]
+pub mod rfb;
+#[cfg(feature = "rtp")]
+pub mod rtp;
+#[cfg(any(feature = "metrics", feature = "preview", feature = "rfb", feature = "rtp"))]
+mod server;
+#[cfg(feature = "shm")]
+pub mod shm;
+pub mod stats;
+pub mod synthetic;
 pub mod system;
 mod types;
+pub mod video;
+pub mod watcher;
 
 pub use types::*;
-pub use error::Error;
+pub use capture::Capture;
+pub use error::{BufferSizeError, Error, StatusError};
+pub use format::Plane;
+#[cfg(feature = "image")]
+pub use image::to_image;
 pub use cuda::CudaCapturer;
+pub use manager::SessionManager;
 pub use system::SystemCapturer;
+pub use watcher::StatusWatcher;
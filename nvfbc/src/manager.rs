@@ -0,0 +1,209 @@
+use std::time::Duration;
+
+use crate::system::{CaptureMethod, SystemFrameInfo};
+use crate::{Error, SessionOptions, Status, SystemCapturer};
+
+/// Maximum number of NvFBC clients that can exist in a single process.
+///
+/// Creating more handles than this makes NvFBC return `NVFBC_ERR_MAX_CLIENTS`.
+pub const MAX_CLIENTS: usize = 10;
+
+/// Identifies a session owned by a [`SessionManager`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SessionId(u32);
+
+impl SessionId {
+	/// Create an identifier from its number, as returned by [`SessionId::to_raw`].
+	pub fn from_raw(id: u32) -> Self {
+		Self(id)
+	}
+
+	/// The number of this identifier, which is unique among the sessions of a manager.
+	pub fn to_raw(self) -> u32 {
+		self.0
+	}
+}
+
+/// A session that is currently capturing.
+#[derive(Debug, Clone)]
+pub struct ActiveSession {
+	/// Identifier of the session.
+	pub id: SessionId,
+	/// Options the session was started with.
+	pub options: SessionOptions,
+}
+
+/// Frames from different sessions whose timestamps lie within the alignment tolerance.
+#[derive(Debug)]
+pub struct FrameGroup<'a> {
+	/// Timestamp of the earliest frame in this group, in microseconds.
+	pub timestamp_us: u64,
+	/// The frames in this group, ordered by timestamp.
+	pub frames: Vec<(SessionId, SystemFrameInfo<'a>)>,
+}
+
+/// A capturer together with the session it is running, if any.
+struct Slot {
+	capturer: SystemCapturer,
+	session: Option<ActiveSession>,
+}
+
+/// Creates and owns multiple system capture sessions.
+///
+/// Every session needs its own NvFBC handle, of which a process can have at most [`MAX_CLIENTS`].
+/// Handles of removed sessions are kept alive and reused for new sessions,
+/// so that adding and removing sessions never leaks handles.
+pub struct SessionManager {
+	slots: Vec<Slot>,
+	limit: usize,
+	next_id: u32,
+	alignment_tolerance: Duration,
+}
+
+impl SessionManager {
+	/// Create a manager that allows up to [`MAX_CLIENTS`] sessions.
+	pub fn new() -> Self {
+		Self::with_limit(MAX_CLIENTS)
+	}
+
+	/// Create a manager that allows up to `limit` sessions.
+	///
+	/// The limit is clamped to [`MAX_CLIENTS`].
+	pub fn with_limit(limit: usize) -> Self {
+		Self {
+			slots: Vec::new(),
+			limit: limit.min(MAX_CLIENTS),
+			next_id: 0,
+			alignment_tolerance: Duration::from_millis(8),
+		}
+	}
+
+	/// Maximum number of sessions this manager will create.
+	pub fn limit(&self) -> usize {
+		self.limit
+	}
+
+	/// Set the maximum difference between frame timestamps for frames to be grouped together.
+	///
+	/// Defaults to 8ms, which is half a frame at 60 fps.
+	pub fn set_alignment_tolerance(&mut self, tolerance: Duration) {
+		self.alignment_tolerance = tolerance;
+	}
+
+	/// Retrieve the status of NVFBC.
+	///
+	/// A temporary handle is created if the manager has no handles yet.
+	pub fn status(&self) -> Result<Status, Error> {
+		match self.slots.first() {
+			Some(slot) => slot.capturer.status(),
+			None => SystemCapturer::new()?.status(),
+		}
+	}
+
+	/// Start a new session configured by `options`.
+	///
+	/// An idle handle is reused if available.
+	/// Returns `NVFBC_ERR_MAX_CLIENTS` if all handles are in use and the limit is reached.
+	pub fn add(&mut self, options: SessionOptions) -> Result<SessionId, Error> {
+		let index = match self.slots.iter().position(|slot| slot.session.is_none()) {
+			Some(index) => index,
+			None if self.slots.len() < self.limit => {
+				self.slots.push(Slot { capturer: SystemCapturer::new()?, session: None });
+				self.slots.len() - 1
+			},
+			None => {
+				return Err(Error::new(
+					nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_MAX_CLIENTS,
+					Some(format!("all {} sessions are in use", self.limit)),
+				));
+			},
+		};
+
+		let slot = &mut self.slots[index];
+		slot.capturer.start_with_options(&options)?;
+
+		let id = SessionId(self.next_id);
+		self.next_id += 1;
+		slot.session = Some(ActiveSession { id, options });
+		Ok(id)
+	}
+
+	/// Stop the session with the given id.
+	///
+	/// The handle of the session is kept to be reused by a later session.
+	/// If the session fails to stop, the handle is destroyed instead.
+	pub fn remove(&mut self, id: SessionId) -> Result<(), Error> {
+		let index = self.slot_index(id)?;
+		let result = self.slots[index].capturer.stop();
+		match result {
+			Ok(()) => self.slots[index].session = None,
+			Err(_) => drop(self.slots.remove(index)),
+		}
+		result
+	}
+
+	/// Sessions that are currently capturing, in the order they were added.
+	pub fn active_sessions(&self) -> Vec<ActiveSession> {
+		let mut sessions: Vec<_> = self.slots.iter().filter_map(|slot| slot.session.clone()).collect();
+		sessions.sort_by_key(|session| session.id);
+		sessions
+	}
+
+	/// Number of handles that are not running a session.
+	pub fn idle_handles(&self) -> usize {
+		self.slots.iter().filter(|slot| slot.session.is_none()).count()
+	}
+
+	/// Grab the next frame of the session with the given id.
+	pub fn next_frame(&mut self, id: SessionId, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<SystemFrameInfo<'_>, Error> {
+		let index = self.slot_index(id)?;
+		self.slots[index].capturer.next_frame(capture_method, timeout)
+	}
+
+	/// Grab the next frame of every active session.
+	///
+	/// The frames are grouped by timestamp: frames whose timestamps are within the alignment tolerance
+	/// of the first frame in a group end up in the same group. Groups are ordered by timestamp.
+	pub fn next_frames(&mut self, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<Vec<FrameGroup<'_>>, Error> {
+		let mut frames = Vec::new();
+		for slot in self.slots.iter_mut() {
+			if let Some(session) = &slot.session {
+				frames.push((session.id, slot.capturer.next_frame(capture_method, timeout)?));
+			}
+		}
+
+		Ok(group_by_timestamp(frames, self.alignment_tolerance))
+	}
+
+	fn slot_index(&self, id: SessionId) -> Result<usize, Error> {
+		self.slots.iter()
+			.position(|slot| slot.session.as_ref().is_some_and(|session| session.id == id))
+			.ok_or_else(|| Error::new(
+				nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_INVALID_PARAM,
+				Some(format!("no active session with id {:?}", id)),
+			))
+	}
+}
+
+impl Default for SessionManager {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Group `frames` of different sessions whose timestamps are within `tolerance` of the first frame in a group.
+///
+/// The frames do not need to be sorted. Groups and the frames in a group are ordered by timestamp, then by session.
+pub fn group_by_timestamp(mut frames: Vec<(SessionId, SystemFrameInfo<'_>)>, tolerance: Duration) -> Vec<FrameGroup<'_>> {
+	frames.sort_by_key(|(id, frame)| (frame.timestamp_us, *id));
+
+	let tolerance = tolerance.as_micros() as u64;
+	let mut groups: Vec<FrameGroup> = Vec::new();
+	for (id, frame) in frames {
+		match groups.last_mut() {
+			Some(group) if frame.timestamp_us - group.timestamp_us <= tolerance => group.frames.push((id, frame)),
+			_ => groups.push(FrameGroup { timestamp_us: frame.timestamp_us, frames: vec![(id, frame)] }),
+		}
+	}
+	groups
+}
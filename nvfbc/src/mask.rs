@@ -0,0 +1,395 @@
+//! Masking regions of the screen in captured frames.
+//!
+//! Regions are given in X screen coordinates, like the tracked boxes of [`Output`](crate::Output)s,
+//! and are translated to every frame through the tracked region, the capture box and the frame scaling of the session.
+//! Which output [`Tracking::Default`] tracks is decided by the driver, so masked sessions must track
+//! [`Tracking::Screen`] or a [`Tracking::Output`].
+//! [`MaskedCapturer`] wraps another capturer and masks every frame before it is returned,
+//! so that masked content never reaches consumers such as recordings or servers.
+//!
+//! ```no_run
+//! use nvfbc::mask::{MaskFill, MaskRegion, MaskedCapturer};
+//! use nvfbc::system::CaptureMethod;
+//! use nvfbc::{BufferFormat, Capture, SessionOptions, SystemCapturer, Tracking};
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let regions = vec![MaskRegion { region: nvfbc::Box { x: 1600, y: 0, w: 320, h: 1080 }, fill: MaskFill::Black }];
+//!     let mut capturer = MaskedCapturer::new(SystemCapturer::new()?, regions);
+//!     let mut options = SessionOptions::new(BufferFormat::Nv12, 30);
+//!     options.tracking = Tracking::Screen;
+//!     capturer.start_with_options(&options)?;
+//!     let frame = capturer.next_frame(CaptureMethod::Blocking, None)?;
+//!     println!("Captured a masked frame of {}x{}", frame.width, frame.height);
+//!     Ok(())
+//! }
+//! ```
+
+use std::time::Duration;
+
+use crate::convert::{check_size, rgb_to_yuv};
+use crate::system::{CaptureMethod, SystemFrameInfo};
+use crate::{Box, BufferFormat, BufferSizeError, Capture, Error, SessionOptions, Size, Status, Tracking};
+
+/// How a masked region is filled.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MaskFill {
+	/// Fill the region with black.
+	Black,
+	/// Replace blocks of `block_size` x `block_size` pixels by their average color.
+	///
+	/// A `block_size` below 2 would leave the region unchanged, so the region is filled with black instead.
+	Pixelate {
+		/// Width and height of a block in frame pixels.
+		block_size: u32,
+	},
+	/// Blur the region, using only pixels within the region.
+	///
+	/// Small radii may leave large text readable, use [`MaskFill::Black`] or [`MaskFill::Pixelate`] for sensitive content.
+	/// A `radius` of 0 would leave the region unchanged, so the region is filled with black instead.
+	Blur {
+		/// Radius of the blur in frame pixels.
+		radius: u32,
+	},
+}
+
+/// A region of the screen to mask.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MaskRegion {
+	/// The region in X screen coordinates.
+	pub region: Box,
+	/// How the region is filled.
+	pub fill: MaskFill,
+}
+
+/// Number of box blur passes, which together approximate a gaussian blur.
+const BLUR_PASSES: usize = 3;
+
+/// The region of the X screen captured by a session with `options`.
+///
+/// Returns `None` if the tracked output does not exist, or for [`Tracking::Default`],
+/// which tracks the primary output, the first connected output or the screen depending on the driver.
+pub fn captured_region(status: &Status, options: &SessionOptions) -> Option<Box> {
+	let tracked = match options.tracking {
+		Tracking::Default => return None,
+		Tracking::Screen => Box { x: 0, y: 0, w: status.screen_size.w, h: status.screen_size.h },
+		Tracking::Output(id) => status.outputs.iter().find(|output| output.id == id)?.tracked_box,
+	};
+	match options.capture_box {
+		// The capture box is relative to the tracked region.
+		Some(capture_box) if capture_box.w != 0 && capture_box.h != 0 => Some(Box {
+			x: tracked.x.saturating_add(capture_box.x),
+			y: tracked.y.saturating_add(capture_box.y),
+			w: capture_box.w,
+			h: capture_box.h,
+		}),
+		_ => Some(tracked),
+	}
+}
+
+/// The pixels of a `width` x `height` frame showing `region` of the screen, if the frame captures `captured`.
+///
+/// Pixels that partially show the region are included. Returns `None` if the region is not visible in the frame.
+pub fn frame_region(region: Box, captured: Box, width: u32, height: u32) -> Option<Box> {
+	let (x0, x1) = scale_span(region.x, region.w, captured.x, captured.w, width)?;
+	let (y0, y1) = scale_span(region.y, region.h, captured.y, captured.h, height)?;
+	Some(Box { x: x0, y: y0, w: x1 - x0, h: y1 - y0 })
+}
+
+/// Map the span of `len` screen pixels at `start` to a span of the `size` frame pixels that show `captured_len` pixels at `captured_start`.
+fn scale_span(start: u32, len: u32, captured_start: u32, captured_len: u32, size: u32) -> Option<(u32, u32)> {
+	if captured_len == 0 {
+		return None;
+	}
+	let (captured_start, captured_len, size) = (captured_start as u64, captured_len as u64, size as u64);
+	let begin = (start as u64).max(captured_start);
+	let end = (start as u64 + len as u64).min(captured_start + captured_len);
+	if end <= begin {
+		return None;
+	}
+	let first = (begin - captured_start) * size / captured_len;
+	let last = ((end - captured_start) * size).div_ceil(captured_len);
+	(last > first).then_some((first as u32, last as u32))
+}
+
+/// Mask `region` of a frame of `width` x `height` pixels in `format`, in frame coordinates.
+///
+/// Planes with subsampled chroma are masked for every sample that covers a pixel of the region,
+/// with a block size and radius of at least one sample.
+pub fn apply_mask(buffer: &mut [u8], width: u32, height: u32, format: BufferFormat, region: Box, fill: MaskFill) -> Result<(), BufferSizeError> {
+	check_size(buffer, width, height, format)?;
+	let fill = match fill {
+		MaskFill::Pixelate { block_size: 0 | 1 } | MaskFill::Blur { radius: 0 } => MaskFill::Black,
+		fill => fill,
+	};
+	let Some(region) = frame_region(region, Box { x: 0, y: 0, w: width, h: height }, width, height) else {
+		return Ok(());
+	};
+
+	for (index, plane) in format.planes(width, height).into_iter().enumerate() {
+		// The region in samples of this plane.
+		let Some(samples) = frame_region(region, Box { x: 0, y: 0, w: width, h: height }, plane.width, plane.height) else {
+			continue;
+		};
+		let scale = |pixels: u32| ((pixels as u64 * plane.width as u64 / width.max(1) as u64) as usize).max(1);
+		let data = &mut buffer[plane.range()];
+		let plane = PlaneRegion {
+			stride: plane.stride(),
+			bytes_per_sample: plane.bytes_per_sample,
+			x: samples.x as usize,
+			y: samples.y as usize,
+			w: samples.w as usize,
+			h: samples.h as usize,
+		};
+		match fill {
+			MaskFill::Black => fill_black(data, &plane, &black_sample(format, index)),
+			MaskFill::Pixelate { block_size } => pixelate(data, &plane, scale(block_size)),
+			MaskFill::Blur { radius } => blur(data, &plane, scale(radius)),
+		}
+	}
+	Ok(())
+}
+
+/// A rectangle of samples within a plane.
+struct PlaneRegion {
+	stride: usize,
+	bytes_per_sample: usize,
+	x: usize,
+	y: usize,
+	w: usize,
+	h: usize,
+}
+
+impl PlaneRegion {
+	/// Byte offset of the sample in column `x` and row `y` of the rectangle.
+	fn offset(&self, x: usize, y: usize) -> usize {
+		(self.y + y) * self.stride + (self.x + x) * self.bytes_per_sample
+	}
+}
+
+/// The value of every byte of a black sample in plane `index` of `format`, or `None` for bytes that are kept, such as alpha.
+fn black_sample(format: BufferFormat, index: usize) -> Vec<Option<u8>> {
+	let [y, u, v] = rgb_to_yuv([0, 0, 0]);
+	match (format, index) {
+		(BufferFormat::Nv12 | BufferFormat::Yuv444p, 0) => vec![Some(y)],
+		(BufferFormat::Nv12, _) => vec![Some(u), Some(v)],
+		(BufferFormat::Yuv444p, 1) => vec![Some(u)],
+		(BufferFormat::Yuv444p, _) => vec![Some(v)],
+		(format, _) => {
+			let mut sample = vec![Some(0); format.bytes_per_pixel().unwrap_or(1)];
+			if let Some(alpha) = format.alpha_offset() {
+				sample[alpha] = None;
+			}
+			sample
+		},
+	}
+}
+
+fn fill_black(data: &mut [u8], region: &PlaneRegion, sample: &[Option<u8>]) {
+	for y in 0..region.h {
+		for x in 0..region.w {
+			let offset = region.offset(x, y);
+			for (byte, value) in data[offset..offset + region.bytes_per_sample].iter_mut().zip(sample) {
+				if let Some(value) = value {
+					*byte = *value;
+				}
+			}
+		}
+	}
+}
+
+/// Replace blocks of `block_size` x `block_size` samples, starting at the top left of the region, by their average.
+fn pixelate(data: &mut [u8], region: &PlaneRegion, block_size: usize) {
+	for block_y in (0..region.h).step_by(block_size) {
+		for block_x in (0..region.w).step_by(block_size) {
+			let rows = block_y..(block_y + block_size).min(region.h);
+			let columns = block_x..(block_x + block_size).min(region.w);
+			let count = (rows.len() * columns.len()) as u32;
+			for channel in 0..region.bytes_per_sample {
+				let mut sum = 0;
+				for y in rows.clone() {
+					for x in columns.clone() {
+						sum += data[region.offset(x, y) + channel] as u32;
+					}
+				}
+				let average = ((sum + count / 2) / count) as u8;
+				for y in rows.clone() {
+					for x in columns.clone() {
+						data[region.offset(x, y) + channel] = average;
+					}
+				}
+			}
+		}
+	}
+}
+
+/// Blur the region with repeated horizontal and vertical box blurs of `radius` samples.
+fn blur(data: &mut [u8], region: &PlaneRegion, radius: usize) {
+	let bytes_per_sample = region.bytes_per_sample;
+	let row = region.w * bytes_per_sample;
+	let mut values: Vec<u8> = (0..region.h)
+		.flat_map(|y| data[region.offset(0, y)..region.offset(0, y) + row].iter().copied())
+		.collect();
+	let mut temp = values.clone();
+
+	for _ in 0..BLUR_PASSES {
+		for y in 0..region.h {
+			for channel in 0..bytes_per_sample {
+				let start = y * row + channel;
+				box_blur(&values[start..], &mut temp[start..], region.w, bytes_per_sample, radius);
+			}
+		}
+		for x in 0..region.w {
+			for channel in 0..bytes_per_sample {
+				let start = x * bytes_per_sample + channel;
+				box_blur(&temp[start..], &mut values[start..], region.h, row, radius);
+			}
+		}
+	}
+
+	for y in 0..region.h {
+		let offset = region.offset(0, y);
+		data[offset..offset + row].copy_from_slice(&values[y * row..(y + 1) * row]);
+	}
+}
+
+/// Average every `step`th value of `source` over a window of `radius` values on both sides, repeating the values at the edges.
+fn box_blur(source: &[u8], destination: &mut [u8], count: usize, step: usize, radius: usize) {
+	let last = count as isize - 1;
+	let value = |i: isize| source[i.clamp(0, last) as usize * step] as u32;
+	let (radius, window) = (radius as isize, 2 * radius as u32 + 1);
+	let mut sum: u32 = (-radius..=radius).map(value).sum();
+	for i in 0..count {
+		destination[i * step] = ((sum + window / 2) / window) as u8;
+		sum += value(i as isize + radius + 1);
+		sum -= value(i as isize - radius);
+	}
+}
+
+/// Masks regions of the screen in the frames of another capturer.
+///
+/// The captured region is determined from the status of the capturer when the session starts,
+/// sessions with [`Tracking::Default`] are rejected because the region it tracks is not known.
+/// If the tracked output moves or changes resolution, the session must be restarted to keep masking the right regions,
+/// see [`StatusWatcher`](crate::watcher::StatusWatcher).
+/// Frames of another size than the session started with are not returned, [`Capture::next_frame`] fails with
+/// `ERR_MUST_RECREATE` instead.
+///
+/// Diff maps of the frames mark pixelated and blurred regions as changed when any of their pixels changed,
+/// and every block as changed after the regions are replaced.
+pub struct MaskedCapturer<C> {
+	inner: C,
+	regions: Vec<MaskRegion>,
+	/// The region of the screen captured by the running session.
+	captured: Option<Box>,
+	/// Size of the frames of the running session.
+	frame_size: Size,
+	/// The most recent frame, with the masks applied.
+	buffer: Vec<u8>,
+	/// The diff map of the most recent frame, with the changes of the masks.
+	diff_map: Vec<u8>,
+	/// Whether the regions were replaced since the previous frame.
+	regions_changed: bool,
+}
+
+impl<C: Capture> MaskedCapturer<C> {
+	/// Mask `regions` in the frames captured by `inner`.
+	pub fn new(inner: C, regions: Vec<MaskRegion>) -> Self {
+		Self {
+			inner,
+			regions,
+			captured: None,
+			frame_size: Size { w: 0, h: 0 },
+			buffer: Vec::new(),
+			diff_map: Vec::new(),
+			regions_changed: false,
+		}
+	}
+
+	/// The masked regions.
+	pub fn regions(&self) -> &[MaskRegion] {
+		&self.regions
+	}
+
+	/// Replace the masked regions, starting with the next frame.
+	pub fn set_regions(&mut self, regions: Vec<MaskRegion>) {
+		self.regions = regions;
+		self.regions_changed = true;
+	}
+
+	/// The wrapped capturer.
+	pub fn into_inner(self) -> C {
+		self.inner
+	}
+}
+
+impl<C: Capture> Capture for MaskedCapturer<C> {
+	fn status(&self) -> Result<Status, Error> {
+		self.inner.status()
+	}
+
+	fn start_with_options(&mut self, options: &SessionOptions) -> Result<(), Error> {
+		if options.tracking == Tracking::Default {
+			return Err(Error::new(
+				nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_INVALID_PARAM,
+				Some("masking requires tracking the screen or an output".to_string()),
+			));
+		}
+		let status = self.inner.status()?;
+		let captured = captured_region(&status, options).ok_or_else(|| {
+			Error::new(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_INVALID_PARAM, Some("unknown output".to_string()))
+		})?;
+		self.inner.start_with_options(options)?;
+		self.captured = Some(captured);
+		self.frame_size = match options.frame_size {
+			Some(size) if size.w != 0 && size.h != 0 => size,
+			_ => Size { w: captured.w, h: captured.h },
+		};
+		Ok(())
+	}
+
+	fn stop(&mut self) -> Result<(), Error> {
+		self.captured = None;
+		self.inner.stop()
+	}
+
+	fn next_frame(&mut self, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<SystemFrameInfo<'_>, Error> {
+		let frame = self.inner.next_frame(capture_method, timeout)?;
+		let Some(captured) = self.captured else {
+			return Err(Error::new(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_BAD_REQUEST, Some("no capture session was started".to_string())));
+		};
+		if (frame.width, frame.height) != (self.frame_size.w, self.frame_size.h) {
+			// The captured region changed, for example after a modeset, so the masks would cover the wrong pixels.
+			return Err(Error::new(
+				nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_MUST_RECREATE,
+				Some("the frame size changed since the session was started".to_string()),
+			));
+		}
+
+		self.buffer.clear();
+		self.buffer.extend_from_slice(frame.buffer);
+		let mut changed = Vec::new();
+		if std::mem::take(&mut self.regions_changed) {
+			changed.push(Box { x: 0, y: 0, w: frame.width, h: frame.height });
+		}
+		for mask in &self.regions {
+			let Some(region) = frame_region(mask.region, captured, frame.width, frame.height) else {
+				continue;
+			};
+			apply_mask(&mut self.buffer, frame.width, frame.height, frame.buffer_format, region, mask.fill)
+				.map_err(|e| Error::new(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_INTERNAL, Some(e.to_string())))?;
+
+			// A changed pixel changes the pixelated or blurred pixels around it, which may be in unchanged blocks.
+			let region = frame.buffer_format.sample_region(region, frame.width, frame.height);
+			let spreads = matches!(mask.fill, MaskFill::Pixelate { .. } | MaskFill::Blur { .. });
+			if spreads && frame.diff_map.is_some_and(|diff_map| diff_map.any_changed_in(region)) {
+				changed.push(region);
+			}
+		}
+
+		let diff_map = frame.diff_map.map(|diff_map| diff_map.with_changed(&changed, &mut self.diff_map));
+		Ok(SystemFrameInfo { buffer: &self.buffer, diff_map, ..frame })
+	}
+}
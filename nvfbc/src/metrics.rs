@@ -0,0 +1,342 @@
+//! Prometheus metrics for capture sessions, served over HTTP without an async runtime.
+//!
+//! This module requires the `metrics` feature.
+//!
+//! Every session is registered in a [`MetricsRegistry`] under a name, which is exported as the `session` label.
+//! [`MetricsCapturer`] records the metrics of a [`Capture`] source automatically,
+//! and [`MetricsServer`] serves the metrics of all sessions in the Prometheus text format on `/metrics`.
+//!
+//! ```no_run
+//! use nvfbc::metrics::{MetricsCapturer, MetricsRegistry, MetricsServer};
+//! use nvfbc::system::CaptureMethod;
+//! use nvfbc::{BufferFormat, Capture, SessionOptions, SystemCapturer};
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let registry = MetricsRegistry::new();
+//!     let _server = MetricsServer::bind("127.0.0.1:9464", registry.clone())?;
+//!
+//!     let mut capturer = MetricsCapturer::new(SystemCapturer::new()?, registry.session("primary"));
+//!     capturer.start_with_options(&SessionOptions::new(BufferFormat::Nv12, 60))?;
+//!     loop {
+//!         capturer.next_frame(CaptureMethod::Blocking, None)?;
+//!     }
+//! }
+//! ```
+//!
+//! # Exported metrics
+//! | Name | Type | Description |
+//! | --- | --- | --- |
+//! | `nvfbc_grabs_total` | counter | Number of grab calls. |
+//! | `nvfbc_frames_captured_total` | counter | Number of new frames captured. |
+//! | `nvfbc_missed_frames_total` | counter | Number of frames missed between grabs, as reported by NvFBC. |
+//! | `nvfbc_grab_errors_total` | counter | Number of failed grabs, by NvFBC status `code`. |
+//! | `nvfbc_grab_latency_seconds` | histogram | Duration of grab calls. |
+//! | `nvfbc_session_recreations_total` | counter | Number of times the capture session was created again. |
+//! | `nvfbc_frame_width`, `nvfbc_frame_height` | gauge | Size of the last captured frame. |
+//! | `nvfbc_in_modeset` | gauge | 1 if the last known status reported a modeset. |
+//! | `nvfbc_currently_capturing` | gauge | 1 if the last known status reported an active capture session. |
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::common::lock_ignoring_poison;
+use crate::server::{read_http_request, Acceptor, HttpRequest};
+use crate::system::{CaptureMethod, SystemFrameInfo};
+use crate::{Capture, Error, SessionOptions, Status};
+
+/// Upper bounds in seconds of the buckets of the grab latency histogram.
+pub const LATENCY_BUCKETS: [f64; 12] = [0.0005, 0.001, 0.0025, 0.005, 0.0075, 0.01, 0.0167, 0.025, 0.05, 0.1, 0.25, 1.0];
+
+/// Metrics of a single session.
+#[derive(Debug, Clone, Default)]
+struct SessionState {
+	grabs: u64,
+	frames_captured: u64,
+	missed_frames: u64,
+	errors: BTreeMap<u32, u64>,
+	/// Number of grabs per latency bucket, not cumulative. The last entry counts grabs above all bounds.
+	latency_buckets: [u64; LATENCY_BUCKETS.len() + 1],
+	latency_sum: f64,
+	recreations: u64,
+	frame_width: u32,
+	frame_height: u32,
+	in_modeset: bool,
+	currently_capturing: bool,
+}
+
+/// The metrics of all registered sessions.
+///
+/// Cloning a registry returns a handle to the same metrics.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsRegistry {
+	sessions: Arc<Mutex<BTreeMap<String, SessionState>>>,
+}
+
+impl MetricsRegistry {
+	/// Create an empty registry.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Register the session `name`, or return the existing session with that name.
+	pub fn session(&self, name: &str) -> SessionMetrics {
+		self.lock().entry(name.to_string()).or_default();
+		SessionMetrics { registry: self.clone(), name: name.to_string() }
+	}
+
+	/// Stop exporting the metrics of the session `name`.
+	pub fn remove(&self, name: &str) {
+		self.lock().remove(name);
+	}
+
+	/// Names of the registered sessions.
+	pub fn sessions(&self) -> Vec<String> {
+		self.lock().keys().cloned().collect()
+	}
+
+	/// The metrics of all sessions in the Prometheus text exposition format.
+	pub fn render(&self) -> String {
+		let sessions = self.lock();
+		let mut out = String::new();
+
+		let mut family = |name: &str, kind: &str, help: &str, value: &dyn Fn(&SessionState) -> f64| {
+			let _ = writeln!(out, "# HELP {} {}", name, help);
+			let _ = writeln!(out, "# TYPE {} {}", name, kind);
+			for (session, state) in sessions.iter() {
+				let _ = writeln!(out, "{}{{session=\"{}\"}} {}", name, escape(session), value(state));
+			}
+		};
+		family("nvfbc_grabs_total", "counter", "Number of grab calls.", &|s| s.grabs as f64);
+		family("nvfbc_frames_captured_total", "counter", "Number of new frames captured.", &|s| s.frames_captured as f64);
+		family("nvfbc_missed_frames_total", "counter", "Number of frames missed between grabs.", &|s| s.missed_frames as f64);
+		family("nvfbc_session_recreations_total", "counter", "Number of times the capture session was created again.", &|s| s.recreations as f64);
+		family("nvfbc_frame_width", "gauge", "Width of the last captured frame.", &|s| s.frame_width as f64);
+		family("nvfbc_frame_height", "gauge", "Height of the last captured frame.", &|s| s.frame_height as f64);
+		family("nvfbc_in_modeset", "gauge", "Whether the X server is in modeset.", &|s| s.in_modeset as u8 as f64);
+		family("nvfbc_currently_capturing", "gauge", "Whether there is a capture session on this system.", &|s| s.currently_capturing as u8 as f64);
+
+		let _ = writeln!(out, "# HELP nvfbc_grab_errors_total Number of failed grabs by NvFBC status code.");
+		let _ = writeln!(out, "# TYPE nvfbc_grab_errors_total counter");
+		for (session, state) in sessions.iter() {
+			for (code, count) in &state.errors {
+				let _ = writeln!(out, "nvfbc_grab_errors_total{{session=\"{}\",code=\"{}\"}} {}", escape(session), code, count);
+			}
+		}
+
+		let _ = writeln!(out, "# HELP nvfbc_grab_latency_seconds Duration of grab calls.");
+		let _ = writeln!(out, "# TYPE nvfbc_grab_latency_seconds histogram");
+		for (session, state) in sessions.iter() {
+			let session = escape(session);
+			let mut cumulative = 0;
+			for (bound, count) in LATENCY_BUCKETS.iter().zip(&state.latency_buckets) {
+				cumulative += count;
+				let _ = writeln!(out, "nvfbc_grab_latency_seconds_bucket{{session=\"{}\",le=\"{}\"}} {}", session, bound, cumulative);
+			}
+			let _ = writeln!(out, "nvfbc_grab_latency_seconds_bucket{{session=\"{}\",le=\"+Inf\"}} {}", session, state.grabs);
+			let _ = writeln!(out, "nvfbc_grab_latency_seconds_sum{{session=\"{}\"}} {}", session, state.latency_sum);
+			let _ = writeln!(out, "nvfbc_grab_latency_seconds_count{{session=\"{}\"}} {}", session, state.grabs);
+		}
+
+		out
+	}
+
+	fn lock(&self) -> MutexGuard<'_, BTreeMap<String, SessionState>> {
+		lock_ignoring_poison(&self.sessions)
+	}
+}
+
+/// Escape a label value for the Prometheus text format.
+fn escape(value: &str) -> String {
+	value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Records the metrics of a single session in a [`MetricsRegistry`].
+#[derive(Debug, Clone)]
+pub struct SessionMetrics {
+	registry: MetricsRegistry,
+	name: String,
+}
+
+impl SessionMetrics {
+	/// Name of the session, exported as the `session` label.
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	/// Record a grab that took `latency`.
+	pub fn record_grab(&self, latency: Duration, result: Result<&SystemFrameInfo, &Error>) {
+		self.update(|state| {
+			state.grabs += 1;
+			let seconds = latency.as_secs_f64();
+			let bucket = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound).unwrap_or(LATENCY_BUCKETS.len());
+			state.latency_buckets[bucket] += 1;
+			state.latency_sum += seconds;
+
+			match result {
+				Ok(frame) => {
+					state.frames_captured += frame.is_new_frame as u64;
+					state.missed_frames += frame.missed_frames as u64;
+					state.frame_width = frame.width;
+					state.frame_height = frame.height;
+				},
+				Err(error) => *state.errors.entry(error.code()).or_default() += 1,
+			}
+		});
+	}
+
+	/// Record that the capture session was created again, for example after `ERR_MUST_RECREATE`.
+	pub fn record_recreation(&self) {
+		self.update(|state| state.recreations += 1);
+	}
+
+	/// Record the latest status of NvFBC.
+	pub fn set_status(&self, status: &Status) {
+		self.update(|state| {
+			state.in_modeset = status.in_modeset;
+			state.currently_capturing = status.currently_capturing;
+		});
+	}
+
+	fn update(&self, update: impl FnOnce(&mut SessionState)) {
+		// Re-register the session if it was removed from the registry.
+		update(self.registry.lock().entry(self.name.clone()).or_default());
+	}
+}
+
+/// Wraps a [`Capture`] source and records its metrics.
+///
+/// The status is recorded whenever it is retrieved, when a session is started or stopped,
+/// and by the grab following a failed grab. A successful grab records that there is an active capture session
+/// and no modeset. Every session started after the first one counts as a recreation.
+pub struct MetricsCapturer<C> {
+	inner: C,
+	metrics: SessionMetrics,
+	started: bool,
+	/// Set when a grab failed, because the status may have changed, for example by a modeset.
+	status_stale: bool,
+}
+
+impl<C: Capture> MetricsCapturer<C> {
+	/// Record the metrics of `inner` as `metrics`.
+	pub fn new(inner: C, metrics: SessionMetrics) -> Self {
+		Self { inner, metrics, started: false, status_stale: false }
+	}
+
+	/// The metrics of this capturer.
+	pub fn metrics(&self) -> &SessionMetrics {
+		&self.metrics
+	}
+
+	/// The wrapped capturer.
+	pub fn inner(&self) -> &C {
+		&self.inner
+	}
+
+	/// The wrapped capturer.
+	pub fn inner_mut(&mut self) -> &mut C {
+		&mut self.inner
+	}
+
+	/// Return the wrapped capturer.
+	pub fn into_inner(self) -> C {
+		self.inner
+	}
+
+	fn refresh_status(&self) {
+		if let Ok(status) = self.inner.status() {
+			self.metrics.set_status(&status);
+		}
+	}
+}
+
+impl<C: Capture> Capture for MetricsCapturer<C> {
+	fn status(&self) -> Result<Status, Error> {
+		let status = self.inner.status()?;
+		self.metrics.set_status(&status);
+		Ok(status)
+	}
+
+	fn start_with_options(&mut self, options: &SessionOptions) -> Result<(), Error> {
+		self.inner.start_with_options(options)?;
+		if self.started {
+			self.metrics.record_recreation();
+		}
+		self.started = true;
+		self.refresh_status();
+		Ok(())
+	}
+
+	fn stop(&mut self) -> Result<(), Error> {
+		let result = self.inner.stop();
+		self.refresh_status();
+		result
+	}
+
+	fn next_frame(&mut self, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<SystemFrameInfo<'_>, Error> {
+		// The status cannot be retrieved while the frame of a grab is borrowed, so it is refreshed by the next grab.
+		if self.status_stale {
+			self.refresh_status();
+		}
+		let started = Instant::now();
+		let result = self.inner.next_frame(capture_method, timeout);
+		self.metrics.record_grab(started.elapsed(), result.as_ref());
+		self.status_stale = result.is_err();
+		if result.is_ok() {
+			self.metrics.update(|state| {
+				state.in_modeset = false;
+				state.currently_capturing = true;
+			});
+		}
+		result
+	}
+}
+
+/// Serves the metrics of a [`MetricsRegistry`] on `/metrics` from a background thread.
+///
+/// Every connection is served on its own thread. The server stops accepting connections when it is dropped.
+pub struct MetricsServer {
+	acceptor: Acceptor,
+}
+
+impl MetricsServer {
+	/// Listen on `addr` and serve the metrics of `registry`.
+	///
+	/// Use port 0 to let the operating system pick a free port, see [`MetricsServer::local_addr`].
+	pub fn bind(addr: impl ToSocketAddrs, registry: MetricsRegistry) -> io::Result<Self> {
+		let listener = TcpListener::bind(addr)?;
+		let acceptor = Acceptor::spawn("nvfbc-metrics", listener, move |stream| serve(stream, &registry))?;
+		Ok(Self { acceptor })
+	}
+
+	/// The address the server listens on.
+	pub fn local_addr(&self) -> SocketAddr {
+		self.acceptor.local_addr()
+	}
+}
+
+/// Answer a single HTTP request.
+fn serve(mut stream: TcpStream, registry: &MetricsRegistry) -> io::Result<()> {
+	stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+	stream.set_write_timeout(Some(Duration::from_secs(5)))?;
+
+	let HttpRequest { method, path } = read_http_request(&mut stream)?;
+	let (status, content_type, body) = match (method.as_str(), path.as_str()) {
+		("GET" | "HEAD", "/metrics") => ("200 OK", "text/plain; version=0.0.4; charset=utf-8", registry.render()),
+		(_, "/metrics") => ("405 Method Not Allowed", "text/plain; charset=utf-8", "Method not allowed\n".to_string()),
+		_ => ("404 Not Found", "text/plain; charset=utf-8", "Not found\n".to_string()),
+	};
+
+	write!(
+		stream,
+		"HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+		status, content_type, body.len(),
+	)?;
+	if method != "HEAD" {
+		stream.write_all(body.as_bytes())?;
+	}
+	stream.flush()
+}
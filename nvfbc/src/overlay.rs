@@ -0,0 +1,473 @@
+//! Burning text such as timestamps and frame ids into captured frames.
+//!
+//! Text is drawn with a built-in 5x7 bitmap font covering printable ASCII, scaled by an integer factor.
+//! [`OverlayCapturer`] wraps another capturer and draws an [`Overlay`] into every frame it returns.
+//!
+//! ```no_run
+//! use nvfbc::overlay::{Overlay, OverlayCapturer, OverlayField, OverlayPosition};
+//! use nvfbc::system::CaptureMethod;
+//! use nvfbc::{BufferFormat, Capture, SessionOptions, SystemCapturer};
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let mut overlay = Overlay::new(vec![
+//!         OverlayField::Text("qa-host-1".to_string()),
+//!         OverlayField::WallClock,
+//!         OverlayField::FrameId,
+//!     ]);
+//!     overlay.position = OverlayPosition::BottomRight;
+//!     let mut capturer = OverlayCapturer::new(SystemCapturer::new()?, overlay);
+//!     capturer.start_with_options(&SessionOptions::new(BufferFormat::Nv12, 30))?;
+//!     let frame = capturer.next_frame(CaptureMethod::Blocking, None)?;
+//!     println!("Captured frame {} with an overlay", frame.current_frame);
+//!     Ok(())
+//! }
+//! ```
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::convert::{check_size, rgb_to_yuv};
+use crate::system::{CaptureMethod, SystemFrameInfo};
+use crate::{Box, BufferFormat, BufferSizeError, Capture, Error, FrameGrabInfo, SessionOptions, Status};
+
+/// Width of a glyph of the built-in font, in pixels at scale 1.
+pub const GLYPH_WIDTH: u32 = 5;
+
+/// Height of a glyph of the built-in font, in pixels at scale 1.
+pub const GLYPH_HEIGHT: u32 = 7;
+
+/// Columns of the glyphs of the printable ASCII characters, from `' '` to `'~'`, with the top row in the least significant bit.
+const FONT: [[u8; 5]; 95] = [
+	[0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+	[0x00, 0x00, 0x5f, 0x00, 0x00], // '!'
+	[0x00, 0x07, 0x00, 0x07, 0x00], // '"'
+	[0x14, 0x7f, 0x14, 0x7f, 0x14], // '#'
+	[0x24, 0x2a, 0x7f, 0x2a, 0x12], // '$'
+	[0x23, 0x13, 0x08, 0x64, 0x62], // '%'
+	[0x36, 0x49, 0x55, 0x22, 0x50], // '&'
+	[0x00, 0x05, 0x03, 0x00, 0x00], // '''
+	[0x00, 0x1c, 0x22, 0x41, 0x00], // '('
+	[0x00, 0x41, 0x22, 0x1c, 0x00], // ')'
+	[0x14, 0x08, 0x3e, 0x08, 0x14], // '*'
+	[0x08, 0x08, 0x3e, 0x08, 0x08], // '+'
+	[0x00, 0x50, 0x30, 0x00, 0x00], // ','
+	[0x08, 0x08, 0x08, 0x08, 0x08], // '-'
+	[0x00, 0x60, 0x60, 0x00, 0x00], // '.'
+	[0x20, 0x10, 0x08, 0x04, 0x02], // '/'
+	[0x3e, 0x51, 0x49, 0x45, 0x3e], // '0'
+	[0x00, 0x42, 0x7f, 0x40, 0x00], // '1'
+	[0x42, 0x61, 0x51, 0x49, 0x46], // '2'
+	[0x21, 0x41, 0x45, 0x4b, 0x31], // '3'
+	[0x18, 0x14, 0x12, 0x7f, 0x10], // '4'
+	[0x27, 0x45, 0x45, 0x45, 0x39], // '5'
+	[0x3c, 0x4a, 0x49, 0x49, 0x30], // '6'
+	[0x01, 0x71, 0x09, 0x05, 0x03], // '7'
+	[0x36, 0x49, 0x49, 0x49, 0x36], // '8'
+	[0x06, 0x49, 0x49, 0x29, 0x1e], // '9'
+	[0x00, 0x36, 0x36, 0x00, 0x00], // ':'
+	[0x00, 0x56, 0x36, 0x00, 0x00], // ';'
+	[0x08, 0x14, 0x22, 0x41, 0x00], // '<'
+	[0x14, 0x14, 0x14, 0x14, 0x14], // '='
+	[0x00, 0x41, 0x22, 0x14, 0x08], // '>'
+	[0x02, 0x01, 0x51, 0x09, 0x06], // '?'
+	[0x32, 0x49, 0x79, 0x41, 0x3e], // '@'
+	[0x7e, 0x11, 0x11, 0x11, 0x7e], // 'A'
+	[0x7f, 0x49, 0x49, 0x49, 0x36], // 'B'
+	[0x3e, 0x41, 0x41, 0x41, 0x22], // 'C'
+	[0x7f, 0x41, 0x41, 0x22, 0x1c], // 'D'
+	[0x7f, 0x49, 0x49, 0x49, 0x41], // 'E'
+	[0x7f, 0x09, 0x09, 0x09, 0x01], // 'F'
+	[0x3e, 0x41, 0x49, 0x49, 0x7a], // 'G'
+	[0x7f, 0x08, 0x08, 0x08, 0x7f], // 'H'
+	[0x00, 0x41, 0x7f, 0x41, 0x00], // 'I'
+	[0x20, 0x40, 0x41, 0x3f, 0x01], // 'J'
+	[0x7f, 0x08, 0x14, 0x22, 0x41], // 'K'
+	[0x7f, 0x40, 0x40, 0x40, 0x40], // 'L'
+	[0x7f, 0x02, 0x0c, 0x02, 0x7f], // 'M'
+	[0x7f, 0x04, 0x08, 0x10, 0x7f], // 'N'
+	[0x3e, 0x41, 0x41, 0x41, 0x3e], // 'O'
+	[0x7f, 0x09, 0x09, 0x09, 0x06], // 'P'
+	[0x3e, 0x41, 0x51, 0x21, 0x5e], // 'Q'
+	[0x7f, 0x09, 0x19, 0x29, 0x46], // 'R'
+	[0x46, 0x49, 0x49, 0x49, 0x31], // 'S'
+	[0x01, 0x01, 0x7f, 0x01, 0x01], // 'T'
+	[0x3f, 0x40, 0x40, 0x40, 0x3f], // 'U'
+	[0x1f, 0x20, 0x40, 0x20, 0x1f], // 'V'
+	[0x3f, 0x40, 0x38, 0x40, 0x3f], // 'W'
+	[0x63, 0x14, 0x08, 0x14, 0x63], // 'X'
+	[0x07, 0x08, 0x70, 0x08, 0x07], // 'Y'
+	[0x61, 0x51, 0x49, 0x45, 0x43], // 'Z'
+	[0x00, 0x7f, 0x41, 0x41, 0x00], // '['
+	[0x02, 0x04, 0x08, 0x10, 0x20], // '\'
+	[0x00, 0x41, 0x41, 0x7f, 0x00], // ']'
+	[0x04, 0x02, 0x01, 0x02, 0x04], // '^'
+	[0x40, 0x40, 0x40, 0x40, 0x40], // '_'
+	[0x00, 0x01, 0x02, 0x04, 0x00], // '`'
+	[0x20, 0x54, 0x54, 0x54, 0x78], // 'a'
+	[0x7f, 0x48, 0x44, 0x44, 0x38], // 'b'
+	[0x38, 0x44, 0x44, 0x44, 0x20], // 'c'
+	[0x38, 0x44, 0x44, 0x48, 0x7f], // 'd'
+	[0x38, 0x54, 0x54, 0x54, 0x18], // 'e'
+	[0x08, 0x7e, 0x09, 0x01, 0x02], // 'f'
+	[0x0c, 0x52, 0x52, 0x52, 0x3e], // 'g'
+	[0x7f, 0x08, 0x04, 0x04, 0x78], // 'h'
+	[0x00, 0x44, 0x7d, 0x40, 0x00], // 'i'
+	[0x20, 0x40, 0x44, 0x3d, 0x00], // 'j'
+	[0x7f, 0x10, 0x28, 0x44, 0x00], // 'k'
+	[0x00, 0x41, 0x7f, 0x40, 0x00], // 'l'
+	[0x7c, 0x04, 0x18, 0x04, 0x78], // 'm'
+	[0x7c, 0x08, 0x04, 0x04, 0x78], // 'n'
+	[0x38, 0x44, 0x44, 0x44, 0x38], // 'o'
+	[0x7c, 0x14, 0x14, 0x14, 0x08], // 'p'
+	[0x08, 0x14, 0x14, 0x18, 0x7c], // 'q'
+	[0x7c, 0x08, 0x04, 0x04, 0x08], // 'r'
+	[0x48, 0x54, 0x54, 0x54, 0x20], // 's'
+	[0x04, 0x3f, 0x44, 0x40, 0x20], // 't'
+	[0x3c, 0x40, 0x40, 0x20, 0x7c], // 'u'
+	[0x1c, 0x20, 0x40, 0x20, 0x1c], // 'v'
+	[0x3c, 0x40, 0x30, 0x40, 0x3c], // 'w'
+	[0x44, 0x28, 0x10, 0x28, 0x44], // 'x'
+	[0x0c, 0x50, 0x50, 0x50, 0x3c], // 'y'
+	[0x44, 0x64, 0x54, 0x4c, 0x44], // 'z'
+	[0x00, 0x08, 0x36, 0x41, 0x00], // '{'
+	[0x00, 0x00, 0x7f, 0x00, 0x00], // '|'
+	[0x00, 0x41, 0x36, 0x08, 0x00], // '}'
+	[0x02, 0x01, 0x02, 0x04, 0x02], // '~'
+];
+
+/// Whether the pixel in `column` and `row` of the glyph of `c` is set. Characters outside printable ASCII are drawn as `'?'`.
+pub fn glyph_pixel(c: char, column: u32, row: u32) -> bool {
+	let index = match c {
+		' '..='~' => c as usize - ' ' as usize,
+		_ => '?' as usize - ' ' as usize,
+	};
+	column < GLYPH_WIDTH && row < GLYPH_HEIGHT && FONT[index][column as usize] & (1 << row) != 0
+}
+
+/// A value drawn by an [`Overlay`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum OverlayField {
+	/// The frame id, [`FrameGrabInfo::current_frame`].
+	FrameId,
+	/// The grab timestamp, [`FrameGrabInfo::timestamp_us`], in seconds with microseconds.
+	Timestamp,
+	/// The number of missed frames, [`FrameGrabInfo::missed_frames`].
+	MissedFrames,
+	/// The UTC wall-clock time when the overlay is drawn, as `YYYY-MM-DD HH:MM:SS.mmm`.
+	WallClock,
+	/// Fixed text. A `'\n'` starts a new line.
+	Text(String),
+}
+
+/// Where an [`Overlay`] is drawn in the frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum OverlayPosition {
+	/// The top left corner of the frame.
+	TopLeft,
+	/// The top right corner of the frame.
+	TopRight,
+	/// The bottom left corner of the frame.
+	BottomLeft,
+	/// The bottom right corner of the frame.
+	BottomRight,
+	/// The top left corner of the overlay, including its background box, in frame pixels.
+	At { x: u32, y: u32 },
+}
+
+/// Text drawn into frames, built from [`OverlayField`]s separated by spaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Overlay {
+	/// The values to draw.
+	pub fields: Vec<OverlayField>,
+	/// Where the overlay is drawn. Overlays that do not fit the frame are clipped.
+	pub position: OverlayPosition,
+	/// Size of a pixel of the font, in frame pixels.
+	pub scale: u32,
+	/// RGB color of the text.
+	pub color: [u8; 3],
+	/// RGB color of the box behind the text, or `None` to draw only the text.
+	pub background: Option<[u8; 3]>,
+}
+
+impl Overlay {
+	/// Create an overlay drawing `fields` in white on black in the top left corner, at scale 2.
+	pub fn new(fields: Vec<OverlayField>) -> Self {
+		Self {
+			fields,
+			position: OverlayPosition::TopLeft,
+			scale: 2,
+			color: [0xff, 0xff, 0xff],
+			background: Some([0, 0, 0]),
+		}
+	}
+
+	/// The text drawn for the frame described by `info`.
+	pub fn text(&self, info: &FrameGrabInfo) -> String {
+		let fields: Vec<String> = self.fields.iter().map(|field| match field {
+			OverlayField::FrameId => info.current_frame.to_string(),
+			OverlayField::Timestamp => format!("{}.{:06}", info.timestamp_us / 1_000_000, info.timestamp_us % 1_000_000),
+			OverlayField::MissedFrames => info.missed_frames.to_string(),
+			OverlayField::WallClock => format_utc(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()),
+			OverlayField::Text(text) => text.clone(),
+		}).collect();
+		fields.join(" ")
+	}
+
+	/// Width and height of the overlay showing `text`, including its background box, in frame pixels.
+	pub fn size(&self, text: &str) -> (u32, u32) {
+		let scale = self.scale.max(1);
+		let columns = text.lines().map(|line| line.chars().count() as u32).max().unwrap_or(0);
+		let rows = text.lines().count().max(1) as u32;
+		// A pixel of spacing between glyphs and lines, and around the text.
+		((columns * (GLYPH_WIDTH + 1) + 1) * scale, (rows * (GLYPH_HEIGHT + 1) + 1) * scale)
+	}
+
+	/// Draw the overlay for the frame described by `info` into `buffer`.
+	pub fn draw(&self, buffer: &mut [u8], info: &FrameGrabInfo, format: BufferFormat) -> Result<(), BufferSizeError> {
+		self.draw_text(buffer, info.width, info.height, format, &self.text(info))
+	}
+
+	/// Draw `text` with the style of the overlay into a frame of `width` x `height` pixels in `format`.
+	pub fn draw_text(&self, buffer: &mut [u8], width: u32, height: u32, format: BufferFormat, text: &str) -> Result<(), BufferSizeError> {
+		check_size(buffer, width, height, format)?;
+		let Some(bounds) = self.bounds(text, width, height) else {
+			return Ok(());
+		};
+		let (overlay_width, overlay_height) = self.size(text);
+		let layer = Layer {
+			left: bounds.x,
+			top: bounds.y,
+			width: bounds.w,
+			height: bounds.h,
+			pixels: self.render(text, overlay_width, overlay_height),
+			stride: overlay_width as usize,
+		};
+		layer.composite(buffer, width, height, format);
+		Ok(())
+	}
+
+	/// The pixels of a frame of `width` x `height` pixels in `format` that drawing `text` may change,
+	/// or `None` if the overlay is outside of the frame.
+	///
+	/// Pixels that share a subsampled chroma sample with the overlay are included.
+	pub fn area(&self, text: &str, width: u32, height: u32, format: BufferFormat) -> Option<Box> {
+		self.bounds(text, width, height).map(|bounds| format.sample_region(bounds, width, height))
+	}
+
+	/// The pixels of a frame of `width` x `height` pixels covered by the overlay showing `text`, clipped to the frame.
+	fn bounds(&self, text: &str, width: u32, height: u32) -> Option<Box> {
+		let (overlay_width, overlay_height) = self.size(text);
+		let (x, y) = match self.position {
+			OverlayPosition::TopLeft => (0, 0),
+			OverlayPosition::TopRight => (width.saturating_sub(overlay_width), 0),
+			OverlayPosition::BottomLeft => (0, height.saturating_sub(overlay_height)),
+			OverlayPosition::BottomRight => (width.saturating_sub(overlay_width), height.saturating_sub(overlay_height)),
+			OverlayPosition::At { x, y } => (x, y),
+		};
+		let bounds = Box {
+			x,
+			y,
+			w: overlay_width.min(width.saturating_sub(x)),
+			h: overlay_height.min(height.saturating_sub(y)),
+		};
+		(bounds.w != 0 && bounds.h != 0).then_some(bounds)
+	}
+
+	/// Render `text` as the colors of the overlay pixels, `None` where the frame shows through.
+	fn render(&self, text: &str, width: u32, height: u32) -> Vec<Option<[u8; 3]>> {
+		let scale = self.scale.max(1);
+		let mut pixels = vec![self.background; width as usize * height as usize];
+		for (row, line) in text.lines().enumerate() {
+			for (column, c) in line.chars().enumerate() {
+				let left = (column as u32 * (GLYPH_WIDTH + 1) + 1) * scale;
+				let top = (row as u32 * (GLYPH_HEIGHT + 1) + 1) * scale;
+				for y in 0..GLYPH_HEIGHT * scale {
+					for x in 0..GLYPH_WIDTH * scale {
+						if glyph_pixel(c, x / scale, y / scale) {
+							pixels[((top + y) * width + left + x) as usize] = Some(self.color);
+						}
+					}
+				}
+			}
+		}
+		pixels
+	}
+}
+
+/// A rendered overlay at its position in the frame, clipped to the frame.
+struct Layer {
+	left: u32,
+	top: u32,
+	width: u32,
+	height: u32,
+	pixels: Vec<Option<[u8; 3]>>,
+	stride: usize,
+}
+
+impl Layer {
+	/// The color of the overlay at frame pixel `x`, `y`, if it covers it.
+	fn pixel(&self, x: u32, y: u32) -> Option<[u8; 3]> {
+		if x < self.left || y < self.top || x >= self.left + self.width || y >= self.top + self.height {
+			return None;
+		}
+		self.pixels[(y - self.top) as usize * self.stride + (x - self.left) as usize]
+	}
+
+	fn composite(&self, buffer: &mut [u8], width: u32, height: u32, format: BufferFormat) {
+		let planes = format.planes(width, height);
+		let rows = self.top..self.top + self.height;
+		let columns = self.left..self.left + self.width;
+		match format {
+			BufferFormat::Nv12 => {
+				let (luma, chroma) = (&planes[0], &planes[1]);
+				for y in rows.clone() {
+					for x in columns.clone() {
+						if let Some(rgb) = self.pixel(x, y) {
+							buffer[luma.offset + y as usize * luma.stride() + x as usize] = rgb_to_yuv(rgb)[0];
+						}
+					}
+				}
+				// Every chroma sample covering overlay pixels gets the average chroma of those pixels.
+				for y in rows.start / 2..rows.end.div_ceil(2) {
+					for x in columns.start / 2..columns.end.div_ceil(2) {
+						let covered: Vec<[u8; 3]> = [(0, 0), (1, 0), (0, 1), (1, 1)]
+							.iter()
+							.filter_map(|(dx, dy)| self.pixel(x * 2 + dx, y * 2 + dy))
+							.map(rgb_to_yuv)
+							.collect();
+						if covered.is_empty() {
+							continue;
+						}
+						let average = |channel: usize| {
+							let sum: u32 = covered.iter().map(|yuv| yuv[channel] as u32).sum();
+							((sum + covered.len() as u32 / 2) / covered.len() as u32) as u8
+						};
+						let offset = chroma.offset + y as usize * chroma.stride() + x as usize * 2;
+						buffer[offset..offset + 2].copy_from_slice(&[average(1), average(2)]);
+					}
+				}
+			},
+			BufferFormat::Yuv444p => {
+				for y in rows {
+					for x in columns.clone() {
+						if let Some(rgb) = self.pixel(x, y) {
+							for (plane, value) in planes.iter().zip(rgb_to_yuv(rgb)) {
+								buffer[plane.offset + y as usize * plane.stride() + x as usize] = value;
+							}
+						}
+					}
+				}
+			},
+			format => {
+				let (Some(bytes_per_pixel), Some(offsets)) = (format.bytes_per_pixel(), format.rgb_offsets()) else {
+					return;
+				};
+				let stride = planes[0].stride();
+				for y in rows {
+					for x in columns.clone() {
+						if let Some(rgb) = self.pixel(x, y) {
+							let pixel = y as usize * stride + x as usize * bytes_per_pixel;
+							for (offset, value) in offsets.iter().zip(rgb) {
+								buffer[pixel + offset] = value;
+							}
+							if let Some(alpha) = format.alpha_offset() {
+								buffer[pixel + alpha] = 0xff;
+							}
+						}
+					}
+				}
+			},
+		}
+	}
+}
+
+/// Format a time since the Unix epoch as `YYYY-MM-DD HH:MM:SS.mmm`.
+fn format_utc(since_epoch: Duration) -> String {
+	let seconds = since_epoch.as_secs();
+	let (days, time) = (seconds / 86_400, seconds % 86_400);
+	// Civil date from the number of days since 1970-01-01, in the proleptic Gregorian calendar.
+	let days = days as i64 + 719_468;
+	let era = days.div_euclid(146_097);
+	let day_of_era = days.rem_euclid(146_097);
+	let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month_index = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+	let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+	let year = year_of_era + era * 400 + (month <= 2) as i64;
+	format!(
+		"{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+		year, month, day, time / 3600, time / 60 % 60, time % 60, since_epoch.subsec_millis(),
+	)
+}
+
+/// Draws an [`Overlay`] into the frames of another capturer.
+///
+/// Diff maps of the frames mark the blocks under the overlay as changed, and the blocks it covered in the previous frame.
+pub struct OverlayCapturer<C> {
+	inner: C,
+	overlay: Overlay,
+	/// The most recent frame, with the overlay drawn into it.
+	buffer: Vec<u8>,
+	/// The diff map of the most recent frame, with the overlay marked as changed.
+	diff_map: Vec<u8>,
+	/// The pixels changed by the overlay in the most recent frame.
+	area: Option<Box>,
+}
+
+impl<C: Capture> OverlayCapturer<C> {
+	/// Draw `overlay` into the frames captured by `inner`.
+	pub fn new(inner: C, overlay: Overlay) -> Self {
+		Self { inner, overlay, buffer: Vec::new(), diff_map: Vec::new(), area: None }
+	}
+
+	/// The drawn overlay.
+	pub fn overlay(&self) -> &Overlay {
+		&self.overlay
+	}
+
+	/// Replace the drawn overlay, starting with the next frame.
+	pub fn set_overlay(&mut self, overlay: Overlay) {
+		self.overlay = overlay;
+	}
+
+	/// The wrapped capturer.
+	pub fn into_inner(self) -> C {
+		self.inner
+	}
+}
+
+impl<C: Capture> Capture for OverlayCapturer<C> {
+	fn status(&self) -> Result<Status, Error> {
+		self.inner.status()
+	}
+
+	fn start_with_options(&mut self, options: &SessionOptions) -> Result<(), Error> {
+		self.area = None;
+		self.inner.start_with_options(options)
+	}
+
+	fn stop(&mut self) -> Result<(), Error> {
+		self.inner.stop()
+	}
+
+	fn next_frame(&mut self, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<SystemFrameInfo<'_>, Error> {
+		let frame = self.inner.next_frame(capture_method, timeout)?;
+		let text = self.overlay.text(&frame.grab_info());
+		self.buffer.clear();
+		self.buffer.extend_from_slice(frame.buffer);
+		self.overlay.draw_text(&mut self.buffer, frame.width, frame.height, frame.buffer_format, &text)
+			.map_err(|e| Error::new(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_INTERNAL, Some(e.to_string())))?;
+
+		// The overlay changes pixels that the inner diff map reports as unchanged, and the previous overlay is gone.
+		let area = self.overlay.area(&text, frame.width, frame.height, frame.buffer_format);
+		let changed: Vec<Box> = self.area.into_iter().chain(area).collect();
+		self.area = area;
+		let diff_map = frame.diff_map.map(|diff_map| diff_map.with_changed(&changed, &mut self.diff_map));
+		Ok(SystemFrameInfo { buffer: &self.buffer, diff_map, ..frame })
+	}
+}
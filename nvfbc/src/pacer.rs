@@ -0,0 +1,219 @@
+//! Pacing captured frames to a constant frame rate.
+//!
+//! NvFBC delivers a frame whenever the screen changes, while encoders usually expect a fixed frame rate.
+//! [`Pacer`] maps the grab timestamps of captured frames onto a fixed clock,
+//! duplicating the last frame for slots without a new frame and holding back frames that arrive in a slot that was already filled.
+//!
+//! The pacer only decides what to emit, it does not hold frame data.
+//! Every emitted frame is either the frame that was just pushed, or the newest frame pushed before it,
+//! so the caller only keeps a copy of the previous frame:
+//!
+//! ```no_run
+//! use nvfbc::pacer::Pacer;
+//! use nvfbc::system::CaptureMethod;
+//! use nvfbc::{BufferFormat, SystemCapturer};
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let mut capturer = SystemCapturer::new()?;
+//!     capturer.start(BufferFormat::Nv12, 60)?;
+//!
+//!     let mut pacer = Pacer::new(60);
+//!     let mut previous = Vec::new();
+//!     loop {
+//!         let frame = capturer.next_frame(CaptureMethod::Blocking, None)?;
+//!         for paced in pacer.push(&frame.grab_info()) {
+//!             let data = if paced.current_frame == frame.current_frame { frame.buffer } else { &previous[..] };
+//!             // Encode `data` with presentation timestamp `paced.pts`.
+//!         }
+//!         previous.clear();
+//!         previous.extend_from_slice(frame.buffer);
+//!     }
+//! }
+//! ```
+
+use crate::FrameGrabInfo;
+
+/// A frame to emit on the fixed clock.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PacedFrame {
+	/// Presentation timestamp, in frame intervals since the first frame.
+	pub pts: u64,
+	/// Identifier of the captured frame to emit, see [`FrameGrabInfo::current_frame`].
+	pub current_frame: u32,
+	/// Whether this repeats the previously emitted frame instead of emitting a new frame.
+	pub duplicate: bool,
+}
+
+/// Number of frames emitted, duplicated and dropped by a [`Pacer`].
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct PacerStats {
+	/// Number of emitted frames, including duplicates.
+	pub emitted: u64,
+	/// Number of emitted frames that repeat the previous frame.
+	pub duplicates: u64,
+	/// Number of new frames that were not emitted, because a newer frame replaced them before a slot was free.
+	pub drops: u64,
+}
+
+/// Maps captured frames onto a constant frame rate clock.
+///
+/// The clock starts at the timestamp of the first new frame. Slot `n` covers the timestamps from
+/// `n` up to `n + 1` frame intervals after the start, and is emitted with presentation timestamp `n`.
+/// The first new frame in a slot is emitted immediately. The newest of the later frames in the same slot
+/// is held back and emitted in the next slot, unless a newer frame arrives in that slot, and the others are dropped.
+/// Slots that pass without a new frame are filled with the held back frame or duplicates of the last emitted frame
+/// once a later frame arrives, or when the clock is advanced with [`Pacer::advance_to`].
+///
+/// Frames that repeat the last frame, because `is_new_frame` is false or their frame id did not change, never start a slot.
+#[derive(Debug, Clone)]
+pub struct Pacer {
+	/// The clock emits `frames` frames every `period_us` microseconds.
+	frames: u64,
+	period_us: u64,
+
+	/// Timestamp of the start of slot 0.
+	start_us: Option<u64>,
+
+	/// The next slot that has not been emitted.
+	next_pts: u64,
+
+	/// Identifier of the last emitted frame.
+	last_frame: Option<u32>,
+
+	/// Identifier of a new frame that arrived in a slot that was already emitted.
+	pending: Option<u32>,
+
+	stats: PacerStats,
+}
+
+impl Pacer {
+	/// Create a pacer emitting `fps` frames per second.
+	///
+	/// # Panics
+	/// Panics if `fps` is zero.
+	pub fn new(fps: u32) -> Self {
+		assert!(fps > 0, "fps must be larger than zero");
+		Self::with_rate(fps as u64, 1_000_000)
+	}
+
+	/// Create a pacer emitting a frame every `interval_us` microseconds.
+	///
+	/// # Panics
+	/// Panics if `interval_us` is zero.
+	pub fn with_interval(interval_us: u64) -> Self {
+		assert!(interval_us > 0, "interval must be larger than zero");
+		Self::with_rate(1, interval_us)
+	}
+
+	fn with_rate(frames: u64, period_us: u64) -> Self {
+		Self {
+			frames,
+			period_us,
+			start_us: None,
+			next_pts: 0,
+			last_frame: None,
+			pending: None,
+			stats: PacerStats::default(),
+		}
+	}
+
+	/// Time between two emitted frames, rounded down to whole microseconds.
+	///
+	/// Slots are computed from the exact frame rate, so the clock does not drift because of this rounding.
+	pub fn interval_us(&self) -> u64 {
+		self.period_us / self.frames
+	}
+
+	/// Timestamp of the start of the clock, once the first frame was pushed.
+	pub fn start_us(&self) -> Option<u64> {
+		self.start_us
+	}
+
+	/// Presentation timestamp of the next frame that will be emitted.
+	pub fn next_pts(&self) -> u64 {
+		self.next_pts
+	}
+
+	/// Number of frames emitted, duplicated and dropped so far.
+	pub fn stats(&self) -> PacerStats {
+		self.stats
+	}
+
+	/// Identifier of the frame that is held back to be emitted in the next slot, if any.
+	pub fn pending(&self) -> Option<u32> {
+		self.pending
+	}
+
+	/// The slot containing `timestamp_us`. Timestamps before the start of the clock belong to slot 0.
+	fn slot(&self, start_us: u64, timestamp_us: u64) -> u64 {
+		(timestamp_us.saturating_sub(start_us) as u128 * self.frames as u128 / self.period_us as u128) as u64
+	}
+
+	/// Push a captured frame, returning the frames to emit in order.
+	///
+	/// These are the held back frame or duplicates of the last emitted frame for every slot that passed without a new frame,
+	/// followed by the pushed frame itself unless its slot was already emitted.
+	pub fn push(&mut self, info: &FrameGrabInfo) -> Vec<PacedFrame> {
+		let is_new_frame = info.is_new_frame && self.pending.or(self.last_frame) != Some(info.current_frame);
+		let Some(start_us) = self.start_us else {
+			if !is_new_frame {
+				return Vec::new();
+			}
+			self.start_us = Some(info.timestamp_us);
+			return vec![self.emit(info.current_frame, false)];
+		};
+
+		let slot = self.slot(start_us, info.timestamp_us);
+		let mut frames = self.fill_until(slot);
+		if !is_new_frame {
+			return frames;
+		}
+
+		// A newer frame replaces the held back frame.
+		if self.pending.take().is_some() {
+			self.stats.drops += 1;
+		}
+		if slot < self.next_pts {
+			self.pending = Some(info.current_frame);
+		} else {
+			frames.push(self.emit(info.current_frame, false));
+		}
+		frames
+	}
+
+	/// Advance the clock to `timestamp_us` without a new frame,
+	/// returning the held back frame or duplicates for every slot that ended before `timestamp_us` and was not emitted.
+	///
+	/// `timestamp_us` must use the same clock as the grab timestamps.
+	pub fn advance_to(&mut self, timestamp_us: u64) -> Vec<PacedFrame> {
+		let Some(start_us) = self.start_us else {
+			return Vec::new();
+		};
+
+		// Slots are complete once their end has passed.
+		let complete = self.slot(start_us, timestamp_us);
+		self.fill_until(complete)
+	}
+
+	/// Emit the held back frame or duplicates for all slots before `slot` that were not emitted.
+	fn fill_until(&mut self, slot: u64) -> Vec<PacedFrame> {
+		let mut frames = Vec::new();
+		while self.next_pts < slot {
+			match (self.pending.take(), self.last_frame) {
+				(Some(pending), _) => frames.push(self.emit(pending, false)),
+				(None, Some(last_frame)) => frames.push(self.emit(last_frame, true)),
+				(None, None) => break,
+			}
+		}
+		frames
+	}
+
+	fn emit(&mut self, current_frame: u32, duplicate: bool) -> PacedFrame {
+		let frame = PacedFrame { pts: self.next_pts, current_frame, duplicate };
+		self.next_pts += 1;
+		self.last_frame = Some(current_frame);
+		self.stats.emitted += 1;
+		self.stats.duplicates += duplicate as u64;
+		frame
+	}
+}
@@ -0,0 +1,360 @@
+//! A preview of the screen in the browser, served as an MJPEG stream over HTTP without an async runtime.
+//!
+//! This module requires the `preview` feature.
+//!
+//! [`PreviewServer`] runs a capture session in system memory in the background,
+//! encodes frames as JPEG on the CPU and serves the following endpoints:
+//!
+//! | Path | Content |
+//! | --- | --- |
+//! | `/` | An HTML page showing the stream. |
+//! | `/stream.mjpg` | The frames as a `multipart/x-mixed-replace` stream of JPEG images. |
+//! | `/snapshot.png` | The latest frame as a PNG image. |
+//! | `/status.json` | The current [`Status`] of the capture source. |
+//!
+//! ```no_run
+//! use nvfbc::preview::{PreviewOptions, PreviewServer};
+//! use nvfbc::SystemCapturer;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let server = PreviewServer::bind("127.0.0.1:8080", SystemCapturer::new, PreviewOptions::new(10))?;
+//!     println!("Open http://{}/ in a browser", server.local_addr());
+//!     std::thread::park();
+//!     Ok(())
+//! }
+//! ```
+
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::common::{ignore_poison, lock_ignoring_poison};
+use crate::server::{read_http_request, Acceptor, CaptureSupervisor, HttpRequest, POLL_INTERVAL};
+use crate::{BufferFormat, Capture, Error, SessionOptions, Status};
+
+/// Boundary between the JPEG images of the MJPEG stream.
+const BOUNDARY: &str = "nvfbc-frame";
+
+/// How often the status of the capture source is refreshed.
+const STATUS_INTERVAL: Duration = Duration::from_secs(1);
+
+const INDEX_HTML: &str = "<!DOCTYPE html>
+<html>
+<head><title>NvFBC preview</title></head>
+<body style=\"margin: 0; background: #000\">
+<img src=\"/stream.mjpg\" style=\"display: block; max-width: 100%; margin: auto\">
+</body>
+</html>
+";
+
+/// Configuration of a [`PreviewServer`].
+#[derive(Debug, Clone)]
+pub struct PreviewOptions {
+	/// Options of the capture session.
+	///
+	/// The buffer format is always [`BufferFormat::Rgb`], other formats are ignored.
+	pub session: SessionOptions,
+
+	/// Maximum number of frames per second that are encoded and streamed.
+	pub fps: u32,
+
+	/// JPEG quality, from 1 to 100.
+	pub quality: u8,
+}
+
+impl PreviewOptions {
+	/// Stream at most `fps` frames per second with a JPEG quality of 80.
+	pub fn new(fps: u32) -> Self {
+		Self {
+			session: SessionOptions::new(BufferFormat::Rgb, fps),
+			fps,
+			quality: 80,
+		}
+	}
+}
+
+/// Encode a packed RGB888 frame as JPEG with `quality` from 1 to 100.
+pub fn encode_jpeg(rgb: &[u8], width: u32, height: u32, quality: u8) -> io::Result<Vec<u8>> {
+	let (width, height) = encoded_size(rgb, width, height)?;
+	let mut jpeg = Vec::new();
+	jpeg_encoder::Encoder::new(&mut jpeg, quality.clamp(1, 100))
+		.encode(rgb, width as u16, height as u16, jpeg_encoder::ColorType::Rgb)
+		.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+	Ok(jpeg)
+}
+
+/// Encode a packed RGB888 frame as PNG.
+pub fn encode_png(rgb: &[u8], width: u32, height: u32) -> io::Result<Vec<u8>> {
+	let (width, height) = encoded_size(rgb, width, height)?;
+	let mut png = Vec::new();
+	let mut encoder = png::Encoder::new(&mut png, width, height);
+	encoder.set_color(png::ColorType::Rgb);
+	encoder.set_depth(png::BitDepth::Eight);
+	encoder.write_header()
+		.and_then(|mut writer| writer.write_image_data(rgb))
+		.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+	Ok(png)
+}
+
+/// Check that `rgb` holds a frame of `width` x `height` pixels that fits in a JPEG image.
+fn encoded_size(rgb: &[u8], width: u32, height: u32) -> io::Result<(u32, u32)> {
+	if width == 0 || height == 0 || width > u16::MAX as u32 || height > u16::MAX as u32 {
+		return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("cannot encode a frame of {}x{} pixels", width, height)));
+	}
+	if rgb.len() != width as usize * height as usize * 3 {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			format!("expected {} bytes for a frame of {}x{} pixels, got {}", width as usize * height as usize * 3, width, height, rgb.len()),
+		));
+	}
+	Ok((width, height))
+}
+
+/// The latest frame, shared between the capture thread and the clients.
+#[derive(Debug, Default)]
+struct Latest {
+	/// Incremented for every encoded frame, 0 before the first frame.
+	sequence: u64,
+	width: u32,
+	height: u32,
+	rgb: Arc<Vec<u8>>,
+	jpeg: Arc<Vec<u8>>,
+	/// The last known status, or the error retrieving it.
+	status: Option<Result<Status, String>>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+	latest: Mutex<Latest>,
+	frame_ready: Condvar,
+	stop: AtomicBool,
+}
+
+impl Shared {
+	fn lock(&self) -> MutexGuard<'_, Latest> {
+		lock_ignoring_poison(&self.latest)
+	}
+
+	fn stopping(&self) -> bool {
+		self.stop.load(Ordering::Relaxed)
+	}
+
+	/// Wait until a frame newer than `sequence` is available, or return `None` when the server stops.
+	fn wait_for_frame(&self, sequence: u64) -> Option<(u64, Arc<Vec<u8>>)> {
+		let mut latest = self.lock();
+		while latest.sequence <= sequence {
+			if self.stopping() {
+				return None;
+			}
+			latest = ignore_poison(self.frame_ready.wait_timeout(latest, POLL_INTERVAL)).0;
+		}
+		Some((latest.sequence, latest.jpeg.clone()))
+	}
+}
+
+/// Serves a live preview of a capture source over HTTP.
+///
+/// Dropping the server stops the capture session and all threads.
+pub struct PreviewServer {
+	acceptor: Acceptor,
+	shared: Arc<Shared>,
+	capture_thread: Option<JoinHandle<()>>,
+}
+
+impl PreviewServer {
+	/// Listen on `addr` and serve a preview of the frames captured from the capturer created by `create`.
+	///
+	/// The capturer is created on the capture thread, because capturers such as [`SystemCapturer`](crate::SystemCapturer)
+	/// cannot be sent to other threads. Creating the capturer and starting the capture session are retried when they fail.
+	/// Use port 0 to let the operating system pick a free port, see [`PreviewServer::local_addr`].
+	pub fn bind<C, F>(addr: impl ToSocketAddrs, create: F, options: PreviewOptions) -> io::Result<Self>
+	where
+		C: Capture,
+		F: FnMut() -> Result<C, Error> + Send + 'static,
+	{
+		if options.fps == 0 {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "fps must be larger than zero"));
+		}
+
+		let listener = TcpListener::bind(addr)?;
+		let shared = Arc::new(Shared::default());
+
+		let capture_thread = std::thread::Builder::new()
+			.name("nvfbc-preview-capture".to_string())
+			.spawn({
+				let shared = shared.clone();
+				move || capture(create, &options, &shared)
+			})?;
+
+		// Streams stay open, so every client is served by its own thread.
+		let acceptor = Acceptor::spawn("nvfbc-preview", listener, {
+			let shared = shared.clone();
+			move |stream| serve(stream, &shared)
+		});
+		let acceptor = match acceptor {
+			Ok(acceptor) => acceptor,
+			Err(e) => {
+				shared.stop.store(true, Ordering::Relaxed);
+				let _ = capture_thread.join();
+				return Err(e);
+			},
+		};
+
+		Ok(Self {
+			acceptor,
+			shared,
+			capture_thread: Some(capture_thread),
+		})
+	}
+
+	/// The address the server listens on.
+	pub fn local_addr(&self) -> SocketAddr {
+		self.acceptor.local_addr()
+	}
+}
+
+impl Drop for PreviewServer {
+	fn drop(&mut self) {
+		self.shared.stop.store(true, Ordering::Relaxed);
+		self.shared.frame_ready.notify_all();
+		self.acceptor.stop();
+		if let Some(thread) = self.capture_thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+/// Capture and encode frames until the server stops.
+fn capture<C: Capture>(create: impl FnMut() -> Result<C, Error>, options: &PreviewOptions, shared: &Shared) {
+	let interval = Duration::from_secs(1) / options.fps;
+	let session = SessionOptions { buffer_format: BufferFormat::Rgb, ..options.session.clone() };
+	let mut supervisor = CaptureSupervisor::new(create, session, &shared.stop);
+	let mut status_refreshed: Option<Instant> = None;
+	let mut next_frame_at = Instant::now();
+
+	loop {
+		if let Some(capturer) = supervisor.capturer() {
+			if status_refreshed.is_none_or(|refreshed| refreshed.elapsed() >= STATUS_INTERVAL) {
+				let status = capturer.status().map_err(|e| e.to_string());
+				shared.lock().status = Some(status);
+				status_refreshed = Some(Instant::now());
+			}
+		}
+
+		// Throttle to the configured frame rate.
+		let now = Instant::now();
+		if next_frame_at > now {
+			std::thread::sleep(next_frame_at - now);
+		}
+		next_frame_at = next_frame_at.max(now) + interval;
+
+		let created = supervisor.capturer().is_some();
+		let encoded = match supervisor.next_frame(interval) {
+			None => break,
+			Some(Ok(frame)) if frame.is_new_frame || shared.lock().sequence == 0 => {
+				let encoded = encode_jpeg(frame.buffer, frame.width, frame.height, options.quality);
+				encoded.map(|jpeg| (frame.width, frame.height, frame.buffer.to_vec(), jpeg))
+			},
+			Some(Ok(_)) => continue,
+			Some(Err(e)) => {
+				if !created {
+					shared.lock().status = Some(Err(e.to_string()));
+				}
+				continue;
+			},
+		};
+
+		if let Ok((width, height, rgb, jpeg)) = encoded {
+			let mut latest = shared.lock();
+			latest.sequence += 1;
+			latest.width = width;
+			latest.height = height;
+			latest.rgb = Arc::new(rgb);
+			latest.jpeg = Arc::new(jpeg);
+			drop(latest);
+			shared.frame_ready.notify_all();
+		}
+	}
+}
+
+/// Answer a single HTTP request.
+fn serve(mut stream: TcpStream, shared: &Shared) -> io::Result<()> {
+	stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+	stream.set_write_timeout(Some(Duration::from_secs(5)))?;
+
+	let HttpRequest { method, path } = read_http_request(&mut stream)?;
+	let (method, path) = (method.as_str(), path.as_str());
+
+	let known = matches!(path, "/" | "/stream.mjpg" | "/snapshot.png" | "/status.json");
+	if !known {
+		return respond(&mut stream, method, "404 Not Found", "text/plain; charset=utf-8", b"Not found\n");
+	}
+	if method != "GET" && method != "HEAD" {
+		return respond(&mut stream, method, "405 Method Not Allowed", "text/plain; charset=utf-8", b"Method not allowed\n");
+	}
+
+	match path {
+		"/stream.mjpg" => stream_frames(&mut stream, method, shared),
+		"/snapshot.png" => {
+			let (width, height, rgb) = {
+				let latest = shared.lock();
+				(latest.width, latest.height, latest.rgb.clone())
+			};
+			if rgb.is_empty() {
+				return respond(&mut stream, method, "503 Service Unavailable", "text/plain; charset=utf-8", b"No frame captured yet\n");
+			}
+			match encode_png(&rgb, width, height) {
+				Ok(png) => respond(&mut stream, method, "200 OK", "image/png", &png),
+				Err(e) => respond(&mut stream, method, "500 Internal Server Error", "text/plain; charset=utf-8", format!("{}\n", e).as_bytes()),
+			}
+		},
+		"/status.json" => {
+			let status = shared.lock().status.clone();
+			let (code, body) = match status {
+				Some(Ok(status)) => ("200 OK", serde_json::to_string(&status)),
+				Some(Err(error)) => ("503 Service Unavailable", serde_json::to_string(&serde_json::json!({ "error": error }))),
+				None => ("503 Service Unavailable", serde_json::to_string(&serde_json::json!({ "error": "status not retrieved yet" }))),
+			};
+			let body = body.map_err(io::Error::other)?;
+			respond(&mut stream, method, code, "application/json", body.as_bytes())
+		},
+		_ => respond(&mut stream, method, "200 OK", "text/html; charset=utf-8", INDEX_HTML.as_bytes()),
+	}
+}
+
+fn respond(stream: &mut TcpStream, method: &str, status: &str, content_type: &str, body: &[u8]) -> io::Result<()> {
+	write!(
+		stream,
+		"HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+		status, content_type, body.len(),
+	)?;
+	if method != "HEAD" {
+		stream.write_all(body)?;
+	}
+	stream.flush()
+}
+
+/// Send every new frame as a part of a `multipart/x-mixed-replace` response until the client disconnects.
+fn stream_frames(stream: &mut TcpStream, method: &str, shared: &Shared) -> io::Result<()> {
+	write!(
+		stream,
+		"HTTP/1.1 200 OK\r\nContent-Type: multipart/x-mixed-replace; boundary={}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+		BOUNDARY,
+	)?;
+	if method == "HEAD" {
+		return stream.flush();
+	}
+
+	let mut sequence = 0;
+	while let Some((latest, jpeg)) = shared.wait_for_frame(sequence) {
+		sequence = latest;
+		write!(stream, "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n", BOUNDARY, jpeg.len())?;
+		stream.write_all(&jpeg)?;
+		stream.write_all(b"\r\n")?;
+		stream.flush()?;
+	}
+	Ok(())
+}
@@ -0,0 +1,685 @@
+//! Recording captured frames to disk and replaying them without NvFBC.
+//!
+//! A recording stores the status and session options at the time of recording,
+//! followed by every frame with its grab info. Frame data is optionally compressed with zstd,
+//! which requires the `zstd` feature.
+//!
+//! [`ReplayCapturer`] implements [`Capture`] on top of a recording,
+//! so that code written against [`Capture`] can be run against a real desktop session on a machine without a GPU.
+//!
+//! # File format
+//! All integers are little endian. The file starts with a header:
+//!
+//! | Field | Type |
+//! | --- | --- |
+//! | Magic, `NVFBCREC` | 8 bytes |
+//! | Format version, currently 1 | `u16` |
+//! | Compression, 0 for none and 1 for zstd | `u8` |
+//! | Status | see below |
+//! | Session options | see below |
+//!
+//! The status is stored as a `u8` with the flags `is_capture_possible`, `currently_capturing`, `can_create_now`,
+//! `xrandr_available` and `in_modeset` in bits 0 to 4, followed by the screen width and height and the NvFBC major and
+//! minor version as `u32`, the number of outputs as `u32` and for every output its id, the length of its name,
+//! the UTF-8 name and its tracked box as x, y, w and h.
+//!
+//! The session options are stored as the buffer format and fps as `u32`, the tracking as a `u8` kind
+//! (0 for default, 1 for output, 2 for screen) followed by an output id as `u32`, the capture box as a `u8` that is 1
+//! if present followed by x, y, w and h, the frame size as a `u8` that is 1 if present followed by w and h,
+//! and `with_cursor` as a `u8`.
+//!
+//! Every frame is stored as the tag `1` (`u8`), followed by the buffer format, width, height and current frame as `u32`,
+//! `is_new_frame` as `u8`, the timestamp in microseconds as `u64`, the missed frames, the size of the frame data
+//! and the size of the stored, possibly compressed, data as `u32`, and finally the stored data.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::system::{CaptureMethod, SystemFrameInfo};
+use crate::{
+	convert,
+	Box,
+	BufferFormat,
+	Capture,
+	Error,
+	FrameGrabInfo,
+	Output,
+	SessionOptions,
+	Size,
+	Status,
+	Tracking,
+	Version,
+};
+
+const MAGIC: &[u8; 8] = b"NVFBCREC";
+const FORMAT_VERSION: u16 = 1;
+const FRAME_TAG: u8 = 1;
+
+/// Compression of the frame data in a recording.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Compression {
+	/// Frame data is stored as captured.
+	None,
+	/// Frame data is compressed with zstd at the given level.
+	#[cfg(feature = "zstd")]
+	Zstd(i32),
+}
+
+impl Compression {
+	fn id(&self) -> u8 {
+		match self {
+			Compression::None => 0,
+			#[cfg(feature = "zstd")]
+			Compression::Zstd(_) => 1,
+		}
+	}
+
+	fn from_id(id: u8) -> io::Result<Self> {
+		match id {
+			0 => Ok(Compression::None),
+			#[cfg(feature = "zstd")]
+			1 => Ok(Compression::Zstd(0)),
+			#[cfg(not(feature = "zstd"))]
+			1 => Err(io::Error::new(io::ErrorKind::Unsupported, "recording is compressed with zstd, enable the zstd feature")),
+			id => Err(invalid_data(format!("unknown compression {}", id))),
+		}
+	}
+
+	fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+		match self {
+			Compression::None => Ok(data.to_vec()),
+			#[cfg(feature = "zstd")]
+			Compression::Zstd(level) => zstd::bulk::compress(data, *level),
+		}
+	}
+
+	fn decompress(&self, data: Vec<u8>, size: usize) -> io::Result<Vec<u8>> {
+		let data = match self {
+			Compression::None => data,
+			#[cfg(feature = "zstd")]
+			Compression::Zstd(_) => zstd::bulk::decompress(&data, size)?,
+		};
+		if data.len() != size {
+			return Err(invalid_data(format!("expected {} bytes of frame data, got {}", size, data.len())));
+		}
+		Ok(data)
+	}
+}
+
+/// Status and session options stored at the start of a recording.
+#[derive(Debug, Clone)]
+pub struct RecordingHeader {
+	/// Status of NvFBC when the recording was started.
+	pub status: Status,
+	/// Options of the recorded capture session.
+	pub options: SessionOptions,
+	/// Compression of the frame data.
+	pub compression: Compression,
+}
+
+/// A frame read from a recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedFrame {
+	/// Format of the frame data.
+	pub buffer_format: BufferFormat,
+	/// Grab info of the frame as it was captured.
+	pub info: FrameGrabInfo,
+	/// The uncompressed frame data.
+	pub data: Vec<u8>,
+}
+
+impl RecordedFrame {
+	/// The frame as if it was just captured.
+	pub fn frame_info(&self) -> SystemFrameInfo<'_> {
+		SystemFrameInfo {
+			buffer: &self.data,
+			width: self.info.width,
+			height: self.info.height,
+			buffer_format: self.buffer_format,
+			current_frame: self.info.current_frame,
+			is_new_frame: self.info.is_new_frame,
+			timestamp_us: self.info.timestamp_us,
+			missed_frames: self.info.missed_frames,
+			diff_map: None,
+		}
+	}
+}
+
+/// Writes captured frames to a recording.
+pub struct RecordingWriter<W: Write> {
+	writer: W,
+	compression: Compression,
+	frames: u64,
+}
+
+impl<W: Write> RecordingWriter<W> {
+	/// Start a recording of a session with `options`, writing the header to `writer`.
+	///
+	/// Fails with [`io::ErrorKind::InvalidInput`] if `status` has more outputs than NvFBC reports,
+	/// which could not be read back.
+	pub fn new(mut writer: W, status: &Status, options: &SessionOptions, compression: Compression) -> io::Result<Self> {
+		if status.outputs.len() > nvfbc_sys::NVFBC_OUTPUT_MAX as usize {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				format!("cannot record {} outputs, at most {} are supported", status.outputs.len(), nvfbc_sys::NVFBC_OUTPUT_MAX),
+			));
+		}
+
+		let mut header = Vec::new();
+		header.extend_from_slice(MAGIC);
+		header.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+		header.push(compression.id());
+		encode_status(&mut header, status);
+		encode_options(&mut header, options);
+		writer.write_all(&header)?;
+
+		Ok(Self { writer, compression, frames: 0 })
+	}
+
+	/// Number of frames written so far.
+	pub fn frames(&self) -> u64 {
+		self.frames
+	}
+
+	/// Append a captured frame to the recording.
+	pub fn write_frame(&mut self, frame: &SystemFrameInfo) -> io::Result<()> {
+		let data = self.compression.compress(frame.buffer)?;
+
+		let mut record = Vec::with_capacity(42);
+		record.push(FRAME_TAG);
+		put_u32(&mut record, frame.buffer_format as u32);
+		put_u32(&mut record, frame.width);
+		put_u32(&mut record, frame.height);
+		put_u32(&mut record, frame.current_frame);
+		record.push(frame.is_new_frame as u8);
+		record.extend_from_slice(&frame.timestamp_us.to_le_bytes());
+		put_u32(&mut record, frame.missed_frames);
+		put_u32(&mut record, length(frame.buffer.len())?);
+		put_u32(&mut record, length(data.len())?);
+		self.writer.write_all(&record)?;
+		self.writer.write_all(&data)?;
+		self.frames += 1;
+
+		Ok(())
+	}
+
+	/// Flush and return the underlying writer.
+	pub fn finish(mut self) -> io::Result<W> {
+		self.writer.flush()?;
+		Ok(self.writer)
+	}
+}
+
+impl RecordingWriter<BufWriter<File>> {
+	/// Start a recording in the file at `path`.
+	pub fn create(path: impl AsRef<Path>, status: &Status, options: &SessionOptions, compression: Compression) -> io::Result<Self> {
+		Self::new(BufWriter::new(File::create(path)?), status, options, compression)
+	}
+}
+
+/// Reads frames from a recording.
+pub struct RecordingReader<R: Read> {
+	reader: R,
+	header: RecordingHeader,
+}
+
+impl<R: Read> RecordingReader<R> {
+	/// Read the header of a recording from `reader`.
+	pub fn new(mut reader: R) -> io::Result<Self> {
+		let mut magic = [0; 8];
+		reader.read_exact(&mut magic)?;
+		if &magic != MAGIC {
+			return Err(invalid_data("not an NvFBC recording".to_string()));
+		}
+		let version = u16::from_le_bytes(read_array(&mut reader)?);
+		if version != FORMAT_VERSION {
+			return Err(invalid_data(format!("unsupported recording format version {}", version)));
+		}
+
+		let compression = Compression::from_id(read_u8(&mut reader)?)?;
+		let status = decode_status(&mut reader)?;
+		let options = decode_options(&mut reader)?;
+
+		Ok(Self { reader, header: RecordingHeader { status, options, compression } })
+	}
+
+	/// The header of the recording.
+	pub fn header(&self) -> &RecordingHeader {
+		&self.header
+	}
+
+	/// Read the next frame, or `None` at the end of the recording.
+	pub fn next_frame(&mut self) -> io::Result<Option<RecordedFrame>> {
+		let mut tag = [0];
+		if self.reader.read(&mut tag)? == 0 {
+			return Ok(None);
+		}
+		if tag[0] != FRAME_TAG {
+			return Err(invalid_data(format!("unknown record tag {}", tag[0])));
+		}
+
+		let reader = &mut self.reader;
+		let buffer_format = decode_buffer_format(read_u32(reader)?)?;
+		let width = read_u32(reader)?;
+		let height = read_u32(reader)?;
+		let current_frame = read_u32(reader)?;
+		let is_new_frame = read_u8(reader)? != 0;
+		let timestamp_us = u64::from_le_bytes(read_array(reader)?);
+		let missed_frames = read_u32(reader)?;
+		let byte_size = read_u32(reader)?;
+		let stored_size = read_u32(reader)?;
+
+		if byte_size as usize != buffer_format.frame_size(width, height) {
+			return Err(invalid_data(format!("frame of {} bytes does not match its {}x{} size", byte_size, width, height)));
+		}
+
+		let mut data = Vec::new();
+		reader.take(stored_size as u64).read_to_end(&mut data)?;
+		if data.len() != stored_size as usize {
+			return Err(io::ErrorKind::UnexpectedEof.into());
+		}
+		let data = self.header.compression.decompress(data, byte_size as usize)?;
+
+		Ok(Some(RecordedFrame {
+			buffer_format,
+			info: FrameGrabInfo { width, height, byte_size, current_frame, is_new_frame, timestamp_us, missed_frames },
+			data,
+		}))
+	}
+}
+
+impl RecordingReader<BufReader<File>> {
+	/// Open the recording in the file at `path`.
+	pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+		Self::new(BufReader::new(File::open(path)?))
+	}
+}
+
+/// How a [`ReplayCapturer`] paces the recorded frames.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Pacing {
+	/// Make frames available at the rate they were recorded, using their timestamps.
+	RealTime,
+	/// Return the next recorded frame on every grab, without waiting.
+	AsFastAsPossible,
+}
+
+/// Replays a recording as a [`Capture`] source.
+///
+/// The tracking, capture box and frame size of the session options are ignored,
+/// since they were applied when the frames were recorded.
+/// If a different buffer format is requested, frames are converted.
+///
+/// Starting a session after stopping one continues where the previous session stopped.
+pub struct ReplayCapturer<R: Read = BufReader<File>> {
+	reader: RecordingReader<R>,
+	pacing: Pacing,
+
+	/// Options of the running session, if any.
+	session: Option<SessionOptions>,
+
+	/// When the running session was started.
+	started: Instant,
+
+	/// Timestamp of the first frame returned by the running session.
+	first_timestamp: Option<u64>,
+
+	/// The most recently returned frame.
+	current: Option<RecordedFrame>,
+
+	/// The frame after `current`, if it was already read.
+	pending: Option<RecordedFrame>,
+
+	/// Whether the end of the recording was reached.
+	finished: bool,
+
+	/// `current` converted to the requested buffer format, if it differs from the recorded format.
+	converted: Vec<u8>,
+}
+
+impl ReplayCapturer {
+	/// Replay the recording in the file at `path`.
+	pub fn open(path: impl AsRef<Path>, pacing: Pacing) -> io::Result<Self> {
+		Ok(Self::new(RecordingReader::open(path)?, pacing))
+	}
+}
+
+impl<R: Read> ReplayCapturer<R> {
+	/// Replay the frames of `reader`.
+	pub fn new(reader: RecordingReader<R>, pacing: Pacing) -> Self {
+		Self {
+			reader,
+			pacing,
+			session: None,
+			started: Instant::now(),
+			first_timestamp: None,
+			current: None,
+			pending: None,
+			finished: false,
+			converted: Vec::new(),
+		}
+	}
+
+	/// The header of the replayed recording.
+	pub fn header(&self) -> &RecordingHeader {
+		self.reader.header()
+	}
+
+	/// Whether all recorded frames have been returned.
+	pub fn is_finished(&mut self) -> Result<bool, Error> {
+		Ok(self.peek()?.is_none())
+	}
+
+	/// The frame after the most recently returned frame, reading it if needed.
+	fn peek(&mut self) -> Result<Option<&RecordedFrame>, Error> {
+		if self.pending.is_none() && !self.finished {
+			self.pending = self.reader.next_frame().map_err(read_error)?;
+			self.finished = self.pending.is_none();
+		}
+		Ok(self.pending.as_ref())
+	}
+
+	/// When `frame` is due for real-time pacing.
+	fn due_at(&mut self, frame: &FrameGrabInfo) -> Instant {
+		let first = *self.first_timestamp.get_or_insert(frame.timestamp_us);
+		self.started + Duration::from_micros(frame.timestamp_us.saturating_sub(first))
+	}
+
+	/// Take the latest recorded frame that is due at `now`, counting the frames before it as missed.
+	fn take_due(&mut self, now: Instant) -> Result<Option<RecordedFrame>, Error> {
+		let mut latest: Option<RecordedFrame> = None;
+		while let Some(info) = self.peek()?.map(|frame| frame.info) {
+			if self.due_at(&info) > now {
+				break;
+			}
+
+			let mut frame = self.pending.take().expect("peeked frame");
+			if let Some(skipped) = latest {
+				frame.info.missed_frames = frame.info.missed_frames
+					.saturating_add(skipped.info.missed_frames)
+					.saturating_add(skipped.info.is_new_frame as u32);
+			}
+			latest = Some(frame);
+		}
+		Ok(latest)
+	}
+
+	/// Make the next frame current for a real-time paced grab. Returns whether the current frame changed.
+	fn grab_real_time(&mut self, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<bool, Error> {
+		let now = Instant::now();
+		let due = self.take_due(now)?;
+
+		// Frames that are due but skipped while waiting for a newer frame.
+		let mut missed = 0;
+		match (capture_method, due) {
+			(CaptureMethod::NoWait | CaptureMethod::NoWaitIfNewFrame, Some(frame)) => {
+				self.current = Some(frame);
+				return Ok(true);
+			},
+			(CaptureMethod::NoWait, None) if self.current.is_some() => return Ok(false),
+			(CaptureMethod::Blocking, Some(frame)) if self.current.is_none() => {
+				self.current = Some(frame);
+				return Ok(true);
+			},
+			(CaptureMethod::Blocking, Some(frame)) => {
+				missed = frame.info.missed_frames.saturating_add(frame.info.is_new_frame as u32);
+			},
+			_ => {},
+		}
+
+		let Some(info) = self.peek()?.map(|frame| frame.info) else {
+			return Err(end_of_recording());
+		};
+		let wait = self.due_at(&info).saturating_duration_since(now);
+		match timeout {
+			Some(timeout) if timeout > Duration::ZERO && timeout < wait && self.current.is_some() => {
+				std::thread::sleep(timeout);
+				Ok(false)
+			},
+			_ => {
+				std::thread::sleep(wait);
+				let mut frame = self.pending.take().expect("peeked frame");
+				frame.info.missed_frames = frame.info.missed_frames.saturating_add(missed);
+				self.current = Some(frame);
+				Ok(true)
+			},
+		}
+	}
+}
+
+fn read_error(error: io::Error) -> Error {
+	Error::new(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_INTERNAL, Some(format!("failed to read recording: {}", error)))
+}
+
+fn end_of_recording() -> Error {
+	Error::new(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_BAD_REQUEST, Some("end of recording".to_string()))
+}
+
+fn not_started() -> Error {
+	Error::new(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_BAD_REQUEST, Some("no capture session was started".to_string()))
+}
+
+impl<R: Read> Capture for ReplayCapturer<R> {
+	fn status(&self) -> Result<Status, Error> {
+		Ok(Status { currently_capturing: self.session.is_some(), ..self.header().status.clone() })
+	}
+
+	fn start_with_options(&mut self, options: &SessionOptions) -> Result<(), Error> {
+		if self.session.is_some() {
+			return Err(Error::new(
+				nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_BAD_REQUEST,
+				Some("a capture session was already started".to_string()),
+			));
+		}
+
+		self.session = Some(options.clone());
+		self.started = Instant::now();
+		self.first_timestamp = None;
+		Ok(())
+	}
+
+	fn stop(&mut self) -> Result<(), Error> {
+		self.session.take().ok_or_else(not_started)?;
+		Ok(())
+	}
+
+	fn next_frame(&mut self, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<SystemFrameInfo<'_>, Error> {
+		let Some(session) = &self.session else {
+			return Err(not_started());
+		};
+		let buffer_format = session.buffer_format;
+
+		let is_new_frame = match self.pacing {
+			Pacing::AsFastAsPossible => {
+				self.peek()?;
+				self.current = Some(self.pending.take().ok_or_else(end_of_recording)?);
+				true
+			},
+			Pacing::RealTime => self.grab_real_time(capture_method, timeout)?,
+		};
+
+		let Some(current) = &self.current else {
+			return Err(end_of_recording());
+		};
+		if is_new_frame && current.buffer_format != buffer_format {
+			let FrameGrabInfo { width, height, .. } = current.info;
+			let rgb = convert::to_rgb(&current.data, width, height, current.buffer_format)
+				.map_err(|e| Error::new(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_INTERNAL, Some(e.to_string())))?;
+			self.converted = convert::from_rgb(&rgb, width, height, buffer_format)
+				.map_err(|e| Error::new(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_INTERNAL, Some(e.to_string())))?;
+		}
+
+		let mut frame = current.frame_info();
+		if current.buffer_format != buffer_format {
+			frame.buffer = &self.converted;
+			frame.buffer_format = buffer_format;
+		}
+		if !is_new_frame {
+			frame.is_new_frame = false;
+			frame.missed_frames = 0;
+		}
+		Ok(frame)
+	}
+}
+
+fn invalid_data(message: String) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn length(len: usize) -> io::Result<u32> {
+	u32::try_from(len).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame is too large to record"))
+}
+
+fn put_u32(buffer: &mut Vec<u8>, value: u32) {
+	buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_box(buffer: &mut Vec<u8>, value: &Box) {
+	for value in [value.x, value.y, value.w, value.h] {
+		put_u32(buffer, value);
+	}
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+	let mut bytes = [0; N];
+	reader.read_exact(&mut bytes)?;
+	Ok(bytes)
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+	Ok(read_array::<1>(reader)?[0])
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+	Ok(u32::from_le_bytes(read_array(reader)?))
+}
+
+fn read_box(reader: &mut impl Read) -> io::Result<Box> {
+	Ok(Box { x: read_u32(reader)?, y: read_u32(reader)?, w: read_u32(reader)?, h: read_u32(reader)? })
+}
+
+fn decode_buffer_format(value: u32) -> io::Result<BufferFormat> {
+	BufferFormat::ALL.into_iter()
+		.find(|format| *format as u32 == value)
+		.ok_or_else(|| invalid_data(format!("unknown buffer format {}", value)))
+}
+
+/// Maximum length in bytes of an output name.
+///
+/// NvFBC reports names of at most `NVFBC_OUTPUT_NAME_LEN` bytes, but every byte that is not valid UTF-8
+/// is decoded as U+FFFD, which takes 3 bytes.
+const MAX_OUTPUT_NAME_LEN: usize = 3 * nvfbc_sys::NVFBC_OUTPUT_NAME_LEN as usize;
+
+/// `name` truncated to at most [`MAX_OUTPUT_NAME_LEN`] bytes, at a character boundary.
+fn truncate_output_name(name: &str) -> &str {
+	let len = (0..=name.len().min(MAX_OUTPUT_NAME_LEN)).rev().find(|&len| name.is_char_boundary(len)).unwrap_or(0);
+	&name[..len]
+}
+
+pub(crate) fn encode_status(buffer: &mut Vec<u8>, status: &Status) {
+	let flags = [
+		status.is_capture_possible,
+		status.currently_capturing,
+		status.can_create_now,
+		status.xrandr_available,
+		status.in_modeset,
+	];
+	buffer.push(flags.iter().enumerate().fold(0, |bits, (i, flag)| bits | (*flag as u8) << i));
+	put_u32(buffer, status.screen_size.w);
+	put_u32(buffer, status.screen_size.h);
+	put_u32(buffer, status.nvfbc_version.major);
+	put_u32(buffer, status.nvfbc_version.minor);
+	put_u32(buffer, status.outputs.len() as u32);
+	for output in &status.outputs {
+		let name = truncate_output_name(&output.name);
+		put_u32(buffer, output.id);
+		put_u32(buffer, name.len() as u32);
+		buffer.extend_from_slice(name.as_bytes());
+		put_box(buffer, &output.tracked_box);
+	}
+}
+
+fn decode_status(reader: &mut impl Read) -> io::Result<Status> {
+	let flags = read_u8(reader)?;
+	let flag = |i: u8| flags & (1 << i) != 0;
+	let screen_size = Size { w: read_u32(reader)?, h: read_u32(reader)? };
+	let nvfbc_version = Version { major: read_u32(reader)?, minor: read_u32(reader)? };
+
+	let count = read_u32(reader)?;
+	if count > nvfbc_sys::NVFBC_OUTPUT_MAX {
+		return Err(invalid_data(format!("recording has {} outputs", count)));
+	}
+	let mut outputs = Vec::with_capacity(count as usize);
+	for _ in 0..count {
+		let id = read_u32(reader)?;
+		let len = read_u32(reader)?;
+		if len as usize > MAX_OUTPUT_NAME_LEN {
+			return Err(invalid_data(format!("output name of {} bytes", len)));
+		}
+		let mut name = vec![0; len as usize];
+		reader.read_exact(&mut name)?;
+		let name = String::from_utf8(name).map_err(|_| invalid_data("output name is not UTF-8".to_string()))?;
+		outputs.push(Output { id, name, tracked_box: read_box(reader)? });
+	}
+
+	Ok(Status {
+		is_capture_possible: flag(0),
+		currently_capturing: flag(1),
+		can_create_now: flag(2),
+		screen_size,
+		xrandr_available: flag(3),
+		outputs,
+		nvfbc_version,
+		in_modeset: flag(4),
+	})
+}
+
+fn encode_options(buffer: &mut Vec<u8>, options: &SessionOptions) {
+	put_u32(buffer, options.buffer_format as u32);
+	put_u32(buffer, options.fps);
+	let (kind, id) = match options.tracking {
+		Tracking::Default => (0, 0),
+		Tracking::Output(id) => (1, id),
+		Tracking::Screen => (2, 0),
+	};
+	buffer.push(kind);
+	put_u32(buffer, id);
+	buffer.push(options.capture_box.is_some() as u8);
+	put_box(buffer, &options.capture_box.unwrap_or(Box { x: 0, y: 0, w: 0, h: 0 }));
+	buffer.push(options.frame_size.is_some() as u8);
+	let frame_size = options.frame_size.unwrap_or(Size { w: 0, h: 0 });
+	put_u32(buffer, frame_size.w);
+	put_u32(buffer, frame_size.h);
+	buffer.push(options.with_cursor as u8);
+}
+
+pub(crate) fn decode_options(reader: &mut impl Read) -> io::Result<SessionOptions> {
+	let buffer_format = decode_buffer_format(read_u32(reader)?)?;
+	let fps = read_u32(reader)?;
+	let kind = read_u8(reader)?;
+	let id = read_u32(reader)?;
+	let tracking = match kind {
+		0 => Tracking::Default,
+		1 => Tracking::Output(id),
+		2 => Tracking::Screen,
+		kind => return Err(invalid_data(format!("unknown tracking {}", kind))),
+	};
+	let has_capture_box = read_u8(reader)? != 0;
+	let capture_box = read_box(reader)?;
+	let has_frame_size = read_u8(reader)? != 0;
+	let frame_size = Size { w: read_u32(reader)?, h: read_u32(reader)? };
+	let with_cursor = read_u8(reader)? != 0;
+
+	Ok(SessionOptions {
+		buffer_format,
+		fps,
+		tracking,
+		capture_box: has_capture_box.then_some(capture_box),
+		frame_size: has_frame_size.then_some(frame_size),
+		with_cursor,
+		// Diff maps are not recorded.
+		diff_map_scaling_factor: None,
+	})
+}
@@ -0,0 +1,212 @@
+//! Resizing frames on the CPU.
+//!
+//! NvFBC scales frames on the GPU to a single size, see [`SessionOptions::frame_size`](crate::SessionOptions::frame_size).
+//! This module resizes captured frames to any number of other sizes, for example previews and thumbnails of a recording.
+//! Every plane is resized separately in its own format, so the chroma planes of NV12 frames stay subsampled.
+//! Large frames are resized on multiple threads.
+//!
+//! ```
+//! use nvfbc::resample::{resize, Filter};
+//! use nvfbc::{BufferFormat, Size};
+//!
+//! let frame = vec![0x80; BufferFormat::Nv12.frame_size(1920, 1080)];
+//! let preview = resize(&frame, Size { w: 1920, h: 1080 }, BufferFormat::Nv12, Size { w: 640, h: 360 }, Filter::Lanczos3).unwrap();
+//! assert_eq!(preview.len(), BufferFormat::Nv12.frame_size(640, 360));
+//! ```
+
+use crate::convert::check_size;
+use crate::{BufferFormat, BufferSizeError, Size};
+
+/// Number of fractional bits of the fixed-point filter weights.
+const PRECISION_BITS: u32 = 14;
+
+/// Number of output bytes of a pass above which it is split over multiple threads.
+const PARALLEL_BYTES: usize = 1 << 18;
+
+/// The filter used to compute an output sample from the input samples around it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Filter {
+	/// The average of the input samples covered by the output sample, or the nearest sample when enlarging.
+	Box,
+	/// Linear interpolation between the nearest samples.
+	Bilinear,
+	/// A windowed sinc over three samples on either side. Sharpest, but slowest, and may ring around hard edges.
+	Lanczos3,
+}
+
+impl Filter {
+	/// Distance from the center beyond which the filter is zero, in input samples when enlarging.
+	fn support(&self) -> f64 {
+		match self {
+			Filter::Box => 0.5,
+			Filter::Bilinear => 1.0,
+			Filter::Lanczos3 => 3.0,
+		}
+	}
+
+	fn weight(&self, x: f64) -> f64 {
+		match self {
+			Filter::Box => if (-0.5..0.5).contains(&x) { 1.0 } else { 0.0 },
+			Filter::Bilinear => (1.0 - x.abs()).max(0.0),
+			Filter::Lanczos3 => if x.abs() < 3.0 { sinc(x) * sinc(x / 3.0) } else { 0.0 },
+		}
+	}
+}
+
+fn sinc(x: f64) -> f64 {
+	if x == 0.0 {
+		1.0
+	} else {
+		let x = x * std::f64::consts::PI;
+		x.sin() / x
+	}
+}
+
+/// Resize a frame of `size` pixels in `format` to `new_size` pixels.
+pub fn resize(buffer: &[u8], size: Size, format: BufferFormat, new_size: Size, filter: Filter) -> Result<Vec<u8>, BufferSizeError> {
+	let mut output = vec![0; format.frame_size(new_size.w, new_size.h)];
+	resize_into(buffer, size, format, &mut output, new_size, filter)?;
+	Ok(output)
+}
+
+/// Resize a frame of `size` pixels in `format` into `output`, a frame of `new_size` pixels in the same format.
+pub fn resize_into(buffer: &[u8], size: Size, format: BufferFormat, output: &mut [u8], new_size: Size, filter: Filter) -> Result<(), BufferSizeError> {
+	check_size(buffer, size.w, size.h, format)?;
+	check_size(output, new_size.w, new_size.h, format)?;
+
+	for (plane, new_plane) in format.planes(size.w, size.h).iter().zip(format.planes(new_size.w, new_size.h)) {
+		resize_plane(
+			&buffer[plane.range()],
+			Size { w: plane.width, h: plane.height },
+			&mut output[new_plane.range()],
+			Size { w: new_plane.width, h: new_plane.height },
+			plane.bytes_per_sample,
+			filter,
+		);
+	}
+	Ok(())
+}
+
+/// The input samples contributing to an output sample.
+struct Taps {
+	/// Index of the first input sample.
+	start: usize,
+	/// Fixed-point weights of the input samples, starting at `start`, that add up to one.
+	weights: Vec<i32>,
+}
+
+/// The taps of every output sample when resizing `len` samples to `new_len` samples.
+///
+/// Input samples beyond the edges repeat the samples at the edges.
+fn taps(len: u32, new_len: u32, filter: Filter) -> Vec<Taps> {
+	let ratio = len as f64 / new_len as f64;
+	// Widen the filter when shrinking, so that it covers all input samples.
+	let scale = ratio.max(1.0);
+	let support = filter.support() * scale;
+	let last = len as i64 - 1;
+
+	(0..new_len).map(|i| {
+		let center = (i as f64 + 0.5) * ratio;
+		let first = (center - support).floor() as i64;
+		let end = (center + support).ceil() as i64;
+		let start = first.clamp(0, last);
+		let mut weights = vec![0.0; (end.clamp(0, last) - start + 1) as usize];
+		for j in first..=end {
+			weights[(j.clamp(0, last) - start) as usize] += filter.weight((j as f64 + 0.5 - center) / scale);
+		}
+
+		let total: f64 = weights.iter().sum();
+		if total.abs() < f64::EPSILON {
+			// Fall back to the nearest sample.
+			let nearest = (center.floor() as i64).clamp(0, last);
+			return Taps { start: nearest as usize, weights: vec![1 << PRECISION_BITS] };
+		}
+		let mut weights: Vec<i32> = weights.iter().map(|weight| (weight / total * (1 << PRECISION_BITS) as f64).round() as i32).collect();
+		// Make the rounded weights add up to one again.
+		let error = (1 << PRECISION_BITS) - weights.iter().sum::<i32>();
+		if let Some(largest) = weights.iter_mut().max() {
+			*largest += error;
+		}
+		Taps { start: start as usize, weights }
+	}).collect()
+}
+
+/// Convert a fixed-point sum of weighted samples to a sample.
+fn sample(sum: i32) -> u8 {
+	((sum + (1 << (PRECISION_BITS - 1))) >> PRECISION_BITS).clamp(0, 255) as u8
+}
+
+/// Resize a plane of `size` samples of `channels` interleaved bytes, first horizontally and then vertically.
+fn resize_plane(input: &[u8], size: Size, output: &mut [u8], new_size: Size, channels: usize, filter: Filter) {
+	if new_size.w == 0 || new_size.h == 0 {
+		return;
+	}
+	if size.w == 0 || size.h == 0 {
+		output.fill(0);
+		return;
+	}
+	if size == new_size {
+		output.copy_from_slice(input);
+		return;
+	}
+
+	let row = size.w as usize * channels;
+	let new_row = new_size.w as usize * channels;
+	let horizontal = taps(size.w, new_size.w, filter);
+	let vertical = taps(size.h, new_size.h, filter);
+
+	let mut resized_rows = vec![0; new_row * size.h as usize];
+	for_rows(&mut resized_rows, new_row, |y, output| {
+		let input = &input[y * row..(y + 1) * row];
+		for (x, taps) in horizontal.iter().enumerate() {
+			for channel in 0..channels {
+				let sum: i32 = taps.weights.iter().enumerate()
+					.map(|(i, weight)| input[(taps.start + i) * channels + channel] as i32 * weight)
+					.sum();
+				output[x * channels + channel] = sample(sum);
+			}
+		}
+	});
+
+	for_rows(output, new_row, |y, output| {
+		let taps = &vertical[y];
+		let mut sums = vec![0i32; new_row];
+		for (i, weight) in taps.weights.iter().enumerate() {
+			let input = &resized_rows[(taps.start + i) * new_row..(taps.start + i + 1) * new_row];
+			for (sum, &value) in sums.iter_mut().zip(input) {
+				*sum += value as i32 * weight;
+			}
+		}
+		for (value, sum) in output.iter_mut().zip(sums) {
+			*value = sample(sum);
+		}
+	});
+}
+
+/// Call `f` with the index and the bytes of every row of `row` bytes in `buffer`, on multiple threads if it is large.
+fn for_rows(buffer: &mut [u8], row: usize, f: impl Fn(usize, &mut [u8]) + Sync) {
+	let rows = buffer.len() / row;
+	let threads = if buffer.len() >= PARALLEL_BYTES {
+		std::thread::available_parallelism().map_or(1, |threads| threads.get()).min(rows)
+	} else {
+		1
+	};
+	if threads <= 1 {
+		buffer.chunks_exact_mut(row).enumerate().for_each(|(y, output)| f(y, output));
+		return;
+	}
+
+	let rows_per_thread = rows.div_ceil(threads);
+	std::thread::scope(|scope| {
+		for (chunk, output) in buffer.chunks_mut(rows_per_thread * row).enumerate() {
+			let f = &f;
+			scope.spawn(move || {
+				for (y, output) in (chunk * rows_per_thread..).zip(output.chunks_exact_mut(row)) {
+					f(y, output);
+				}
+			});
+		}
+	});
+}
@@ -0,0 +1,835 @@
+//! A view-only VNC server speaking RFB 3.8, sending only the regions that changed according to the diff map.
+//!
+//! This module requires the `rfb` feature.
+//!
+//! [`RfbServer`] runs a capture session in system memory in the background and serves its frames
+//! to any number of viewers at the same time, without authentication.
+//! Frame updates contain the regions that NvFBC marked as changed in the [`DiffMap`](crate::diff_map::DiffMap),
+//! or the entire frame if the session has no diff maps.
+//! Updates are sent with the Raw or ZRLE encoding, whichever the viewer prefers,
+//! and resolution changes are announced with the DesktopSize pseudo-encoding.
+//! Viewers without DesktopSize keep the size they connected with and receive the part of the frames that fits in it.
+//! Frames wider or higher than 65535 pixels, the maximum of the protocol, are cropped.
+//! Input events of viewers are ignored.
+//!
+//! ```no_run
+//! use nvfbc::rfb::{RfbOptions, RfbServer};
+//! use nvfbc::SystemCapturer;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let _server = RfbServer::bind("127.0.0.1:5900", SystemCapturer::new, RfbOptions::new(30))?;
+//!     std::thread::park();
+//!     Ok(())
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use flate2::{Compress, Compression, FlushCompress};
+
+use crate::common::{ignore_poison, lock_ignoring_poison};
+use crate::convert;
+use crate::server::{Acceptor, CaptureSupervisor, POLL_INTERVAL};
+use crate::{Box, BufferFormat, Capture, Error, SessionOptions};
+
+/// The protocol version of this server.
+const PROTOCOL_VERSION: &[u8; 12] = b"RFB 003.008\n";
+
+/// The "None" security type.
+const SECURITY_NONE: u8 = 1;
+
+const ENCODING_RAW: i32 = 0;
+const ENCODING_ZRLE: i32 = 16;
+const ENCODING_DESKTOP_SIZE: i32 = -223;
+
+/// Width and height of the tiles of the ZRLE encoding.
+const ZRLE_TILE_SIZE: u32 = 64;
+
+/// Number of frames whose changed regions are kept for viewers that did not request an update yet.
+const DAMAGE_HISTORY: usize = 64;
+
+/// Maximum number of rectangles in an update, above which their bounding box is sent instead.
+const MAX_RECTANGLES: usize = 64;
+
+/// Maximum length of the cut text of a viewer that is accepted.
+const MAX_CUT_TEXT: u32 = 1 << 20;
+
+/// Configuration of an [`RfbServer`].
+#[derive(Debug, Clone)]
+pub struct RfbOptions {
+	/// Options of the capture session.
+	///
+	/// The buffer format is always [`BufferFormat::Rgb`], other formats are ignored.
+	/// Without a [`SessionOptions::diff_map_scaling_factor`] every update contains the entire frame.
+	pub session: SessionOptions,
+
+	/// Name of the desktop shown by viewers.
+	pub name: String,
+}
+
+impl RfbOptions {
+	/// Capture at `fps` frames per second with diff maps of 16x16 pixel blocks.
+	pub fn new(fps: u32) -> Self {
+		Self {
+			session: SessionOptions {
+				diff_map_scaling_factor: Some(16),
+				..SessionOptions::new(BufferFormat::Rgb, fps)
+			},
+			name: "NvFBC".to_string(),
+		}
+	}
+}
+
+/// The latest frame and the regions that changed in the frames before it.
+#[derive(Debug, Default)]
+struct Framebuffer {
+	/// Incremented for every frame, 0 before the first frame.
+	sequence: u64,
+	width: u32,
+	height: u32,
+	rgb: Arc<Vec<u8>>,
+	/// The regions that changed in the most recent frames, by sequence.
+	damage: VecDeque<(u64, Vec<Box>)>,
+}
+
+impl Framebuffer {
+	/// The area of the frame that can be sent, which is cropped to the maximum size of the protocol.
+	fn full(&self) -> Box {
+		let (w, h) = clamp_size(self.width, self.height);
+		Box { x: 0, y: 0, w, h }
+	}
+
+	/// The regions that changed after frame `sequence`, or `None` if they are no longer known.
+	fn damage_since(&self, sequence: u64) -> Option<Vec<Box>> {
+		let oldest = self.damage.front().map_or(self.sequence + 1, |(oldest, _)| *oldest);
+		if oldest > sequence + 1 {
+			return None;
+		}
+		Some(self.damage.iter()
+			.filter(|(frame, _)| *frame > sequence)
+			.flat_map(|(_, boxes)| boxes.iter().copied())
+			.collect())
+	}
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+	framebuffer: Mutex<Framebuffer>,
+	changed: Condvar,
+	stop: AtomicBool,
+	viewers: AtomicUsize,
+}
+
+impl Shared {
+	fn lock(&self) -> MutexGuard<'_, Framebuffer> {
+		lock_ignoring_poison(&self.framebuffer)
+	}
+
+	fn stopping(&self) -> bool {
+		self.stop.load(Ordering::Relaxed)
+	}
+
+	fn wait<'a>(&self, framebuffer: MutexGuard<'a, Framebuffer>) -> MutexGuard<'a, Framebuffer> {
+		ignore_poison(self.changed.wait_timeout(framebuffer, POLL_INTERVAL)).0
+	}
+}
+
+/// Crop a frame size to the maximum size of the protocol.
+fn clamp_size(width: u32, height: u32) -> (u32, u32) {
+	(width.min(u16::MAX as u32), height.min(u16::MAX as u32))
+}
+
+/// A view-only VNC server for the frames of a capture source.
+///
+/// Dropping the server stops the capture session and disconnects all viewers.
+pub struct RfbServer {
+	acceptor: Acceptor,
+	shared: Arc<Shared>,
+	capture_thread: Option<JoinHandle<()>>,
+}
+
+impl RfbServer {
+	/// Listen on `addr` and serve the frames captured from the capturer created by `create`.
+	///
+	/// The capturer is created on the capture thread, because capturers such as [`SystemCapturer`](crate::SystemCapturer)
+	/// cannot be sent to other threads. Creating the capturer and starting the capture session are retried when they fail.
+	/// Use port 0 to let the operating system pick a free port, see [`RfbServer::local_addr`].
+	pub fn bind<C, F>(addr: impl ToSocketAddrs, create: F, options: RfbOptions) -> io::Result<Self>
+	where
+		C: Capture,
+		F: FnMut() -> Result<C, Error> + Send + 'static,
+	{
+		let listener = TcpListener::bind(addr)?;
+		let shared = Arc::new(Shared::default());
+
+		let session = SessionOptions { buffer_format: BufferFormat::Rgb, ..options.session };
+		let capture_thread = std::thread::Builder::new()
+			.name("nvfbc-rfb-capture".to_string())
+			.spawn({
+				let shared = shared.clone();
+				move || capture(create, &session, &shared)
+			})?;
+
+		let name = options.name;
+		let acceptor = Acceptor::spawn("nvfbc-rfb", listener, {
+			let shared = shared.clone();
+			move |stream| {
+				shared.viewers.fetch_add(1, Ordering::Relaxed);
+				let result = serve(&stream, &shared, &name);
+				let _ = stream.shutdown(Shutdown::Both);
+				shared.viewers.fetch_sub(1, Ordering::Relaxed);
+				result
+			}
+		});
+		let acceptor = match acceptor {
+			Ok(acceptor) => acceptor,
+			Err(e) => {
+				shared.stop.store(true, Ordering::Relaxed);
+				let _ = capture_thread.join();
+				return Err(e);
+			},
+		};
+
+		Ok(Self {
+			acceptor,
+			shared,
+			capture_thread: Some(capture_thread),
+		})
+	}
+
+	/// The address the server listens on.
+	pub fn local_addr(&self) -> SocketAddr {
+		self.acceptor.local_addr()
+	}
+
+	/// Number of connected viewers, including viewers that are still in the handshake.
+	pub fn viewers(&self) -> usize {
+		self.shared.viewers.load(Ordering::Relaxed)
+	}
+}
+
+impl Drop for RfbServer {
+	fn drop(&mut self) {
+		self.shared.stop.store(true, Ordering::Relaxed);
+		self.shared.changed.notify_all();
+		self.acceptor.stop();
+		if let Some(thread) = self.capture_thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+/// Capture frames and record the regions that changed until the server stops.
+fn capture<C: Capture>(create: impl FnMut() -> Result<C, Error>, session: &SessionOptions, shared: &Shared) {
+	let interval = Duration::from_secs(1) / session.fps.max(1);
+	let mut supervisor = CaptureSupervisor::new(create, session.clone(), &shared.stop);
+
+	while let Some(frame) = supervisor.next_frame(interval) {
+		let Ok(frame) = frame else {
+			continue;
+		};
+
+		let (sequence, width, height) = {
+			let framebuffer = shared.lock();
+			(framebuffer.sequence, framebuffer.width, framebuffer.height)
+		};
+		let resized = (frame.width, frame.height) != (width, height);
+		if !frame.is_new_frame && !resized && sequence > 0 {
+			continue;
+		}
+		let damage = match frame.diff_map {
+			Some(diff_map) if !resized && sequence > 0 => diff_map.changed_boxes(frame.width, frame.height),
+			_ => vec![Box { x: 0, y: 0, w: frame.width, h: frame.height }],
+		};
+		if damage.is_empty() {
+			continue;
+		}
+		// Capturers that ignore the buffer format of the session are converted.
+		let rgb = match convert::to_rgb(frame.buffer, frame.width, frame.height, frame.buffer_format) {
+			Ok(rgb) => Arc::new(rgb),
+			Err(_) => {
+				// The frame does not match its size and format, so start the session again.
+				supervisor.restart();
+				continue;
+			},
+		};
+
+		let mut framebuffer = shared.lock();
+		framebuffer.sequence += 1;
+		framebuffer.width = frame.width;
+		framebuffer.height = frame.height;
+		framebuffer.rgb = rgb;
+		let sequence = framebuffer.sequence;
+		framebuffer.damage.push_back((sequence, damage));
+		if framebuffer.damage.len() > DAMAGE_HISTORY {
+			framebuffer.damage.pop_front();
+		}
+		drop(framebuffer);
+		shared.changed.notify_all();
+	}
+}
+
+/// The pixel format of a viewer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct PixelFormat {
+	bits_per_pixel: u8,
+	depth: u8,
+	big_endian: bool,
+	true_colour: bool,
+	max: [u16; 3],
+	shift: [u8; 3],
+}
+
+impl PixelFormat {
+	/// The pixel format announced by the server: 32 bits per pixel, little endian, with 8 bits per color.
+	const DEFAULT: PixelFormat = PixelFormat {
+		bits_per_pixel: 32,
+		depth: 24,
+		big_endian: false,
+		true_colour: true,
+		max: [255, 255, 255],
+		shift: [16, 8, 0],
+	};
+
+	fn encode(&self) -> [u8; 16] {
+		let mut encoded = [0; 16];
+		encoded[0] = self.bits_per_pixel;
+		encoded[1] = self.depth;
+		encoded[2] = self.big_endian as u8;
+		encoded[3] = self.true_colour as u8;
+		for (i, max) in self.max.iter().enumerate() {
+			encoded[4 + i * 2..6 + i * 2].copy_from_slice(&max.to_be_bytes());
+		}
+		encoded[10..13].copy_from_slice(&self.shift);
+		encoded
+	}
+
+	fn decode(encoded: &[u8; 16]) -> Self {
+		let max = |i: usize| u16::from_be_bytes([encoded[4 + i * 2], encoded[5 + i * 2]]);
+		PixelFormat {
+			bits_per_pixel: encoded[0],
+			depth: encoded[1],
+			big_endian: encoded[2] != 0,
+			true_colour: encoded[3] != 0,
+			max: [max(0), max(1), max(2)],
+			shift: [encoded[10], encoded[11], encoded[12]],
+		}
+	}
+
+	fn bytes_per_pixel(&self) -> usize {
+		self.bits_per_pixel as usize / 8
+	}
+
+	/// The bytes of a pixel that are sent as a compressed pixel in ZRLE.
+	fn cpixel(&self) -> Range<usize> {
+		if self.bits_per_pixel != 32 || self.depth > 24 {
+			return 0..self.bytes_per_pixel();
+		}
+		let mask = (0..3).fold(0u64, |mask, i| mask | (self.max[i] as u64) << self.shift[i]);
+		match (mask <= 0x00ff_ffff, mask & 0xff == 0, self.big_endian) {
+			(true, _, false) => 0..3,
+			(true, _, true) => 1..4,
+			(false, true, false) => 1..4,
+			(false, true, true) => 0..3,
+			_ => 0..4,
+		}
+	}
+}
+
+/// Converts RGB888 to the pixel format of a viewer.
+struct Translator {
+	format: PixelFormat,
+	tables: [[u32; 256]; 3],
+	cpixel: Range<usize>,
+}
+
+impl Translator {
+	fn new(format: PixelFormat) -> Self {
+		let mut tables = [[0; 256]; 3];
+		for (channel, table) in tables.iter_mut().enumerate() {
+			let max = format.max[channel] as u32;
+			for (value, entry) in table.iter_mut().enumerate() {
+				*entry = ((value as u32 * max + 127) / 255).checked_shl(format.shift[channel] as u32).unwrap_or(0);
+			}
+		}
+		Self { cpixel: format.cpixel(), format, tables }
+	}
+
+	fn pixel(&self, rgb: &[u8]) -> u32 {
+		self.tables[0][rgb[0] as usize] | self.tables[1][rgb[1] as usize] | self.tables[2][rgb[2] as usize]
+	}
+
+	fn put(&self, output: &mut Vec<u8>, pixel: u32) {
+		let bytes = if self.format.big_endian { pixel.to_be_bytes() } else { pixel.to_le_bytes() };
+		match (self.format.bytes_per_pixel(), self.format.big_endian) {
+			(1, _) => output.push(pixel as u8),
+			(2, false) => output.extend_from_slice(&bytes[..2]),
+			(2, true) => output.extend_from_slice(&bytes[2..]),
+			_ => output.extend_from_slice(&bytes),
+		}
+	}
+
+	fn put_cpixel(&self, output: &mut Vec<u8>, pixel: u32) {
+		let start = output.len();
+		self.put(output, pixel);
+		let cpixel = output[start + self.cpixel.start..start + self.cpixel.end].to_vec();
+		output.truncate(start);
+		output.extend_from_slice(&cpixel);
+	}
+}
+
+/// A framebuffer update request of a viewer.
+#[derive(Debug, Copy, Clone)]
+struct UpdateRequest {
+	incremental: bool,
+	area: Box,
+}
+
+/// State of a viewer, updated by the thread reading its messages.
+#[derive(Debug)]
+struct Viewer {
+	pixel_format: PixelFormat,
+	encodings: Vec<i32>,
+	request: Option<UpdateRequest>,
+	/// Set when the viewer disconnected or sent an invalid message.
+	closed: bool,
+}
+
+impl Viewer {
+	fn supports(&self, encoding: i32) -> bool {
+		self.encodings.contains(&encoding)
+	}
+
+	/// The first encoding in the preference order of the viewer that this server supports.
+	fn encoding(&self) -> i32 {
+		self.encodings.iter()
+			.copied()
+			.find(|&encoding| encoding == ENCODING_RAW || encoding == ENCODING_ZRLE)
+			.unwrap_or(ENCODING_RAW)
+	}
+}
+
+/// A framebuffer update to send to a viewer.
+struct Update {
+	rgb: Arc<Vec<u8>>,
+	/// Width of the frame in `rgb`, which may be larger than the framebuffer of the viewer.
+	width: u32,
+	/// The new size of the framebuffer, if it changed.
+	desktop_size: Option<(u32, u32)>,
+	rectangles: Vec<Box>,
+	pixel_format: PixelFormat,
+	encoding: i32,
+}
+
+/// What was last sent to a viewer.
+struct Sent {
+	sequence: u64,
+	width: u32,
+	height: u32,
+}
+
+/// Serve a single viewer until it disconnects or the server stops.
+fn serve(stream: &TcpStream, shared: &Arc<Shared>, name: &str) -> io::Result<()> {
+	let mut writer = stream;
+	stream.set_write_timeout(Some(Duration::from_secs(10)))?;
+	stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+	stream.set_nodelay(true)?;
+
+	// Protocol version.
+	writer.write_all(PROTOCOL_VERSION)?;
+	let mut version = [0; 12];
+	read_exact(stream, &mut version)?;
+	if !supported_version(&version) {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, "unsupported protocol version"));
+	}
+
+	// Security handshake, without authentication.
+	writer.write_all(&[1, SECURITY_NONE])?;
+	let security = read_u8(stream)?;
+	if security != SECURITY_NONE {
+		let reason = b"unsupported security type";
+		writer.write_all(&1u32.to_be_bytes())?;
+		writer.write_all(&(reason.len() as u32).to_be_bytes())?;
+		writer.write_all(reason)?;
+		return Err(io::Error::new(io::ErrorKind::InvalidData, "unsupported security type"));
+	}
+	writer.write_all(&0u32.to_be_bytes())?;
+
+	// ClientInit, the shared flag is ignored because the desktop is always shared.
+	read_u8(stream)?;
+
+	// ServerInit, once the size of the frames is known.
+	let mut framebuffer = shared.lock();
+	while framebuffer.sequence == 0 {
+		if shared.stopping() {
+			return Ok(());
+		}
+		framebuffer = shared.wait(framebuffer);
+	}
+	let full = framebuffer.full();
+	let mut sent = Sent { sequence: 0, width: full.w, height: full.h };
+	drop(framebuffer);
+
+	let mut init = Vec::with_capacity(24 + name.len());
+	init.extend_from_slice(&(sent.width as u16).to_be_bytes());
+	init.extend_from_slice(&(sent.height as u16).to_be_bytes());
+	init.extend_from_slice(&PixelFormat::DEFAULT.encode());
+	init.extend_from_slice(&(name.len() as u32).to_be_bytes());
+	init.extend_from_slice(name.as_bytes());
+	writer.write_all(&init)?;
+
+	// Viewers may wait indefinitely before sending a message.
+	stream.set_read_timeout(None)?;
+	let viewer = Arc::new(Mutex::new(Viewer {
+		pixel_format: PixelFormat::DEFAULT,
+		encodings: Vec::new(),
+		request: None,
+		closed: false,
+	}));
+	let reader = std::thread::Builder::new()
+		.name("nvfbc-rfb-reader".to_string())
+		.spawn({
+			let (stream, viewer, shared) = (stream.try_clone()?, viewer.clone(), shared.clone());
+			move || {
+				let result = read_messages(&stream, &viewer, &shared);
+				lock(&viewer).closed = true;
+				// Take the lock so that the serving thread does not miss the notification.
+				drop(shared.lock());
+				shared.changed.notify_all();
+				result
+			}
+		})?;
+
+	let mut compressor = Compress::new(Compression::default(), true);
+	let result = loop {
+		match next_update(shared, &viewer, &mut sent) {
+			Ok(Some(update)) => {
+				if let Err(e) = send_update(&mut writer, &update, &mut compressor) {
+					break Err(e);
+				}
+			},
+			Ok(None) => break Ok(()),
+			Err(e) => break Err(e),
+		}
+	};
+
+	let _ = stream.shutdown(Shutdown::Both);
+	let _ = reader.join();
+	result
+}
+
+fn lock(viewer: &Mutex<Viewer>) -> MutexGuard<'_, Viewer> {
+	lock_ignoring_poison(viewer)
+}
+
+/// Wait until an update can be sent to the viewer, or return `None` when the viewer disconnected or the server stops.
+fn next_update(shared: &Shared, viewer: &Mutex<Viewer>, sent: &mut Sent) -> io::Result<Option<Update>> {
+	let mut framebuffer = shared.lock();
+	loop {
+		if shared.stopping() {
+			return Ok(None);
+		}
+
+		let mut viewer = lock(viewer);
+		if viewer.closed {
+			return Ok(None);
+		}
+		if let Some(request) = viewer.request {
+			let full = framebuffer.full();
+			let resized = (full.w, full.h) != (sent.width, sent.height);
+			let desktop_size = resized && viewer.supports(ENCODING_DESKTOP_SIZE);
+			// Viewers that cannot resize keep their size and only receive the part of the frame that fits.
+			let visible = if desktop_size {
+				full
+			} else {
+				Box { x: 0, y: 0, w: full.w.min(sent.width), h: full.h.min(sent.height) }
+			};
+
+			let rectangles = if desktop_size {
+				vec![full]
+			} else if !request.incremental {
+				intersect(&[request.area], &visible)
+			} else if framebuffer.sequence > sent.sequence {
+				let damage = framebuffer.damage_since(sent.sequence).unwrap_or_else(|| vec![full]);
+				intersect(&intersect(&damage, &request.area), &visible)
+			} else {
+				Vec::new()
+			};
+			sent.sequence = framebuffer.sequence;
+
+			if desktop_size || !rectangles.is_empty() || !request.incremental {
+				viewer.request = None;
+				if desktop_size {
+					sent.width = full.w;
+					sent.height = full.h;
+				}
+				return Ok(Some(Update {
+					rgb: framebuffer.rgb.clone(),
+					width: framebuffer.width,
+					desktop_size: desktop_size.then_some((full.w, full.h)),
+					rectangles: simplify(rectangles),
+					pixel_format: viewer.pixel_format,
+					encoding: viewer.encoding(),
+				}));
+			}
+		}
+		drop(viewer);
+
+		framebuffer = shared.wait(framebuffer);
+	}
+}
+
+/// The parts of `boxes` within `area`.
+fn intersect(boxes: &[Box], area: &Box) -> Vec<Box> {
+	boxes.iter()
+		.filter_map(|b| {
+			let x = b.x.max(area.x);
+			let y = b.y.max(area.y);
+			let right = (b.x + b.w).min(area.x.saturating_add(area.w));
+			let bottom = (b.y + b.h).min(area.y.saturating_add(area.h));
+			(right > x && bottom > y).then_some(Box { x, y, w: right - x, h: bottom - y })
+		})
+		.collect()
+}
+
+/// Remove duplicate rectangles, and replace too many rectangles by their bounding box.
+fn simplify(mut rectangles: Vec<Box>) -> Vec<Box> {
+	rectangles.sort_by_key(|b| (b.y, b.x, b.w, b.h));
+	rectangles.dedup();
+	if rectangles.len() <= MAX_RECTANGLES {
+		return rectangles;
+	}
+
+	let x = rectangles.iter().map(|b| b.x).min().unwrap_or(0);
+	let y = rectangles.iter().map(|b| b.y).min().unwrap_or(0);
+	let right = rectangles.iter().map(|b| b.x + b.w).max().unwrap_or(0);
+	let bottom = rectangles.iter().map(|b| b.y + b.h).max().unwrap_or(0);
+	vec![Box { x, y, w: right - x, h: bottom - y }]
+}
+
+/// Encode and send a framebuffer update.
+fn send_update(writer: &mut impl Write, update: &Update, compressor: &mut Compress) -> io::Result<()> {
+	let translator = Translator::new(update.pixel_format);
+	let count = update.rectangles.len() + update.desktop_size.is_some() as usize;
+
+	let mut message = vec![0, 0];
+	message.extend_from_slice(&(count as u16).to_be_bytes());
+	if let Some((w, h)) = update.desktop_size {
+		put_rectangle_header(&mut message, &Box { x: 0, y: 0, w, h }, ENCODING_DESKTOP_SIZE);
+	}
+
+	for rectangle in &update.rectangles {
+		put_rectangle_header(&mut message, rectangle, update.encoding);
+		match update.encoding {
+			ENCODING_ZRLE => {
+				let data = encode_zrle(&update.rgb, update.width, rectangle, &translator);
+				let compressed = deflate(compressor, &data)?;
+				message.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+				message.extend_from_slice(&compressed);
+			},
+			_ => encode_raw(&mut message, &update.rgb, update.width, rectangle, &translator),
+		}
+	}
+
+	writer.write_all(&message)?;
+	writer.flush()
+}
+
+fn put_rectangle_header(message: &mut Vec<u8>, rectangle: &Box, encoding: i32) {
+	for value in [rectangle.x, rectangle.y, rectangle.w, rectangle.h] {
+		message.extend_from_slice(&(value as u16).to_be_bytes());
+	}
+	message.extend_from_slice(&encoding.to_be_bytes());
+}
+
+/// The rows of packed RGB888 pixels of `rectangle`, in a frame that is `width` pixels wide.
+fn rows<'a>(rgb: &'a [u8], width: u32, rectangle: &'a Box) -> impl Iterator<Item = &'a [u8]> + 'a {
+	let stride = width as usize * 3;
+	(rectangle.y..rectangle.y + rectangle.h).map(move |y| {
+		let start = y as usize * stride + rectangle.x as usize * 3;
+		&rgb[start..start + rectangle.w as usize * 3]
+	})
+}
+
+fn encode_raw(message: &mut Vec<u8>, rgb: &[u8], width: u32, rectangle: &Box, translator: &Translator) {
+	message.reserve(rectangle.w as usize * rectangle.h as usize * translator.format.bytes_per_pixel());
+	for row in rows(rgb, width, rectangle) {
+		for pixel in row.chunks_exact(3) {
+			translator.put(message, translator.pixel(pixel));
+		}
+	}
+}
+
+/// Encode `rectangle` as uncompressed ZRLE data, using solid, packed palette and raw tiles.
+fn encode_zrle(rgb: &[u8], width: u32, rectangle: &Box, translator: &Translator) -> Vec<u8> {
+	let mut data = Vec::new();
+	let mut pixels = Vec::with_capacity((ZRLE_TILE_SIZE * ZRLE_TILE_SIZE) as usize);
+	for y in (rectangle.y..rectangle.y + rectangle.h).step_by(ZRLE_TILE_SIZE as usize) {
+		for x in (rectangle.x..rectangle.x + rectangle.w).step_by(ZRLE_TILE_SIZE as usize) {
+			let tile = Box {
+				x,
+				y,
+				w: ZRLE_TILE_SIZE.min(rectangle.x + rectangle.w - x),
+				h: ZRLE_TILE_SIZE.min(rectangle.y + rectangle.h - y),
+			};
+			pixels.clear();
+			pixels.extend(rows(rgb, width, &tile).flat_map(|row| row.chunks_exact(3)).map(|pixel| translator.pixel(pixel)));
+			encode_zrle_tile(&mut data, &pixels, tile.w as usize, translator);
+		}
+	}
+	data
+}
+
+fn encode_zrle_tile(data: &mut Vec<u8>, pixels: &[u32], width: usize, translator: &Translator) {
+	let mut palette: Vec<u32> = Vec::with_capacity(17);
+	for &pixel in pixels {
+		if !palette.contains(&pixel) {
+			palette.push(pixel);
+			if palette.len() > 16 {
+				break;
+			}
+		}
+	}
+
+	match palette.len() {
+		1 => {
+			data.push(1);
+			translator.put_cpixel(data, palette[0]);
+		},
+		2..=16 => {
+			data.push(palette.len() as u8);
+			for &color in &palette {
+				translator.put_cpixel(data, color);
+			}
+			let bits = match palette.len() {
+				2 => 1,
+				3..=4 => 2,
+				_ => 4,
+			};
+			for row in pixels.chunks(width) {
+				let (mut byte, mut used) = (0u8, 0);
+				for pixel in row {
+					let index = palette.iter().position(|color| color == pixel).unwrap_or(0) as u8;
+					byte |= index << (8 - bits - used);
+					used += bits;
+					if used == 8 {
+						data.push(byte);
+						(byte, used) = (0, 0);
+					}
+				}
+				if used > 0 {
+					data.push(byte);
+				}
+			}
+		},
+		_ => {
+			data.push(0);
+			for &pixel in pixels {
+				translator.put_cpixel(data, pixel);
+			}
+		},
+	}
+}
+
+/// Compress `input` with the zlib stream of the viewer, flushing the output so the viewer can decompress it.
+fn deflate(compressor: &mut Compress, input: &[u8]) -> io::Result<Vec<u8>> {
+	let mut output = Vec::with_capacity(input.len() / 2 + 64);
+	let start = compressor.total_in();
+	loop {
+		let consumed = (compressor.total_in() - start) as usize;
+		if output.len() == output.capacity() {
+			output.reserve(output.capacity().max(4096));
+		}
+		compressor.compress_vec(&input[consumed..], &mut output, FlushCompress::Sync)
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+		// The flush is complete when all input is consumed and the output was not limited by its capacity.
+		if (compressor.total_in() - start) as usize == input.len() && output.len() < output.capacity() {
+			return Ok(output);
+		}
+	}
+}
+
+fn supported_version(version: &[u8; 12]) -> bool {
+	let Ok(version) = std::str::from_utf8(version) else {
+		return false;
+	};
+	let Some((major, minor)) = version.strip_prefix("RFB ").and_then(|v| v.trim_end().split_once('.')) else {
+		return false;
+	};
+	matches!((major.parse::<u32>(), minor.parse::<u32>()), (Ok(3), Ok(minor)) if minor >= 8)
+}
+
+/// Read the messages of a viewer until it disconnects.
+fn read_messages(stream: &TcpStream, viewer: &Mutex<Viewer>, shared: &Shared) -> io::Result<()> {
+	loop {
+		match read_u8(stream)? {
+			// SetPixelFormat
+			0 => {
+				let mut message = [0; 19];
+				read_exact(stream, &mut message)?;
+				let mut encoded = [0; 16];
+				encoded.copy_from_slice(&message[3..]);
+				let format = PixelFormat::decode(&encoded);
+				if !format.true_colour || !matches!(format.bits_per_pixel, 8 | 16 | 32) {
+					return Err(io::Error::new(io::ErrorKind::Unsupported, "unsupported pixel format"));
+				}
+				lock(viewer).pixel_format = format;
+			},
+			// SetEncodings
+			2 => {
+				let mut header = [0; 3];
+				read_exact(stream, &mut header)?;
+				let count = u16::from_be_bytes([header[1], header[2]]) as usize;
+				let mut encodings = vec![0; count * 4];
+				read_exact(stream, &mut encodings)?;
+				lock(viewer).encodings = encodings.chunks_exact(4)
+					.map(|encoding| i32::from_be_bytes([encoding[0], encoding[1], encoding[2], encoding[3]]))
+					.collect();
+			},
+			// FramebufferUpdateRequest
+			3 => {
+				let mut message = [0; 9];
+				read_exact(stream, &mut message)?;
+				let value = |i: usize| u16::from_be_bytes([message[1 + i * 2], message[2 + i * 2]]) as u32;
+				let request = UpdateRequest {
+					incremental: message[0] != 0,
+					area: Box { x: value(0), y: value(1), w: value(2), h: value(3) },
+				};
+				lock(viewer).request = Some(request);
+				drop(shared.lock());
+				shared.changed.notify_all();
+			},
+			// KeyEvent and PointerEvent are ignored, this server is view-only.
+			4 => read_exact(stream, &mut [0; 7])?,
+			5 => read_exact(stream, &mut [0; 5])?,
+			// ClientCutText
+			6 => {
+				let mut header = [0; 7];
+				read_exact(stream, &mut header)?;
+				let len = u32::from_be_bytes([header[3], header[4], header[5], header[6]]);
+				if len > MAX_CUT_TEXT {
+					return Err(io::Error::new(io::ErrorKind::InvalidData, "cut text is too long"));
+				}
+				io::copy(&mut stream.take(len as u64), &mut io::sink())?;
+			},
+			message => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown message type {}", message))),
+		}
+	}
+}
+
+fn read_exact(mut stream: &TcpStream, buffer: &mut [u8]) -> io::Result<()> {
+	stream.read_exact(buffer)
+}
+
+fn read_u8(stream: &TcpStream) -> io::Result<u8> {
+	let mut value = [0];
+	read_exact(stream, &mut value)?;
+	Ok(value[0])
+}
@@ -0,0 +1,712 @@
+//! Streaming uncompressed frames as RTP raw video (RFC 4175), controlled over RTSP.
+//!
+//! This module requires the `rtp` feature.
+//!
+//! [`Packetizer`] splits frames captured in [`BufferFormat::Rgb`], [`BufferFormat::Rgba`] or [`BufferFormat::Yuv444p`]
+//! into RTP packets, using the grab timestamp of the frames for the 90 kHz RTP clock.
+//! [`RtspServer`] runs a capture session in the background and streams its frames over UDP to every client
+//! that set up and played the stream with RTSP. When the frame size changes, the sessions set up
+//! for the previous size end with an RTCP BYE and clients set up the stream again. Uncompressed video needs a lot of bandwidth,
+//! so this is meant for local networks, preferably with jumbo frames, see [`RtspOptions::max_packet_size`].
+//!
+//! ```no_run
+//! use nvfbc::rtp::{RtspOptions, RtspServer};
+//! use nvfbc::{BufferFormat, SystemCapturer};
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let server = RtspServer::bind("0.0.0.0:8554", SystemCapturer::new, RtspOptions::new(BufferFormat::Rgb, 30))?;
+//!     println!("Streaming on rtsp://{}/", server.local_addr());
+//!     std::thread::park();
+//!     Ok(())
+//! }
+//! ```
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::common::{ignore_poison, lock_ignoring_poison};
+use crate::server::{Acceptor, CaptureSupervisor, POLL_INTERVAL};
+use crate::system::SystemFrameInfo;
+use crate::{BufferFormat, Capture, Error, SessionOptions, Size};
+
+/// Size of the RTP header without CSRCs.
+const RTP_HEADER_SIZE: usize = 12;
+
+/// Size of the extended sequence number in the RFC 4175 payload header.
+const EXTENDED_SEQUENCE_SIZE: usize = 2;
+
+/// Size of the header of a line segment in the RFC 4175 payload header.
+const SEGMENT_HEADER_SIZE: usize = 6;
+
+/// Frequency of the RTP clock of video.
+const CLOCK_RATE: u64 = 90_000;
+
+/// Default RTP payload type, the first dynamic payload type.
+pub const DEFAULT_PAYLOAD_TYPE: u8 = 96;
+
+/// Default maximum size of an RTP packet, which fits in an Ethernet frame with the default MTU.
+pub const DEFAULT_MAX_PACKET_SIZE: usize = 1400;
+
+/// Maximum size of an RTSP request head that is read.
+const MAX_REQUEST_SIZE: usize = 8192;
+
+/// How long a DESCRIBE or SETUP request waits for the first frame, which determines the size of the stream.
+const FIRST_FRAME_TIMEOUT: Duration = Duration::from_secs(5);
+
+
+/// Pixel layout of the RTP payload, the `sampling` parameter of RFC 4175.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Sampling {
+	/// 8-bit R, G and B per pixel, from [`BufferFormat::Rgb`].
+	Rgb,
+	/// 8-bit R, G, B and A per pixel, from [`BufferFormat::Rgba`].
+	Rgba,
+	/// 8-bit Cb, Y and Cr per pixel, from [`BufferFormat::Yuv444p`].
+	YCbCr444,
+}
+
+impl Sampling {
+	/// The sampling used to stream frames in `format`, or `None` if the format cannot be streamed.
+	pub fn for_format(format: BufferFormat) -> Option<Self> {
+		match format {
+			BufferFormat::Rgb => Some(Sampling::Rgb),
+			BufferFormat::Rgba => Some(Sampling::Rgba),
+			BufferFormat::Yuv444p => Some(Sampling::YCbCr444),
+			_ => None,
+		}
+	}
+
+	/// The buffer format of frames streamed with this sampling.
+	pub fn buffer_format(&self) -> BufferFormat {
+		match self {
+			Sampling::Rgb => BufferFormat::Rgb,
+			Sampling::Rgba => BufferFormat::Rgba,
+			Sampling::YCbCr444 => BufferFormat::Yuv444p,
+		}
+	}
+
+	/// Number of bytes of a pixel group, which holds a single pixel for these samplings.
+	pub fn pgroup(&self) -> usize {
+		match self {
+			Sampling::Rgb | Sampling::YCbCr444 => 3,
+			Sampling::Rgba => 4,
+		}
+	}
+
+	/// The name of this sampling in the SDP.
+	pub fn name(&self) -> &'static str {
+		match self {
+			Sampling::Rgb => "RGB",
+			Sampling::Rgba => "RGBA",
+			Sampling::YCbCr444 => "YCbCr-4:4:4",
+		}
+	}
+}
+
+/// Splits frames into RTP packets with an RFC 4175 payload.
+///
+/// Every frame is sent line by line. A packet holds as many pixels as fit in the maximum packet size,
+/// possibly from several lines. The marker bit is set on the last packet of a frame.
+#[derive(Debug, Clone)]
+pub struct Packetizer {
+	sampling: Sampling,
+	payload_type: u8,
+	ssrc: u32,
+	max_packet_size: usize,
+	/// The extended sequence number of the next packet.
+	sequence: u32,
+	/// Added to the RTP timestamps, which should start at a random value.
+	timestamp_offset: u32,
+}
+
+impl Packetizer {
+	/// Create a packetizer for frames with `sampling`, sending packets of at most `max_packet_size` bytes from `ssrc`.
+	///
+	/// The sequence number and timestamps start at random values.
+	pub fn new(sampling: Sampling, ssrc: u32, max_packet_size: usize) -> Self {
+		let random = random();
+		Self {
+			sampling,
+			payload_type: DEFAULT_PAYLOAD_TYPE,
+			ssrc,
+			max_packet_size,
+			sequence: random as u16 as u32,
+			timestamp_offset: (random >> 32) as u32,
+		}
+	}
+
+	/// Use `payload_type` instead of [`DEFAULT_PAYLOAD_TYPE`].
+	pub fn with_payload_type(mut self, payload_type: u8) -> Self {
+		self.payload_type = payload_type & 0x7f;
+		self
+	}
+
+	/// The sampling of the frames.
+	pub fn sampling(&self) -> Sampling {
+		self.sampling
+	}
+
+	/// The RTP payload type of the packets.
+	pub fn payload_type(&self) -> u8 {
+		self.payload_type
+	}
+
+	/// The synchronization source of the packets.
+	pub fn ssrc(&self) -> u32 {
+		self.ssrc
+	}
+
+	/// The RTP sequence number of the next packet.
+	pub fn next_sequence(&self) -> u16 {
+		self.sequence as u16
+	}
+
+	/// The RTP timestamp of a frame grabbed at `timestamp_us`.
+	pub fn rtp_timestamp(&self, timestamp_us: u64) -> u32 {
+		((timestamp_us as u128 * CLOCK_RATE as u128 / 1_000_000) as u32).wrapping_add(self.timestamp_offset)
+	}
+
+	/// Split `frame` into packets and pass each of them to `emit`.
+	///
+	/// The frame must be in the buffer format of the sampling of this packetizer.
+	pub fn packetize(&mut self, frame: &SystemFrameInfo, mut emit: impl FnMut(&[u8]) -> io::Result<()>) -> io::Result<()> {
+		if Sampling::for_format(frame.buffer_format) != Some(self.sampling) {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				format!("cannot stream {:?} frames as {}", frame.buffer_format, self.sampling.name()),
+			));
+		}
+		if frame.buffer.len() != frame.buffer_format.frame_size(frame.width, frame.height) {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "the frame buffer does not match the frame size"));
+		}
+		if frame.width == 0 || frame.height == 0 || frame.width > 0x7fff || frame.height > 0x8000 {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("cannot stream frames of {}x{} pixels", frame.width, frame.height)));
+		}
+		let pgroup = self.sampling.pgroup();
+		if self.max_packet_size < RTP_HEADER_SIZE + EXTENDED_SEQUENCE_SIZE + SEGMENT_HEADER_SIZE + pgroup {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "the maximum packet size is too small"));
+		}
+
+		let interleaved;
+		let data = match self.sampling {
+			Sampling::YCbCr444 => {
+				interleaved = interleave_yuv444p(frame);
+				&interleaved
+			},
+			_ => frame.buffer,
+		};
+
+		let stride = frame.width as usize * pgroup;
+		let timestamp = self.rtp_timestamp(frame.timestamp_us);
+		// The line and the byte offset within the line of the next pixel to send.
+		let (mut line, mut offset) = (0usize, 0usize);
+		let mut packet = Vec::with_capacity(self.max_packet_size);
+		let mut segments: Vec<(usize, usize, usize)> = Vec::new();
+
+		while line < frame.height as usize {
+			// Fill the packet with segments of whole pixel groups.
+			segments.clear();
+			let mut payload = 0;
+			while line < frame.height as usize {
+				let headers = RTP_HEADER_SIZE + EXTENDED_SEQUENCE_SIZE + SEGMENT_HEADER_SIZE * (segments.len() + 1);
+				let available = self.max_packet_size.saturating_sub(headers + payload) / pgroup * pgroup;
+				if available == 0 {
+					break;
+				}
+				let length = available.min(stride - offset);
+				segments.push((line, offset, length));
+				payload += length;
+				offset += length;
+				if offset == stride {
+					(line, offset) = (line + 1, 0);
+				}
+			}
+
+			let last = line == frame.height as usize;
+			packet.clear();
+			packet.push(0x80);
+			packet.push((last as u8) << 7 | self.payload_type);
+			packet.extend_from_slice(&(self.sequence as u16).to_be_bytes());
+			packet.extend_from_slice(&timestamp.to_be_bytes());
+			packet.extend_from_slice(&self.ssrc.to_be_bytes());
+			packet.extend_from_slice(&((self.sequence >> 16) as u16).to_be_bytes());
+			for (i, &(line, offset, length)) in segments.iter().enumerate() {
+				let continuation = if i + 1 < segments.len() { 0x8000 } else { 0 };
+				packet.extend_from_slice(&(length as u16).to_be_bytes());
+				packet.extend_from_slice(&(line as u16).to_be_bytes());
+				packet.extend_from_slice(&((offset / pgroup) as u16 | continuation).to_be_bytes());
+			}
+			for &(line, offset, length) in &segments {
+				let start = line * stride + offset;
+				packet.extend_from_slice(&data[start..start + length]);
+			}
+
+			self.sequence = self.sequence.wrapping_add(1);
+			emit(&packet)?;
+		}
+		Ok(())
+	}
+}
+
+/// Interleave the planes of a YUV444P frame into Cb, Y, Cr pixel groups.
+fn interleave_yuv444p(frame: &SystemFrameInfo) -> Vec<u8> {
+	let planes = BufferFormat::Yuv444p.planes(frame.width, frame.height);
+	let (y, u, v) = (&frame.buffer[planes[0].range()], &frame.buffer[planes[1].range()], &frame.buffer[planes[2].range()]);
+	let mut interleaved = Vec::with_capacity(y.len() * 3);
+	for ((&y, &u), &v) in y.iter().zip(u).zip(v) {
+		interleaved.extend_from_slice(&[u, y, v]);
+	}
+	interleaved
+}
+
+/// The SDP describing a stream of `width` x `height` frames at `fps` frames per second.
+pub fn sdp(sampling: Sampling, payload_type: u8, width: u32, height: u32, fps: u32, name: &str, address: IpAddr) -> String {
+	let address_type = if address.is_ipv4() { "IP4" } else { "IP6" };
+	format!(
+		"v=0\r\n\
+		o=- 0 0 IN {address_type} {address}\r\n\
+		s={name}\r\n\
+		c=IN {address_type} {address}\r\n\
+		t=0 0\r\n\
+		a=control:*\r\n\
+		m=video 0 RTP/AVP {payload_type}\r\n\
+		a=rtpmap:{payload_type} raw/{CLOCK_RATE}\r\n\
+		a=fmtp:{payload_type} sampling={sampling}; width={width}; height={height}; depth=8; colorimetry=BT709-2; exactframerate={fps}\r\n\
+		a=control:stream\r\n",
+		sampling = sampling.name(),
+	)
+}
+
+/// A random value, for SSRCs, session identifiers and initial sequence numbers.
+fn random() -> u64 {
+	let mut hasher = RandomState::new().build_hasher();
+	hasher.write_u128(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_nanos());
+	hasher.finish()
+}
+
+/// Configuration of an [`RtspServer`].
+#[derive(Debug, Clone)]
+pub struct RtspOptions {
+	/// Options of the capture session.
+	///
+	/// The buffer format must be [`BufferFormat::Rgb`], [`BufferFormat::Rgba`] or [`BufferFormat::Yuv444p`].
+	pub session: SessionOptions,
+
+	/// Maximum size of an RTP packet. On networks with jumbo frames this can be increased up to 8972.
+	pub max_packet_size: usize,
+
+	/// Name of the stream in the SDP.
+	pub name: String,
+}
+
+impl RtspOptions {
+	/// Stream frames in `buffer_format` captured at `fps` frames per second.
+	pub fn new(buffer_format: BufferFormat, fps: u32) -> Self {
+		Self {
+			session: SessionOptions::new(buffer_format, fps),
+			max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+			name: "NvFBC".to_string(),
+		}
+	}
+}
+
+/// RTCP packet type of a BYE packet.
+const RTCP_BYE: u8 = 203;
+
+/// A client that set up the stream.
+struct RtpSession {
+	socket: UdpSocket,
+	/// Sends the BYE packet when the session ends. RTCP packets of the client are ignored.
+	rtcp_socket: UdpSocket,
+	destination: SocketAddr,
+	rtcp_destination: SocketAddr,
+	packetizer: Packetizer,
+	/// Size of the frames announced in the SDP. The session ends when the size of the frames changes.
+	size: Size,
+	playing: bool,
+}
+
+impl RtpSession {
+	/// Tell the client that the stream ended with an RTCP BYE packet.
+	fn end(&self) {
+		let mut bye = [0x81, RTCP_BYE, 0, 1, 0, 0, 0, 0];
+		bye[4..].copy_from_slice(&self.packetizer.ssrc().to_be_bytes());
+		// The client also notices that the stream ended when it times out, so the packet may be lost.
+		let _ = self.rtcp_socket.send_to(&bye, self.rtcp_destination);
+	}
+}
+
+#[derive(Default)]
+struct Shared {
+	/// Size of the most recently captured frame.
+	size: Mutex<Option<Size>>,
+	first_frame: Condvar,
+	/// The sessions by id. The map is always locked before a session.
+	sessions: Mutex<HashMap<String, Arc<Mutex<RtpSession>>>>,
+	stop: AtomicBool,
+}
+
+impl Shared {
+	fn sessions(&self) -> MutexGuard<'_, HashMap<String, Arc<Mutex<RtpSession>>>> {
+		lock_ignoring_poison(&self.sessions)
+	}
+
+	fn stopping(&self) -> bool {
+		self.stop.load(Ordering::Relaxed)
+	}
+
+	/// Wait until the first frame was captured and return its size.
+	fn wait_for_size(&self) -> Option<Size> {
+		let deadline = Instant::now() + FIRST_FRAME_TIMEOUT;
+		let mut size = lock_ignoring_poison(&self.size);
+		while size.is_none() && !self.stopping() && Instant::now() < deadline {
+			size = ignore_poison(self.first_frame.wait_timeout(size, POLL_INTERVAL)).0;
+		}
+		*size
+	}
+}
+
+/// Streams captured frames to RTSP clients as RTP raw video.
+///
+/// Dropping the server stops the capture session and all streams.
+pub struct RtspServer {
+	acceptor: Acceptor,
+	shared: Arc<Shared>,
+	capture_thread: Option<JoinHandle<()>>,
+}
+
+impl RtspServer {
+	/// Listen for RTSP requests on `addr` and stream the frames captured from the capturer created by `create`.
+	///
+	/// The capturer is created on the capture thread, because capturers such as [`SystemCapturer`](crate::SystemCapturer)
+	/// cannot be sent to other threads. Creating the capturer and starting the capture session are retried when they fail.
+	/// Use port 0 to let the operating system pick a free port, see [`RtspServer::local_addr`].
+	pub fn bind<C, F>(addr: impl ToSocketAddrs, create: F, options: RtspOptions) -> io::Result<Self>
+	where
+		C: Capture,
+		F: FnMut() -> Result<C, Error> + Send + 'static,
+	{
+		let Some(sampling) = Sampling::for_format(options.session.buffer_format) else {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				format!("cannot stream {:?} frames, use RGB, RGBA or YUV444P", options.session.buffer_format),
+			));
+		};
+
+		let listener = TcpListener::bind(addr)?;
+		let shared = Arc::new(Shared::default());
+
+		let capture_thread = std::thread::Builder::new()
+			.name("nvfbc-rtp-capture".to_string())
+			.spawn({
+				let (shared, session) = (shared.clone(), options.session.clone());
+				move || capture(create, &session, &shared)
+			})?;
+
+		let acceptor = Acceptor::spawn("nvfbc-rtsp", listener, {
+			let shared = shared.clone();
+			move |stream| {
+				let mut sessions = Vec::new();
+				let result = serve(stream, &shared, &options, sampling, &mut sessions);
+				// Stop streaming to the sessions of this client when it disconnects.
+				let mut all = shared.sessions();
+				for id in sessions {
+					all.remove(&id);
+				}
+				result
+			}
+		});
+		let acceptor = match acceptor {
+			Ok(acceptor) => acceptor,
+			Err(e) => {
+				shared.stop.store(true, Ordering::Relaxed);
+				let _ = capture_thread.join();
+				return Err(e);
+			},
+		};
+
+		Ok(Self {
+			acceptor,
+			shared,
+			capture_thread: Some(capture_thread),
+		})
+	}
+
+	/// The address the server listens on for RTSP requests.
+	pub fn local_addr(&self) -> SocketAddr {
+		self.acceptor.local_addr()
+	}
+
+	/// Number of clients that set up the stream.
+	pub fn sessions(&self) -> usize {
+		self.shared.sessions().len()
+	}
+}
+
+impl Drop for RtspServer {
+	fn drop(&mut self) {
+		self.shared.stop.store(true, Ordering::Relaxed);
+		self.acceptor.stop();
+		if let Some(thread) = self.capture_thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+/// Capture frames and send them to the playing sessions until the server stops.
+///
+/// Sessions set up for another frame size are ended, because their clients expect frames of the size in the SDP.
+fn capture<C: Capture>(create: impl FnMut() -> Result<C, Error>, session: &SessionOptions, shared: &Shared) {
+	let interval = Duration::from_secs(1) / session.fps.max(1);
+	let mut supervisor = CaptureSupervisor::new(create, session.clone(), &shared.stop);
+
+	while let Some(frame) = supervisor.next_frame(interval) {
+		let Ok(frame) = frame else {
+			continue;
+		};
+
+		let size = Size { w: frame.width, h: frame.height };
+		*lock_ignoring_poison(&shared.size) = Some(size);
+		shared.first_frame.notify_all();
+
+		let (ended, sessions) = {
+			let mut all = shared.sessions();
+			let mut ended = Vec::new();
+			all.retain(|_, session| {
+				let resized = lock_ignoring_poison(session).size != size;
+				if resized {
+					ended.push(session.clone());
+				}
+				!resized
+			});
+			(ended, all.values().cloned().collect::<Vec<_>>())
+		};
+		for session in ended {
+			lock_ignoring_poison(&session).end();
+		}
+		if !frame.is_new_frame {
+			continue;
+		}
+
+		for session in sessions {
+			let mut session = lock_ignoring_poison(&session);
+			if !session.playing {
+				continue;
+			}
+			let RtpSession { socket, destination, packetizer, .. } = &mut *session;
+			// Packets that cannot be sent are lost, like any other UDP packet.
+			let _ = packetizer.packetize(&frame, |packet| socket.send_to(packet, *destination).map(|_| ()));
+		}
+	}
+}
+
+/// An RTSP request.
+struct Request {
+	method: String,
+	uri: String,
+	headers: Vec<(String, String)>,
+}
+
+impl Request {
+	fn header(&self, name: &str) -> Option<&str> {
+		self.headers.iter()
+			.find(|(key, _)| key.eq_ignore_ascii_case(name))
+			.map(|(_, value)| value.as_str())
+	}
+
+	/// The session identifier, without parameters such as the timeout.
+	fn session(&self) -> Option<&str> {
+		self.header("Session").map(|session| session.split(';').next().unwrap_or_default().trim())
+	}
+}
+
+/// Read a request, or return `None` when the client closed the connection.
+fn read_request(reader: &mut BufReader<TcpStream>) -> io::Result<Option<Request>> {
+	let mut lines = Vec::new();
+	let mut size = 0;
+	loop {
+		let mut line = String::new();
+		if reader.read_line(&mut line)? == 0 {
+			return Ok(None);
+		}
+		size += line.len();
+		if size > MAX_REQUEST_SIZE {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, "request is too large"));
+		}
+		let line = line.trim_end();
+		if line.is_empty() {
+			// Skip empty lines between requests.
+			if lines.is_empty() {
+				continue;
+			}
+			break;
+		}
+		lines.push(line.to_string());
+	}
+
+	let mut request_line = lines[0].split_whitespace();
+	let method = request_line.next().unwrap_or_default().to_string();
+	let uri = request_line.next().unwrap_or_default().to_string();
+	let headers: Vec<(String, String)> = lines[1..].iter()
+		.filter_map(|line| line.split_once(':'))
+		.map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+		.collect();
+	let request = Request { method, uri, headers };
+
+	// Requests handled by this server have no body, but skip it if there is one.
+	let length = request.header("Content-Length").and_then(|length| length.parse::<u64>().ok()).unwrap_or(0);
+	io::copy(&mut reader.by_ref().take(length.min(MAX_REQUEST_SIZE as u64)), &mut io::sink())?;
+	Ok(Some(request))
+}
+
+/// Answer the requests of a single RTSP client until it disconnects, recording the sessions it sets up.
+fn serve(stream: TcpStream, shared: &Shared, options: &RtspOptions, sampling: Sampling, sessions: &mut Vec<String>) -> io::Result<()> {
+	stream.set_read_timeout(Some(Duration::from_secs(60)))?;
+	stream.set_write_timeout(Some(Duration::from_secs(5)))?;
+	let local_ip = stream.local_addr()?.ip();
+	let peer_ip = stream.peer_addr()?.ip();
+	let mut writer = stream.try_clone()?;
+	let mut reader = BufReader::new(stream);
+
+	while let Some(request) = read_request(&mut reader)? {
+		if shared.stopping() {
+			break;
+		}
+		let cseq = request.header("CSeq").unwrap_or("0").to_string();
+		let mut headers = vec![("CSeq".to_string(), cseq)];
+		let mut body = String::new();
+
+		let status = match request.method.as_str() {
+			"OPTIONS" => {
+				headers.push(("Public".to_string(), "OPTIONS, DESCRIBE, SETUP, PLAY, TEARDOWN".to_string()));
+				"200 OK"
+			},
+			"DESCRIBE" => match shared.wait_for_size() {
+				Some(size) => {
+					body = sdp(sampling, DEFAULT_PAYLOAD_TYPE, size.w, size.h, options.session.fps, &options.name, local_ip);
+					let base = if request.uri.ends_with('/') { request.uri.clone() } else { format!("{}/", request.uri) };
+					headers.push(("Content-Base".to_string(), base));
+					headers.push(("Content-Type".to_string(), "application/sdp".to_string()));
+					"200 OK"
+				},
+				None => "503 Service Unavailable",
+			},
+			"SETUP" => setup(&request, shared, options, sampling, local_ip, peer_ip, &mut headers, sessions)?,
+			"PLAY" | "TEARDOWN" => {
+				let id = request.session().unwrap_or_default().to_string();
+				let mut all = shared.sessions();
+				match all.get(&id) {
+					Some(session) if request.method == "PLAY" => {
+						let mut session = lock_ignoring_poison(session);
+						session.playing = true;
+						headers.push(("Session".to_string(), id.clone()));
+						headers.push(("Range".to_string(), "npt=0.000-".to_string()));
+						headers.push((
+							"RTP-Info".to_string(),
+							format!("url={};seq={}", request.uri, session.packetizer.next_sequence()),
+						));
+						"200 OK"
+					},
+					Some(_) => {
+						all.remove(&id);
+						sessions.retain(|session| *session != id);
+						headers.push(("Session".to_string(), id.clone()));
+						"200 OK"
+					},
+					None => "454 Session Not Found",
+				}
+			},
+			_ => "501 Not Implemented",
+		};
+
+		let mut response = format!("RTSP/1.0 {}\r\n", status);
+		for (key, value) in &headers {
+			response.push_str(&format!("{}: {}\r\n", key, value));
+		}
+		response.push_str(&format!("Content-Length: {}\r\n\r\n{}", body.len(), body));
+		writer.write_all(response.as_bytes())?;
+		writer.flush()?;
+	}
+	Ok(())
+}
+
+/// Set up a session streaming to the UDP ports of the client.
+#[allow(clippy::too_many_arguments)]
+fn setup(
+	request: &Request,
+	shared: &Shared,
+	options: &RtspOptions,
+	sampling: Sampling,
+	local_ip: IpAddr,
+	peer_ip: IpAddr,
+	headers: &mut Vec<(String, String)>,
+	sessions: &mut Vec<String>,
+) -> io::Result<&'static str> {
+	let transport = request.header("Transport").unwrap_or_default();
+	let parameters: Vec<&str> = transport.split(';').map(str::trim).collect();
+	let client_port = parameters.iter()
+		.find_map(|parameter| parameter.strip_prefix("client_port="))
+		.and_then(|ports| {
+			let (rtp, rtcp) = ports.split_once('-').unwrap_or((ports, ""));
+			let rtp = rtp.parse::<u16>().ok()?;
+			Some((rtp, rtcp.parse::<u16>().unwrap_or(rtp.wrapping_add(1))))
+		});
+	let udp = parameters.first().is_some_and(|profile| *profile == "RTP/AVP" || *profile == "RTP/AVP/UDP");
+	let (Some((rtp_port, rtcp_port)), true) = (client_port, udp) else {
+		return Ok("461 Unsupported Transport");
+	};
+	if request.session().is_some() {
+		// Aggregate control of several streams is not needed, there is only one.
+		return Ok("459 Aggregate Operation Not Allowed");
+	}
+	let Some(size) = shared.wait_for_size() else {
+		return Ok("503 Service Unavailable");
+	};
+
+	let (socket, rtcp_socket) = bind_port_pair(local_ip)?;
+	let server_port = socket.local_addr()?.port();
+	let id = format!("{:016X}", random());
+	let packetizer = Packetizer::new(sampling, random() as u32, options.max_packet_size);
+	headers.push(("Session".to_string(), format!("{};timeout=60", id)));
+	headers.push((
+		"Transport".to_string(),
+		format!(
+			"RTP/AVP;unicast;client_port={}-{};server_port={}-{};ssrc={:08X}",
+			rtp_port, rtcp_port, server_port, server_port + 1, packetizer.ssrc(),
+		),
+	));
+
+	shared.sessions().insert(id.clone(), Arc::new(Mutex::new(RtpSession {
+		socket,
+		rtcp_socket,
+		destination: SocketAddr::new(peer_ip, rtp_port),
+		rtcp_destination: SocketAddr::new(peer_ip, rtcp_port),
+		packetizer,
+		size,
+		playing: false,
+	})));
+	sessions.push(id);
+	Ok("200 OK")
+}
+
+/// Bind UDP sockets to an even port for RTP and the next port for RTCP.
+fn bind_port_pair(ip: IpAddr) -> io::Result<(UdpSocket, UdpSocket)> {
+	for _ in 0..64 {
+		let socket = UdpSocket::bind(SocketAddr::new(ip, 0))?;
+		let port = socket.local_addr()?.port();
+		if port % 2 != 0 || port == u16::MAX {
+			continue;
+		}
+		if let Ok(rtcp_socket) = UdpSocket::bind(SocketAddr::new(ip, port + 1)) {
+			return Ok((socket, rtcp_socket));
+		}
+	}
+	Err(io::Error::new(io::ErrorKind::AddrInUse, "no free pair of UDP ports"))
+}
@@ -0,0 +1,227 @@
+//! Building blocks shared by the servers of the `metrics`, `preview`, `rfb` and `rtp` features.
+
+use std::io;
+#[cfg(any(feature = "metrics", feature = "preview"))]
+use std::io::Read;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+#[cfg(any(feature = "preview", feature = "rfb", feature = "rtp"))]
+use std::time::{Duration, Instant};
+
+#[cfg(any(feature = "preview", feature = "rfb", feature = "rtp"))]
+use crate::system::{CaptureMethod, SystemFrameInfo};
+#[cfg(any(feature = "preview", feature = "rfb", feature = "rtp"))]
+use crate::{Capture, Error, SessionOptions};
+
+/// Maximum size of an HTTP request head that is read.
+#[cfg(any(feature = "metrics", feature = "preview"))]
+const MAX_REQUEST_SIZE: usize = 8192;
+
+/// How long to wait before creating the capturer or starting the capture session again after it failed.
+#[cfg(any(feature = "preview", feature = "rfb", feature = "rtp"))]
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// How often waiting threads check whether the server is stopping.
+#[cfg(any(feature = "preview", feature = "rfb", feature = "rtp"))]
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Accepts connections on a background thread and serves every connection on its own thread.
+pub(crate) struct Acceptor {
+	local_addr: SocketAddr,
+	stop: Arc<AtomicBool>,
+	thread: Option<JoinHandle<()>>,
+}
+
+impl Acceptor {
+	/// Accept connections on `listener` until stopped, serving every connection with `serve`.
+	///
+	/// The accepting thread is called `name`, the threads serving connections `name-client`.
+	pub(crate) fn spawn<F>(name: &str, listener: TcpListener, serve: F) -> io::Result<Self>
+	where
+		F: Fn(TcpStream) -> io::Result<()> + Send + Sync + 'static,
+	{
+		let local_addr = listener.local_addr()?;
+		let stop = Arc::new(AtomicBool::new(false));
+		let client_name = format!("{}-client", name);
+		let serve = Arc::new(serve);
+
+		let thread = std::thread::Builder::new()
+			.name(name.to_string())
+			.spawn({
+				let stop = stop.clone();
+				move || {
+					for stream in listener.incoming() {
+						if stop.load(Ordering::Relaxed) {
+							break;
+						}
+						let Ok(stream) = stream else {
+							continue;
+						};
+						let serve = serve.clone();
+						let _ = std::thread::Builder::new()
+							.name(client_name.clone())
+							.spawn(move || {
+								// Errors only affect this connection.
+								let _ = serve(stream);
+							});
+					}
+				}
+			})?;
+
+		Ok(Self { local_addr, stop, thread: Some(thread) })
+	}
+
+	/// The address the listener is bound to.
+	pub(crate) fn local_addr(&self) -> SocketAddr {
+		self.local_addr
+	}
+
+	/// Stop accepting connections and wait for the accepting thread to exit.
+	///
+	/// Connections that are being served are not closed.
+	pub(crate) fn stop(&mut self) {
+		self.stop.store(true, Ordering::Relaxed);
+		if let Some(thread) = self.thread.take() {
+			// Wake up the accepting thread, which is blocked accepting connections.
+			let _ = TcpStream::connect(self.local_addr);
+			let _ = thread.join();
+		}
+	}
+}
+
+impl Drop for Acceptor {
+	fn drop(&mut self) {
+		self.stop();
+	}
+}
+
+/// The request line of an HTTP request.
+#[cfg(any(feature = "metrics", feature = "preview"))]
+pub(crate) struct HttpRequest {
+	pub method: String,
+	/// The path of the request target, without the query.
+	pub path: String,
+}
+
+/// Read the head of an HTTP request from `stream`, of which only the request line is used.
+///
+/// At most [`MAX_REQUEST_SIZE`] bytes are read, the rest of the head and the body are ignored.
+#[cfg(any(feature = "metrics", feature = "preview"))]
+pub(crate) fn read_http_request(stream: &mut TcpStream) -> io::Result<HttpRequest> {
+	let mut request = Vec::new();
+	let mut buffer = [0; 1024];
+	while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < MAX_REQUEST_SIZE {
+		let read = stream.read(&mut buffer)?;
+		if read == 0 {
+			break;
+		}
+		request.extend_from_slice(&buffer[..read]);
+	}
+
+	let request = String::from_utf8_lossy(&request);
+	let mut request_line = request.lines().next().unwrap_or_default().split_whitespace();
+	let (method, target) = (request_line.next().unwrap_or_default(), request_line.next().unwrap_or_default());
+	Ok(HttpRequest {
+		method: method.to_string(),
+		path: target.split('?').next().unwrap_or_default().to_string(),
+	})
+}
+
+/// Wait before retrying a failed operation, returning whether `stop` was set.
+#[cfg(any(feature = "preview", feature = "rfb", feature = "rtp"))]
+fn wait_to_retry(stop: &AtomicBool) -> bool {
+	let retry_at = Instant::now() + RESTART_DELAY;
+	while !stop.load(Ordering::Relaxed) && Instant::now() < retry_at {
+		std::thread::sleep(POLL_INTERVAL);
+	}
+	stop.load(Ordering::Relaxed)
+}
+
+/// Runs the capture session of a server on its capture thread.
+///
+/// The capturer is created on first use. Creating the capturer, starting the session and grabbing frames
+/// are retried after [`RESTART_DELAY`] when they fail, stopping and starting the session again after a failed grab.
+#[cfg(any(feature = "preview", feature = "rfb", feature = "rtp"))]
+pub(crate) struct CaptureSupervisor<'a, C: Capture, F> {
+	create: F,
+	session: SessionOptions,
+	stop: &'a AtomicBool,
+	capturer: Option<C>,
+	started: bool,
+	/// Whether the last operation failed, so that the next grab waits and starts again.
+	failed: bool,
+}
+
+#[cfg(any(feature = "preview", feature = "rfb", feature = "rtp"))]
+impl<'a, C: Capture, F: FnMut() -> Result<C, Error>> CaptureSupervisor<'a, C, F> {
+	/// Capture with `session` from the capturer created by `create`, until `stop` is set.
+	pub(crate) fn new(create: F, session: SessionOptions, stop: &'a AtomicBool) -> Self {
+		Self { create, session, stop, capturer: None, started: false, failed: false }
+	}
+
+	/// The capturer, once it was created.
+	#[cfg(feature = "preview")]
+	pub(crate) fn capturer(&self) -> Option<&C> {
+		self.capturer.as_ref()
+	}
+
+	/// Stop the session after a frame could not be used, so that it is started again by the next grab.
+	#[cfg(feature = "rfb")]
+	pub(crate) fn restart(&mut self) {
+		self.failed = true;
+	}
+
+	/// Grab the next frame, waiting at most `timeout` for it.
+	///
+	/// Returns the error if the capturer could not be created, the session could not be started or the grab failed.
+	/// Returns `None` once the server is stopping.
+	pub(crate) fn next_frame(&mut self, timeout: Duration) -> Option<Result<SystemFrameInfo<'_>, Error>> {
+		if self.failed {
+			self.failed = false;
+			if let Some(capturer) = self.capturer.as_mut().filter(|_| self.started) {
+				// The session may need to be created again, for example after a modeset.
+				let _ = capturer.stop();
+			}
+			self.started = false;
+			if wait_to_retry(self.stop) {
+				return None;
+			}
+		}
+		if self.stop.load(Ordering::Relaxed) {
+			return None;
+		}
+
+		if self.capturer.is_none() {
+			match (self.create)() {
+				Ok(capturer) => self.capturer = Some(capturer),
+				Err(e) => {
+					self.failed = true;
+					return Some(Err(e));
+				},
+			}
+		}
+		let capturer = self.capturer.as_mut()?;
+		if !self.started {
+			if let Err(e) = capturer.start_with_options(&self.session) {
+				self.failed = true;
+				return Some(Err(e));
+			}
+			self.started = true;
+		}
+
+		let result = capturer.next_frame(CaptureMethod::Blocking, Some(timeout));
+		self.failed = result.is_err();
+		Some(result)
+	}
+}
+
+#[cfg(any(feature = "preview", feature = "rfb", feature = "rtp"))]
+impl<C: Capture, F> Drop for CaptureSupervisor<'_, C, F> {
+	fn drop(&mut self) {
+		if let Some(capturer) = self.capturer.as_mut().filter(|_| self.started) {
+			let _ = capturer.stop();
+		}
+	}
+}
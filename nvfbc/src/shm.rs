@@ -0,0 +1,513 @@
+//! Sharing captured frames with other processes through a ring of shared memory slots.
+//!
+//! This module requires the `shm` feature.
+//!
+//! NvFBC limits the number of clients that can capture at the same time. Instead of every process opening
+//! its own capture session, a single process captures and publishes its frames with a [`FramePublisher`],
+//! and every other process reads them with a [`FrameSubscriber`] without copying them through a socket.
+//!
+//! The publisher copies every frame into the next of a fixed number of slots in a memfd. Every slot is guarded by a seqlock:
+//! subscribers copy the newest slot and retry if the publisher overwrote it in the meantime,
+//! so neither side ever waits for the other. The file is sealed against shrinking, so a subscriber cannot be
+//! terminated by a publisher that resizes the ring. When the frame size or buffer format changes,
+//! the publisher grows the file if needed and publishes the new layout, which subscribers map automatically.
+//!
+//! Subscribers can open the ring through the path returned by [`FramePublisher::path`], or receive its file descriptor,
+//! for example by inheriting it or over a Unix socket.
+//!
+//! # Memory layout
+//! All integers are in native byte order. The first page holds the header of the ring:
+//!
+//! | Field | Type |
+//! | --- | --- |
+//! | Magic, `NVFBCSHM` | 8 bytes |
+//! | Layout version, currently 1 | `u32` |
+//! | Number of slots | `u32` |
+//! | Layout sequence, odd while the layout changes | `u32` |
+//! | Futex word, incremented for every published frame | `u32` |
+//! | Width, height and buffer format of the frames | 3 x `u32` |
+//! | Padding | `u32` |
+//! | Size of a slot in bytes, a multiple of the page size | `u64` |
+//! | Size of the file in bytes | `u64` |
+//! | Number of published frames | `u64` |
+//!
+//! Slot `i` starts at one page plus `i` times the slot size. Frame `n` is published in slot `n % slots`.
+//! Every slot starts with a 64 byte header followed by the frame data:
+//!
+//! | Field | Type |
+//! | --- | --- |
+//! | Sequence, odd while the slot is written | `u64` |
+//! | Index of the frame in the slot | `u64` |
+//! | Timestamp in microseconds | `u64` |
+//! | Width, height, buffer format, current frame and missed frames | 5 x `u32` |
+//! | Whether the frame is new, 0 or 1 | `u32` |
+//! | Size of the frame data | `u64` |
+//!
+//! ```no_run
+//! use nvfbc::shm::FramePublisher;
+//! use nvfbc::system::CaptureMethod;
+//! use nvfbc::{BufferFormat, SystemCapturer};
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let mut capturer = SystemCapturer::new()?;
+//!     capturer.start(BufferFormat::Rgb, 30)?;
+//!
+//!     let mut publisher = FramePublisher::new("nvfbc", 4)?;
+//!     println!("Publishing frames on {}", publisher.path().display());
+//!     loop {
+//!         let frame = capturer.next_frame(CaptureMethod::Blocking, None)?;
+//!         publisher.publish(&frame)?;
+//!     }
+//! }
+//! ```
+
+use std::ffi::CString;
+use std::fs::File;
+use std::io;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+use std::path::{Path, PathBuf};
+use std::ptr::NonNull;
+use std::sync::atomic::{fence, AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::system::SystemFrameInfo;
+use crate::BufferFormat;
+
+/// Magic bytes at the start of a ring.
+const MAGIC: [u8; 8] = *b"NVFBCSHM";
+
+/// Version of the memory layout.
+const VERSION: u32 = 1;
+
+/// Size of the header of the ring, which is padded to a page.
+const HEADER_SIZE: usize = 4096;
+
+/// Size of the header of a slot.
+const SLOT_HEADER_SIZE: usize = 64;
+
+/// Slots are padded to whole pages.
+const PAGE_SIZE: usize = 4096;
+
+/// Index of a frame stored in a slot that does not hold a frame.
+const NO_FRAME: u64 = u64::MAX;
+
+/// How long a subscriber retries reading a frame that the publisher keeps changing before giving up.
+const RETRY_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[repr(C)]
+struct RingHeader {
+	magic: [u8; 8],
+	version: AtomicU32,
+	slot_count: AtomicU32,
+	layout_sequence: AtomicU32,
+	wake: AtomicU32,
+	width: AtomicU32,
+	height: AtomicU32,
+	buffer_format: AtomicU32,
+	_padding: u32,
+	slot_size: AtomicU64,
+	file_size: AtomicU64,
+	published: AtomicU64,
+}
+
+#[repr(C)]
+struct SlotHeader {
+	sequence: AtomicU64,
+	frame: AtomicU64,
+	timestamp_us: AtomicU64,
+	width: AtomicU32,
+	height: AtomicU32,
+	buffer_format: AtomicU32,
+	current_frame: AtomicU32,
+	missed_frames: AtomicU32,
+	is_new_frame: AtomicU32,
+	byte_size: AtomicU64,
+}
+
+const _: () = assert!(std::mem::size_of::<RingHeader>() <= HEADER_SIZE && std::mem::size_of::<SlotHeader>() <= SLOT_HEADER_SIZE);
+
+/// A shared memory mapping of a ring.
+struct Mapping {
+	ptr: NonNull<u8>,
+	len: usize,
+}
+
+// The mapping is only accessed through atomics and the seqlock protocol, so it can be used from any thread.
+unsafe impl Send for Mapping {}
+
+impl Mapping {
+	fn new(file: &File, len: usize, writable: bool) -> io::Result<Self> {
+		let protection = if writable { libc::PROT_READ | libc::PROT_WRITE } else { libc::PROT_READ };
+		let ptr = unsafe { libc::mmap(std::ptr::null_mut(), len, protection, libc::MAP_SHARED, file.as_raw_fd(), 0) };
+		if ptr == libc::MAP_FAILED {
+			return Err(io::Error::last_os_error());
+		}
+		let ptr = NonNull::new(ptr as *mut u8).ok_or_else(|| io::Error::other("mmap returned a null pointer"))?;
+		Ok(Self { ptr, len })
+	}
+
+	fn header(&self) -> &RingHeader {
+		// The mapping is page aligned and at least `HEADER_SIZE` bytes.
+		unsafe { &*(self.ptr.as_ptr() as *const RingHeader) }
+	}
+
+	/// The header of the slot at `offset`, if the slot is within the mapping.
+	fn slot(&self, offset: usize) -> Option<&SlotHeader> {
+		if offset.checked_add(SLOT_HEADER_SIZE)? > self.len || !offset.is_multiple_of(8) {
+			return None;
+		}
+		Some(unsafe { &*(self.ptr.as_ptr().add(offset) as *const SlotHeader) })
+	}
+}
+
+impl Drop for Mapping {
+	fn drop(&mut self) {
+		unsafe { libc::munmap(self.ptr.as_ptr() as *mut libc::c_void, self.len) };
+	}
+}
+
+/// Size of a slot holding frames of `frame_size` bytes.
+fn slot_size(frame_size: usize) -> usize {
+	(SLOT_HEADER_SIZE + frame_size).div_ceil(PAGE_SIZE) * PAGE_SIZE
+}
+
+fn invalid_input(message: impl Into<String>) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidInput, message.into())
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Wake up all subscribers waiting on `word`.
+fn futex_wake(word: &AtomicU32) {
+	unsafe { libc::syscall(libc::SYS_futex, word.as_ptr(), libc::FUTEX_WAKE, i32::MAX, std::ptr::null::<libc::timespec>()) };
+}
+
+/// Wait at most `timeout` until `word` no longer contains `expected`, or until woken up.
+fn futex_wait(word: &AtomicU32, expected: u32, timeout: Duration) {
+	let timeout = libc::timespec {
+		tv_sec: timeout.as_secs().min(libc::time_t::MAX as u64) as libc::time_t,
+		tv_nsec: timeout.subsec_nanos() as _,
+	};
+	// Spurious wake-ups and interruptions are handled by the caller, which checks the word again.
+	unsafe { libc::syscall(libc::SYS_futex, word.as_ptr(), libc::FUTEX_WAIT, expected, &timeout as *const libc::timespec) };
+}
+
+/// Publishes frames to a ring of slots in shared memory.
+pub struct FramePublisher {
+	file: File,
+	map: Mapping,
+	slot_count: u32,
+	/// Width, height and buffer format of the frames in the current layout.
+	layout: Option<(u32, u32, BufferFormat)>,
+	slot_size: usize,
+	published: u64,
+}
+
+impl FramePublisher {
+	/// Create a ring of `slot_count` slots in a new memfd named `name`.
+	///
+	/// Subscribers can read a frame as long as the publisher did not overwrite its slot,
+	/// so more slots allow slower subscribers. At least 2 slots are required.
+	pub fn new(name: &str, slot_count: u32) -> io::Result<Self> {
+		if slot_count < 2 {
+			return Err(invalid_input("a ring needs at least 2 slots"));
+		}
+		let name = CString::new(name).map_err(|_| invalid_input("the name contains a nul byte"))?;
+		let fd = unsafe { libc::memfd_create(name.as_ptr(), libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING) };
+		if fd < 0 {
+			return Err(io::Error::last_os_error());
+		}
+		let file = File::from(unsafe { OwnedFd::from_raw_fd(fd) });
+		file.set_len(HEADER_SIZE as u64)?;
+		if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_ADD_SEALS, libc::F_SEAL_SHRINK | libc::F_SEAL_SEAL) } < 0 {
+			return Err(io::Error::last_os_error());
+		}
+
+		let map = Mapping::new(&file, HEADER_SIZE, true)?;
+		unsafe { std::ptr::copy_nonoverlapping(MAGIC.as_ptr(), map.ptr.as_ptr(), MAGIC.len()) };
+		let header = map.header();
+		header.version.store(VERSION, Ordering::Relaxed);
+		header.slot_count.store(slot_count, Ordering::Relaxed);
+		header.file_size.store(HEADER_SIZE as u64, Ordering::Release);
+
+		Ok(Self {
+			file,
+			map,
+			slot_count,
+			layout: None,
+			slot_size: 0,
+			published: 0,
+		})
+	}
+
+	/// Path through which other processes of the same user can open the ring, see [`FrameSubscriber::open`].
+	pub fn path(&self) -> PathBuf {
+		PathBuf::from(format!("/proc/{}/fd/{}", std::process::id(), self.file.as_raw_fd()))
+	}
+
+	/// Number of slots of the ring.
+	pub fn slot_count(&self) -> u32 {
+		self.slot_count
+	}
+
+	/// Number of frames published so far.
+	pub fn published(&self) -> u64 {
+		self.published
+	}
+
+	/// Copy `frame` into the next slot and wake up waiting subscribers.
+	///
+	/// If the size or buffer format of the frame differs from the previous frame, the new layout is published first.
+	pub fn publish(&mut self, frame: &SystemFrameInfo) -> io::Result<()> {
+		let frame_size = frame.buffer_format.frame_size(frame.width, frame.height);
+		if frame.buffer.len() != frame_size {
+			return Err(invalid_input("the frame buffer does not match the frame size"));
+		}
+
+		let layout = (frame.width, frame.height, frame.buffer_format);
+		let layout_sequence = self.map.header().layout_sequence.load(Ordering::Relaxed);
+		let relayout = self.layout != Some(layout);
+		if relayout {
+			// Subscribers retry while the layout sequence is odd, until the first frame of the new layout is written.
+			self.map.header().layout_sequence.store(layout_sequence.wrapping_add(1), Ordering::Relaxed);
+			fence(Ordering::Release);
+			if let Err(e) = self.republish_layout(layout, frame_size) {
+				// The previous layout was left untouched.
+				self.map.header().layout_sequence.store(layout_sequence.wrapping_add(2), Ordering::Release);
+				return Err(e);
+			}
+		}
+
+		let index = self.published;
+		let offset = HEADER_SIZE + (index % self.slot_count as u64) as usize * self.slot_size;
+		let slot = self.map.slot(offset).expect("slot is within the mapping");
+		let sequence = slot.sequence.load(Ordering::Relaxed);
+		slot.sequence.store(sequence.wrapping_add(1), Ordering::Relaxed);
+		fence(Ordering::Release);
+
+		slot.frame.store(index, Ordering::Relaxed);
+		slot.timestamp_us.store(frame.timestamp_us, Ordering::Relaxed);
+		slot.width.store(frame.width, Ordering::Relaxed);
+		slot.height.store(frame.height, Ordering::Relaxed);
+		slot.buffer_format.store(frame.buffer_format as u32, Ordering::Relaxed);
+		slot.current_frame.store(frame.current_frame, Ordering::Relaxed);
+		slot.missed_frames.store(frame.missed_frames, Ordering::Relaxed);
+		slot.is_new_frame.store(frame.is_new_frame as u32, Ordering::Relaxed);
+		slot.byte_size.store(frame_size as u64, Ordering::Relaxed);
+		unsafe {
+			let data = self.map.ptr.as_ptr().add(offset + SLOT_HEADER_SIZE);
+			std::ptr::copy_nonoverlapping(frame.buffer.as_ptr(), data, frame_size);
+		}
+		slot.sequence.store(sequence.wrapping_add(2), Ordering::Release);
+
+		let header = self.map.header();
+		if relayout {
+			header.layout_sequence.store(layout_sequence.wrapping_add(2), Ordering::Release);
+		}
+		self.published += 1;
+		header.published.store(self.published, Ordering::Release);
+		header.wake.fetch_add(1, Ordering::Release);
+		futex_wake(&header.wake);
+		Ok(())
+	}
+
+	/// Resize the slots for frames of `frame_size` bytes, growing the file if needed.
+	///
+	/// Must be called with an odd layout sequence.
+	fn republish_layout(&mut self, layout: (u32, u32, BufferFormat), frame_size: usize) -> io::Result<()> {
+		let slot_size = slot_size(frame_size);
+		let file_size = slot_size.checked_mul(self.slot_count as usize)
+			.and_then(|size| size.checked_add(HEADER_SIZE))
+			.ok_or_else(|| invalid_input("the frames are too large"))?;
+		if file_size > self.map.len {
+			// The file is sealed against shrinking, so it only grows to the largest layout.
+			self.file.set_len(file_size as u64)?;
+			self.map = Mapping::new(&self.file, file_size, true)?;
+		}
+
+		let header = self.map.header();
+		header.width.store(layout.0, Ordering::Relaxed);
+		header.height.store(layout.1, Ordering::Relaxed);
+		header.buffer_format.store(layout.2 as u32, Ordering::Relaxed);
+		header.slot_size.store(slot_size as u64, Ordering::Relaxed);
+		header.file_size.store(self.map.len as u64, Ordering::Relaxed);
+		for slot in 0..self.slot_count as usize {
+			if let Some(slot) = self.map.slot(HEADER_SIZE + slot * slot_size) {
+				// The slot header may now overlap the frame data of the previous layout, which could hold an odd sequence.
+				slot.sequence.store(0, Ordering::Relaxed);
+				slot.frame.store(NO_FRAME, Ordering::Relaxed);
+			}
+		}
+		self.layout = Some(layout);
+		self.slot_size = slot_size;
+		Ok(())
+	}
+}
+
+impl AsFd for FramePublisher {
+	fn as_fd(&self) -> BorrowedFd<'_> {
+		self.file.as_fd()
+	}
+}
+
+/// Reads frames from a ring created by a [`FramePublisher`], possibly in another process.
+///
+/// The ring is mapped read-only. Frames are copied out of their slot, so they remain consistent
+/// while the publisher continues to write.
+pub struct FrameSubscriber {
+	file: File,
+	map: Mapping,
+	buffer: Vec<u8>,
+	/// Index of the last frame that was returned.
+	last: Option<u64>,
+}
+
+impl FrameSubscriber {
+	/// Open the ring at `path`, such as the path returned by [`FramePublisher::path`].
+	pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+		Self::from_fd(File::open(path)?.into())
+	}
+
+	/// Read the ring of the memfd `fd`.
+	pub fn from_fd(fd: OwnedFd) -> io::Result<Self> {
+		let file = File::from(fd);
+		if file.metadata()?.len() < HEADER_SIZE as u64 {
+			return Err(invalid_data("the file is too small to be a frame ring"));
+		}
+		let map = Mapping::new(&file, HEADER_SIZE, false)?;
+		let header = map.header();
+		if header.magic != MAGIC {
+			return Err(invalid_data("the file is not a frame ring"));
+		}
+		if header.version.load(Ordering::Relaxed) != VERSION {
+			return Err(invalid_data(format!("unsupported frame ring version {}", header.version.load(Ordering::Relaxed))));
+		}
+		Ok(Self { file, map, buffer: Vec::new(), last: None })
+	}
+
+	/// Number of slots of the ring.
+	pub fn slot_count(&self) -> u32 {
+		self.map.header().slot_count.load(Ordering::Relaxed)
+	}
+
+	/// Number of frames published so far.
+	pub fn published(&self) -> u64 {
+		self.map.header().published.load(Ordering::Acquire)
+	}
+
+	/// The most recently published frame, or `None` if no frame was published yet.
+	///
+	/// Fails with [`io::ErrorKind::TimedOut`] if the publisher keeps changing the frame for longer than a second,
+	/// for example because it stopped while writing it.
+	pub fn latest(&mut self) -> io::Result<Option<SystemFrameInfo<'_>>> {
+		let deadline = Instant::now() + RETRY_TIMEOUT;
+		let retry = || {
+			if Instant::now() >= deadline {
+				return Err(io::Error::new(io::ErrorKind::TimedOut, "the publisher did not finish writing the frame"));
+			}
+			std::thread::yield_now();
+			Ok(())
+		};
+		loop {
+			let header = self.map.header();
+			let layout_sequence = header.layout_sequence.load(Ordering::Acquire);
+			if !layout_sequence.is_multiple_of(2) {
+				retry()?;
+				continue;
+			}
+			let published = header.published.load(Ordering::Acquire);
+			if published == 0 {
+				return Ok(None);
+			}
+
+			let file_size = header.file_size.load(Ordering::Relaxed) as usize;
+			let slot_count = header.slot_count.load(Ordering::Relaxed).max(1) as u64;
+			let slot_size = header.slot_size.load(Ordering::Relaxed) as usize;
+			if file_size > self.map.len {
+				// The publisher grew the ring for a larger layout.
+				self.map = Mapping::new(&self.file, file_size, false)?;
+				continue;
+			}
+
+			let index = published - 1;
+			let offset = slot_size.checked_mul((index % slot_count) as usize).and_then(|offset| offset.checked_add(HEADER_SIZE));
+			let Some(slot) = offset.and_then(|offset| self.map.slot(offset)) else {
+				return Err(invalid_data("a slot is outside of the frame ring"));
+			};
+			let sequence = slot.sequence.load(Ordering::Acquire);
+			if !sequence.is_multiple_of(2) || slot.frame.load(Ordering::Relaxed) != index {
+				// The publisher already overwrote the slot with a newer frame.
+				retry()?;
+				continue;
+			}
+
+			let width = slot.width.load(Ordering::Relaxed);
+			let height = slot.height.load(Ordering::Relaxed);
+			let buffer_format = slot.buffer_format.load(Ordering::Relaxed);
+			let current_frame = slot.current_frame.load(Ordering::Relaxed);
+			let missed_frames = slot.missed_frames.load(Ordering::Relaxed);
+			let is_new_frame = slot.is_new_frame.load(Ordering::Relaxed) != 0;
+			let timestamp_us = slot.timestamp_us.load(Ordering::Relaxed);
+			let byte_size = (slot.byte_size.load(Ordering::Relaxed) as usize).min(slot_size.saturating_sub(SLOT_HEADER_SIZE));
+			let data_offset = offset.unwrap_or_default() + SLOT_HEADER_SIZE;
+			if data_offset + byte_size > self.map.len {
+				return Err(invalid_data("a frame is outside of the frame ring"));
+			}
+			self.buffer.resize(byte_size, 0);
+			unsafe { std::ptr::copy_nonoverlapping(self.map.ptr.as_ptr().add(data_offset), self.buffer.as_mut_ptr(), byte_size) };
+
+			// The copy is only valid if neither the slot nor the layout changed while copying.
+			fence(Ordering::Acquire);
+			if slot.sequence.load(Ordering::Relaxed) != sequence || header.layout_sequence.load(Ordering::Relaxed) != layout_sequence {
+				retry()?;
+				continue;
+			}
+
+			let buffer_format = BufferFormat::ALL.into_iter()
+				.find(|format| *format as u32 == buffer_format)
+				.ok_or_else(|| invalid_data(format!("unknown buffer format {}", buffer_format)))?;
+			if buffer_format.frame_size(width, height) != byte_size {
+				return Err(invalid_data("the frame data does not match the frame size"));
+			}
+
+			self.last = Some(index);
+			return Ok(Some(SystemFrameInfo {
+				buffer: &self.buffer,
+				width,
+				height,
+				buffer_format,
+				current_frame,
+				is_new_frame,
+				timestamp_us,
+				missed_frames,
+				diff_map: None,
+			}));
+		}
+	}
+
+	/// Wait for a frame that was published after the frame previously returned by this subscriber and return the newest frame.
+	///
+	/// Returns `None` if no frame was published within `timeout`, or waits indefinitely if `timeout` is `None`.
+	pub fn wait_for_frame(&mut self, timeout: Option<Duration>) -> io::Result<Option<SystemFrameInfo<'_>>> {
+		let deadline = timeout.map(|timeout| Instant::now() + timeout);
+		loop {
+			let header = self.map.header();
+			let wake = header.wake.load(Ordering::Acquire);
+			let published = header.published.load(Ordering::Acquire);
+			if published > self.last.map_or(0, |last| last + 1) {
+				return self.latest();
+			}
+
+			let remaining = match deadline {
+				Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+					Some(remaining) if !remaining.is_zero() => remaining,
+					_ => return Ok(None),
+				},
+				None => Duration::from_secs(1),
+			};
+			futex_wait(&header.wake, wake, remaining);
+		}
+	}
+}
@@ -0,0 +1,306 @@
+//! Aggregate statistics about grabbed frames.
+//!
+//! [`StatsCapturer`] wraps any [`Capture`] source and records every grab in a [`StatsCollector`].
+//! Monitoring code can poll a [`StatsSnapshot`] from another thread through a [`StatsHandle`].
+//!
+//! ```no_run
+//! use nvfbc::stats::StatsCapturer;
+//! use nvfbc::system::CaptureMethod;
+//! use nvfbc::{BufferFormat, Capture, SessionOptions, SystemCapturer};
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let mut capturer = StatsCapturer::new(SystemCapturer::new()?);
+//!     let stats = capturer.handle();
+//!     std::thread::spawn(move || loop {
+//!         std::thread::sleep(std::time::Duration::from_secs(10));
+//!         println!("{:?}", stats.snapshot());
+//!     });
+//!
+//!     capturer.start_with_options(&SessionOptions::new(BufferFormat::Nv12, 60))?;
+//!     loop {
+//!         capturer.next_frame(CaptureMethod::Blocking, Some(std::time::Duration::from_millis(100)))?;
+//!     }
+//! }
+//! ```
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::common::lock_ignoring_poison;
+use crate::system::{CaptureMethod, SystemFrameInfo};
+use crate::{Capture, Error, SessionOptions, Status};
+
+/// Default length of the sliding window.
+pub const DEFAULT_WINDOW: Duration = Duration::from_secs(10);
+
+/// Result of a single grab.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GrabOutcome {
+	/// A frame was returned.
+	Frame {
+		/// Whether the frame is new, see [`SystemFrameInfo::is_new_frame`].
+		is_new_frame: bool,
+		/// Number of frames missed since the previous grab, see [`SystemFrameInfo::missed_frames`].
+		missed_frames: u32,
+		/// Whether the grab waited for a new frame until its timeout expired.
+		timed_out: bool,
+	},
+	/// The grab failed with this NvFBC status code.
+	Error(u32),
+}
+
+/// Statistics of a capturer, over a sliding window and since it was created.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StatsSnapshot {
+	/// Length of the window that the windowed statistics are computed over.
+	///
+	/// This is shorter than the configured window until the collector has existed for that long.
+	pub window: Duration,
+	/// Number of grabs in the window, including failed grabs.
+	pub grabs: u64,
+	/// Median duration of a grab call in the window.
+	pub latency_p50: Duration,
+	/// 99th percentile duration of a grab call in the window.
+	pub latency_p99: Duration,
+	/// Number of new frames per second delivered in the window.
+	pub fps: f64,
+	/// Number of new frames in the window.
+	pub new_frames: u64,
+	/// Number of grabs in the window that returned a frame that was not new.
+	pub repeats: u64,
+	/// Fraction of the frames returned in the window that were new, or 0 if no frames were returned.
+	pub new_frame_ratio: f64,
+	/// Total number of grabs since the collector was created.
+	pub total_grabs: u64,
+	/// Total number of missed frames reported by NvFBC since the collector was created.
+	pub missed_frames: u64,
+	/// Total number of grabs that timed out waiting for a new frame.
+	pub timeouts: u64,
+	/// Total number of failed grabs by NvFBC status code.
+	pub errors: BTreeMap<u32, u64>,
+}
+
+/// A grab within the sliding window.
+#[derive(Debug, Copy, Clone)]
+struct Sample {
+	at: Instant,
+	latency: Duration,
+	/// Whether the returned frame was new, or `None` if the grab failed.
+	is_new_frame: Option<bool>,
+}
+
+/// Collects statistics about grabs.
+#[derive(Debug, Clone)]
+pub struct StatsCollector {
+	window: Duration,
+	created: Instant,
+	samples: VecDeque<Sample>,
+	total_grabs: u64,
+	missed_frames: u64,
+	timeouts: u64,
+	errors: BTreeMap<u32, u64>,
+}
+
+impl Default for StatsCollector {
+	fn default() -> Self {
+		Self::new(DEFAULT_WINDOW)
+	}
+}
+
+impl StatsCollector {
+	/// Create a collector computing windowed statistics over the last `window`.
+	pub fn new(window: Duration) -> Self {
+		Self::starting_at(window, Instant::now())
+	}
+
+	/// Create a collector that was started at `created`, which is used to compute the fps of the first window.
+	pub fn starting_at(window: Duration, created: Instant) -> Self {
+		Self {
+			window,
+			created,
+			samples: VecDeque::new(),
+			total_grabs: 0,
+			missed_frames: 0,
+			timeouts: 0,
+			errors: BTreeMap::new(),
+		}
+	}
+
+	/// Length of the sliding window.
+	pub fn window(&self) -> Duration {
+		self.window
+	}
+
+	/// Record a grab that finished at `at` and took `latency`.
+	pub fn record(&mut self, at: Instant, latency: Duration, outcome: GrabOutcome) {
+		self.total_grabs += 1;
+		let is_new_frame = match outcome {
+			GrabOutcome::Frame { is_new_frame, missed_frames, timed_out } => {
+				self.missed_frames += missed_frames as u64;
+				self.timeouts += timed_out as u64;
+				Some(is_new_frame)
+			},
+			GrabOutcome::Error(code) => {
+				*self.errors.entry(code).or_default() += 1;
+				None
+			},
+		};
+
+		self.samples.push_back(Sample { at, latency, is_new_frame });
+		self.prune(at);
+	}
+
+	/// Record the result of a grab that was started at `started` and finished at `at`.
+	pub fn record_grab(
+		&mut self,
+		started: Instant,
+		at: Instant,
+		capture_method: CaptureMethod,
+		timeout: Option<Duration>,
+		result: Result<&SystemFrameInfo, &Error>,
+	) {
+		let outcome = match result {
+			Ok(frame) => GrabOutcome::Frame {
+				is_new_frame: frame.is_new_frame,
+				missed_frames: frame.missed_frames,
+				timed_out: !frame.is_new_frame
+					&& !matches!(capture_method, CaptureMethod::NoWait)
+					&& timeout.is_some_and(|timeout| timeout > Duration::ZERO),
+			},
+			Err(error) => GrabOutcome::Error(error.code()),
+		};
+		self.record(at, at.saturating_duration_since(started), outcome);
+	}
+
+	/// Statistics as of now.
+	pub fn snapshot(&self) -> StatsSnapshot {
+		self.snapshot_at(Instant::now())
+	}
+
+	/// Statistics as of `now`.
+	pub fn snapshot_at(&self, now: Instant) -> StatsSnapshot {
+		let window = self.window.min(now.saturating_duration_since(self.created));
+		let samples: Vec<&Sample> = self.samples.iter()
+			.filter(|sample| now.saturating_duration_since(sample.at) <= window)
+			.collect();
+
+		let mut latencies: Vec<Duration> = samples.iter().map(|sample| sample.latency).collect();
+		latencies.sort();
+		let percentile = |p: usize| {
+			latencies.get((latencies.len() * p / 100).min(latencies.len().saturating_sub(1)))
+				.copied()
+				.unwrap_or_default()
+		};
+
+		let new_frames = samples.iter().filter(|sample| sample.is_new_frame == Some(true)).count() as u64;
+		let repeats = samples.iter().filter(|sample| sample.is_new_frame == Some(false)).count() as u64;
+
+		StatsSnapshot {
+			window,
+			grabs: samples.len() as u64,
+			latency_p50: percentile(50),
+			latency_p99: percentile(99),
+			fps: if window.is_zero() { 0.0 } else { new_frames as f64 / window.as_secs_f64() },
+			new_frames,
+			repeats,
+			new_frame_ratio: if new_frames + repeats == 0 { 0.0 } else { new_frames as f64 / (new_frames + repeats) as f64 },
+			total_grabs: self.total_grabs,
+			missed_frames: self.missed_frames,
+			timeouts: self.timeouts,
+			errors: self.errors.clone(),
+		}
+	}
+
+	/// Forget samples that fell out of the window.
+	fn prune(&mut self, now: Instant) {
+		while self.samples.front().is_some_and(|sample| now.saturating_duration_since(sample.at) > self.window) {
+			self.samples.pop_front();
+		}
+	}
+}
+
+/// Shared access to the statistics of a [`StatsCapturer`], for example from a monitoring thread.
+#[derive(Debug, Clone)]
+pub struct StatsHandle {
+	collector: Arc<Mutex<StatsCollector>>,
+}
+
+impl StatsHandle {
+	/// Statistics as of now.
+	pub fn snapshot(&self) -> StatsSnapshot {
+		self.lock().snapshot()
+	}
+
+	fn lock(&self) -> std::sync::MutexGuard<'_, StatsCollector> {
+		lock_ignoring_poison(&self.collector)
+	}
+}
+
+/// Wraps a [`Capture`] source and collects statistics about every grab.
+pub struct StatsCapturer<C> {
+	inner: C,
+	stats: StatsHandle,
+}
+
+impl<C: Capture> StatsCapturer<C> {
+	/// Collect statistics about `inner` over the [`DEFAULT_WINDOW`].
+	pub fn new(inner: C) -> Self {
+		Self::with_window(inner, DEFAULT_WINDOW)
+	}
+
+	/// Collect statistics about `inner` over the last `window`.
+	pub fn with_window(inner: C, window: Duration) -> Self {
+		Self {
+			inner,
+			stats: StatsHandle { collector: Arc::new(Mutex::new(StatsCollector::new(window))) },
+		}
+	}
+
+	/// A handle to poll the statistics, which can be sent to other threads.
+	pub fn handle(&self) -> StatsHandle {
+		self.stats.clone()
+	}
+
+	/// Statistics as of now.
+	pub fn snapshot(&self) -> StatsSnapshot {
+		self.stats.snapshot()
+	}
+
+	/// The wrapped capturer.
+	pub fn inner(&self) -> &C {
+		&self.inner
+	}
+
+	/// The wrapped capturer.
+	pub fn inner_mut(&mut self) -> &mut C {
+		&mut self.inner
+	}
+
+	/// Return the wrapped capturer.
+	pub fn into_inner(self) -> C {
+		self.inner
+	}
+}
+
+impl<C: Capture> Capture for StatsCapturer<C> {
+	fn status(&self) -> Result<Status, Error> {
+		self.inner.status()
+	}
+
+	fn start_with_options(&mut self, options: &SessionOptions) -> Result<(), Error> {
+		self.inner.start_with_options(options)
+	}
+
+	fn stop(&mut self) -> Result<(), Error> {
+		self.inner.stop()
+	}
+
+	fn next_frame(&mut self, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<SystemFrameInfo<'_>, Error> {
+		let started = Instant::now();
+		let result = self.inner.next_frame(capture_method, timeout);
+		self.stats.lock().record_grab(started, Instant::now(), capture_method, timeout, result.as_ref());
+		result
+	}
+}
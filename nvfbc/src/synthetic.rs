@@ -0,0 +1,303 @@
+//! A frame source that generates test patterns without NvFBC.
+//!
+//! This allows code written against [`Capture`] to be tested on machines without an NVIDIA GPU.
+
+use std::time::{Duration, Instant};
+
+use crate::diff_map::DiffMap;
+use crate::system::{CaptureMethod, SystemFrameInfo};
+use crate::{
+	convert,
+	Box,
+	BufferFormat,
+	Capture,
+	Error,
+	Output,
+	SessionOptions,
+	Size,
+	Status,
+	Tracking,
+	Version,
+};
+
+/// Colors of the vertical bars in the test pattern.
+const BARS: [[u8; 3]; 8] = [
+	[0xff, 0xff, 0xff],
+	[0xff, 0xff, 0x00],
+	[0x00, 0xff, 0xff],
+	[0x00, 0xff, 0x00],
+	[0xff, 0x00, 0xff],
+	[0xff, 0x00, 0x00],
+	[0x00, 0x00, 0xff],
+	[0x00, 0x00, 0x00],
+];
+
+/// Generates frames with a test pattern: vertical color bars with a gray square that moves every frame.
+///
+/// Frames are generated at the rate requested when starting the session,
+/// with timestamps counted from the start of the session.
+/// Diff maps are computed by comparing the test patterns of consecutive frames.
+pub struct SyntheticCapturer {
+	/// Status reported by this capturer, describing the simulated screen and outputs.
+	status: Status,
+
+	/// Options of the running session, if any.
+	session: Option<SessionOptions>,
+
+	/// When the running session was started.
+	started: Instant,
+
+	/// Size of the frames of the running session.
+	frame_size: Size,
+
+	/// The most recently generated frame, in the requested buffer format.
+	buffer: Vec<u8>,
+
+	/// Index of the most recently generated frame.
+	frame_index: Option<u64>,
+
+	/// The most recently generated frame as packed RGB888, to compute diff maps.
+	previous: Vec<u8>,
+
+	/// Diff map of the most recently grabbed frame, if diff maps were requested.
+	diff_map: Vec<u8>,
+}
+
+impl SyntheticCapturer {
+	/// Create a capturer simulating an X screen of `width` x `height` pixels with a single output covering it.
+	pub fn new(width: u32, height: u32) -> Self {
+		Self::with_status(Status {
+			is_capture_possible: true,
+			currently_capturing: false,
+			can_create_now: true,
+			screen_size: Size { w: width, h: height },
+			xrandr_available: true,
+			outputs: vec![Output {
+				id: 1,
+				name: "SYNTHETIC-0".to_string(),
+				tracked_box: Box { x: 0, y: 0, w: width, h: height },
+			}],
+			nvfbc_version: Version::COMPILED,
+			in_modeset: false,
+		})
+	}
+
+	/// Create a capturer simulating the screen and outputs described by `status`.
+	///
+	/// Sessions with [`Tracking::Default`] capture the first output, like NvFBC does without a primary output,
+	/// or the entire screen if XRandR is not available.
+	pub fn with_status(status: Status) -> Self {
+		Self {
+			status,
+			session: None,
+			started: Instant::now(),
+			frame_size: Size { w: 0, h: 0 },
+			buffer: Vec::new(),
+			frame_index: None,
+			previous: Vec::new(),
+			diff_map: Vec::new(),
+		}
+	}
+
+	/// Render the test pattern of frame `index` as packed RGB888.
+	pub fn pattern(width: u32, height: u32, index: u64) -> Vec<u8> {
+		let (width, height) = (width as usize, height as usize);
+		let mut rgb = vec![0; width * height * 3];
+		if rgb.is_empty() {
+			return rgb;
+		}
+
+		for row in rgb.chunks_exact_mut(width * 3) {
+			for (x, pixel) in row.chunks_exact_mut(3).enumerate() {
+				pixel.copy_from_slice(&BARS[x * BARS.len() / width]);
+			}
+		}
+
+		// A square of a quarter of the height, moving horizontally by 4 pixels per frame.
+		let side = (height / 4).clamp(1, width);
+		let travel = (width - side).max(1);
+		let left = (index as usize * 4) % travel;
+		let top = (height - side.min(height)) / 2;
+		for y in top..(top + side).min(height) {
+			for x in left..left + side {
+				let index = (y * width + x) * 3;
+				rgb[index..index + 3].copy_from_slice(&[0x80, 0x80, 0x80]);
+			}
+		}
+
+		rgb
+	}
+
+	fn frame_interval(&self) -> Duration {
+		Duration::from_secs(1) / self.session.as_ref().map_or(1, |session| session.fps.max(1))
+	}
+
+	/// Index of the frame that is due at `instant`.
+	fn frame_due_at(&self, instant: Instant) -> u64 {
+		(instant.saturating_duration_since(self.started).as_micros() / self.frame_interval().as_micros().max(1)) as u64
+	}
+
+	fn render(&mut self, index: u64) -> Result<(), Error> {
+		let Some(session) = &self.session else {
+			return Err(not_started());
+		};
+
+		let rgb = Self::pattern(self.frame_size.w, self.frame_size.h, index);
+		self.buffer.resize(session.buffer_format.frame_size(self.frame_size.w, self.frame_size.h), 0);
+		convert::from_rgb_into(&rgb, self.frame_size.w, self.frame_size.h, session.buffer_format, &mut self.buffer)
+			.map_err(|e| Error::new(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_INTERNAL, Some(e.to_string())))?;
+
+		if let Some(scaling_factor) = session.diff_map_scaling_factor {
+			self.diff_map = diff_blocks(&self.previous, &rgb, self.frame_size, scaling_factor.max(1));
+		}
+		self.previous = rgb;
+		Ok(())
+	}
+}
+
+/// Compute the diff map between two packed RGB888 frames of `size`, marking every block as changed if there is no previous frame.
+fn diff_blocks(previous: &[u8], current: &[u8], size: Size, scaling_factor: u32) -> Vec<u8> {
+	let blocks = DiffMap::size_for(size.w, size.h, scaling_factor);
+	if previous.len() != current.len() {
+		return vec![1; blocks.w as usize * blocks.h as usize];
+	}
+
+	let mut diff_map = vec![0; blocks.w as usize * blocks.h as usize];
+	let (width, scaling_factor) = (size.w as usize, scaling_factor as usize);
+	for (y, (previous, current)) in previous.chunks_exact(width * 3).zip(current.chunks_exact(width * 3)).enumerate() {
+		for (block_x, (previous, current)) in previous.chunks(scaling_factor * 3).zip(current.chunks(scaling_factor * 3)).enumerate() {
+			if previous != current {
+				diff_map[y / scaling_factor * blocks.w as usize + block_x] = 1;
+			}
+		}
+	}
+	diff_map
+}
+
+fn not_started() -> Error {
+	Error::new(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_BAD_REQUEST, Some("no capture session was started".to_string()))
+}
+
+fn invalid_param(message: &str) -> Error {
+	Error::new(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_INVALID_PARAM, Some(message.to_string()))
+}
+
+impl Capture for SyntheticCapturer {
+	fn status(&self) -> Result<Status, Error> {
+		Ok(Status { currently_capturing: self.session.is_some(), ..self.status.clone() })
+	}
+
+	fn start_with_options(&mut self, options: &SessionOptions) -> Result<(), Error> {
+		if self.session.is_some() {
+			return Err(Error::new(
+				nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_BAD_REQUEST,
+				Some("a capture session was already started".to_string()),
+			));
+		}
+		if options.fps == 0 {
+			return Err(invalid_param("fps must be larger than zero"));
+		}
+		if options.diff_map_scaling_factor.is_some() && options.buffer_format == BufferFormat::Yuv444p {
+			return Err(invalid_param("diff maps are not supported for the YUV444P buffer format"));
+		}
+
+		let screen = Box { x: 0, y: 0, w: self.status.screen_size.w, h: self.status.screen_size.h };
+		let tracked = match options.tracking {
+			Tracking::Default => self.status.outputs.first()
+				.filter(|_| self.status.xrandr_available)
+				.map_or(screen, |output| output.tracked_box),
+			Tracking::Screen => screen,
+			Tracking::Output(id) => self.status.outputs.iter()
+				.find(|output| output.id == id)
+				.ok_or_else(|| invalid_param("unknown output"))?
+				.tracked_box,
+		};
+
+		let captured = match options.capture_box {
+			Some(capture_box) if capture_box.w == 0 || capture_box.h == 0 => tracked,
+			Some(capture_box) => {
+				if capture_box.x as u64 + capture_box.w as u64 > tracked.w as u64
+					|| capture_box.y as u64 + capture_box.h as u64 > tracked.h as u64
+				{
+					return Err(invalid_param("capture box does not fit within the tracked region"));
+				}
+				capture_box
+			},
+			None => tracked,
+		};
+
+		self.frame_size = match options.frame_size {
+			Some(size) if size.w != 0 && size.h != 0 => size,
+			_ => Size { w: captured.w, h: captured.h },
+		};
+		self.session = Some(options.clone());
+		self.started = Instant::now();
+		self.frame_index = None;
+		self.previous.clear();
+		self.diff_map.clear();
+		Ok(())
+	}
+
+	fn stop(&mut self) -> Result<(), Error> {
+		self.session.take().ok_or_else(not_started)?;
+		Ok(())
+	}
+
+	fn next_frame(&mut self, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<SystemFrameInfo<'_>, Error> {
+		let Some(session) = &self.session else {
+			return Err(not_started());
+		};
+		let buffer_format = session.buffer_format;
+		let diff_map_scaling_factor = session.diff_map_scaling_factor;
+
+		let now = Instant::now();
+		let due = self.frame_due_at(now);
+		let wanted = match (capture_method, self.frame_index) {
+			(CaptureMethod::NoWait, _) => due,
+			(CaptureMethod::NoWaitIfNewFrame, Some(last)) if due > last => due,
+			(CaptureMethod::NoWaitIfNewFrame, None) => due,
+			_ => due + 1,
+		};
+
+		let mut index = due;
+		if wanted > due {
+			let at = self.started + self.frame_interval() * wanted as u32;
+			let wait = at.saturating_duration_since(now);
+			match timeout {
+				Some(timeout) if timeout > Duration::ZERO && timeout < wait => std::thread::sleep(timeout),
+				_ => {
+					std::thread::sleep(wait);
+					index = wanted;
+				},
+			}
+		}
+
+		let is_new_frame = self.frame_index.is_none_or(|last| index > last);
+		let missed_frames = self.frame_index.map_or(0, |last| index.saturating_sub(last + 1)) as u32;
+		if is_new_frame {
+			self.render(index)?;
+			self.frame_index = Some(index);
+		} else {
+			// Nothing changed since the previously grabbed frame.
+			self.diff_map.fill(0);
+		}
+		let index = self.frame_index.unwrap_or(index);
+
+		let diff_map = diff_map_scaling_factor.map(|scaling_factor| {
+			let size = DiffMap::size_for(self.frame_size.w, self.frame_size.h, scaling_factor);
+			DiffMap { data: &self.diff_map, width: size.w, height: size.h, scaling_factor: scaling_factor.max(1) }
+		});
+
+		Ok(SystemFrameInfo {
+			buffer: &self.buffer,
+			width: self.frame_size.w,
+			height: self.frame_size.h,
+			buffer_format,
+			current_frame: index as u32,
+			is_new_frame,
+			timestamp_us: (self.frame_interval() * index as u32).as_micros() as u64,
+			missed_frames,
+			diff_map,
+		})
+	}
+}
@@ -13,20 +13,30 @@ use nvfbc_sys::{
 use crate::common::{
 	Handle,
 	check_ret,
+	nvfbc_grab_span,
+	nvfbc_span,
+	record_grab_info,
 	create_capture_session,
 	create_handle,
 	destroy_capture_session,
 	destroy_handle,
 	status,
+	struct_versions,
 };
+use crate::diff_map::DiffMap;
 use crate::{
 	BufferFormat,
+	Capture,
 	Error,
+	FrameGrabInfo,
+	SessionOptions,
+	Size,
 	Status,
 	CaptureType,
 };
 
 /// Different methods for capturing a frame.
+#[derive(Debug, Copy, Clone)]
 pub enum CaptureMethod {
 	NoWait = NVFBC_TOSYS_GRAB_FLAGS_NVFBC_TOSYS_GRAB_FLAGS_NOWAIT as isize,
 	NoWaitIfNewFrame = NVFBC_TOSYS_GRAB_FLAGS_NVFBC_TOSYS_GRAB_FLAGS_NOFLAGS as isize,
@@ -44,12 +54,37 @@ pub struct SystemFrameInfo<'a> {
 	pub width: u32,
 	/// Height of the captured frame.
 	pub height: u32,
+	/// Format of the data in the buffer.
+	pub buffer_format: BufferFormat,
 	/// Incremental ID of the current frame.
 	///
 	/// This can be used to identify a frame.
 	pub current_frame: u32,
 	/// Whether this frame is a new frame.
 	pub is_new_frame: bool,
+	/// Time in microseconds when the display server started rendering the frame.
+	pub timestamp_us: u64,
+	/// Number of frames the display server rendered since the previous grab that were not captured.
+	pub missed_frames: u32,
+	/// Blocks of the frame that changed since the previously captured frame.
+	///
+	/// Only available if the session was started with a [`SessionOptions::diff_map_scaling_factor`].
+	pub diff_map: Option<DiffMap<'a>>,
+}
+
+impl SystemFrameInfo<'_> {
+	/// Information about this frame, without the frame data.
+	pub fn grab_info(&self) -> FrameGrabInfo {
+		FrameGrabInfo {
+			width: self.width,
+			height: self.height,
+			byte_size: self.buffer.len() as u32,
+			current_frame: self.current_frame,
+			is_new_frame: self.is_new_frame,
+			timestamp_us: self.timestamp_us,
+			missed_frames: self.missed_frames,
+		}
+	}
 }
 
 impl std::fmt::Debug for SystemFrameInfo<'_> {
@@ -59,7 +94,12 @@ impl std::fmt::Debug for SystemFrameInfo<'_> {
 			.field("buffer_len", &self.buffer.len())
 			.field("width", &self.width)
 			.field("height", &self.height)
+			.field("buffer_format", &self.buffer_format)
 			.field("current_frame", &self.current_frame)
+			.field("is_new_frame", &self.is_new_frame)
+			.field("timestamp_us", &self.timestamp_us)
+			.field("missed_frames", &self.missed_frames)
+			.field("diff_map", &self.diff_map.map(|diff_map| (diff_map.width, diff_map.height, diff_map.scaling_factor)))
 			.finish()
 	}
 }
@@ -78,6 +118,29 @@ pub struct SystemCapturer {
 	/// Since the writes to the pointer happen without the compiler knowing about it,
 	/// the pointer is also stored in a [`Cell`].
 	buffer: Box<Cell<*mut c_void>>,
+
+	/// The pointer to the diff map buffer, stored like [`SystemCapturer::buffer`].
+	diff_map: Box<Cell<*mut c_void>>,
+
+	/// The diff maps generated by the current capture session, if any.
+	diff_map_setup: Option<DiffMapSetup>,
+
+	/// The buffer format of the current capture session.
+	buffer_format: BufferFormat,
+}
+
+/// Diff map configuration of a capture session.
+#[derive(Debug, Copy, Clone)]
+struct DiffMapSetup {
+	scaling_factor: u32,
+
+	/// Size of the diff maps as reported when setting up the session.
+	size: Size,
+
+	/// Size of the first frame grabbed in the session.
+	///
+	/// NvFBC may reallocate the diff map when the frame size changes, after which its size is unknown.
+	frame_size: Option<Size>,
 }
 
 impl SystemCapturer {
@@ -86,7 +149,13 @@ impl SystemCapturer {
 	/// This also initializes a handle for the NVFBC API.
 	pub fn new() -> Result<Self, Error> {
 		let handle = create_handle()?;
-		let self_ = Self { handle, buffer: Box::new(Cell::new(null_mut())) };
+		let self_ = Self {
+			handle,
+			buffer: Box::new(Cell::new(null_mut())),
+			diff_map: Box::new(Cell::new(null_mut())),
+			diff_map_setup: None,
+			buffer_format: BufferFormat::Rgb,
+		};
 		Ok(self_)
 	}
 
@@ -97,17 +166,44 @@ impl SystemCapturer {
 
 	/// Start a capture session with the desired buffer format.
 	pub fn start(&mut self, buffer_format: BufferFormat, fps: u32) -> Result<(), Error> {
-		create_capture_session(
-			self.handle,
-			CaptureType::ToSystem,
-			std::time::Duration::from_millis(1000 / fps as u64),
-		)?;
+		self.start_with_options(&SessionOptions::new(buffer_format, fps))
+	}
 
+	/// Start a capture session configured by `options`.
+	pub fn start_with_options(&mut self, options: &SessionOptions) -> Result<(), Error> {
+		create_capture_session(self.handle, CaptureType::ToSystem, options)?;
+
+		nvfbc_span!(
+			"NvFBCToSysSetUp",
+			handle = self.handle,
+			buffer_format = ?options.buffer_format,
+			diff_map_scaling_factor = ?options.diff_map_scaling_factor,
+		);
 		let mut params: nvfbc_sys::NVFBC_TOSYS_SETUP_PARAMS = unsafe { MaybeUninit::zeroed().assume_init() };
-		params.dwVersion = nvfbc_sys::NVFBC_TOSYS_SETUP_PARAMS_VER;
-		params.eBufferFormat = buffer_format as u32;
+		params.dwVersion = struct_versions().tosys_setup;
+		params.eBufferFormat = options.buffer_format as u32;
 		params.ppBuffer = self.buffer.as_ptr();
-		check_ret(self.handle, unsafe { nvfbc_sys::NvFBCToSysSetUp(self.handle, &mut params) })
+		if let Some(scaling_factor) = options.diff_map_scaling_factor {
+			params.bWithDiffMap = nvfbc_sys::_NVFBC_BOOL_NVFBC_TRUE;
+			params.ppDiffMap = self.diff_map.as_ptr();
+			params.dwDiffMapScalingFactor = scaling_factor;
+		}
+		let result = check_ret(self.handle, unsafe { nvfbc_sys::NvFBCToSysSetUp(self.handle, &mut params) });
+		match result {
+			Ok(()) => {
+				self.buffer_format = options.buffer_format;
+				self.diff_map_setup = options.diff_map_scaling_factor.map(|scaling_factor| DiffMapSetup {
+					scaling_factor: scaling_factor.max(1),
+					size: Size { w: params.diffMapSize.w, h: params.diffMapSize.h },
+					frame_size: None,
+				});
+			},
+			Err(_) => {
+				// Don't leave a half initialized session behind on this handle.
+				destroy_capture_session(self.handle).ok();
+			},
+		}
+		result
 	}
 
 	/// Stop a capture session.
@@ -124,29 +220,77 @@ impl SystemCapturer {
 	/// If this restriction would be lifted, there would be a risk of unsound behaviour.
 	/// For example: calling next_frame() twice would overwrite the first buffer with the content of the second buffer.
 	/// Changing resolution inbetween the two calls could lead to reading out of bounds memory.
-	pub fn next_frame(&mut self, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<SystemFrameInfo, Error> {
+	pub fn next_frame(&mut self, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<SystemFrameInfo<'_>, Error> {
+		nvfbc_grab_span!(
+			"NvFBCToSysGrabFrame",
+			handle = self.handle,
+			buffer_format = ?self.buffer_format,
+			flags = capture_method as u32,
+			timeout_ms = timeout.map(|timeout| timeout.as_millis() as u64),
+		);
 		let mut frame_info: nvfbc_sys::NVFBC_FRAME_GRAB_INFO = unsafe { MaybeUninit::zeroed().assume_init() };
 		let mut params: nvfbc_sys::NVFBC_TOSYS_GRAB_FRAME_PARAMS = unsafe { MaybeUninit::zeroed().assume_init() };
-		params.dwVersion = nvfbc_sys::NVFBC_TOSYS_GRAB_FRAME_PARAMS_VER;
+		params.dwVersion = struct_versions().tosys_grab_frame;
 		params.dwFlags = capture_method as u32;
 		params.pFrameGrabInfo = &mut frame_info;
 		if let Some(timeout) = timeout {
 			params.dwTimeoutMs = timeout.as_millis() as u32;
 		}
 		check_ret(self.handle, unsafe { nvfbc_sys::NvFBCToSysGrabFrame(self.handle, &mut params) })?;
+		record_grab_info(&frame_info);
 		let buffer_ptr = unsafe { self.buffer.as_ptr().read_volatile().cast() };
 		let buffer = unsafe { std::slice::from_raw_parts(buffer_ptr, frame_info.dwByteSize as usize) };
 
+		let frame_size = Size { w: frame_info.dwWidth, h: frame_info.dwHeight };
+		let diff_map_ptr: *const u8 = unsafe { self.diff_map.as_ptr().read_volatile().cast() };
+		if let Some(setup) = &mut self.diff_map_setup {
+			setup.frame_size.get_or_insert(frame_size);
+		}
+		let diff_map = match &self.diff_map_setup {
+			Some(setup) if !diff_map_ptr.is_null() && setup.frame_size == Some(frame_size) => {
+				let len = setup.size.w as usize * setup.size.h as usize;
+				Some(DiffMap {
+					data: unsafe { std::slice::from_raw_parts(diff_map_ptr, len) },
+					width: setup.size.w,
+					height: setup.size.h,
+					scaling_factor: setup.scaling_factor,
+				})
+			},
+			_ => None,
+		};
+
 		Ok(SystemFrameInfo {
 			buffer,
 			width: frame_info.dwWidth,
 			height: frame_info.dwHeight,
+			buffer_format: self.buffer_format,
 			current_frame: frame_info.dwCurrentFrame,
 			is_new_frame: frame_info.bIsNewFrame != 0,
+			timestamp_us: frame_info.ulTimestampUs,
+			missed_frames: frame_info.dwMissedFrames,
+			diff_map,
 		})
 	}
 }
 
+impl Capture for SystemCapturer {
+	fn status(&self) -> Result<Status, Error> {
+		SystemCapturer::status(self)
+	}
+
+	fn start_with_options(&mut self, options: &SessionOptions) -> Result<(), Error> {
+		SystemCapturer::start_with_options(self, options)
+	}
+
+	fn stop(&mut self) -> Result<(), Error> {
+		SystemCapturer::stop(self)
+	}
+
+	fn next_frame(&mut self, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<SystemFrameInfo<'_>, Error> {
+		SystemCapturer::next_frame(self, capture_method, timeout)
+	}
+}
+
 impl Drop for SystemCapturer {
 	fn drop(&mut self) {
 		// TODO: Figure out why this crashes (nvfbc examples also fail here..)
@@ -1,6 +1,10 @@
-use std::ffi::CStr;
+use std::os::raw::c_char;
 
-#[derive(Debug, Copy, Clone)]
+use crate::StatusError;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum CaptureType {
 	/// Capture frames to a buffer in system memory.
 	ToSystem = nvfbc_sys::_NVFBC_CAPTURE_TYPE_NVFBC_CAPTURE_TO_SYS as isize,
@@ -12,7 +16,9 @@ pub enum CaptureType {
 	ToOpenGl = nvfbc_sys::_NVFBC_CAPTURE_TYPE_NVFBC_CAPTURE_TO_GL as isize,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum BufferFormat {
 	/// Data will be converted to ARGB8888 byte-order format. 32 bpp.
 	Argb = nvfbc_sys::_NVFBC_BUFFER_FORMAT_NVFBC_BUFFER_FORMAT_ARGB as isize,
@@ -39,7 +45,8 @@ pub enum BufferFormat {
 /// scans a region of 1600x1200+1920+0, then setting a capture box of
 /// 800x600+100+50 effectively captures a region of 800x600+2020+50 relative to
 /// the X screen.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Box {
 	/// X offset of the box.
 	pub x: u32,
@@ -52,7 +59,8 @@ pub struct Box {
 }
 
 /// Size used to describe the size of a frame.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Size {
 	/// Width.
 	pub w: u32,
@@ -60,11 +68,190 @@ pub struct Size {
 	pub h: u32,
 }
 
+/// Region of the framebuffer that a capture session tracks.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Tracking {
+	/// Track the primary RandR output if XRandR is available, otherwise the entire X screen.
+	Default,
+	/// Track the RandR output with the given [`Output::id`].
+	Output(u32),
+	/// Track the entire X screen.
+	Screen,
+}
+
+/// Options used to create a capture session.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionOptions {
+	/// Format of the captured frames.
+	pub buffer_format: BufferFormat,
+
+	/// Rate at which the display server generates new frames.
+	pub fps: u32,
+
+	/// Region of the framebuffer to track.
+	#[cfg_attr(feature = "serde", serde(default = "default_tracking"))]
+	pub tracking: Tracking,
+
+	/// Crop the tracked region to this box, or capture the entire tracked region if `None`.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub capture_box: Option<Box>,
+
+	/// Scale the captured frames to this size, or disable scaling if `None`.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub frame_size: Option<Size>,
+
+	/// Whether the mouse cursor should be composited to the frame.
+	#[cfg_attr(feature = "serde", serde(default = "default_with_cursor"))]
+	pub with_cursor: bool,
+
+	/// Generate a diff map for every frame with blocks of this many pixels, or no diff maps if `None`.
+	///
+	/// Diff maps are only generated when capturing to system memory, and not for the [`BufferFormat::Yuv444p`] buffer format.
+	/// See [`SystemFrameInfo::diff_map`](crate::system::SystemFrameInfo::diff_map).
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub diff_map_scaling_factor: Option<u32>,
+}
+
+impl SessionOptions {
+	/// Create options that capture the default tracked region with the cursor composited.
+	pub fn new(buffer_format: BufferFormat, fps: u32) -> Self {
+		Self {
+			buffer_format,
+			fps,
+			tracking: Tracking::Default,
+			capture_box: None,
+			frame_size: None,
+			with_cursor: true,
+			diff_map_scaling_factor: None,
+		}
+	}
+}
+
+#[cfg(feature = "serde")]
+fn default_tracking() -> Tracking {
+	Tracking::Default
+}
+
+#[cfg(feature = "serde")]
+fn default_with_cursor() -> bool {
+	true
+}
+
+/// Version of the NvFBC API.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Version {
+	/// Major version.
+	pub major: u32,
+	/// Minor version.
+	pub minor: u32,
+}
+
+/// How an installed NvFBC version relates to the version this crate was compiled against.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Compatibility {
+	/// The installed version is the compiled version or newer.
+	Full,
+	/// The installed version is older, but its struct layouts are compatible.
+	///
+	/// Features added in newer versions, such as [`Status::in_modeset`], are not available.
+	Fallback,
+	/// The installed version is too old to be used.
+	Unsupported,
+}
+
+impl Version {
+	/// The version this crate was compiled against.
+	pub const COMPILED: Version = Version { major: nvfbc_sys::NVFBC_VERSION_MAJOR, minor: nvfbc_sys::NVFBC_VERSION_MINOR };
+
+	/// First NVIDIA driver branches shipping each NvFBC version.
+	const DRIVERS: [(Version, &'static str); 3] = [
+		(Version { major: 1, minor: 6 }, "410"),
+		(Version { major: 1, minor: 7 }, "440"),
+		(Version { major: 1, minor: 8 }, "470"),
+	];
+
+	/// Parse a version as encoded by NvFBC, with the major version in the second byte.
+	pub fn from_raw(version: u32) -> Self {
+		Self { major: version >> 8, minor: version & 0xff }
+	}
+
+	/// Encode this version the way NvFBC does.
+	pub fn to_raw(self) -> u32 {
+		nvfbc_sys::nvfbc_api_version(self.major, self.minor)
+	}
+
+	/// The API version negotiated with the installed NvFBC library.
+	///
+	/// This is [`Version::COMPILED`] until a handle has been created using an older version.
+	pub fn negotiated() -> Self {
+		Self::from_raw(crate::common::api_version())
+	}
+
+	/// The versions tried in order when creating a handle, after this version was negotiated.
+	///
+	/// These are this version, followed by the older versions whose struct layouts are compatible.
+	pub fn fallbacks(self) -> Vec<Version> {
+		crate::common::candidate_api_versions(self.to_raw()).map(|(version, _)| Self::from_raw(version)).collect()
+	}
+
+	/// The first NVIDIA driver branch that ships this version, if known.
+	pub fn minimum_driver(&self) -> Option<&'static str> {
+		Self::DRIVERS.iter()
+			.find(|(version, _)| version == self)
+			.map(|(_, driver)| *driver)
+	}
+
+	/// How this version, as installed on the system, relates to [`Version::COMPILED`].
+	pub fn compatibility(&self) -> Compatibility {
+		if *self >= Self::COMPILED {
+			Compatibility::Full
+		} else if nvfbc_sys::StructVersions::for_api_version(self.to_raw()).is_some() {
+			Compatibility::Fallback
+		} else {
+			Compatibility::Unsupported
+		}
+	}
+
+	/// Describe what is needed to get full support, if this version is not fully supported.
+	pub fn diagnostic(&self) -> Option<String> {
+		match self.compatibility() {
+			Compatibility::Full => None,
+			Compatibility::Fallback => Some(format!(
+				"NvFBC {} is installed, which is older than NvFBC {}; newer features are unavailable. {}",
+				self, Self::COMPILED, Self::COMPILED.requirement(),
+			)),
+			Compatibility::Unsupported => Some(format!(
+				"NvFBC {} is installed, which is not supported. {}",
+				self, Self::COMPILED.requirement(),
+			)),
+		}
+	}
+
+	/// Describe the driver required for this version.
+	pub(crate) fn requirement(&self) -> String {
+		match self.minimum_driver() {
+			Some(driver) => format!("NvFBC {} requires NVIDIA driver {} or newer", self, driver),
+			None => format!("NvFBC {} requires a newer NVIDIA driver", self),
+		}
+	}
+}
+
+impl std::fmt::Display for Version {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{}.{}", self.major, self.minor)
+	}
+}
+
 /// Describes an RandR output.
 ///
 /// Filling this structure relies on the XRandR extension.  This feature cannot
 /// be used if the extension is missing or its version is below the requirements.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Output {
 	/// Identifier of the RandR output.
 	pub id: u32,
@@ -78,7 +265,8 @@ pub struct Output {
 	pub tracked_box: Box,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Status {
 	/// Whether or not framebuffer capture is supported by the graphics driver.
 	pub is_capture_possible: bool,
@@ -105,7 +293,7 @@ pub struct Status {
 	pub outputs: Vec<Output>,
 
 	/// Version of the NvFBC library running on this system.
-	pub nvfbc_version: u32,
+	pub nvfbc_version: Version,
 
 	/// Whether the X server is currently in modeset.
 	///
@@ -117,34 +305,89 @@ pub struct Status {
 	pub in_modeset: bool,
 }
 
-impl From<nvfbc_sys::_NVFBC_GET_STATUS_PARAMS> for Status {
-	fn from(status: nvfbc_sys::_NVFBC_GET_STATUS_PARAMS) -> Self {
+/// Information about a grabbed frame, without the frame data itself.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameGrabInfo {
+	/// Width of the captured frame.
+	pub width: u32,
+	/// Height of the captured frame.
+	pub height: u32,
+	/// Size of the frame in bytes.
+	pub byte_size: u32,
+	/// Incremental ID of the current frame.
+	pub current_frame: u32,
+	/// Whether this frame is a new frame.
+	pub is_new_frame: bool,
+	/// Time in microseconds when the display server started rendering the frame.
+	pub timestamp_us: u64,
+	/// Number of frames the display server rendered since the previous grab that were not captured.
+	pub missed_frames: u32,
+}
+
+impl From<nvfbc_sys::NVFBC_FRAME_GRAB_INFO> for FrameGrabInfo {
+	fn from(frame_info: nvfbc_sys::NVFBC_FRAME_GRAB_INFO) -> Self {
+		Self {
+			width: frame_info.dwWidth,
+			height: frame_info.dwHeight,
+			byte_size: frame_info.dwByteSize,
+			current_frame: frame_info.dwCurrentFrame,
+			is_new_frame: frame_info.bIsNewFrame != 0,
+			timestamp_us: frame_info.ulTimestampUs,
+			missed_frames: frame_info.dwMissedFrames,
+		}
+	}
+}
+
+impl TryFrom<nvfbc_sys::_NVFBC_GET_STATUS_PARAMS> for Status {
+	type Error = StatusError;
+
+	fn try_from(status: nvfbc_sys::_NVFBC_GET_STATUS_PARAMS) -> Result<Self, Self::Error> {
+		if status.dwOutputNum > nvfbc_sys::NVFBC_OUTPUT_MAX {
+			return Err(StatusError::TooManyOutputs { count: status.dwOutputNum, max: nvfbc_sys::NVFBC_OUTPUT_MAX });
+		}
+
+		let screen_size = Size { w: status.screenSize.w, h: status.screenSize.h };
 		let mut outputs = Vec::with_capacity(status.dwOutputNum as usize);
-		for output_index in 0..status.dwOutputNum {
+		for output in &status.outputs[..status.dwOutputNum as usize] {
+			let tracked_box = Box {
+				x: output.trackedBox.x,
+				y: output.trackedBox.y,
+				w: output.trackedBox.w,
+				h: output.trackedBox.h,
+			};
+			if tracked_box.x as u64 + tracked_box.w as u64 > screen_size.w as u64
+				|| tracked_box.y as u64 + tracked_box.h as u64 > screen_size.h as u64
+			{
+				return Err(StatusError::TrackedBoxOutOfBounds { output_id: output.dwId, tracked_box, screen_size });
+			}
+
 			outputs.push(Output {
-				id: status.outputs[output_index as usize].dwId,
-				name: unsafe {
-					CStr::from_ptr(&status.outputs[output_index as usize].name as *const i8)
-						.to_str().unwrap().to_string()
-				},
-				tracked_box: Box {
-					x: status.outputs[output_index as usize].trackedBox.x,
-					y: status.outputs[output_index as usize].trackedBox.y,
-					w: status.outputs[output_index as usize].trackedBox.w,
-					h: status.outputs[output_index as usize].trackedBox.h,
-				},
+				id: output.dwId,
+				name: decode_output_name(&output.name),
+				tracked_box,
 			});
 		}
 
-		Self {
+		Ok(Self {
 			is_capture_possible: status.bIsCapturePossible == nvfbc_sys::_NVFBC_BOOL_NVFBC_TRUE,
 			currently_capturing: status.bCurrentlyCapturing == nvfbc_sys::_NVFBC_BOOL_NVFBC_TRUE,
 			can_create_now: status.bCanCreateNow == nvfbc_sys::_NVFBC_BOOL_NVFBC_TRUE,
-			screen_size: Size { w: status.screenSize.w, h: status.screenSize.h },
+			screen_size,
 			xrandr_available: status.bXRandRAvailable == nvfbc_sys::_NVFBC_BOOL_NVFBC_TRUE,
 			outputs,
-			nvfbc_version: status.dwNvFBCVersion,
+			nvfbc_version: Version::from_raw(status.dwNvFBCVersion),
 			in_modeset: status.bInModeset == nvfbc_sys::_NVFBC_BOOL_NVFBC_TRUE,
-		}
+		})
 	}
 }
+
+/// Decode the name of an output, which is not guaranteed to be NUL terminated nor valid UTF-8.
+fn decode_output_name(name: &[c_char]) -> String {
+	let name = &name[..name.len().min(nvfbc_sys::NVFBC_OUTPUT_NAME_LEN as usize)];
+	let bytes: Vec<u8> = name.iter()
+		.map(|&c| c as u8)
+		.take_while(|&c| c != 0)
+		.collect();
+	String::from_utf8_lossy(&bytes).into_owned()
+}
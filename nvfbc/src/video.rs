@@ -0,0 +1,407 @@
+//! Writing captured frames as YUV4MPEG2 (Y4M) or headerless raw video.
+//!
+//! Both containers store planar YUV frames, as expected by tools such as ffmpeg and x264.
+//! NV12 and YUV444P frames are repacked, RGB frames are converted using [`convert`](crate::convert).
+//!
+//! ```no_run
+//! use nvfbc::video::{Container, VideoWriter, WriterOptions};
+//! use nvfbc::{BufferFormat, SystemCapturer};
+//! use nvfbc::system::CaptureMethod;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let mut capturer = SystemCapturer::new()?;
+//!     capturer.start(BufferFormat::Nv12, 60)?;
+//!
+//!     let mut writer = VideoWriter::create("capture.y4m", WriterOptions::new(Container::Y4m, 60))?;
+//!     for _ in 0..600 {
+//!         writer.write_frame(&capturer.next_frame(CaptureMethod::Blocking, None)?)?;
+//!     }
+//!     writer.flush()?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::convert;
+use crate::system::SystemFrameInfo;
+use crate::{BufferFormat, BufferSizeError, Size};
+
+/// File format of the written video.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Container {
+	/// YUV4MPEG2, a stream header followed by frames that each start with a `FRAME` line.
+	Y4m,
+	/// Frames written back to back without any header.
+	Raw,
+}
+
+/// Chroma subsampling of the written frames.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Chroma {
+	/// Chroma subsampled by two in both directions (I420), as produced by NV12 capture.
+	C420,
+	/// Full resolution chroma, as produced by YUV444P capture.
+	C444,
+}
+
+impl Chroma {
+	/// Chroma subsampling that stores `format` without loss, `C420` for RGB formats.
+	pub fn for_format(format: BufferFormat) -> Self {
+		match format {
+			BufferFormat::Yuv444p => Chroma::C444,
+			_ => Chroma::C420,
+		}
+	}
+
+	/// The buffer format NvFBC captures in with this chroma subsampling.
+	fn buffer_format(&self) -> BufferFormat {
+		match self {
+			Chroma::C420 => BufferFormat::Nv12,
+			Chroma::C444 => BufferFormat::Yuv444p,
+		}
+	}
+
+	/// Size of the U and V planes of a frame of `width` x `height` pixels.
+	fn plane_size(&self, width: u32, height: u32) -> (usize, usize) {
+		match self {
+			Chroma::C420 => (width.div_ceil(2) as usize, height.div_ceil(2) as usize),
+			Chroma::C444 => (width as usize, height as usize),
+		}
+	}
+}
+
+/// What to do when the frame size changes in the middle of a stream.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ResizePolicy {
+	/// Return [`WriterError::Resized`] and drop the frame.
+	Fail,
+	/// Finish the current file and continue in a new file with the new frame size.
+	///
+	/// Only supported by writers that can open new files, such as [`VideoWriter::create`].
+	NewFile,
+}
+
+/// Options for a [`VideoWriter`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WriterOptions {
+	/// File format of the written video.
+	pub container: Container,
+	/// Frame rate written in the Y4M header.
+	pub fps: u32,
+	/// Chroma subsampling of the written frames, by default derived from the format of the first frame.
+	pub chroma: Option<Chroma>,
+	/// What to do when the frame size changes.
+	pub on_resize: ResizePolicy,
+}
+
+impl WriterOptions {
+	/// Options writing `container` at `fps`, failing when the frame size changes.
+	///
+	/// Y4M streams with an `fps` of zero are rejected when their first frame is written.
+	pub fn new(container: Container, fps: u32) -> Self {
+		Self { container, fps, chroma: None, on_resize: ResizePolicy::Fail }
+	}
+}
+
+/// Reasons why a frame could not be written.
+#[derive(Debug)]
+pub enum WriterError {
+	/// Writing to or opening the output failed.
+	Io(io::Error),
+	/// The frame buffer does not match its dimensions and format.
+	BufferSize(BufferSizeError),
+	/// The frame size changed and the resize policy is [`ResizePolicy::Fail`].
+	Resized {
+		/// Frame size of the current stream.
+		old: Size,
+		/// Size of the rejected frame.
+		new: Size,
+	},
+}
+
+impl fmt::Display for WriterError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			WriterError::Io(error) => write!(f, "Failed to write video: {}", error),
+			WriterError::BufferSize(error) => write!(f, "Invalid frame: {}", error),
+			WriterError::Resized { old, new } => write!(
+				f,
+				"Frame size changed from {}x{} to {}x{} in the middle of the stream",
+				old.w, old.h, new.w, new.h,
+			),
+		}
+	}
+}
+
+impl std::error::Error for WriterError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			WriterError::Io(error) => Some(error),
+			WriterError::BufferSize(error) => Some(error),
+			WriterError::Resized { .. } => None,
+		}
+	}
+}
+
+impl From<io::Error> for WriterError {
+	fn from(error: io::Error) -> Self {
+		WriterError::Io(error)
+	}
+}
+
+impl From<BufferSizeError> for WriterError {
+	fn from(error: BufferSizeError) -> Self {
+		WriterError::BufferSize(error)
+	}
+}
+
+/// Opens the output for the file with the given index, starting at 0.
+type Opener<W> = std::boxed::Box<dyn FnMut(usize) -> io::Result<W>>;
+
+/// Writes frames as Y4M or raw planar YUV.
+///
+/// The stream header is written when the first frame arrives,
+/// using its dimensions and the chroma subsampling from [`WriterOptions::chroma`] or the first frame's format.
+/// Y4M headers declare BT.709 limited range, matching the RGB to YUV conversion of NvFBC.
+pub struct VideoWriter<W: Write> {
+	options: WriterOptions,
+	writer: W,
+	open: Option<Opener<W>>,
+	file_index: usize,
+	/// Frame size and chroma subsampling of the current stream, once its first frame is written.
+	stream: Option<(Size, Chroma)>,
+	frames: u64,
+	planar: Vec<u8>,
+	rgb: Vec<u8>,
+	yuv: Vec<u8>,
+}
+
+impl<W: Write> VideoWriter<W> {
+	/// Write a single stream to `writer`.
+	///
+	/// [`ResizePolicy::NewFile`] is treated as [`ResizePolicy::Fail`], since no new file can be opened.
+	pub fn new(writer: W, options: WriterOptions) -> Self {
+		Self {
+			options,
+			writer,
+			open: None,
+			file_index: 0,
+			stream: None,
+			frames: 0,
+			planar: Vec::new(),
+			rgb: Vec::new(),
+			yuv: Vec::new(),
+		}
+	}
+
+	/// Write to outputs created by `open`, which receives the index of the file starting at 0.
+	///
+	/// A new output is opened whenever the frame size changes and the resize policy is [`ResizePolicy::NewFile`].
+	pub fn with_opener(options: WriterOptions, mut open: impl FnMut(usize) -> io::Result<W> + 'static) -> io::Result<Self> {
+		let writer = open(0)?;
+		let mut video = Self::new(writer, options);
+		video.open = Some(std::boxed::Box::new(open));
+		Ok(video)
+	}
+
+	/// The options of this writer.
+	pub fn options(&self) -> &WriterOptions {
+		&self.options
+	}
+
+	/// Index of the file currently being written, incremented whenever a new file is started.
+	pub fn file_index(&self) -> usize {
+		self.file_index
+	}
+
+	/// Number of frames written to the current file.
+	pub fn frames(&self) -> u64 {
+		self.frames
+	}
+
+	/// Frame size and chroma subsampling of the current file, or `None` before the first frame.
+	pub fn stream(&self) -> Option<(Size, Chroma)> {
+		self.stream
+	}
+
+	/// Write a captured frame.
+	pub fn write_frame(&mut self, frame: &SystemFrameInfo) -> Result<(), WriterError> {
+		self.write_buffer(frame.buffer, frame.width, frame.height, frame.buffer_format)
+	}
+
+	/// Write a frame of `width` x `height` pixels stored in `buffer` in `format`.
+	pub fn write_buffer(&mut self, buffer: &[u8], width: u32, height: u32, format: BufferFormat) -> Result<(), WriterError> {
+		convert::check_size(buffer, width, height, format)?;
+
+		let size = Size { w: width, h: height };
+		let chroma = match self.stream {
+			Some((old, chroma)) if old == size => chroma,
+			Some((old, chroma)) => {
+				match (self.options.on_resize, &mut self.open) {
+					(ResizePolicy::NewFile, Some(open)) => {
+						self.writer.flush()?;
+						self.writer = open(self.file_index + 1)?;
+						self.file_index += 1;
+						self.frames = 0;
+					},
+					_ => return Err(WriterError::Resized { old, new: size }),
+				}
+				self.start_stream(size, chroma)?;
+				chroma
+			},
+			None => {
+				let chroma = self.options.chroma.unwrap_or_else(|| Chroma::for_format(format));
+				self.start_stream(size, chroma)?;
+				chroma
+			},
+		};
+
+		self.fill_planar(buffer, width, height, format, chroma)?;
+		if self.options.container == Container::Y4m {
+			self.writer.write_all(b"FRAME\n")?;
+		}
+		self.writer.write_all(&self.planar)?;
+		self.frames += 1;
+
+		Ok(())
+	}
+
+	/// Flush the current output.
+	pub fn flush(&mut self) -> io::Result<()> {
+		self.writer.flush()
+	}
+
+	/// Flush and return the current output.
+	pub fn into_inner(mut self) -> io::Result<W> {
+		self.writer.flush()?;
+		Ok(self.writer)
+	}
+
+	fn start_stream(&mut self, size: Size, chroma: Chroma) -> io::Result<()> {
+		if self.options.container == Container::Y4m {
+			if self.options.fps == 0 {
+				return Err(io::Error::new(io::ErrorKind::InvalidInput, "the frame rate of a Y4M stream must be larger than zero"));
+			}
+			self.writer.write_all(y4m_header(size, chroma, self.options.fps).as_bytes())?;
+		}
+		self.stream = Some((size, chroma));
+		Ok(())
+	}
+
+	/// Store `buffer` as planar YUV with `chroma` subsampling in `self.planar`.
+	fn fill_planar(&mut self, buffer: &[u8], width: u32, height: u32, format: BufferFormat, chroma: Chroma) -> Result<(), BufferSizeError> {
+		let luma_len = width as usize * height as usize;
+		let (chroma_width, chroma_height) = chroma.plane_size(width, height);
+		let chroma_len = chroma_width * chroma_height;
+		self.planar.resize(luma_len + 2 * chroma_len, 0);
+
+		if !format.is_yuv() {
+			let yuv_format = chroma.buffer_format();
+			let mut yuv = std::mem::take(&mut self.yuv);
+			yuv.resize(yuv_format.frame_size(width, height), 0);
+			self.rgb.resize(BufferFormat::Rgb.frame_size(width, height), 0);
+			convert::to_rgb_into(buffer, width, height, format, &mut self.rgb)?;
+			convert::from_rgb_into(&self.rgb, width, height, yuv_format, &mut yuv)?;
+			let result = self.fill_planar(&yuv, width, height, yuv_format, chroma);
+			self.yuv = yuv;
+			return result;
+		}
+
+		let planes = format.planes(width, height);
+		let (luma, rest) = self.planar.split_at_mut(luma_len);
+		let (u, v) = rest.split_at_mut(chroma_len);
+		luma.copy_from_slice(&buffer[planes[0].range()]);
+
+		let (width, height) = (width as usize, height as usize);
+		match (format, chroma) {
+			(BufferFormat::Nv12, Chroma::C420) => {
+				for (i, uv) in buffer[planes[1].range()].chunks_exact(2).enumerate() {
+					u[i] = uv[0];
+					v[i] = uv[1];
+				}
+			},
+			(BufferFormat::Nv12, Chroma::C444) => {
+				let plane = &buffer[planes[1].range()];
+				let stride = planes[1].stride();
+				for y in 0..height {
+					for x in 0..width {
+						let index = (y / 2) * stride + (x / 2) * 2;
+						u[y * width + x] = plane[index];
+						v[y * width + x] = plane[index + 1];
+					}
+				}
+			},
+			(_, Chroma::C444) => {
+				u.copy_from_slice(&buffer[planes[1].range()]);
+				v.copy_from_slice(&buffer[planes[2].range()]);
+			},
+			(_, Chroma::C420) => {
+				let (src_u, src_v) = (&buffer[planes[1].range()], &buffer[planes[2].range()]);
+				for cy in 0..chroma_height {
+					for cx in 0..chroma_width {
+						let (mut sum_u, mut sum_v, mut count) = (0u32, 0u32, 0u32);
+						for y in (cy * 2)..(cy * 2 + 2).min(height) {
+							for x in (cx * 2)..(cx * 2 + 2).min(width) {
+								sum_u += src_u[y * width + x] as u32;
+								sum_v += src_v[y * width + x] as u32;
+								count += 1;
+							}
+						}
+						u[cy * chroma_width + cx] = ((sum_u + count / 2) / count) as u8;
+						v[cy * chroma_width + cx] = ((sum_v + count / 2) / count) as u8;
+					}
+				}
+			},
+		}
+
+		Ok(())
+	}
+}
+
+impl VideoWriter<BufWriter<File>> {
+	/// Write to the file at `path`.
+	///
+	/// With [`ResizePolicy::NewFile`], later files are named after `path` with the file index appended to the stem,
+	/// e.g. `capture.y4m`, `capture-1.y4m`, `capture-2.y4m`.
+	pub fn create(path: impl AsRef<Path>, options: WriterOptions) -> io::Result<Self> {
+		let path = path.as_ref().to_path_buf();
+		Self::with_opener(options, move |index| Ok(BufWriter::new(File::create(numbered_path(&path, index))?)))
+	}
+}
+
+/// The path of the file with `index` for a video written to `path`.
+pub fn numbered_path(path: &Path, index: usize) -> PathBuf {
+	if index == 0 {
+		return path.to_path_buf();
+	}
+
+	let mut name = path.file_stem().unwrap_or_default().to_os_string();
+	name.push(format!("-{}", index));
+	if let Some(extension) = path.extension() {
+		name.push(".");
+		name.push(extension);
+	}
+	path.with_file_name(name)
+}
+
+/// The Y4M stream header for frames of `size` at `fps`.
+///
+/// Chroma of NV12 frames is sited in the center of every 2x2 block, which Y4M calls `420jpeg`.
+/// The range is declared with `XCOLORRANGE=LIMITED`, which ffmpeg reads and writes.
+/// The matrix is declared with `XCOLORMATRIX=BT709`, which is not a standard Y4M tag but an extension of this crate.
+/// Readers skip unknown `X` tags, so tools such as ffmpeg should be told `-colorspace bt709` explicitly.
+pub fn y4m_header(size: Size, chroma: Chroma, fps: u32) -> String {
+	let chroma = match chroma {
+		Chroma::C420 => "420jpeg",
+		Chroma::C444 => "444",
+	};
+	format!(
+		"YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C{} XCOLORRANGE=LIMITED XCOLORMATRIX=BT709\n",
+		size.w, size.h, fps, chroma,
+	)
+}
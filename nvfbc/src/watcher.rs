@@ -0,0 +1,165 @@
+use std::time::{Duration, Instant};
+
+use crate::common::{Handle, create_handle, destroy_handle, status};
+use crate::{Box, Error, Output, Size, Status};
+
+/// A change between two consecutive [`Status`] snapshots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatusEvent {
+	/// A RandR output was connected.
+	OutputAdded(Output),
+	/// A RandR output was disconnected.
+	OutputRemoved(Output),
+	/// The region tracked by a RandR output moved or changed size.
+	TrackedBoxChanged {
+		/// Identifier of the RandR output.
+		id: u32,
+		/// Name of the RandR output.
+		name: String,
+		/// Previously tracked region.
+		old: Box,
+		/// Currently tracked region.
+		new: Box,
+	},
+	/// The size of the X screen changed.
+	ScreenResized {
+		/// Previous size of the X screen.
+		old: Size,
+		/// Current size of the X screen.
+		new: Size,
+	},
+	/// The X server entered a modeset.
+	ModesetEntered,
+	/// The X server exited a modeset.
+	ModesetExited,
+	/// Whether a capture session can be created changed to the given value.
+	CanCreateNowChanged(bool),
+	/// A capture session was started on this system.
+	///
+	/// Note that this includes capture sessions of the current process.
+	CaptureStarted,
+	/// There is no longer a capture session on this system.
+	CaptureStopped,
+}
+
+/// Compute the events that describe the changes from `old` to `new`.
+///
+/// Outputs are matched by their [`Output::id`].
+pub fn diff(old: &Status, new: &Status) -> Vec<StatusEvent> {
+	let mut events = Vec::new();
+
+	if old.in_modeset != new.in_modeset {
+		events.push(if new.in_modeset { StatusEvent::ModesetEntered } else { StatusEvent::ModesetExited });
+	}
+
+	if old.screen_size != new.screen_size {
+		events.push(StatusEvent::ScreenResized { old: old.screen_size, new: new.screen_size });
+	}
+
+	for output in &old.outputs {
+		if !new.outputs.iter().any(|o| o.id == output.id) {
+			events.push(StatusEvent::OutputRemoved(output.clone()));
+		}
+	}
+
+	for output in &new.outputs {
+		match old.outputs.iter().find(|o| o.id == output.id) {
+			None => events.push(StatusEvent::OutputAdded(output.clone())),
+			Some(previous) if previous.tracked_box != output.tracked_box => events.push(StatusEvent::TrackedBoxChanged {
+				id: output.id,
+				name: output.name.clone(),
+				old: previous.tracked_box,
+				new: output.tracked_box,
+			}),
+			Some(_) => {},
+		}
+	}
+
+	if old.can_create_now != new.can_create_now {
+		events.push(StatusEvent::CanCreateNowChanged(new.can_create_now));
+	}
+
+	if old.currently_capturing != new.currently_capturing {
+		events.push(if new.currently_capturing { StatusEvent::CaptureStarted } else { StatusEvent::CaptureStopped });
+	}
+
+	events
+}
+
+/// Polls the status of NVFBC and reports what changed between polls.
+pub struct StatusWatcher {
+	/// The nvfbc handle used to query the status.
+	handle: Handle,
+
+	/// Time between two polls in [`StatusWatcher::wait`].
+	interval: Duration,
+
+	/// The most recent snapshot.
+	last: Status,
+
+	/// When the most recent snapshot was taken.
+	last_poll: Instant,
+}
+
+impl StatusWatcher {
+	/// Create a watcher that polls every `interval`.
+	///
+	/// This creates a handle for the NVFBC API and takes the initial snapshot.
+	pub fn new(interval: Duration) -> Result<Self, Error> {
+		let handle = create_handle()?;
+		let last = match status(handle) {
+			Ok(last) => last,
+			Err(e) => {
+				destroy_handle(handle).ok();
+				return Err(e);
+			},
+		};
+
+		Ok(Self { handle, interval, last, last_poll: Instant::now() })
+	}
+
+	/// Time between two polls.
+	pub fn interval(&self) -> Duration {
+		self.interval
+	}
+
+	/// Change the time between two polls.
+	pub fn set_interval(&mut self, interval: Duration) {
+		self.interval = interval;
+	}
+
+	/// The most recent snapshot.
+	pub fn status(&self) -> &Status {
+		&self.last
+	}
+
+	/// Take a new snapshot immediately and return the changes since the previous one.
+	pub fn poll(&mut self) -> Result<Vec<StatusEvent>, Error> {
+		let current = status(self.handle)?;
+		self.last_poll = Instant::now();
+		let events = diff(&self.last, &current);
+		self.last = current;
+		Ok(events)
+	}
+
+	/// Block until a poll reports at least one change, polling every interval.
+	pub fn wait(&mut self) -> Result<Vec<StatusEvent>, Error> {
+		loop {
+			let elapsed = self.last_poll.elapsed();
+			if elapsed < self.interval {
+				std::thread::sleep(self.interval - elapsed);
+			}
+
+			let events = self.poll()?;
+			if !events.is_empty() {
+				return Ok(events);
+			}
+		}
+	}
+}
+
+impl Drop for StatusWatcher {
+	fn drop(&mut self) {
+		destroy_handle(self.handle).ok();
+	}
+}
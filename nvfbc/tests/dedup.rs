@@ -0,0 +1,108 @@
+use nvfbc::convert::from_rgb;
+use nvfbc::dedup::{frame_hash, DedupOptions, Deduplicator, Similarity};
+use nvfbc::synthetic::SyntheticCapturer;
+use nvfbc::system::CaptureMethod;
+use nvfbc::{BufferFormat, Capture, SessionOptions};
+
+const WIDTH: u32 = 100;
+const HEIGHT: u32 = 60;
+
+fn frame(index: u64, format: BufferFormat) -> Vec<u8> {
+	from_rgb(&SyntheticCapturer::pattern(WIDTH, HEIGHT, index), WIDTH, HEIGHT, format).unwrap()
+}
+
+#[test]
+fn exact_duplicates_in_every_format() {
+	for format in BufferFormat::ALL {
+		let mut deduplicator = Deduplicator::new(DedupOptions::default());
+		let first = deduplicator.check_buffer(&frame(0, format), WIDTH, HEIGHT, format).unwrap();
+		assert_eq!(first.similarity, Similarity::Distinct);
+		assert_eq!(first.changed_tiles, 2, "{:?}", format);
+		assert_eq!(first.hash, frame_hash(&frame(0, format), WIDTH, HEIGHT, format).unwrap());
+
+		let second = deduplicator.check_buffer(&frame(0, format), WIDTH, HEIGHT, format).unwrap();
+		assert_eq!(second.similarity, Similarity::Duplicate, "{:?}", format);
+		assert!(second.is_duplicate());
+		assert_eq!((second.hash, second.changed_tiles, second.changed_pixels), (first.hash, 0, None));
+
+		// The square moved, in the left tile only.
+		let third = deduplicator.check_buffer(&frame(1, format), WIDTH, HEIGHT, format).unwrap();
+		assert_eq!((third.similarity, third.changed_tiles), (Similarity::Distinct, 1), "{:?}", format);
+		assert_ne!(third.hash, first.hash);
+		assert!(!third.is_duplicate());
+	}
+}
+
+#[test]
+fn layout_changes_are_distinct() {
+	let mut deduplicator = Deduplicator::new(DedupOptions { tile_size: 16, ..DedupOptions::default() });
+	let rgb = frame(0, BufferFormat::Rgb);
+	deduplicator.check_buffer(&rgb, WIDTH, HEIGHT, BufferFormat::Rgb).unwrap();
+
+	// The same bytes in another format or size are not duplicates.
+	let yuv = deduplicator.check_buffer(&rgb, WIDTH, HEIGHT, BufferFormat::Yuv444p).unwrap();
+	assert_eq!((yuv.similarity, yuv.changed_tiles), (Similarity::Distinct, 7 * 4));
+	let transposed = deduplicator.check_buffer(&rgb, HEIGHT, WIDTH, BufferFormat::Yuv444p).unwrap();
+	assert_eq!(transposed.similarity, Similarity::Distinct);
+	assert_ne!(yuv.hash, transposed.hash);
+	assert!(deduplicator.check_buffer(&rgb[1..], WIDTH, HEIGHT, BufferFormat::Rgb).is_err());
+
+	deduplicator.reset();
+	let after_reset = deduplicator.check_buffer(&rgb, HEIGHT, WIDTH, BufferFormat::Yuv444p).unwrap();
+	assert_eq!(after_reset.similarity, Similarity::Distinct);
+}
+
+#[test]
+fn near_duplicates_add_up() {
+	let options = DedupOptions { tile_size: 16, near_duplicate_pixels: Some(10), sample_tolerance: 2 };
+	let mut deduplicator = Deduplicator::new(options);
+	let mut rgb = vec![0x40; (WIDTH * HEIGHT * 3) as usize];
+	deduplicator.check_buffer(&rgb, WIDTH, HEIGHT, BufferFormat::Rgb).unwrap();
+
+	// Differences within the tolerance change the hash, but no pixels.
+	rgb[0] = 0x42;
+	let noise = deduplicator.check_buffer(&rgb, WIDTH, HEIGHT, BufferFormat::Rgb).unwrap();
+	assert_eq!((noise.similarity, noise.changed_tiles, noise.changed_pixels), (Similarity::NearDuplicate, 1, Some(0)));
+
+	// Pixels changing in several tiles, compared with the last distinct frame.
+	for (pixel, expected) in [(1, 1), (40, 2), (99 + 59 * 100, 3)] {
+		rgb[pixel * 3 + 2] = 0xff;
+		let comparison = deduplicator.check_buffer(&rgb, WIDTH, HEIGHT, BufferFormat::Rgb).unwrap();
+		assert_eq!((comparison.similarity, comparison.changed_pixels), (Similarity::NearDuplicate, Some(expected)));
+	}
+	for pixel in 200..208 {
+		rgb[pixel * 3] = 0;
+	}
+	let distinct = deduplicator.check_buffer(&rgb, WIDTH, HEIGHT, BufferFormat::Rgb).unwrap();
+	assert_eq!((distinct.similarity, distinct.changed_pixels), (Similarity::Distinct, Some(11)));
+
+	// The distinct frame became the reference.
+	let duplicate = deduplicator.check_buffer(&rgb, WIDTH, HEIGHT, BufferFormat::Rgb).unwrap();
+	assert_eq!((duplicate.similarity, duplicate.changed_pixels), (Similarity::Duplicate, Some(0)));
+}
+
+#[test]
+fn chroma_changes_count_for_every_pixel_of_the_sample() {
+	let options = DedupOptions { near_duplicate_pixels: Some(100), ..DedupOptions::default() };
+	let mut deduplicator = Deduplicator::new(options);
+	let mut nv12 = frame(0, BufferFormat::Nv12);
+	deduplicator.check_buffer(&nv12, WIDTH, HEIGHT, BufferFormat::Nv12).unwrap();
+
+	// The V sample of the chroma in the second row and column covers 2x2 pixels.
+	let chroma = BufferFormat::Nv12.planes(WIDTH, HEIGHT)[1];
+	nv12[chroma.offset + chroma.stride() + 3] ^= 0x80;
+	let comparison = deduplicator.check_buffer(&nv12, WIDTH, HEIGHT, BufferFormat::Nv12).unwrap();
+	assert_eq!((comparison.similarity, comparison.changed_pixels), (Similarity::NearDuplicate, Some(4)));
+}
+
+#[test]
+fn repeated_grabs_are_duplicates() {
+	let mut capturer = SyntheticCapturer::new(WIDTH, HEIGHT);
+	capturer.start_with_options(&SessionOptions::new(BufferFormat::Bgra, 1)).unwrap();
+	let mut deduplicator = Deduplicator::new(DedupOptions::default());
+
+	let frame = capturer.next_frame(CaptureMethod::NoWait, None).unwrap();
+	assert_eq!(deduplicator.check(&frame).unwrap().similarity, Similarity::Distinct);
+	let frame = capturer.next_frame(CaptureMethod::NoWait, None).unwrap();
+	assert_eq!(deduplicator.check(&frame).unwrap().similarity, Similarity::Duplicate);
+}
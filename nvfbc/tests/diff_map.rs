@@ -0,0 +1,177 @@
+use std::time::Duration;
+
+use nvfbc::convert::from_rgb;
+use nvfbc::diff_map::{DiffMap, DiffMapCapturer, TileDiffer};
+use nvfbc::synthetic::SyntheticCapturer;
+use nvfbc::system::CaptureMethod;
+use nvfbc::{Box, BufferFormat, Capture, SessionOptions, Size};
+
+#[test]
+fn changed_boxes_merge_adjacent_blocks() {
+	let data = [
+		1, 1, 0, 1,
+		1, 1, 0, 0,
+		0, 0, 0, 1,
+	];
+	let diff_map = DiffMap { data: &data, width: 4, height: 3, scaling_factor: 16 };
+	assert_eq!(DiffMap::size_for(60, 40, 16), Size { w: 4, h: 3 });
+	assert!(diff_map.any_changed());
+	assert_eq!(diff_map.changed_blocks(), 6);
+	assert!(diff_map.is_changed(3, 0));
+	assert!(!diff_map.is_changed(2, 0));
+	assert!(!diff_map.is_changed(4, 0));
+
+	// Blocks at the right and bottom edges are clipped to the frame.
+	assert_eq!(diff_map.changed_boxes(60, 40), vec![
+		Box { x: 0, y: 0, w: 32, h: 32 },
+		Box { x: 48, y: 0, w: 12, h: 16 },
+		Box { x: 48, y: 32, w: 12, h: 8 },
+	]);
+
+	let unchanged = DiffMap { data: &[0; 12], ..diff_map };
+	assert!(!unchanged.any_changed());
+	assert!(unchanged.changed_boxes(60, 40).is_empty());
+}
+
+#[test]
+fn regions_are_marked_as_changed() {
+	let data = [
+		1, 0, 0, 0,
+		0, 0, 0, 0,
+		0, 0, 0, 1,
+	];
+	let diff_map = DiffMap { data: &data, width: 4, height: 3, scaling_factor: 16 };
+	assert!(diff_map.any_changed_in(Box { x: 10, y: 10, w: 10, h: 10 }));
+	assert!(!diff_map.any_changed_in(Box { x: 16, y: 0, w: 32, h: 32 }));
+	assert!(diff_map.any_changed_in(Box { x: 50, y: 35, w: 100, h: 100 }));
+	assert!(!diff_map.any_changed_in(Box { x: 0, y: 0, w: 0, h: 40 }));
+
+	// Partially covered blocks are marked, regions outside of the map are ignored.
+	let mut copy = Vec::new();
+	let marked = diff_map.with_changed(&[Box { x: 20, y: 15, w: 20, h: 2 }, Box { x: 100, y: 100, w: 10, h: 10 }], &mut copy);
+	assert_eq!((marked.width, marked.height, marked.scaling_factor), (4, 3, 16));
+	assert_eq!(marked.data, [
+		1, 1, 1, 0,
+		0, 1, 1, 0,
+		0, 0, 0, 1,
+	]);
+	assert_eq!(diff_map.with_changed(&[], &mut copy), diff_map);
+}
+
+#[test]
+fn synthetic_frames_have_diff_maps() {
+	let mut capturer = SyntheticCapturer::new(64, 48);
+	let options = SessionOptions { diff_map_scaling_factor: Some(16), ..SessionOptions::new(BufferFormat::Bgra, 100) };
+	capturer.start_with_options(&options).unwrap();
+
+	// Everything changed in the first frame.
+	let frame = capturer.next_frame(CaptureMethod::NoWait, None).unwrap();
+	let diff_map = frame.diff_map.unwrap();
+	assert_eq!((diff_map.width, diff_map.height, diff_map.scaling_factor), (4, 3, 16));
+	assert_eq!(diff_map.changed_blocks(), 12);
+
+	// Only the moving square changes in later frames.
+	let frame = capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+	let boxes = frame.diff_map.unwrap().changed_boxes(frame.width, frame.height);
+	assert!(!boxes.is_empty());
+	assert!(boxes.iter().all(|b| b.y == 16 && b.h == 16));
+
+	// A repeated frame did not change.
+	let frame = capturer.next_frame(CaptureMethod::Blocking, Some(Duration::from_millis(1))).unwrap();
+	assert!(!frame.is_new_frame);
+	assert!(!frame.diff_map.unwrap().any_changed());
+
+	capturer.stop().unwrap();
+	let options = SessionOptions { diff_map_scaling_factor: Some(16), ..SessionOptions::new(BufferFormat::Yuv444p, 100) };
+	assert!(capturer.start_with_options(&options).is_err());
+	capturer.start_with_options(&SessionOptions::new(BufferFormat::Bgra, 100)).unwrap();
+	assert!(capturer.next_frame(CaptureMethod::NoWait, None).unwrap().diff_map.is_none());
+}
+
+#[test]
+fn tile_differ_matches_synthetic_diff_maps() {
+	let mut capturer = SyntheticCapturer::new(100, 60);
+	let options = SessionOptions { diff_map_scaling_factor: Some(16), ..SessionOptions::new(BufferFormat::Bgra, 100) };
+	capturer.start_with_options(&options).unwrap();
+	let mut differ = TileDiffer::new(16);
+
+	for _ in 0..4 {
+		let frame = capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+		let diff_map = differ.diff_frame(&frame).unwrap();
+		assert_eq!(Some(diff_map), frame.diff_map);
+	}
+}
+
+#[test]
+fn tile_differ_in_every_format() {
+	let (width, height) = (50, 30);
+	for format in BufferFormat::ALL {
+		let mut differ = TileDiffer::new(16);
+		let mut rgb = SyntheticCapturer::pattern(width, height, 0);
+		let buffer = from_rgb(&rgb, width, height, format).unwrap();
+		let diff_map = differ.diff(&buffer, width, height, format).unwrap();
+		assert_eq!((diff_map.width, diff_map.height, diff_map.scaling_factor), (4, 2, 16));
+		assert_eq!(diff_map.changed_blocks(), 8, "{:?}", format);
+		assert!(!differ.diff(&buffer, width, height, format).unwrap().any_changed(), "{:?}", format);
+
+		// A single changed pixel in the last, partial, column of blocks.
+		rgb[((20 * width + 49) * 3) as usize] ^= 0xff;
+		let buffer = from_rgb(&rgb, width, height, format).unwrap();
+		let diff_map = differ.diff(&buffer, width, height, format).unwrap();
+		assert_eq!(diff_map.changed_blocks(), 1, "{:?}", format);
+		assert!(diff_map.is_changed(3, 1), "{:?}", format);
+		assert_eq!(diff_map.changed_boxes(width, height), vec![Box { x: 48, y: 16, w: 2, h: 14 }]);
+
+		// Every block changed when the layout changes.
+		let buffer = from_rgb(&rgb, height, width, format).unwrap();
+		assert_eq!(differ.diff(&buffer, height, width, format).unwrap().changed_blocks(), 2 * 4);
+		differ.reset();
+		assert_eq!(differ.diff(&buffer, height, width, format).unwrap().changed_blocks(), 2 * 4);
+		assert!(differ.diff(&buffer[1..], height, width, format).is_err());
+	}
+
+	// Chroma samples are compared too.
+	let mut differ = TileDiffer::new(8);
+	let mut nv12 = from_rgb(&SyntheticCapturer::pattern(width, height, 0), width, height, BufferFormat::Nv12).unwrap();
+	differ.diff(&nv12, width, height, BufferFormat::Nv12).unwrap();
+	let chroma = BufferFormat::Nv12.planes(width, height)[1];
+	// The sample in chroma row 5 and column 9 covers pixels 18-19 in rows 10-11.
+	nv12[chroma.offset + 5 * chroma.stride() + 9 * 2] ^= 0x40;
+	let diff_map = differ.diff(&nv12, width, height, BufferFormat::Nv12).unwrap();
+	assert_eq!(diff_map.changed_boxes(width, height), vec![Box { x: 16, y: 8, w: 8, h: 8 }]);
+
+	// With an odd width, the chroma sample in column 8 still covers pixels 16-17 only.
+	let (width, height) = (65, 16);
+	let mut differ = TileDiffer::new(16);
+	let mut nv12 = from_rgb(&SyntheticCapturer::pattern(width, height, 0), width, height, BufferFormat::Nv12).unwrap();
+	differ.diff(&nv12, width, height, BufferFormat::Nv12).unwrap();
+	let chroma = BufferFormat::Nv12.planes(width, height)[1];
+	nv12[chroma.offset + 8 * 2] ^= 0x40;
+	let diff_map = differ.diff(&nv12, width, height, BufferFormat::Nv12).unwrap();
+	assert_eq!(diff_map.changed_boxes(width, height), vec![Box { x: 16, y: 0, w: 16, h: 16 }]);
+}
+
+#[test]
+fn diff_map_capturer_adds_diff_maps() {
+	let mut capturer = DiffMapCapturer::new(SyntheticCapturer::new(64, 48), 16);
+	capturer.start_with_options(&SessionOptions::new(BufferFormat::Nv12, 100)).unwrap();
+
+	let frame = capturer.next_frame(CaptureMethod::NoWait, None).unwrap();
+	let diff_map = frame.diff_map.unwrap();
+	assert_eq!((diff_map.width, diff_map.height, diff_map.changed_blocks()), (4, 3, 12));
+
+	// Only the moving square changes in later frames.
+	let frame = capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+	let boxes = frame.diff_map.unwrap().changed_boxes(frame.width, frame.height);
+	assert!(!boxes.is_empty());
+	assert!(boxes.iter().all(|b| b.y == 16 && b.h == 16));
+	let frame = capturer.next_frame(CaptureMethod::Blocking, Some(Duration::from_millis(1))).unwrap();
+	assert!(!frame.diff_map.unwrap().any_changed());
+
+	// Diff maps from the capturer are kept.
+	capturer.stop().unwrap();
+	let options = SessionOptions { diff_map_scaling_factor: Some(32), ..SessionOptions::new(BufferFormat::Bgra, 100) };
+	capturer.start_with_options(&options).unwrap();
+	let frame = capturer.next_frame(CaptureMethod::NoWait, None).unwrap();
+	assert_eq!(frame.diff_map.unwrap().scaling_factor, 32);
+}
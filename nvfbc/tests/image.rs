@@ -0,0 +1,89 @@
+#![cfg(feature = "image")]
+
+use image::DynamicImage;
+use nvfbc::convert::{from_rgb, to_rgb};
+use nvfbc::recording::RecordedFrame;
+use nvfbc::synthetic::SyntheticCapturer;
+use nvfbc::system::SystemFrameInfo;
+use nvfbc::{BufferFormat, FrameGrabInfo};
+
+const WIDTH: u32 = 33;
+const HEIGHT: u32 = 17;
+
+fn frame_info(buffer: &[u8], format: BufferFormat) -> SystemFrameInfo<'_> {
+	SystemFrameInfo {
+		buffer,
+		width: WIDTH,
+		height: HEIGHT,
+		buffer_format: format,
+		current_frame: 1,
+		is_new_frame: true,
+		timestamp_us: 0,
+		missed_frames: 0,
+		diff_map: None,
+	}
+}
+
+fn recorded(data: Vec<u8>, format: BufferFormat) -> RecordedFrame {
+	let info = FrameGrabInfo {
+		width: WIDTH,
+		height: HEIGHT,
+		byte_size: data.len() as u32,
+		current_frame: 1,
+		is_new_frame: true,
+		timestamp_us: 0,
+		missed_frames: 0,
+	};
+	RecordedFrame { buffer_format: format, info, data }
+}
+
+#[test]
+fn every_format_converts_to_an_image() {
+	let rgb = SyntheticCapturer::pattern(WIDTH, HEIGHT, 3);
+	for format in BufferFormat::ALL {
+		let buffer = from_rgb(&rgb, WIDTH, HEIGHT, format).unwrap();
+		let expected = to_rgb(&buffer, WIDTH, HEIGHT, format).unwrap();
+		let image = frame_info(&buffer, format).to_image().unwrap();
+		assert_eq!((image.width(), image.height()), (WIDTH, HEIGHT));
+		match (&image, format.alpha_offset()) {
+			(DynamicImage::ImageRgba8(rgba), Some(_)) => assert!(rgba.pixels().all(|pixel| pixel[3] == 0xff)),
+			(DynamicImage::ImageRgb8(_), None) => {},
+			_ => panic!("unexpected image type for {:?}", format),
+		}
+		assert_eq!(image.to_rgb8().into_raw(), expected, "{:?}", format);
+
+		let image = recorded(buffer.clone(), format).into_image().unwrap();
+		assert_eq!(image.to_rgb8().into_raw(), expected, "{:?}", format);
+	}
+}
+
+#[test]
+fn rgb_frames_are_borrowed() {
+	let rgb = SyntheticCapturer::pattern(WIDTH, HEIGHT, 0);
+	let frame = frame_info(&rgb, BufferFormat::Rgb);
+	let image = frame.as_rgb_image().unwrap().unwrap();
+	assert_eq!(image.as_raw().as_ptr(), rgb.as_ptr());
+	assert_eq!(image.get_pixel(WIDTH - 1, 0).0, rgb[((WIDTH - 1) * 3) as usize..][..3]);
+	assert!(frame.as_rgba_image().unwrap().is_none());
+
+	let rgba = from_rgb(&rgb, WIDTH, HEIGHT, BufferFormat::Rgba).unwrap();
+	let frame = frame_info(&rgba, BufferFormat::Rgba);
+	assert_eq!(frame.as_rgba_image().unwrap().unwrap().as_raw().as_ptr(), rgba.as_ptr());
+	assert!(frame.as_rgb_image().unwrap().is_none());
+
+	let nv12 = from_rgb(&rgb, WIDTH, HEIGHT, BufferFormat::Nv12).unwrap();
+	assert!(frame_info(&nv12, BufferFormat::Nv12).as_rgb_image().unwrap().is_none());
+}
+
+#[test]
+fn size_mismatches_are_errors() {
+	let rgb = SyntheticCapturer::pattern(WIDTH, HEIGHT, 0);
+	for format in BufferFormat::ALL {
+		let buffer = from_rgb(&rgb, WIDTH, HEIGHT, format).unwrap();
+		let short = &buffer[..buffer.len() - 1];
+		let error = frame_info(short, format).to_image().unwrap_err();
+		assert_eq!((error.expected, error.actual), (buffer.len(), buffer.len() - 1));
+		assert!(recorded(short.to_vec(), format).into_image().is_err());
+	}
+	assert!(frame_info(&rgb[1..], BufferFormat::Rgb).as_rgb_image().is_err());
+}
@@ -0,0 +1,280 @@
+#![cfg(feature = "ipc")]
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use nvfbc::convert::from_rgb;
+use nvfbc::ipc::{self, IpcServer};
+use nvfbc::synthetic::SyntheticCapturer;
+use nvfbc::BufferFormat;
+
+/// A frame received from the server.
+struct Frame {
+	buffer_format: u32,
+	width: u32,
+	height: u32,
+	current_frame: u32,
+	is_new_frame: bool,
+	timestamp_us: u64,
+	data: Vec<u8>,
+}
+
+/// A message received from the server.
+enum Message {
+	Status { screen: (u32, u32), outputs: Vec<(u32, String, [u32; 4])> },
+	Subscribed,
+	Unsubscribed,
+	Frame(Frame),
+	Error(u32, String),
+}
+
+/// A reference client of the protocol, implemented from the documentation of `nvfbc::ipc`.
+struct Client {
+	stream: UnixStream,
+}
+
+impl Client {
+	fn connect(server: &IpcServer) -> Self {
+		let stream = UnixStream::connect(server.path()).unwrap();
+		stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+		Self { stream }
+	}
+
+	fn send(&mut self, kind: u8, body: &[u8]) {
+		let mut message = vec![kind];
+		message.extend_from_slice(&(body.len() as u32).to_le_bytes());
+		message.extend_from_slice(body);
+		self.stream.write_all(&message).unwrap();
+	}
+
+	/// Subscribe to a session tracking `output` (or the default region) cropped to `capture_box`.
+	fn subscribe(&mut self, format: BufferFormat, fps: u32, output: Option<u32>, capture_box: Option<[u32; 4]>, transfer: u8) {
+		let mut body = Vec::new();
+		body.extend_from_slice(&(format as u32).to_le_bytes());
+		body.extend_from_slice(&fps.to_le_bytes());
+		body.push(output.map_or(0, |_| 1));
+		body.extend_from_slice(&output.unwrap_or(0).to_le_bytes());
+		body.push(capture_box.is_some() as u8);
+		for value in capture_box.unwrap_or_default() {
+			body.extend_from_slice(&value.to_le_bytes());
+		}
+		// No frame size, with the cursor.
+		body.extend_from_slice(&[0; 9]);
+		body.push(1);
+		body.push(transfer);
+		self.send(ipc::SUBSCRIBE, &body);
+	}
+
+	/// Receive the next message, with the file descriptor attached to it if any.
+	fn receive(&mut self) -> Message {
+		let mut header = [0; ipc::MESSAGE_HEADER_SIZE];
+		let mut control = [0u64; 8];
+		let mut iov = libc::iovec { iov_base: header.as_mut_ptr() as *mut libc::c_void, iov_len: header.len() };
+		let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+		msg.msg_iov = &mut iov;
+		msg.msg_iovlen = 1;
+		msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+		msg.msg_controllen = std::mem::size_of_val(&control) as _;
+		let received = unsafe { libc::recvmsg(self.stream.as_raw_fd(), &mut msg, libc::MSG_CMSG_CLOEXEC) };
+		assert!(received > 0, "connection closed");
+		self.stream.read_exact(&mut header[received as usize..]).unwrap();
+
+		let mut fd = None;
+		unsafe {
+			let cmsg = libc::CMSG_FIRSTHDR(&msg);
+			if !cmsg.is_null() && (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+				let raw = std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::c_int);
+				fd = Some(OwnedFd::from_raw_fd(raw));
+			}
+		}
+
+		let len = u32::from_le_bytes(header[1..5].try_into().unwrap()) as usize;
+		let mut body = vec![0; len];
+		self.stream.read_exact(&mut body).unwrap();
+		let mut reader = &body[..];
+		let message = match header[0] {
+			ipc::STATUS => {
+				let flags = read_u8(&mut reader);
+				assert_eq!(flags & 1, 1, "capture is not possible");
+				let screen = (read_u32(&mut reader), read_u32(&mut reader));
+				let _version = (read_u32(&mut reader), read_u32(&mut reader));
+				let outputs = (0..read_u32(&mut reader)).map(|_| {
+					let id = read_u32(&mut reader);
+					let mut name = vec![0; read_u32(&mut reader) as usize];
+					reader.read_exact(&mut name).unwrap();
+					let tracked_box = [(); 4].map(|_| read_u32(&mut reader));
+					(id, String::from_utf8(name).unwrap(), tracked_box)
+				}).collect();
+				Message::Status { screen, outputs }
+			},
+			ipc::SUBSCRIBED => Message::Subscribed,
+			ipc::UNSUBSCRIBED => Message::Unsubscribed,
+			ipc::FRAME => {
+				let buffer_format = read_u32(&mut reader);
+				let width = read_u32(&mut reader);
+				let height = read_u32(&mut reader);
+				let current_frame = read_u32(&mut reader);
+				let is_new_frame = read_u8(&mut reader) != 0;
+				let timestamp_us = u64::from_le_bytes(read_array(&mut reader));
+				let _missed_frames = read_u32(&mut reader);
+				let byte_size = read_u32(&mut reader) as usize;
+				let data = match read_u8(&mut reader) {
+					0 => {
+						assert!(fd.is_none());
+						reader.to_vec()
+					},
+					1 => {
+						assert!(reader.is_empty());
+						let mut file = File::from(fd.take().expect("no memfd was passed"));
+						let seals = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GET_SEALS) };
+						assert_eq!(seals & libc::F_SEAL_WRITE, libc::F_SEAL_WRITE);
+						let mut data = Vec::new();
+						file.read_to_end(&mut data).unwrap();
+						data
+					},
+					transfer => panic!("unknown transfer {}", transfer),
+				};
+				assert_eq!(data.len(), byte_size);
+				Message::Frame(Frame { buffer_format, width, height, current_frame, is_new_frame, timestamp_us, data })
+			},
+			ipc::ERROR => Message::Error(read_u32(&mut reader), String::from_utf8(reader.to_vec()).unwrap()),
+			kind => panic!("unknown message {}", kind),
+		};
+		assert!(fd.is_none(), "unexpected file descriptor");
+		message
+	}
+
+	fn frame(&mut self) -> Frame {
+		match self.receive() {
+			Message::Frame(frame) => frame,
+			_ => panic!("expected a frame"),
+		}
+	}
+}
+
+fn read_array<const N: usize>(reader: &mut &[u8]) -> [u8; N] {
+	let mut bytes = [0; N];
+	reader.read_exact(&mut bytes).unwrap();
+	bytes
+}
+
+fn read_u8(reader: &mut &[u8]) -> u8 {
+	read_array::<1>(reader)[0]
+}
+
+fn read_u32(reader: &mut &[u8]) -> u32 {
+	u32::from_le_bytes(read_array(reader))
+}
+
+fn socket_path(name: &str) -> PathBuf {
+	let path = std::env::temp_dir().join(format!("nvfbc-ipc-{}-{}.sock", name, std::process::id()));
+	let _ = std::fs::remove_file(&path);
+	path
+}
+
+fn server(name: &str) -> IpcServer {
+	IpcServer::bind(socket_path(name), || Ok(SyntheticCapturer::new(64, 48))).unwrap()
+}
+
+/// Whether `data` is a frame of `width` x `height` pixels generated by the synthetic capturer, in `format`.
+fn is_pattern(data: &[u8], width: u32, height: u32, format: BufferFormat) -> bool {
+	(0..64).any(|index| from_rgb(&SyntheticCapturer::pattern(width, height, index), width, height, format).unwrap() == data)
+}
+
+#[test]
+fn reports_status() {
+	let server = server("status");
+	let mut client = Client::connect(&server);
+	client.send(ipc::GET_STATUS, &[]);
+	let Message::Status { screen, outputs } = client.receive() else {
+		panic!("expected the status");
+	};
+	assert_eq!(screen, (64, 48));
+	assert_eq!(outputs, vec![(1, "SYNTHETIC-0".to_string(), [0, 0, 64, 48])]);
+}
+
+#[test]
+fn sends_frames_inline() {
+	let server = server("inline");
+	let mut client = Client::connect(&server);
+	client.subscribe(BufferFormat::Rgb, 50, Some(1), None, 0);
+	assert!(matches!(client.receive(), Message::Subscribed));
+
+	let mut previous: Option<Frame> = None;
+	for _ in 0..3 {
+		let frame = client.frame();
+		assert_eq!((frame.buffer_format, frame.width, frame.height), (BufferFormat::Rgb as u32, 64, 48));
+		assert!(frame.is_new_frame);
+		assert!(is_pattern(&frame.data, 64, 48, BufferFormat::Rgb));
+		if let Some(previous) = previous {
+			assert!(frame.current_frame > previous.current_frame);
+			assert!(frame.timestamp_us > previous.timestamp_us);
+		}
+		previous = Some(frame);
+	}
+
+	// Frames that were already sent arrive before the confirmation.
+	client.send(ipc::UNSUBSCRIBE, &[]);
+	while !matches!(client.receive(), Message::Unsubscribed) {}
+	client.send(ipc::GET_STATUS, &[]);
+	assert!(matches!(client.receive(), Message::Status { .. }));
+}
+
+#[test]
+fn passes_frames_in_memfds() {
+	let server = server("memfd");
+	let mut client = Client::connect(&server);
+	client.subscribe(BufferFormat::Bgra, 50, None, Some([16, 8, 32, 24]), 1);
+	assert!(matches!(client.receive(), Message::Subscribed));
+
+	for _ in 0..3 {
+		let frame = client.frame();
+		assert_eq!((frame.buffer_format, frame.width, frame.height), (BufferFormat::Bgra as u32, 32, 24));
+		assert!(is_pattern(&frame.data, 32, 24, BufferFormat::Bgra));
+	}
+
+	// Subscribing again replaces the session.
+	client.subscribe(BufferFormat::Yuv444p, 50, None, None, 1);
+	let frame = loop {
+		match client.receive() {
+			Message::Frame(frame) if frame.buffer_format == BufferFormat::Yuv444p as u32 => break frame,
+			Message::Frame(_) | Message::Subscribed => continue,
+			_ => panic!("expected a frame"),
+		}
+	};
+	assert!(is_pattern(&frame.data, 64, 48, BufferFormat::Yuv444p));
+}
+
+#[test]
+fn reports_errors() {
+	let server = server("errors");
+	let mut client = Client::connect(&server);
+
+	client.subscribe(BufferFormat::Rgb, 0, None, None, 0);
+	let Message::Error(code, message) = client.receive() else {
+		panic!("expected an error");
+	};
+	assert_eq!(code, nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_INVALID_PARAM);
+	assert!(message.contains("fps"), "{}", message);
+
+	client.send(ipc::SUBSCRIBE, &[1, 2, 3]);
+	assert!(matches!(client.receive(), Message::Error(..)));
+	client.send(42, &[]);
+	let Message::Error(_, message) = client.receive() else {
+		panic!("expected an error");
+	};
+	assert!(message.contains("unknown request"), "{}", message);
+
+	// The connection is still usable.
+	client.send(ipc::GET_STATUS, &[]);
+	assert!(matches!(client.receive(), Message::Status { .. }));
+	assert_eq!(server.clients(), 1);
+
+	let path = server.path().to_path_buf();
+	drop(server);
+	assert!(!path.exists());
+}
@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use nvfbc::manager::{group_by_timestamp, FrameGroup, SessionId};
+use nvfbc::system::SystemFrameInfo;
+use nvfbc::BufferFormat;
+
+const TOLERANCE: Duration = Duration::from_micros(8_000);
+
+fn frame(session: u32, timestamp_us: u64) -> (SessionId, SystemFrameInfo<'static>) {
+	(SessionId::from_raw(session), SystemFrameInfo {
+		buffer: &[],
+		width: 0,
+		height: 0,
+		buffer_format: BufferFormat::Rgb,
+		current_frame: 1,
+		is_new_frame: true,
+		timestamp_us,
+		missed_frames: 0,
+		diff_map: None,
+	})
+}
+
+/// The timestamp of every group, with the sessions and timestamps of its frames.
+fn summary(groups: &[FrameGroup]) -> Vec<(u64, Vec<(u32, u64)>)> {
+	groups.iter()
+		.map(|group| (group.timestamp_us, group.frames.iter().map(|(id, frame)| (id.to_raw(), frame.timestamp_us)).collect()))
+		.collect()
+}
+
+#[test]
+fn tolerance_is_inclusive() {
+	let groups = group_by_timestamp(vec![frame(0, 1_000), frame(1, 9_000), frame(2, 9_001)], TOLERANCE);
+	assert_eq!(summary(&groups), [
+		(1_000, vec![(0, 1_000), (1, 9_000)]),
+		(9_001, vec![(2, 9_001)]),
+	]);
+
+	// Without tolerance only identical timestamps are grouped.
+	let groups = group_by_timestamp(vec![frame(0, 1_000), frame(1, 1_000), frame(2, 1_001)], Duration::ZERO);
+	assert_eq!(summary(&groups), [
+		(1_000, vec![(0, 1_000), (1, 1_000)]),
+		(1_001, vec![(2, 1_001)]),
+	]);
+}
+
+#[test]
+fn groups_do_not_chain() {
+	// Every frame is within the tolerance of the previous one, but not of the first frame of the group.
+	let groups = group_by_timestamp(vec![frame(0, 0), frame(1, 6_000), frame(2, 12_000), frame(3, 18_000)], TOLERANCE);
+	assert_eq!(summary(&groups), [
+		(0, vec![(0, 0), (1, 6_000)]),
+		(12_000, vec![(2, 12_000), (3, 18_000)]),
+	]);
+}
+
+#[test]
+fn unsorted_input_is_sorted() {
+	let groups = group_by_timestamp(vec![frame(3, 30_000), frame(0, 2_000), frame(2, 30_000), frame(1, 0)], TOLERANCE);
+	assert_eq!(summary(&groups), [
+		(0, vec![(1, 0), (0, 2_000)]),
+		// Frames with the same timestamp are ordered by session.
+		(30_000, vec![(2, 30_000), (3, 30_000)]),
+	]);
+}
+
+#[test]
+fn single_session_groups() {
+	let groups = group_by_timestamp(vec![frame(0, 0), frame(1, 20_000), frame(2, 40_000)], TOLERANCE);
+	assert_eq!(summary(&groups), [
+		(0, vec![(0, 0)]),
+		(20_000, vec![(1, 20_000)]),
+		(40_000, vec![(2, 40_000)]),
+	]);
+
+	assert_eq!(summary(&group_by_timestamp(vec![frame(5, 123)], TOLERANCE)), [(123, vec![(5, 123)])]);
+	assert!(group_by_timestamp(Vec::new(), TOLERANCE).is_empty());
+}
@@ -0,0 +1,271 @@
+use std::time::Duration;
+
+use nvfbc::convert::{from_rgb, to_rgb};
+use nvfbc::mask::{apply_mask, captured_region, frame_region, MaskFill, MaskRegion, MaskedCapturer};
+use nvfbc::synthetic::SyntheticCapturer;
+use nvfbc::system::{CaptureMethod, SystemFrameInfo};
+use nvfbc::{Box, BufferFormat, Capture, Error, Output, SessionOptions, Size, Status, Tracking, Version};
+
+const WIDTH: u32 = 32;
+const HEIGHT: u32 = 24;
+
+/// Status of a screen with two outputs next to each other.
+fn status(outputs: [Box; 2]) -> Status {
+	Status {
+		is_capture_possible: true,
+		currently_capturing: false,
+		can_create_now: true,
+		screen_size: Size { w: outputs[1].x + outputs[1].w, h: outputs[0].h.max(outputs[1].h) },
+		xrandr_available: true,
+		outputs: outputs.iter().enumerate().map(|(index, &tracked_box)| Output {
+			id: index as u32 + 1,
+			name: format!("SYNTHETIC-{}", index),
+			tracked_box,
+		}).collect(),
+		nvfbc_version: Version::COMPILED,
+		in_modeset: false,
+	}
+}
+
+fn inside(region: Box, x: u32, y: u32, margin: u32) -> bool {
+	x + margin >= region.x && x < region.x + region.w + margin && y + margin >= region.y && y < region.y + region.h + margin
+}
+
+/// Mask `region` of the synthetic pattern in `format`, returning the pattern and the masked frame as RGB.
+fn mask_pattern(format: BufferFormat, region: Box, fill: MaskFill) -> (Vec<u8>, Vec<u8>) {
+	let mut buffer = from_rgb(&SyntheticCapturer::pattern(WIDTH, HEIGHT, 0), WIDTH, HEIGHT, format).unwrap();
+	let original = to_rgb(&buffer, WIDTH, HEIGHT, format).unwrap();
+	apply_mask(&mut buffer, WIDTH, HEIGHT, format, region, fill).unwrap();
+	(original, to_rgb(&buffer, WIDTH, HEIGHT, format).unwrap())
+}
+
+/// Assert that pixels more than a chroma sample away from `region` are unchanged.
+fn assert_outside_unchanged(original: &[u8], masked: &[u8], region: Box, format: BufferFormat) {
+	for y in 0..HEIGHT {
+		for x in 0..WIDTH {
+			let index = ((y * WIDTH + x) * 3) as usize;
+			if !inside(region, x, y, 2) {
+				assert_eq!(original[index..index + 3], masked[index..index + 3], "{:?} pixel {},{}", format, x, y);
+			}
+		}
+	}
+}
+
+#[test]
+fn black_fill_in_every_format() {
+	let region = Box { x: 5, y: 3, w: 10, h: 7 };
+	for format in BufferFormat::ALL {
+		let (original, masked) = mask_pattern(format, region, MaskFill::Black);
+		for y in region.y..region.y + region.h {
+			for x in region.x..region.x + region.w {
+				let index = ((y * WIDTH + x) * 3) as usize;
+				assert!(masked[index..index + 3].iter().all(|&value| value <= 4), "{:?} pixel {},{} is {:?}", format, x, y, &masked[index..index + 3]);
+			}
+		}
+		assert_outside_unchanged(&original, &masked, region, format);
+	}
+
+	// Alpha is kept.
+	let mut buffer = vec![0xff; (WIDTH * HEIGHT * 4) as usize];
+	apply_mask(&mut buffer, WIDTH, HEIGHT, BufferFormat::Bgra, region, MaskFill::Black).unwrap();
+	assert_eq!(buffer[((3 * WIDTH + 5) * 4) as usize..][..4], [0, 0, 0, 0xff]);
+
+	// Regions outside the frame are ignored, wrong buffer sizes are rejected.
+	apply_mask(&mut buffer, WIDTH, HEIGHT, BufferFormat::Bgra, Box { x: 100, y: 0, w: 10, h: 10 }, MaskFill::Black).unwrap();
+	assert!(apply_mask(&mut buffer[1..], WIDTH, HEIGHT, BufferFormat::Bgra, region, MaskFill::Black).is_err());
+}
+
+#[test]
+fn pixelate_averages_blocks() {
+	let region = Box { x: 2, y: 4, w: 16, h: 11 };
+	let (original, masked) = mask_pattern(BufferFormat::Rgb, region, MaskFill::Pixelate { block_size: 4 });
+	assert_ne!(original, masked);
+	for y in region.y..region.y + region.h {
+		for x in region.x..region.x + region.w {
+			let block = (region.x + (x - region.x) / 4 * 4, region.y + (y - region.y) / 4 * 4);
+			let index = ((y * WIDTH + x) * 3) as usize;
+			let first = ((block.1 * WIDTH + block.0) * 3) as usize;
+			assert_eq!(masked[index..index + 3], masked[first..first + 3], "pixel {},{}", x, y);
+		}
+	}
+	assert_outside_unchanged(&original, &masked, region, BufferFormat::Rgb);
+
+	for format in BufferFormat::ALL {
+		let (original, masked) = mask_pattern(format, region, MaskFill::Pixelate { block_size: 4 });
+		assert_outside_unchanged(&original, &masked, region, format);
+	}
+}
+
+#[test]
+fn blur_stays_within_the_region() {
+	let region = Box { x: 0, y: 6, w: 20, h: 12 };
+	for format in BufferFormat::ALL {
+		let (original, masked) = mask_pattern(format, region, MaskFill::Blur { radius: 3 });
+		assert_ne!(original, masked, "{:?}", format);
+		assert_outside_unchanged(&original, &masked, region, format);
+	}
+
+	// Blurring only mixes values of the region.
+	let (original, masked) = mask_pattern(BufferFormat::Rgb, region, MaskFill::Blur { radius: 3 });
+	let values = |rgb: &[u8], channel: usize| -> Vec<u8> {
+		(region.y..region.y + region.h)
+			.flat_map(|y| (region.x..region.x + region.w).map(move |x| (y, x)))
+			.map(|(y, x)| rgb[((y * WIDTH + x) * 3) as usize + channel])
+			.collect()
+	};
+	for channel in 0..3 {
+		let (before, after) = (values(&original, channel), values(&masked, channel));
+		assert!(after.iter().min() >= before.iter().min() && after.iter().max() <= before.iter().max());
+	}
+}
+
+#[test]
+fn degenerate_fills_do_not_leave_the_region_readable() {
+	let region = Box { x: 5, y: 3, w: 10, h: 7 };
+	for format in BufferFormat::ALL {
+		let black = mask_pattern(format, region, MaskFill::Black).1;
+		for fill in [MaskFill::Pixelate { block_size: 0 }, MaskFill::Pixelate { block_size: 1 }, MaskFill::Blur { radius: 0 }] {
+			assert_eq!(mask_pattern(format, region, fill).1, black, "{:?} {:?}", format, fill);
+		}
+	}
+
+	// A radius that is smaller than a chroma sample still blurs the chroma plane.
+	let mut buffer = from_rgb(&SyntheticCapturer::pattern(WIDTH, HEIGHT, 0), WIDTH, HEIGHT, BufferFormat::Nv12).unwrap();
+	let original = buffer.clone();
+	apply_mask(&mut buffer, WIDTH, HEIGHT, BufferFormat::Nv12, Box { x: 0, y: 0, w: WIDTH, h: HEIGHT }, MaskFill::Blur { radius: 1 }).unwrap();
+	let chroma = (WIDTH * HEIGHT) as usize;
+	assert_ne!(buffer[chroma..], original[chroma..]);
+}
+
+#[test]
+fn regions_are_translated_to_frames() {
+	let status = status([Box { x: 0, y: 0, w: 1920, h: 1080 }, Box { x: 1920, y: 0, w: 1280, h: 1024 }]);
+
+	// The region tracked by default depends on the driver.
+	let mut options = SessionOptions::new(BufferFormat::Rgb, 30);
+	assert_eq!(captured_region(&status, &options), None);
+	options.tracking = Tracking::Screen;
+	assert_eq!(captured_region(&status, &options), Some(Box { x: 0, y: 0, w: 3200, h: 1080 }));
+	options.tracking = Tracking::Output(2);
+	assert_eq!(captured_region(&status, &options), Some(Box { x: 1920, y: 0, w: 1280, h: 1024 }));
+	options.capture_box = Some(Box { x: 100, y: 100, w: 640, h: 480 });
+	let captured = captured_region(&status, &options).unwrap();
+	assert_eq!(captured, Box { x: 2020, y: 100, w: 640, h: 480 });
+	options.tracking = Tracking::Output(3);
+	assert_eq!(captured_region(&status, &options), None);
+
+	// Scaled to half the size, partially visible pixels are included.
+	let region = Box { x: 2000, y: 90, w: 101, h: 100 };
+	assert_eq!(frame_region(region, captured, 320, 240), Some(Box { x: 0, y: 0, w: 41, h: 45 }));
+	assert_eq!(frame_region(region, captured, 640, 480), Some(Box { x: 0, y: 0, w: 81, h: 90 }));
+	assert_eq!(frame_region(Box { x: 2600, y: 500, w: 500, h: 500 }, captured, 640, 480), Some(Box { x: 580, y: 400, w: 60, h: 80 }));
+	assert_eq!(frame_region(Box { x: 0, y: 0, w: 1920, h: 1080 }, captured, 640, 480), None);
+}
+
+#[test]
+fn masked_capturer_masks_frames() {
+	let status = status([Box { x: 0, y: 0, w: 64, h: 48 }, Box { x: 64, y: 0, w: 64, h: 48 }]);
+	let regions = vec![
+		MaskRegion { region: Box { x: 72, y: 8, w: 16, h: 16 }, fill: MaskFill::Black },
+		// Not visible on the second output.
+		MaskRegion { region: Box { x: 0, y: 0, w: 64, h: 48 }, fill: MaskFill::Black },
+	];
+	let mut capturer = MaskedCapturer::new(SyntheticCapturer::with_status(status), regions);
+	assert!(capturer.next_frame(CaptureMethod::Blocking, None).is_err());
+
+	let mut options = SessionOptions::new(BufferFormat::Rgb, 100);
+	assert!(capturer.start_with_options(&options).is_err());
+	options.tracking = Tracking::Output(3);
+	assert!(capturer.start_with_options(&options).is_err());
+	options.tracking = Tracking::Output(2);
+	capturer.start_with_options(&options).unwrap();
+
+	let masked = Box { x: 8, y: 8, w: 16, h: 16 };
+	for _ in 0..3 {
+		let frame = capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+		assert_eq!((frame.width, frame.height), (64, 48));
+		let matches = (0..64).any(|index| {
+			let mut expected = SyntheticCapturer::pattern(64, 48, index);
+			apply_mask(&mut expected, 64, 48, BufferFormat::Rgb, masked, MaskFill::Black).unwrap();
+			expected == frame.buffer
+		});
+		assert!(matches, "frame {} is not masked", frame.current_frame);
+	}
+
+	capturer.set_regions(Vec::new());
+	assert!(capturer.regions().is_empty());
+	let frame = capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+	assert!((0..64).any(|index| SyntheticCapturer::pattern(64, 48, index) == frame.buffer));
+	capturer.stop().unwrap();
+}
+
+/// Starts the session again with a smaller frame size after a number of frames, like a modeset would.
+struct Resizing {
+	inner: SyntheticCapturer,
+	options: Option<SessionOptions>,
+	frames: u32,
+}
+
+impl Capture for Resizing {
+	fn status(&self) -> Result<Status, Error> {
+		self.inner.status()
+	}
+
+	fn start_with_options(&mut self, options: &SessionOptions) -> Result<(), Error> {
+		self.options = Some(options.clone());
+		self.inner.start_with_options(options)
+	}
+
+	fn stop(&mut self) -> Result<(), Error> {
+		self.inner.stop()
+	}
+
+	fn next_frame(&mut self, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<SystemFrameInfo<'_>, Error> {
+		self.frames += 1;
+		if self.frames == 3 {
+			let options = self.options.clone().unwrap();
+			self.inner.stop()?;
+			self.inner.start_with_options(&SessionOptions { frame_size: Some(Size { w: 32, h: 24 }), ..options })?;
+		}
+		self.inner.next_frame(capture_method, timeout)
+	}
+}
+
+#[test]
+fn frames_of_another_size_are_not_masked() {
+	let inner = Resizing { inner: SyntheticCapturer::new(64, 48), options: None, frames: 0 };
+	let regions = vec![MaskRegion { region: Box { x: 8, y: 8, w: 16, h: 16 }, fill: MaskFill::Black }];
+	let mut capturer = MaskedCapturer::new(inner, regions);
+	let mut options = SessionOptions::new(BufferFormat::Rgb, 100);
+	options.tracking = Tracking::Screen;
+	capturer.start_with_options(&options).unwrap();
+
+	for _ in 0..2 {
+		capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+	}
+	let error = capturer.next_frame(CaptureMethod::Blocking, None).unwrap_err();
+	assert_eq!(error.code(), nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_MUST_RECREATE);
+}
+
+#[test]
+fn pixelated_regions_are_marked_in_diff_maps() {
+	let regions = vec![MaskRegion { region: Box { x: 0, y: 0, w: 64, h: 48 }, fill: MaskFill::Pixelate { block_size: 4 } }];
+	let mut capturer = MaskedCapturer::new(SyntheticCapturer::new(64, 48), regions);
+	let mut options = SessionOptions { diff_map_scaling_factor: Some(16), ..SessionOptions::new(BufferFormat::Rgb, 100) };
+	options.tracking = Tracking::Screen;
+	capturer.start_with_options(&options).unwrap();
+
+	// Every block changed in the first frame. Afterwards the moving square changes some blocks,
+	// which changes the pixelated region around them.
+	capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+	let frame = capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+	let diff_map = frame.diff_map.unwrap();
+	assert_eq!(diff_map.changed_blocks(), 4 * 3);
+
+	// Every block changed after the regions are replaced.
+	capturer.set_regions(vec![MaskRegion { region: Box { x: 0, y: 0, w: 8, h: 8 }, fill: MaskFill::Black }]);
+	let frame = capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+	assert_eq!(frame.diff_map.unwrap().changed_blocks(), 4 * 3);
+	let frame = capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+	assert!(frame.diff_map.unwrap().changed_blocks() < 4 * 3);
+}
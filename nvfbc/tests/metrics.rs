@@ -0,0 +1,167 @@
+#![cfg(feature = "metrics")]
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+use nvfbc::metrics::{MetricsCapturer, MetricsRegistry, MetricsServer};
+use nvfbc::synthetic::SyntheticCapturer;
+use nvfbc::system::{CaptureMethod, SystemFrameInfo};
+use nvfbc::{BufferFormat, Capture, Error, SessionOptions, Status};
+
+/// Send a request to the server and return the status line and body of the response.
+fn request(server: &MetricsServer, method: &str, path: &str) -> (String, String) {
+	let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+	write!(stream, "{} {} HTTP/1.1\r\nHost: localhost\r\n\r\n", method, path).unwrap();
+	let mut response = String::new();
+	stream.read_to_string(&mut response).unwrap();
+
+	let (head, body) = response.split_once("\r\n\r\n").unwrap();
+	(head.lines().next().unwrap().to_string(), body.to_string())
+}
+
+/// Value of the sample `name` with exactly `labels` in `metrics`.
+fn sample(metrics: &str, name: &str, labels: &str) -> f64 {
+	let prefix = format!("{}{{{}}} ", name, labels);
+	let line = metrics.lines().find(|line| line.starts_with(&prefix)).unwrap_or_else(|| panic!("{} not found", prefix));
+	line[prefix.len()..].parse().unwrap()
+}
+
+#[test]
+fn serves_session_metrics() {
+	let registry = MetricsRegistry::new();
+	let server = MetricsServer::bind("127.0.0.1:0", registry.clone()).unwrap();
+
+	let mut capturer = MetricsCapturer::new(SyntheticCapturer::new(64, 48), registry.session("primary"));
+	assert!(capturer.next_frame(CaptureMethod::NoWait, None).is_err());
+
+	let options = SessionOptions::new(BufferFormat::Rgb, 100);
+	capturer.start_with_options(&options).unwrap();
+	for _ in 0..3 {
+		capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+	}
+	capturer.next_frame(CaptureMethod::Blocking, Some(Duration::from_millis(1))).unwrap();
+	capturer.stop().unwrap();
+	capturer.start_with_options(&options).unwrap();
+
+	let (status, metrics) = request(&server, "GET", "/metrics");
+	assert_eq!(status, "HTTP/1.1 200 OK");
+	let session = "session=\"primary\"";
+	assert_eq!(sample(&metrics, "nvfbc_grabs_total", session), 5.0);
+	assert_eq!(sample(&metrics, "nvfbc_frames_captured_total", session), 3.0);
+	assert_eq!(sample(&metrics, "nvfbc_session_recreations_total", session), 1.0);
+	assert_eq!(sample(&metrics, "nvfbc_frame_width", session), 64.0);
+	assert_eq!(sample(&metrics, "nvfbc_frame_height", session), 48.0);
+	assert_eq!(sample(&metrics, "nvfbc_currently_capturing", session), 1.0);
+	assert_eq!(sample(&metrics, "nvfbc_in_modeset", session), 0.0);
+	let bad_request = format!("session=\"primary\",code=\"{}\"", nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_BAD_REQUEST);
+	assert_eq!(sample(&metrics, "nvfbc_grab_errors_total", &bad_request), 1.0);
+	assert_eq!(sample(&metrics, "nvfbc_grab_latency_seconds_bucket", "session=\"primary\",le=\"+Inf\""), 5.0);
+	assert_eq!(sample(&metrics, "nvfbc_grab_latency_seconds_count", session), 5.0);
+	assert!(metrics.contains("# TYPE nvfbc_grab_latency_seconds histogram\n"));
+
+	// Buckets are cumulative.
+	let buckets: Vec<f64> = metrics.lines()
+		.filter(|line| line.starts_with("nvfbc_grab_latency_seconds_bucket"))
+		.map(|line| line.rsplit(' ').next().unwrap().parse().unwrap())
+		.collect();
+	assert!(buckets.windows(2).all(|w| w[0] <= w[1]));
+}
+
+/// Reports a modeset in its status and fails to grab while `in_modeset` is set.
+struct Modeset {
+	inner: SyntheticCapturer,
+	in_modeset: bool,
+}
+
+impl Capture for Modeset {
+	fn status(&self) -> Result<Status, Error> {
+		Ok(Status { in_modeset: self.in_modeset, currently_capturing: !self.in_modeset, ..self.inner.status()? })
+	}
+
+	fn start_with_options(&mut self, options: &SessionOptions) -> Result<(), Error> {
+		self.inner.start_with_options(options)
+	}
+
+	fn stop(&mut self) -> Result<(), Error> {
+		self.inner.stop()
+	}
+
+	fn next_frame(&mut self, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<SystemFrameInfo<'_>, Error> {
+		if self.in_modeset {
+			return Err(Error::new(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_MUST_RECREATE, None));
+		}
+		self.inner.next_frame(capture_method, timeout)
+	}
+}
+
+#[test]
+fn grabs_update_the_status_gauges() {
+	let registry = MetricsRegistry::new();
+	let server = MetricsServer::bind("127.0.0.1:0", registry.clone()).unwrap();
+	let gauges = || {
+		let (_, metrics) = request(&server, "GET", "/metrics");
+		let session = "session=\"primary\"";
+		(sample(&metrics, "nvfbc_in_modeset", session), sample(&metrics, "nvfbc_currently_capturing", session))
+	};
+
+	let modeset = Modeset { inner: SyntheticCapturer::new(64, 48), in_modeset: false };
+	let mut capturer = MetricsCapturer::new(modeset, registry.session("primary"));
+	capturer.start_with_options(&SessionOptions::new(BufferFormat::Rgb, 100)).unwrap();
+	capturer.next_frame(CaptureMethod::NoWait, None).unwrap();
+	assert_eq!(gauges(), (0.0, 1.0));
+
+	// The status is refreshed by the grab after a failed grab.
+	capturer.inner_mut().in_modeset = true;
+	assert!(capturer.next_frame(CaptureMethod::NoWait, None).is_err());
+	assert!(capturer.next_frame(CaptureMethod::NoWait, None).is_err());
+	assert_eq!(gauges(), (1.0, 0.0));
+
+	// A successful grab means the modeset is over.
+	capturer.inner_mut().in_modeset = false;
+	capturer.next_frame(CaptureMethod::NoWait, None).unwrap();
+	assert_eq!(gauges(), (0.0, 1.0));
+}
+
+#[test]
+fn serves_connections_concurrently() {
+	let server = MetricsServer::bind("127.0.0.1:0", MetricsRegistry::new()).unwrap();
+
+	// A client that does not send its request does not block other clients.
+	let _idle = TcpStream::connect(server.local_addr()).unwrap();
+	let started = Instant::now();
+	assert_eq!(request(&server, "GET", "/metrics").0, "HTTP/1.1 200 OK");
+	assert!(started.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn exports_every_session() {
+	let registry = MetricsRegistry::new();
+	let server = MetricsServer::bind("127.0.0.1:0", registry.clone()).unwrap();
+	registry.session("DP-0").record_recreation();
+	registry.session("weird \"name\"");
+
+	let (_, metrics) = request(&server, "GET", "/metrics");
+	assert_eq!(sample(&metrics, "nvfbc_session_recreations_total", "session=\"DP-0\""), 1.0);
+	assert_eq!(sample(&metrics, "nvfbc_session_recreations_total", "session=\"weird \\\"name\\\"\""), 0.0);
+
+	registry.remove("DP-0");
+	let (_, metrics) = request(&server, "GET", "/metrics");
+	assert!(!metrics.contains("DP-0"));
+}
+
+#[test]
+fn rejects_other_requests() {
+	let server = MetricsServer::bind("127.0.0.1:0", MetricsRegistry::new()).unwrap();
+	assert_eq!(request(&server, "GET", "/").0, "HTTP/1.1 404 Not Found");
+	assert_eq!(request(&server, "POST", "/metrics").0, "HTTP/1.1 405 Method Not Allowed");
+	assert_eq!(request(&server, "HEAD", "/metrics"), ("HTTP/1.1 200 OK".to_string(), String::new()));
+}
+
+#[test]
+fn stops_when_dropped() {
+	let server = MetricsServer::bind("127.0.0.1:0", MetricsRegistry::new()).unwrap();
+	let addr = server.local_addr();
+	drop(server);
+	assert!(TcpStream::connect(addr).is_err());
+}
@@ -0,0 +1,188 @@
+use nvfbc::convert::{from_rgb, to_rgb};
+use nvfbc::overlay::{glyph_pixel, Overlay, OverlayCapturer, OverlayField, OverlayPosition, GLYPH_HEIGHT, GLYPH_WIDTH};
+use nvfbc::synthetic::SyntheticCapturer;
+use nvfbc::system::CaptureMethod;
+use nvfbc::{Box, BufferFormat, Capture, FrameGrabInfo, SessionOptions};
+
+const WIDTH: u32 = 48;
+const HEIGHT: u32 = 32;
+
+fn info(current_frame: u32, timestamp_us: u64, missed_frames: u32) -> FrameGrabInfo {
+	FrameGrabInfo {
+		width: WIDTH,
+		height: HEIGHT,
+		byte_size: 0,
+		current_frame,
+		is_new_frame: true,
+		timestamp_us,
+		missed_frames,
+	}
+}
+
+fn pixel(rgb: &[u8], x: u32, y: u32) -> [u8; 3] {
+	let index = ((y * WIDTH + x) * 3) as usize;
+	[rgb[index], rgb[index + 1], rgb[index + 2]]
+}
+
+fn close(actual: [u8; 3], expected: [u8; 3]) -> bool {
+	actual.iter().zip(expected).all(|(&a, e)| a.abs_diff(e) <= 6)
+}
+
+#[test]
+fn glyphs() {
+	assert!((0..GLYPH_HEIGHT).all(|row| glyph_pixel('|', 2, row)));
+	assert!((0..GLYPH_HEIGHT).all(|row| !glyph_pixel(' ', 2, row)));
+	assert!(glyph_pixel('-', 0, 3) && !glyph_pixel('-', 0, 2));
+	for column in 0..GLYPH_WIDTH {
+		for row in 0..GLYPH_HEIGHT {
+			assert_eq!(glyph_pixel('\u{e9}', column, row), glyph_pixel('?', column, row));
+		}
+	}
+	assert!(!glyph_pixel('|', GLYPH_WIDTH + 2, 0));
+}
+
+#[test]
+fn text_of_fields() {
+	let overlay = Overlay::new(vec![
+		OverlayField::Text("qa-host".to_string()),
+		OverlayField::FrameId,
+		OverlayField::Timestamp,
+		OverlayField::MissedFrames,
+	]);
+	assert_eq!(overlay.text(&info(42, 12_000_345, 3)), "qa-host 42 12.000345 3");
+
+	let text = Overlay::new(vec![OverlayField::WallClock]).text(&info(0, 0, 0));
+	let digits: Vec<bool> = text.chars().map(|c| c.is_ascii_digit()).collect();
+	assert_eq!(text.len(), "2026-01-01 00:00:00.000".len(), "{}", text);
+	assert!(text.starts_with("20"), "{}", text);
+	for (index, separator) in [(4, '-'), (7, '-'), (10, ' '), (13, ':'), (16, ':'), (19, '.')] {
+		assert_eq!(text.chars().nth(index), Some(separator), "{}", text);
+	}
+	assert_eq!(digits.iter().filter(|&&digit| digit).count(), 17, "{}", text);
+
+	let overlay = Overlay { scale: 3, ..Overlay::new(Vec::new()) };
+	assert_eq!(overlay.size("ab\nc"), ((2 * 6 + 1) * 3, (2 * 8 + 1) * 3));
+}
+
+#[test]
+fn draws_in_every_format() {
+	let overlay = Overlay {
+		position: OverlayPosition::At { x: 9, y: 5 },
+		scale: 2,
+		color: [0xff, 0xff, 0xff],
+		background: Some([0, 0, 0x80]),
+		..Overlay::new(Vec::new())
+	};
+	let (width, height) = overlay.size("|");
+	for format in BufferFormat::ALL {
+		let mut buffer = from_rgb(&SyntheticCapturer::pattern(WIDTH, HEIGHT, 0), WIDTH, HEIGHT, format).unwrap();
+		let original = to_rgb(&buffer, WIDTH, HEIGHT, format).unwrap();
+		overlay.draw_text(&mut buffer, WIDTH, HEIGHT, format, "|").unwrap();
+		let drawn = to_rgb(&buffer, WIDTH, HEIGHT, format).unwrap();
+
+		for y in 0..HEIGHT {
+			for x in 0..WIDTH {
+				let (ox, oy) = (x as i64 - 9, y as i64 - 5);
+				let inside = |margin: i64| ox >= -margin && oy >= -margin && ox < width as i64 + margin && oy < height as i64 + margin;
+				if !inside(2) {
+					assert_eq!(pixel(&drawn, x, y), pixel(&original, x, y), "{:?} pixel {},{}", format, x, y);
+				}
+			}
+		}
+		// The bar of the glyph, in the third column of the font, and the background around it.
+		assert!(close(pixel(&drawn, 9 + 2 + 4, 5 + 2 + 6), [0xff, 0xff, 0xff]), "{:?} {:?}", format, pixel(&drawn, 15, 11));
+		assert!(close(pixel(&drawn, 9, 5 + 6), [0, 0, 0x80]), "{:?} {:?}", format, pixel(&drawn, 9, 11));
+		assert!(close(pixel(&drawn, 9 + width - 1, 5 + height - 1), [0, 0, 0x80]), "{:?}", format);
+	}
+
+	// Without a background only the glyphs are drawn, and alpha is opaque.
+	let overlay = Overlay { background: None, color: [0x10, 0x20, 0x30], ..overlay };
+	let mut buffer = vec![0; (WIDTH * HEIGHT * 4) as usize];
+	overlay.draw_text(&mut buffer, WIDTH, HEIGHT, BufferFormat::Rgba, "|").unwrap();
+	let index = ((11 * WIDTH + 15) * 4) as usize;
+	assert_eq!(buffer[index..index + 4], [0x10, 0x20, 0x30, 0xff]);
+	assert_eq!(buffer.iter().filter(|&&value| value == 0xff).count() as u32, 2 * GLYPH_HEIGHT * 2);
+}
+
+#[test]
+fn positions_and_clipping() {
+	let mut overlay = Overlay { scale: 1, ..Overlay::new(Vec::new()) };
+	let (width, height) = overlay.size("12");
+	for (position, left, top) in [
+		(OverlayPosition::TopLeft, 0, 0),
+		(OverlayPosition::TopRight, WIDTH - width, 0),
+		(OverlayPosition::BottomLeft, 0, HEIGHT - height),
+		(OverlayPosition::BottomRight, WIDTH - width, HEIGHT - height),
+	] {
+		overlay.position = position;
+		let mut buffer = vec![0xff; (WIDTH * HEIGHT * 3) as usize];
+		overlay.draw_text(&mut buffer, WIDTH, HEIGHT, BufferFormat::Rgb, "12").unwrap();
+		assert_eq!(pixel(&buffer, left, top), [0, 0, 0], "{:?}", position);
+		assert_eq!(pixel(&buffer, left + width - 1, top + height - 1), [0, 0, 0], "{:?}", position);
+		let black = buffer.chunks_exact(3).filter(|pixel| pixel == &[0, 0, 0]).count() as u32;
+		assert!(black < width * height);
+	}
+
+	// Overlays larger than the frame or outside of it are clipped.
+	overlay.scale = 4;
+	for position in [OverlayPosition::TopLeft, OverlayPosition::BottomRight, OverlayPosition::At { x: 40, y: 30 }, OverlayPosition::At { x: 100, y: 100 }] {
+		overlay.position = position;
+		for format in BufferFormat::ALL {
+			let mut buffer = vec![0; format.frame_size(WIDTH, HEIGHT)];
+			overlay.draw_text(&mut buffer, WIDTH, HEIGHT, format, "long text\nover two lines").unwrap();
+		}
+	}
+	assert!(overlay.draw_text(&mut [0; 3], WIDTH, HEIGHT, BufferFormat::Rgb, "x").is_err());
+}
+
+#[test]
+fn capturer_draws_the_overlay() {
+	let overlay = Overlay::new(vec![OverlayField::Text("frame".to_string()), OverlayField::FrameId, OverlayField::MissedFrames]);
+	let mut capturer = OverlayCapturer::new(SyntheticCapturer::new(WIDTH * 2, HEIGHT * 2), overlay.clone());
+	capturer.start_with_options(&SessionOptions::new(BufferFormat::Bgra, 100)).unwrap();
+
+	for _ in 0..3 {
+		let frame = capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+		let info = frame.grab_info();
+		let matches = (0..64).any(|index| {
+			let rgb = SyntheticCapturer::pattern(info.width, info.height, index);
+			let mut expected = from_rgb(&rgb, info.width, info.height, BufferFormat::Bgra).unwrap();
+			overlay.draw(&mut expected, &info, BufferFormat::Bgra).unwrap();
+			expected == frame.buffer
+		});
+		assert!(matches, "frame {} has no overlay", info.current_frame);
+	}
+
+	capturer.set_overlay(Overlay::new(Vec::new()));
+	assert!(capturer.overlay().fields.is_empty());
+	capturer.stop().unwrap();
+}
+
+#[test]
+fn capturer_marks_the_overlay_in_diff_maps() {
+	let (width, height) = (WIDTH * 2, HEIGHT * 2);
+	let mut overlay = Overlay::new(vec![OverlayField::FrameId]);
+	overlay.position = OverlayPosition::At { x: 33, y: 17 };
+	overlay.scale = 1;
+	let mut capturer = OverlayCapturer::new(SyntheticCapturer::new(width, height), overlay.clone());
+	let options = SessionOptions { diff_map_scaling_factor: Some(8), ..SessionOptions::new(BufferFormat::Nv12, 100) };
+	capturer.start_with_options(&options).unwrap();
+
+	let mut copy = Vec::new();
+	for _ in 0..3 {
+		let frame = capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+		let diff_map = frame.diff_map.unwrap();
+		let area = overlay.area(&overlay.text(&frame.grab_info()), width, height, BufferFormat::Nv12).unwrap();
+		// The chroma samples of the overlay cover pixel 32 too.
+		assert_eq!((area.x, area.y), (32, 16));
+		assert_eq!(diff_map.with_changed(&[area], &mut copy), diff_map);
+	}
+
+	// The pixels of the previous overlay change back.
+	let previous = overlay.area(&overlay.text(&info(0, 0, 0)), width, height, BufferFormat::Nv12).unwrap();
+	capturer.set_overlay(Overlay { position: OverlayPosition::At { x: 0, y: 48 }, ..overlay.clone() });
+	let frame = capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+	let diff_map = frame.diff_map.unwrap();
+	assert_eq!(diff_map.with_changed(&[previous, Box { x: 0, y: 48, w: 8, h: 9 }], &mut copy), diff_map);
+	capturer.stop().unwrap();
+}
@@ -0,0 +1,142 @@
+use nvfbc::pacer::{PacedFrame, Pacer, PacerStats};
+use nvfbc::FrameGrabInfo;
+
+/// Grab info of a new frame with id `current_frame` rendered at `timestamp_us`.
+fn frame(current_frame: u32, timestamp_us: u64) -> FrameGrabInfo {
+	FrameGrabInfo {
+		width: 1920,
+		height: 1080,
+		byte_size: 1920 * 1080 * 3,
+		current_frame,
+		is_new_frame: true,
+		timestamp_us,
+		missed_frames: 0,
+	}
+}
+
+fn new(pts: u64, current_frame: u32) -> PacedFrame {
+	PacedFrame { pts, current_frame, duplicate: false }
+}
+
+fn duplicate(pts: u64, current_frame: u32) -> PacedFrame {
+	PacedFrame { pts, current_frame, duplicate: true }
+}
+
+#[test]
+fn steady_stream_passes_through() {
+	let mut pacer = Pacer::with_interval(1000);
+	for i in 0..10 {
+		assert_eq!(pacer.push(&frame(i, 5_000 + i as u64 * 1000)), vec![new(i as u64, i)]);
+	}
+	assert_eq!(pacer.stats(), PacerStats { emitted: 10, duplicates: 0, drops: 0 });
+}
+
+#[test]
+fn jitter_within_a_slot_is_absorbed() {
+	let mut pacer = Pacer::with_interval(1000);
+	assert_eq!(pacer.push(&frame(1, 10_000)), vec![new(0, 1)]);
+	assert_eq!(pacer.push(&frame(2, 11_900)), vec![new(1, 2)]);
+	assert_eq!(pacer.push(&frame(3, 12_100)), vec![new(2, 3)]);
+	assert_eq!(pacer.push(&frame(4, 13_999)), vec![new(3, 4)]);
+	assert_eq!(pacer.stats().duplicates, 0);
+	assert_eq!(pacer.stats().drops, 0);
+}
+
+#[test]
+fn gaps_are_filled_with_duplicates() {
+	let mut pacer = Pacer::with_interval(1000);
+	assert_eq!(pacer.push(&frame(1, 0)), vec![new(0, 1)]);
+	assert_eq!(pacer.push(&frame(2, 3_500)), vec![duplicate(1, 1), duplicate(2, 1), new(3, 2)]);
+	assert_eq!(pacer.stats(), PacerStats { emitted: 4, duplicates: 2, drops: 0 });
+}
+
+#[test]
+fn bursts_are_dropped() {
+	let mut pacer = Pacer::with_interval(1000);
+	assert_eq!(pacer.push(&frame(1, 0)), vec![new(0, 1)]);
+	assert_eq!(pacer.push(&frame(2, 1_000)), vec![new(1, 2)]);
+	assert_eq!(pacer.push(&frame(3, 1_300)), vec![]);
+	assert_eq!(pacer.push(&frame(4, 1_600)), vec![]);
+	assert_eq!(pacer.push(&frame(5, 2_100)), vec![new(2, 5)]);
+	assert_eq!(pacer.stats(), PacerStats { emitted: 3, duplicates: 0, drops: 2 });
+}
+
+#[test]
+fn burst_before_a_static_screen_emits_the_newest_frame() {
+	let mut pacer = Pacer::with_interval(1000);
+	assert_eq!(pacer.push(&frame(1, 0)), vec![new(0, 1)]);
+	assert_eq!(pacer.push(&frame(2, 1_000)), vec![new(1, 2)]);
+	assert_eq!(pacer.push(&frame(3, 1_300)), vec![]);
+	assert_eq!(pacer.push(&frame(4, 1_600)), vec![]);
+	assert_eq!(pacer.pending(), Some(4));
+
+	// The screen stopped changing: the newest frame is emitted in the next slot and then repeated.
+	assert_eq!(pacer.push(&FrameGrabInfo { is_new_frame: false, ..frame(4, 3_100) }), vec![new(2, 4)]);
+	assert_eq!(pacer.pending(), None);
+	assert_eq!(pacer.advance_to(5_000), vec![duplicate(3, 4), duplicate(4, 4)]);
+	assert_eq!(pacer.stats(), PacerStats { emitted: 5, duplicates: 2, drops: 1 });
+}
+
+#[test]
+fn clock_does_not_drift() {
+	// 1_000_000 / 60 is not a whole number of microseconds.
+	let mut pacer = Pacer::new(60);
+	assert_eq!(pacer.push(&frame(0, 0)), vec![new(0, 0)]);
+	let hour_us = 3_600_000_000;
+	let paced = pacer.push(&frame(1, hour_us));
+	assert_eq!(paced.last(), Some(&new(60 * 3600, 1)));
+	pacer.advance_to(2 * hour_us);
+	assert_eq!(pacer.next_pts(), 2 * 60 * 3600);
+}
+
+#[test]
+fn repeated_frames_do_not_start_a_slot() {
+	let mut pacer = Pacer::with_interval(1000);
+	assert_eq!(pacer.push(&FrameGrabInfo { is_new_frame: false, ..frame(1, 0) }), vec![]);
+	assert_eq!(pacer.start_us(), None);
+
+	assert_eq!(pacer.push(&frame(1, 500)), vec![new(0, 1)]);
+	// The same frame id again, e.g. after a grab timed out.
+	assert_eq!(pacer.push(&frame(1, 500)), vec![]);
+	// A repeat reported later still advances the clock.
+	assert_eq!(pacer.push(&FrameGrabInfo { is_new_frame: false, ..frame(1, 2_600) }), vec![duplicate(1, 1)]);
+	assert_eq!(pacer.push(&frame(2, 2_700)), vec![new(2, 2)]);
+	assert_eq!(pacer.stats(), PacerStats { emitted: 3, duplicates: 1, drops: 0 });
+}
+
+#[test]
+fn advance_fills_completed_slots() {
+	let mut pacer = Pacer::with_interval(1000);
+	assert_eq!(pacer.advance_to(5_000), vec![]);
+	assert_eq!(pacer.push(&frame(7, 10_000)), vec![new(0, 7)]);
+
+	// Slot 1 is not complete until 12_000.
+	assert_eq!(pacer.advance_to(11_999), vec![]);
+	assert_eq!(pacer.advance_to(12_000), vec![duplicate(1, 7)]);
+	assert_eq!(pacer.advance_to(14_500), vec![duplicate(2, 7), duplicate(3, 7)]);
+
+	// A frame in a slot that was already filled by a duplicate is dropped.
+	assert_eq!(pacer.push(&frame(8, 13_500)), vec![]);
+	assert_eq!(pacer.push(&frame(9, 14_600)), vec![new(4, 9)]);
+	assert_eq!(pacer.stats(), PacerStats { emitted: 5, duplicates: 3, drops: 1 });
+}
+
+#[test]
+fn pts_increase_monotonically() {
+	// Irregular timestamps, including bursts, gaps and a timestamp going backwards.
+	let timestamps = [0, 100, 16_000, 16_500, 70_000, 69_000, 71_000, 120_000, 120_001, 200_000];
+	let mut pacer = Pacer::new(60);
+
+	let mut emitted = Vec::new();
+	for (i, timestamp) in timestamps.iter().enumerate() {
+		emitted.extend(pacer.push(&frame(i as u32, *timestamp)));
+	}
+
+	for (i, paced) in emitted.iter().enumerate() {
+		assert_eq!(paced.pts, i as u64);
+	}
+	let stats = pacer.stats();
+	assert_eq!(stats.emitted, emitted.len() as u64);
+	assert_eq!(stats.emitted - stats.duplicates + stats.drops + pacer.pending().is_some() as u64, timestamps.len() as u64);
+	assert_eq!(pacer.next_pts(), 200_000 / pacer.interval_us() + 1);
+}
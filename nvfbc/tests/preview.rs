@@ -0,0 +1,163 @@
+#![cfg(feature = "preview")]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+use nvfbc::preview::{encode_jpeg, encode_png, PreviewOptions, PreviewServer};
+use nvfbc::synthetic::SyntheticCapturer;
+use nvfbc::Status;
+
+fn server() -> PreviewServer {
+	PreviewServer::bind("127.0.0.1:0", || Ok(SyntheticCapturer::new(64, 48)), PreviewOptions::new(30)).unwrap()
+}
+
+fn connect(addr: SocketAddr, method: &str, path: &str) -> BufReader<TcpStream> {
+	let mut stream = TcpStream::connect(addr).unwrap();
+	stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+	write!(stream, "{} {} HTTP/1.1\r\nHost: localhost\r\n\r\n", method, path).unwrap();
+	BufReader::new(stream)
+}
+
+/// Read the status line and headers of a response, or of a part of a multipart response.
+fn read_head(reader: &mut BufReader<TcpStream>) -> Vec<String> {
+	let mut lines = Vec::new();
+	loop {
+		let mut line = String::new();
+		reader.read_line(&mut line).unwrap();
+		let line = line.trim_end().to_string();
+		if line.is_empty() {
+			return lines;
+		}
+		lines.push(line);
+	}
+}
+
+fn header(head: &[String], name: &str) -> Option<String> {
+	head.iter()
+		.filter_map(|line| line.split_once(": "))
+		.find(|(key, _)| key.eq_ignore_ascii_case(name))
+		.map(|(_, value)| value.to_string())
+}
+
+/// Send a request and return the status line, the headers and the body.
+fn request(addr: SocketAddr, method: &str, path: &str) -> (Vec<String>, Vec<u8>) {
+	let mut reader = connect(addr, method, path);
+	let head = read_head(&mut reader);
+	let mut body = Vec::new();
+	reader.read_to_end(&mut body).unwrap();
+	(head, body)
+}
+
+/// Request the status until the capture thread has retrieved it.
+fn wait_for(addr: SocketAddr, path: &str) -> (Vec<String>, Vec<u8>) {
+	for _ in 0..50 {
+		let (head, body) = request(addr, "GET", path);
+		if head[0] != "HTTP/1.1 503 Service Unavailable" {
+			return (head, body);
+		}
+		std::thread::sleep(Duration::from_millis(20));
+	}
+	panic!("{} was not available in time", path);
+}
+
+#[test]
+fn streams_jpeg_frames() {
+	let server = server();
+	let mut reader = connect(server.local_addr(), "GET", "/stream.mjpg");
+	let head = read_head(&mut reader);
+	assert_eq!(head[0], "HTTP/1.1 200 OK");
+	let content_type = header(&head, "Content-Type").unwrap();
+	let boundary = content_type.strip_prefix("multipart/x-mixed-replace; boundary=").unwrap();
+
+	let mut frames = Vec::new();
+	for _ in 0..3 {
+		let part = read_head(&mut reader);
+		assert_eq!(part[0], format!("--{}", boundary));
+		assert_eq!(header(&part, "Content-Type").as_deref(), Some("image/jpeg"));
+		let length: usize = header(&part, "Content-Length").unwrap().parse().unwrap();
+		let mut jpeg = vec![0; length + 2];
+		reader.read_exact(&mut jpeg).unwrap();
+		assert!(jpeg.ends_with(b"\r\n"));
+		jpeg.truncate(length);
+
+		let image = image::load_from_memory_with_format(&jpeg, image::ImageFormat::Jpeg).unwrap().to_rgb8();
+		assert_eq!(image.dimensions(), (64, 48));
+		frames.push(image.into_raw());
+	}
+
+	// The square of the test pattern moves, so consecutive frames differ.
+	assert_ne!(frames[0], frames[1]);
+	assert_ne!(frames[1], frames[2]);
+}
+
+#[test]
+fn serves_a_png_snapshot() {
+	let server = server();
+	let (head, body) = wait_for(server.local_addr(), "/snapshot.png");
+	assert_eq!(head[0], "HTTP/1.1 200 OK");
+	assert_eq!(header(&head, "Content-Type").as_deref(), Some("image/png"));
+	assert_eq!(header(&head, "Content-Length").unwrap().parse::<usize>().unwrap(), body.len());
+
+	let image = image::load_from_memory_with_format(&body, image::ImageFormat::Png).unwrap().to_rgb8();
+	assert_eq!(image.dimensions(), (64, 48));
+	// The snapshot is lossless: the left column is the white bar of the test pattern.
+	assert_eq!(image.get_pixel(0, 0).0, [0xff, 0xff, 0xff]);
+}
+
+#[test]
+fn serves_the_status() {
+	let server = server();
+	let (head, body) = wait_for(server.local_addr(), "/status.json");
+	assert_eq!(head[0], "HTTP/1.1 200 OK");
+	assert_eq!(header(&head, "Content-Type").as_deref(), Some("application/json"));
+
+	let status: Status = serde_json::from_slice(&body).unwrap();
+	assert_eq!(status.screen_size.w, 64);
+	assert_eq!(status.screen_size.h, 48);
+	assert_eq!(status.outputs.len(), 1);
+}
+
+#[test]
+fn serves_the_index_and_rejects_other_requests() {
+	let server = server();
+	let (head, body) = request(server.local_addr(), "GET", "/");
+	assert_eq!(head[0], "HTTP/1.1 200 OK");
+	assert!(String::from_utf8(body).unwrap().contains("/stream.mjpg"));
+
+	let (head, body) = request(server.local_addr(), "HEAD", "/");
+	assert_eq!(head[0], "HTTP/1.1 200 OK");
+	assert!(body.is_empty());
+
+	assert_eq!(request(server.local_addr(), "POST", "/snapshot.png").0[0], "HTTP/1.1 405 Method Not Allowed");
+	assert_eq!(request(server.local_addr(), "GET", "/metrics").0[0], "HTTP/1.1 404 Not Found");
+}
+
+#[test]
+fn reports_errors_creating_the_capturer() {
+	let create = || Err::<SyntheticCapturer, _>(nvfbc::Error::new(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_X, None));
+	let server = PreviewServer::bind("127.0.0.1:0", create, PreviewOptions::new(30)).unwrap();
+	for _ in 0..50 {
+		let (head, body) = request(server.local_addr(), "GET", "/status.json");
+		assert_eq!(head[0], "HTTP/1.1 503 Service Unavailable");
+		let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+		if body["error"] != "status not retrieved yet" {
+			assert!(body["error"].as_str().unwrap().contains("X error"));
+			return;
+		}
+		std::thread::sleep(Duration::from_millis(20));
+	}
+	panic!("the error was not reported in time");
+}
+
+#[test]
+fn encodes_frames_on_the_cpu() {
+	let rgb = SyntheticCapturer::pattern(32, 16, 0);
+	let jpeg = encode_jpeg(&rgb, 32, 16, 90).unwrap();
+	assert_eq!(&jpeg[..2], &[0xff, 0xd8]);
+	let png = encode_png(&rgb, 32, 16).unwrap();
+	assert_eq!(&png[1..4], b"PNG");
+
+	assert!(encode_jpeg(&rgb, 32, 15, 90).is_err());
+	assert!(encode_png(&[], 0, 0).is_err());
+}
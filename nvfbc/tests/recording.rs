@@ -0,0 +1,199 @@
+use std::io::Cursor;
+use std::time::Duration;
+
+use nvfbc::recording::{Compression, Pacing, RecordingReader, RecordingWriter, ReplayCapturer};
+use nvfbc::synthetic::SyntheticCapturer;
+use nvfbc::system::CaptureMethod;
+use nvfbc::{Box, BufferFormat, Capture, Output, SessionOptions, Tracking};
+
+fn options() -> SessionOptions {
+	SessionOptions {
+		tracking: Tracking::Output(1),
+		capture_box: Some(Box { x: 8, y: 4, w: 32, h: 16 }),
+		with_cursor: false,
+		..SessionOptions::new(BufferFormat::Nv12, 10)
+	}
+}
+
+/// Record `count` synthetic frames and return the recording with the recorded frames.
+fn record(count: usize, compression: Compression) -> (Vec<u8>, Vec<(Vec<u8>, u64)>) {
+	let mut capturer = SyntheticCapturer::new(64, 48);
+	let options = options();
+	capturer.start_with_options(&options).unwrap();
+
+	let mut writer = RecordingWriter::new(Vec::new(), &capturer.status().unwrap(), &options, compression).unwrap();
+	let mut frames = Vec::new();
+	for _ in 0..count {
+		let frame = capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+		writer.write_frame(&frame).unwrap();
+		frames.push((frame.buffer.to_vec(), frame.timestamp_us));
+	}
+	assert_eq!(writer.frames(), count as u64);
+
+	(writer.finish().unwrap(), frames)
+}
+
+fn replay(recording: Vec<u8>, pacing: Pacing) -> ReplayCapturer<Cursor<Vec<u8>>> {
+	ReplayCapturer::new(RecordingReader::new(Cursor::new(recording)).unwrap(), pacing)
+}
+
+fn check_round_trip(compression: Compression) {
+	let (recording, frames) = record(5, compression);
+
+	let mut reader = RecordingReader::new(Cursor::new(recording)).unwrap();
+	let header = reader.header().clone();
+	assert_eq!(header.status.outputs, SyntheticCapturer::new(64, 48).status().unwrap().outputs);
+	assert!(header.status.currently_capturing);
+	assert_eq!(header.options.tracking, Tracking::Output(1));
+	assert_eq!(header.options.capture_box, options().capture_box);
+	assert_eq!(header.options.frame_size, None);
+	assert!(!header.options.with_cursor);
+
+	for (data, timestamp_us) in &frames {
+		let frame = reader.next_frame().unwrap().unwrap();
+		assert_eq!(&frame.data, data);
+		assert_eq!(frame.info.timestamp_us, *timestamp_us);
+		assert_eq!((frame.info.width, frame.info.height), (32, 16));
+		assert_eq!(frame.buffer_format, BufferFormat::Nv12);
+		assert!(frame.info.is_new_frame);
+	}
+	assert!(reader.next_frame().unwrap().is_none());
+}
+
+#[test]
+fn round_trip() {
+	check_round_trip(Compression::None);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn round_trip_zstd() {
+	check_round_trip(Compression::Zstd(3));
+
+	let (compressed, _) = record(5, Compression::Zstd(3));
+	let (uncompressed, _) = record(5, Compression::None);
+	assert!(compressed.len() < uncompressed.len());
+}
+
+#[test]
+fn rejects_invalid_recordings() {
+	assert!(RecordingReader::new(Cursor::new(b"NOTAREC\0\x01\x00\x00".to_vec())).is_err());
+
+	let (mut recording, _) = record(1, Compression::None);
+	recording.truncate(recording.len() - 1);
+	let mut reader = RecordingReader::new(Cursor::new(recording)).unwrap();
+	assert!(reader.next_frame().is_err());
+}
+
+#[test]
+fn replay_as_fast_as_possible() {
+	let (recording, frames) = record(3, Compression::None);
+	let mut capturer = replay(recording, Pacing::AsFastAsPossible);
+	assert!(capturer.next_frame(CaptureMethod::Blocking, None).is_err());
+
+	capturer.start_with_options(&options()).unwrap();
+	assert!(capturer.status().unwrap().currently_capturing);
+	for (data, timestamp_us) in &frames {
+		let frame = capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+		assert_eq!(frame.buffer, &data[..]);
+		assert_eq!(frame.timestamp_us, *timestamp_us);
+	}
+	assert!(capturer.is_finished().unwrap());
+	assert!(capturer.next_frame(CaptureMethod::Blocking, None).is_err());
+	capturer.stop().unwrap();
+}
+
+#[test]
+fn replay_converts_buffer_format() {
+	let (recording, frames) = record(1, Compression::None);
+	let mut capturer = replay(recording, Pacing::AsFastAsPossible);
+	capturer.start_with_options(&SessionOptions::new(BufferFormat::Rgb, 100)).unwrap();
+
+	let frame = capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+	assert_eq!(frame.buffer_format, BufferFormat::Rgb);
+	assert_eq!(frame.buffer, nvfbc::convert::to_rgb(&frames[0].0, 32, 16, BufferFormat::Nv12).unwrap());
+}
+
+#[test]
+fn replay_real_time() {
+	// Frames are recorded at 10 fps, so 100ms apart.
+	let (recording, frames) = record(6, Compression::None);
+	let mut capturer = replay(recording, Pacing::RealTime);
+	capturer.start_with_options(&options()).unwrap();
+
+	let frame = capturer.next_frame(CaptureMethod::NoWait, None).unwrap();
+	assert_eq!(frame.timestamp_us, frames[0].1);
+
+	// No new frame is due yet.
+	let frame = capturer.next_frame(CaptureMethod::NoWait, None).unwrap();
+	assert!(!frame.is_new_frame);
+	assert_eq!(frame.timestamp_us, frames[0].1);
+
+	let frame = capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+	assert!(frame.is_new_frame);
+	assert_eq!(frame.timestamp_us, frames[1].1);
+
+	// After waiting for at least two more frame intervals, the frames in between are skipped.
+	// The thread may sleep longer on a loaded machine, so the frame is identified by its timestamp.
+	std::thread::sleep(Duration::from_millis(250));
+	let frame = capturer.next_frame(CaptureMethod::NoWaitIfNewFrame, None).unwrap();
+	assert!(frame.is_new_frame);
+	let index = frames.iter().position(|(_, timestamp_us)| *timestamp_us == frame.timestamp_us).unwrap();
+	assert!(index >= 3, "frame {} was replayed too early", index);
+	assert_eq!(frame.missed_frames as usize, index - 2);
+}
+
+#[test]
+fn replay_grab_times_out() {
+	// Frames are recorded at 1 fps, so the second frame is not due before the grab times out.
+	let options = SessionOptions { fps: 1, ..options() };
+	let mut capturer = SyntheticCapturer::new(64, 48);
+	capturer.start_with_options(&options).unwrap();
+	let mut writer = RecordingWriter::new(Vec::new(), &capturer.status().unwrap(), &options, Compression::None).unwrap();
+	for _ in 0..2 {
+		writer.write_frame(&capturer.next_frame(CaptureMethod::Blocking, None).unwrap()).unwrap();
+	}
+
+	let mut capturer = replay(writer.finish().unwrap(), Pacing::RealTime);
+	capturer.start_with_options(&options).unwrap();
+	let frame = capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+	assert!(frame.is_new_frame);
+	let timestamp_us = frame.timestamp_us;
+
+	// A grab that times out returns the previous frame again.
+	let frame = capturer.next_frame(CaptureMethod::Blocking, Some(Duration::from_millis(1))).unwrap();
+	assert!(!frame.is_new_frame);
+	assert_eq!(frame.timestamp_us, timestamp_us);
+}
+
+#[test]
+fn long_output_names() {
+	// A name of invalid UTF-8 bytes as decoded from NvFBC, and a name that is too long to record.
+	let lossy = "\u{fffd}".repeat(128);
+	let long = "\u{e9}".repeat(200);
+	let mut status = SyntheticCapturer::new(64, 48).status().unwrap();
+	status.outputs[0].name = lossy.clone();
+	status.outputs.push(Output { id: 2, name: long.clone(), ..status.outputs[0].clone() });
+
+	let recording = RecordingWriter::new(Vec::new(), &status, &options(), Compression::None).unwrap().finish().unwrap();
+	let reader = RecordingReader::new(Cursor::new(recording)).unwrap();
+	let outputs = &reader.header().status.outputs;
+	assert_eq!(outputs[0].name, lossy);
+	assert_eq!(outputs[1].name.len(), 384);
+	assert!(long.starts_with(&outputs[1].name));
+}
+
+#[test]
+fn output_count_is_limited() {
+	let mut status = SyntheticCapturer::new(64, 48).status().unwrap();
+	let output = status.outputs[0].clone();
+	status.outputs = (1..=nvfbc_sys::NVFBC_OUTPUT_MAX).map(|id| Output { id, ..output.clone() }).collect();
+	let recording = RecordingWriter::new(Vec::new(), &status, &options(), Compression::None).unwrap().finish().unwrap();
+	let reader = RecordingReader::new(Cursor::new(recording)).unwrap();
+	assert_eq!(reader.header().status.outputs, status.outputs);
+
+	// More outputs could not be read back, so they are not recorded.
+	status.outputs = (1..=9).map(|id| Output { id, ..output.clone() }).collect();
+	let error = RecordingWriter::new(Vec::new(), &status, &options(), Compression::None).err().unwrap();
+	assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+}
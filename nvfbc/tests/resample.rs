@@ -0,0 +1,112 @@
+use nvfbc::convert::{from_rgb, to_rgb};
+use nvfbc::resample::{resize, resize_into, Filter};
+use nvfbc::synthetic::SyntheticCapturer;
+use nvfbc::{BufferFormat, Size};
+
+const FILTERS: [Filter; 3] = [Filter::Box, Filter::Bilinear, Filter::Lanczos3];
+
+fn solid(size: Size, rgb: [u8; 3], format: BufferFormat) -> Vec<u8> {
+	let pixels: Vec<u8> = rgb.iter().copied().cycle().take((size.w * size.h * 3) as usize).collect();
+	from_rgb(&pixels, size.w, size.h, format).unwrap()
+}
+
+#[test]
+fn solid_frames_stay_solid() {
+	let size = Size { w: 37, h: 23 };
+	let color = [0xc0, 0x40, 0x20];
+	for format in BufferFormat::ALL {
+		let frame = solid(size, color, format);
+		let expected = to_rgb(&frame, size.w, size.h, format).unwrap()[..3].to_vec();
+		for filter in FILTERS {
+			for new_size in [Size { w: 16, h: 9 }, Size { w: 80, h: 41 }, Size { w: 1, h: 1 }, Size { w: 37, h: 5 }] {
+				let resized = resize(&frame, size, format, new_size, filter).unwrap();
+				let rgb = to_rgb(&resized, new_size.w, new_size.h, format).unwrap();
+				for pixel in rgb.chunks_exact(3) {
+					assert_eq!(pixel, &expected[..], "{:?} {:?} {:?}", format, filter, new_size);
+				}
+			}
+		}
+	}
+}
+
+#[test]
+fn box_filter_averages() {
+	let rgb = [
+		0, 10, 20, 40, 50, 60, 0, 0, 0, 1, 1, 1,
+		20, 30, 40, 60, 70, 80, 255, 255, 255, 2, 2, 2,
+	];
+	let resized = resize(&rgb, Size { w: 4, h: 2 }, BufferFormat::Rgb, Size { w: 2, h: 1 }, Filter::Box).unwrap();
+	assert_eq!(resized, [30, 40, 50, 65, 65, 65]);
+
+	// Enlarging repeats the nearest pixel.
+	let resized = resize(&rgb, Size { w: 4, h: 2 }, BufferFormat::Rgb, Size { w: 8, h: 4 }, Filter::Box).unwrap();
+	assert_eq!(resized[..6], [0, 10, 20, 0, 10, 20]);
+	assert_eq!(resized[8 * 3 * 3..][..3], [20, 30, 40]);
+}
+
+#[test]
+fn filters_interpolate_gradients() {
+	let size = Size { w: 64, h: 4 };
+	// The same gradient in all three planes.
+	let gradient: Vec<u8> = (0..size.h * 3).flat_map(|_| (0..size.w).map(|x| (x * 4) as u8)).collect();
+	for filter in FILTERS {
+		for new_size in [Size { w: 16, h: 2 }, Size { w: 200, h: 3 }] {
+			let resized = resize(&gradient, size, BufferFormat::Yuv444p, new_size, filter).unwrap();
+			let luma = &resized[..(new_size.w * new_size.h) as usize];
+			for row in luma.chunks_exact(new_size.w as usize) {
+				assert!(row.windows(2).all(|pair| pair[0] <= pair[1]), "{:?} {:?} {:?}", filter, new_size, row);
+			}
+		}
+	}
+
+	let resized = resize(&gradient, size, BufferFormat::Yuv444p, Size { w: 32, h: 4 }, Filter::Bilinear).unwrap();
+	// The first pixel repeats the edge.
+	assert_eq!(resized[..4], [3, 10, 18, 26]);
+}
+
+#[test]
+fn nv12_planes_are_resized_separately() {
+	let size = Size { w: 64, h: 32 };
+	let rgb: Vec<u8> = (0..size.h).flat_map(|_| (0..size.w).flat_map(|x| if x < 32 { [0xff, 0, 0] } else { [0, 0, 0xff] })).collect();
+	let frame = from_rgb(&rgb, size.w, size.h, BufferFormat::Nv12).unwrap();
+	let new_size = Size { w: 16, h: 8 };
+	for filter in FILTERS {
+		let resized = resize(&frame, size, BufferFormat::Nv12, new_size, filter).unwrap();
+		let rgb = to_rgb(&resized, new_size.w, new_size.h, BufferFormat::Nv12).unwrap();
+		let pixel = |x: u32, y: u32| &rgb[((y * new_size.w + x) * 3) as usize..][..3];
+		assert!(pixel(1, 4)[0] > 0xf0 && pixel(1, 4)[2] < 0x10, "{:?} {:?}", filter, pixel(1, 4));
+		assert!(pixel(14, 4)[2] > 0xf0 && pixel(14, 4)[0] < 0x10, "{:?} {:?}", filter, pixel(14, 4));
+	}
+}
+
+#[test]
+fn large_frames_match_single_rows() {
+	// Columns of the same color, so that every row of the result equals the resized single row.
+	let size = Size { w: 2048, h: 512 };
+	let row: Vec<u8> = (0..size.w).flat_map(|x| [(x % 251) as u8, (x / 8) as u8, 0x80, 0xff]).collect();
+	let frame: Vec<u8> = row.iter().copied().cycle().take(row.len() * size.h as usize).collect();
+	for filter in FILTERS {
+		let new_size = Size { w: 1000, h: 300 };
+		let expected = resize(&row, Size { w: size.w, h: 1 }, BufferFormat::Bgra, Size { w: new_size.w, h: 1 }, filter).unwrap();
+		let mut resized = vec![0; BufferFormat::Bgra.frame_size(new_size.w, new_size.h)];
+		resize_into(&frame, size, BufferFormat::Bgra, &mut resized, new_size, filter).unwrap();
+		for (y, resized_row) in resized.chunks_exact(expected.len()).enumerate() {
+			assert_eq!(resized_row, &expected[..], "{:?} row {}", filter, y);
+		}
+	}
+}
+
+#[test]
+fn several_sizes_from_one_frame() {
+	let size = Size { w: 320, h: 180 };
+	let frame = from_rgb(&SyntheticCapturer::pattern(size.w, size.h, 0), size.w, size.h, BufferFormat::Nv12).unwrap();
+	let mut thumbnail = vec![0; BufferFormat::Nv12.frame_size(160, 90)];
+	resize_into(&frame, size, BufferFormat::Nv12, &mut thumbnail, Size { w: 160, h: 90 }, Filter::Box).unwrap();
+	let preview = resize(&frame, size, BufferFormat::Nv12, Size { w: 64, h: 36 }, Filter::Lanczos3).unwrap();
+	assert_eq!(preview.len(), BufferFormat::Nv12.frame_size(64, 36));
+	assert_eq!(resize(&frame, size, BufferFormat::Nv12, size, Filter::Lanczos3).unwrap(), frame);
+
+	assert!(resize(&frame[1..], size, BufferFormat::Nv12, Size { w: 64, h: 36 }, Filter::Box).is_err());
+	assert!(resize_into(&frame, size, BufferFormat::Nv12, &mut thumbnail[1..], Size { w: 160, h: 90 }, Filter::Box).is_err());
+	assert!(resize(&frame, size, BufferFormat::Nv12, Size { w: 0, h: 0 }, Filter::Box).unwrap().is_empty());
+}
@@ -0,0 +1,438 @@
+#![cfg(feature = "rfb")]
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::{Duration, Instant};
+
+use flate2::{Decompress, FlushDecompress};
+use nvfbc::rfb::{RfbOptions, RfbServer};
+use nvfbc::synthetic::SyntheticCapturer;
+use nvfbc::system::{CaptureMethod, SystemFrameInfo};
+use nvfbc::convert::{from_rgb, to_rgb};
+use nvfbc::{Box, BufferFormat, Capture, Error, SessionOptions, Size, Status};
+
+const RAW: i32 = 0;
+const ZRLE: i32 = 16;
+const DESKTOP_SIZE: i32 = -223;
+
+/// A minimal RFB client using the default pixel format of the server.
+struct Client {
+	stream: TcpStream,
+	width: u32,
+	height: u32,
+	/// The framebuffer as packed RGB888.
+	rgb: Vec<u8>,
+	zlib: Decompress,
+	/// Bytes per pixel of the pixel format set by the client.
+	bytes_per_pixel: usize,
+}
+
+impl Client {
+	fn connect(addr: SocketAddr, encodings: &[i32]) -> Self {
+		let mut stream = TcpStream::connect(addr).unwrap();
+		stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+		let mut version = [0; 12];
+		stream.read_exact(&mut version).unwrap();
+		assert_eq!(&version, b"RFB 003.008\n");
+		stream.write_all(b"RFB 003.008\n").unwrap();
+
+		let mut security_types = vec![0; read_u8(&mut stream) as usize];
+		stream.read_exact(&mut security_types).unwrap();
+		assert_eq!(security_types, [1]);
+		stream.write_all(&[1]).unwrap();
+		assert_eq!(read_u32(&mut stream), 0);
+
+		// ClientInit with the shared flag.
+		stream.write_all(&[1]).unwrap();
+		let width = read_u16(&mut stream) as u32;
+		let height = read_u16(&mut stream) as u32;
+		let mut pixel_format = [0; 16];
+		stream.read_exact(&mut pixel_format).unwrap();
+		assert_eq!(&pixel_format[..4], &[32, 24, 0, 1]);
+		let mut name = vec![0; read_u32(&mut stream) as usize];
+		stream.read_exact(&mut name).unwrap();
+		assert_eq!(name, b"NvFBC");
+
+		let mut message = vec![2, 0];
+		message.extend_from_slice(&(encodings.len() as u16).to_be_bytes());
+		for encoding in encodings {
+			message.extend_from_slice(&encoding.to_be_bytes());
+		}
+		stream.write_all(&message).unwrap();
+
+		Self {
+			stream,
+			width,
+			height,
+			rgb: vec![0; (width * height * 3) as usize],
+			zlib: Decompress::new(true),
+			bytes_per_pixel: 4,
+		}
+	}
+
+	/// Switch to 16 bits per pixel, big endian RGB565.
+	fn set_rgb565(&mut self) {
+		let mut message = vec![0, 0, 0, 0, 16, 16, 1, 1];
+		for max in [31u16, 63, 31] {
+			message.extend_from_slice(&max.to_be_bytes());
+		}
+		message.extend_from_slice(&[11, 5, 0, 0, 0, 0]);
+		self.stream.write_all(&message).unwrap();
+		self.bytes_per_pixel = 2;
+	}
+
+	fn request(&mut self, incremental: bool) {
+		let mut message = vec![3, incremental as u8];
+		for value in [0, 0, self.width as u16, self.height as u16] {
+			message.extend_from_slice(&value.to_be_bytes());
+		}
+		self.stream.write_all(&message).unwrap();
+	}
+
+	/// Read a framebuffer update, apply it and return its rectangles with their encodings.
+	fn read_update(&mut self) -> Vec<(Box, i32)> {
+		assert_eq!(read_u8(&mut self.stream), 0);
+		read_u8(&mut self.stream);
+		let count = read_u16(&mut self.stream);
+
+		let mut rectangles = Vec::new();
+		for _ in 0..count {
+			let x = read_u16(&mut self.stream) as u32;
+			let y = read_u16(&mut self.stream) as u32;
+			let w = read_u16(&mut self.stream) as u32;
+			let h = read_u16(&mut self.stream) as u32;
+			let rectangle = Box { x, y, w, h };
+			let encoding = read_u32(&mut self.stream) as i32;
+			match encoding {
+				RAW => {
+					let mut data = vec![0; (w * h) as usize * self.bytes_per_pixel];
+					self.stream.read_exact(&mut data).unwrap();
+					let pixels: Vec<[u8; 3]> = data.chunks_exact(self.bytes_per_pixel).map(|pixel| self.decode(pixel)).collect();
+					self.put(&rectangle, &pixels);
+				},
+				ZRLE => {
+					let mut compressed = vec![0; read_u32(&mut self.stream) as usize];
+					self.stream.read_exact(&mut compressed).unwrap();
+					let mut data = Vec::with_capacity((w * h * 4) as usize + 1024);
+					self.zlib.decompress_vec(&compressed, &mut data, FlushDecompress::Sync).unwrap();
+					self.put_zrle(&rectangle, &data);
+				},
+				DESKTOP_SIZE => {
+					self.width = w;
+					self.height = h;
+					self.rgb = vec![0; (w * h * 3) as usize];
+				},
+				encoding => panic!("unexpected encoding {}", encoding),
+			}
+			rectangles.push((rectangle, encoding));
+		}
+		rectangles
+	}
+
+	fn decode(&self, pixel: &[u8]) -> [u8; 3] {
+		match pixel.len() {
+			2 => {
+				let value = u16::from_be_bytes([pixel[0], pixel[1]]) as u32;
+				let scale = |value: u32, max: u32| ((value * 255 + max / 2) / max) as u8;
+				[scale(value >> 11, 31), scale(value >> 5 & 63, 63), scale(value & 31, 31)]
+			},
+			// Little endian with red at bit 16, so blue comes first.
+			_ => [pixel[2], pixel[1], pixel[0]],
+		}
+	}
+
+	fn put(&mut self, rectangle: &Box, pixels: &[[u8; 3]]) {
+		for (i, pixel) in pixels.iter().enumerate() {
+			let x = rectangle.x + i as u32 % rectangle.w;
+			let y = rectangle.y + i as u32 / rectangle.w;
+			let index = ((y * self.width + x) * 3) as usize;
+			self.rgb[index..index + 3].copy_from_slice(pixel);
+		}
+	}
+
+	fn put_zrle(&mut self, rectangle: &Box, mut data: &[u8]) {
+		let cpixel = |data: &mut &[u8]| {
+			let pixel = [data[2], data[1], data[0]];
+			*data = &data[3..];
+			pixel
+		};
+		for y in (rectangle.y..rectangle.y + rectangle.h).step_by(64) {
+			for x in (rectangle.x..rectangle.x + rectangle.w).step_by(64) {
+				let tile = Box { x, y, w: 64.min(rectangle.x + rectangle.w - x), h: 64.min(rectangle.y + rectangle.h - y) };
+				let pixel_count = (tile.w * tile.h) as usize;
+				let subencoding = data[0];
+				data = &data[1..];
+				let pixels = match subencoding {
+					0 => (0..pixel_count).map(|_| cpixel(&mut data)).collect(),
+					1 => vec![cpixel(&mut data); pixel_count],
+					2..=16 => {
+						let palette: Vec<[u8; 3]> = (0..subencoding).map(|_| cpixel(&mut data)).collect();
+						let bits = match subencoding {
+							2 => 1,
+							3..=4 => 2,
+							_ => 4,
+						};
+						let row_bytes = (tile.w as usize * bits).div_ceil(8);
+						let mut pixels = Vec::with_capacity(pixel_count);
+						for row in 0..tile.h as usize {
+							let packed = &data[row * row_bytes..(row + 1) * row_bytes];
+							for column in 0..tile.w as usize {
+								let bit = column * bits;
+								let index = (packed[bit / 8] >> (8 - bits - bit % 8)) & ((1 << bits) - 1);
+								pixels.push(palette[index as usize]);
+							}
+						}
+						data = &data[tile.h as usize * row_bytes..];
+						pixels
+					},
+					subencoding => panic!("unexpected ZRLE subencoding {}", subencoding),
+				};
+				self.put(&tile, &pixels);
+			}
+		}
+		assert!(data.is_empty());
+	}
+
+	/// Index of the test pattern frame shown in the framebuffer, if any.
+	fn pattern(&self) -> Option<u64> {
+		(0..64).find(|&index| SyntheticCapturer::pattern(self.width, self.height, index) == self.rgb)
+	}
+}
+
+fn read_u8(stream: &mut TcpStream) -> u8 {
+	let mut value = [0];
+	stream.read_exact(&mut value).unwrap();
+	value[0]
+}
+
+fn read_u16(stream: &mut TcpStream) -> u16 {
+	let mut value = [0; 2];
+	stream.read_exact(&mut value).unwrap();
+	u16::from_be_bytes(value)
+}
+
+fn read_u32(stream: &mut TcpStream) -> u32 {
+	let mut value = [0; 4];
+	stream.read_exact(&mut value).unwrap();
+	u32::from_be_bytes(value)
+}
+
+fn server() -> RfbServer {
+	RfbServer::bind("127.0.0.1:0", || Ok(SyntheticCapturer::new(64, 48)), RfbOptions::new(30)).unwrap()
+}
+
+/// Request a full update and then incremental updates, which only contain the moving square of the test pattern.
+fn check_updates(client: &mut Client, encoding: i32) {
+	client.request(false);
+	let rectangles = client.read_update();
+	assert_eq!(rectangles, vec![(Box { x: 0, y: 0, w: 64, h: 48 }, encoding)]);
+	assert!(client.pattern().is_some());
+
+	for _ in 0..3 {
+		client.request(true);
+		let rectangles = client.read_update();
+		assert!(!rectangles.is_empty());
+		for (rectangle, rectangle_encoding) in rectangles {
+			assert_eq!(rectangle_encoding, encoding);
+			// The square moves within the second row of 16x16 blocks.
+			assert_eq!((rectangle.y, rectangle.h), (16, 16));
+		}
+		assert!(client.pattern().is_some());
+	}
+}
+
+#[test]
+fn raw_updates_follow_the_diff_map() {
+	let server = server();
+	let mut client = Client::connect(server.local_addr(), &[RAW]);
+	assert_eq!((client.width, client.height), (64, 48));
+	check_updates(&mut client, RAW);
+}
+
+#[test]
+fn zrle_updates_follow_the_diff_map() {
+	let server = server();
+	let mut client = Client::connect(server.local_addr(), &[ZRLE, RAW]);
+	check_updates(&mut client, ZRLE);
+}
+
+#[test]
+fn serves_multiple_viewers() {
+	let server = server();
+	let mut raw = Client::connect(server.local_addr(), &[RAW]);
+	let mut zrle = Client::connect(server.local_addr(), &[ZRLE]);
+	assert_eq!(server.viewers(), 2);
+
+	check_updates(&mut raw, RAW);
+	check_updates(&mut zrle, ZRLE);
+
+	drop(raw);
+	drop(zrle);
+	let deadline = Instant::now() + Duration::from_secs(5);
+	while server.viewers() > 0 {
+		assert!(Instant::now() < deadline, "viewers did not disconnect");
+		std::thread::sleep(Duration::from_millis(10));
+	}
+}
+
+#[test]
+fn translates_pixel_formats() {
+	let server = server();
+	let mut client = Client::connect(server.local_addr(), &[RAW]);
+	client.set_rgb565();
+	client.request(false);
+	client.read_update();
+
+	let pixel = |x: u32| {
+		let index = (x * 3) as usize;
+		[client.rgb[index], client.rgb[index + 1], client.rgb[index + 2]]
+	};
+	// The first two bars of the test pattern are white and yellow.
+	assert_eq!(pixel(0), [0xff, 0xff, 0xff]);
+	assert_eq!(pixel(8), [0xff, 0xff, 0x00]);
+}
+
+/// Starts the session again with a smaller frame size after a number of frames.
+struct Resizing {
+	inner: SyntheticCapturer,
+	options: Option<SessionOptions>,
+	frames: u32,
+}
+
+impl Capture for Resizing {
+	fn status(&self) -> Result<Status, Error> {
+		self.inner.status()
+	}
+
+	fn start_with_options(&mut self, options: &SessionOptions) -> Result<(), Error> {
+		self.options = Some(options.clone());
+		self.inner.start_with_options(options)
+	}
+
+	fn stop(&mut self) -> Result<(), Error> {
+		self.inner.stop()
+	}
+
+	fn next_frame(&mut self, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<SystemFrameInfo<'_>, Error> {
+		self.frames += 1;
+		if self.frames == 10 {
+			let options = self.options.clone().unwrap();
+			self.inner.stop()?;
+			self.inner.start_with_options(&SessionOptions { frame_size: Some(Size { w: 32, h: 24 }), ..options })?;
+		}
+		self.inner.next_frame(capture_method, timeout)
+	}
+}
+
+fn resizing_server() -> RfbServer {
+	let create = || Ok(Resizing { inner: SyntheticCapturer::new(64, 48), options: None, frames: 0 });
+	RfbServer::bind("127.0.0.1:0", create, RfbOptions::new(30)).unwrap()
+}
+
+#[test]
+fn announces_resolution_changes() {
+	let server = resizing_server();
+	let mut client = Client::connect(server.local_addr(), &[RAW, DESKTOP_SIZE]);
+	assert_eq!((client.width, client.height), (64, 48));
+
+	client.request(false);
+	client.read_update();
+	loop {
+		client.request(true);
+		let rectangles = client.read_update();
+		if rectangles.iter().any(|(_, encoding)| *encoding == DESKTOP_SIZE) {
+			assert_eq!(rectangles, vec![
+				(Box { x: 0, y: 0, w: 32, h: 24 }, DESKTOP_SIZE),
+				(Box { x: 0, y: 0, w: 32, h: 24 }, RAW),
+			]);
+			break;
+		}
+	}
+	assert_eq!((client.width, client.height), (32, 24));
+	assert!(client.pattern().is_some());
+}
+
+#[test]
+fn keeps_viewers_that_cannot_resize() {
+	let server = resizing_server();
+	let mut client = Client::connect(server.local_addr(), &[RAW]);
+	client.request(false);
+	client.read_update();
+
+	let deadline = Instant::now() + Duration::from_secs(5);
+	loop {
+		assert!(Instant::now() < deadline, "the resized frame was not sent");
+		client.request(true);
+		let rectangles = client.read_update();
+		assert!(rectangles.iter().all(|(_, encoding)| *encoding == RAW));
+		if rectangles == [(Box { x: 0, y: 0, w: 32, h: 24 }, RAW)] {
+			break;
+		}
+	}
+
+	// The viewer keeps its size and shows the smaller frame in its top left corner.
+	assert_eq!((client.width, client.height), (64, 48));
+	let visible: Vec<u8> = client.rgb.chunks_exact(64 * 3).take(24).flat_map(|row| &row[..32 * 3]).copied().collect();
+	assert!((0..64).any(|index| SyntheticCapturer::pattern(32, 24, index) == visible));
+
+	// Later updates stay within the frame.
+	for _ in 0..3 {
+		client.request(false);
+		assert_eq!(client.read_update(), vec![(Box { x: 0, y: 0, w: 32, h: 24 }, RAW)]);
+	}
+	assert_eq!(server.viewers(), 1);
+}
+
+/// Captures frames in `format`, whatever the buffer format of the session is.
+struct IgnoresFormat {
+	inner: SyntheticCapturer,
+	format: BufferFormat,
+}
+
+impl Capture for IgnoresFormat {
+	fn status(&self) -> Result<Status, Error> {
+		self.inner.status()
+	}
+
+	fn start_with_options(&mut self, options: &SessionOptions) -> Result<(), Error> {
+		self.inner.start_with_options(&SessionOptions { buffer_format: self.format, ..options.clone() })
+	}
+
+	fn stop(&mut self) -> Result<(), Error> {
+		self.inner.stop()
+	}
+
+	fn next_frame(&mut self, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<SystemFrameInfo<'_>, Error> {
+		self.inner.next_frame(capture_method, timeout)
+	}
+}
+
+#[test]
+fn converts_frames_of_other_formats() {
+	let create = || Ok(IgnoresFormat { inner: SyntheticCapturer::new(64, 48), format: BufferFormat::Nv12 });
+	let server = RfbServer::bind("127.0.0.1:0", create, RfbOptions::new(30)).unwrap();
+	let mut client = Client::connect(server.local_addr(), &[RAW]);
+	client.request(false);
+	assert_eq!(client.read_update(), vec![(Box { x: 0, y: 0, w: 64, h: 48 }, RAW)]);
+
+	let converted = |index| {
+		let nv12 = from_rgb(&SyntheticCapturer::pattern(64, 48, index), 64, 48, BufferFormat::Nv12).unwrap();
+		to_rgb(&nv12, 64, 48, BufferFormat::Nv12).unwrap()
+	};
+	assert!((0..64).any(|index| converted(index) == client.rgb));
+}
+
+#[test]
+fn crops_frames_larger_than_the_protocol_allows() {
+	let server = RfbServer::bind("127.0.0.1:0", || Ok(SyntheticCapturer::new(70_000, 2)), RfbOptions::new(30)).unwrap();
+	let mut client = Client::connect(server.local_addr(), &[RAW]);
+	assert_eq!((client.width, client.height), (65_535, 2));
+
+	client.request(false);
+	assert_eq!(client.read_update(), vec![(Box { x: 0, y: 0, w: 65_535, h: 2 }, RAW)]);
+	let cropped = |index| -> Vec<u8> {
+		SyntheticCapturer::pattern(70_000, 2, index).chunks_exact(70_000 * 3).flat_map(|row| &row[..65_535 * 3]).copied().collect()
+	};
+	assert!((0..64).any(|index| cropped(index) == client.rgb));
+}
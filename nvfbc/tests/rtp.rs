@@ -0,0 +1,387 @@
+#![cfg(feature = "rtp")]
+
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpStream, UdpSocket};
+use std::time::Duration;
+
+use nvfbc::convert::from_rgb;
+use nvfbc::rtp::{Packetizer, RtspOptions, RtspServer, Sampling, DEFAULT_PAYLOAD_TYPE};
+use nvfbc::synthetic::SyntheticCapturer;
+use nvfbc::system::{CaptureMethod, SystemFrameInfo};
+use nvfbc::{BufferFormat, Capture, Error, SessionOptions, Size, Status};
+
+/// Reassembles frames from RFC 4175 packets.
+struct Depacketizer {
+	width: usize,
+	height: usize,
+	pgroup: usize,
+	ssrc: Option<u32>,
+	/// Extended sequence number of the next packet.
+	sequence: Option<u32>,
+	timestamp: Option<u32>,
+	frame: Vec<u8>,
+	/// Number of bytes of the current frame that were received.
+	received: usize,
+}
+
+impl Depacketizer {
+	fn new(width: u32, height: u32, sampling: Sampling) -> Self {
+		let (width, height, pgroup) = (width as usize, height as usize, sampling.pgroup());
+		Self { width, height, pgroup, ssrc: None, sequence: None, timestamp: None, frame: vec![0; width * height * pgroup], received: 0 }
+	}
+
+	/// Add a packet, returning the timestamp and pixel groups of a frame when its last packet was added.
+	fn push(&mut self, packet: &[u8]) -> Option<(u32, Vec<u8>)> {
+		assert_eq!(packet[0], 0x80);
+		let marker = packet[1] & 0x80 != 0;
+		assert_eq!(packet[1] & 0x7f, DEFAULT_PAYLOAD_TYPE);
+		let timestamp = u32::from_be_bytes(packet[4..8].try_into().unwrap());
+		let ssrc = u32::from_be_bytes(packet[8..12].try_into().unwrap());
+		assert_eq!(*self.ssrc.get_or_insert(ssrc), ssrc);
+
+		let sequence = (u16::from_be_bytes(packet[12..14].try_into().unwrap()) as u32) << 16
+			| u16::from_be_bytes(packet[2..4].try_into().unwrap()) as u32;
+		if let Some(expected) = self.sequence {
+			assert_eq!(sequence, expected, "packets are not consecutive");
+		}
+		self.sequence = Some(sequence.wrapping_add(1));
+		if let Some(frame_timestamp) = self.timestamp {
+			assert_eq!(timestamp, frame_timestamp, "packets of a frame have different timestamps");
+		}
+		self.timestamp = Some(timestamp);
+
+		// Read segment headers until one without the continuation bit.
+		let mut segments = Vec::new();
+		let mut position = 14;
+		loop {
+			let header = &packet[position..position + 6];
+			let length = u16::from_be_bytes([header[0], header[1]]) as usize;
+			let line = (u16::from_be_bytes([header[2], header[3]]) & 0x7fff) as usize;
+			let offset = u16::from_be_bytes([header[4], header[5]]);
+			segments.push((length, line, (offset & 0x7fff) as usize));
+			position += 6;
+			if offset & 0x8000 == 0 {
+				break;
+			}
+		}
+		for (length, line, offset) in segments {
+			assert_eq!(length % self.pgroup, 0);
+			assert!(line < self.height && offset * self.pgroup + length <= self.width * self.pgroup);
+			let start = (line * self.width + offset) * self.pgroup;
+			self.frame[start..start + length].copy_from_slice(&packet[position..position + length]);
+			position += length;
+			self.received += length;
+		}
+		assert_eq!(position, packet.len());
+
+		if !marker {
+			return None;
+		}
+		assert_eq!(self.received, self.frame.len(), "the frame is incomplete");
+		self.received = 0;
+		Some((self.timestamp.take().unwrap(), self.frame.clone()))
+	}
+}
+
+/// Convert the pixel groups of a frame to the buffer of `format` as captured.
+fn to_buffer(pgroups: &[u8], format: BufferFormat) -> Vec<u8> {
+	if format != BufferFormat::Yuv444p {
+		return pgroups.to_vec();
+	}
+	let mut planes = [Vec::new(), Vec::new(), Vec::new()];
+	for pixel in pgroups.chunks_exact(3) {
+		planes[0].push(pixel[1]);
+		planes[1].push(pixel[0]);
+		planes[2].push(pixel[2]);
+	}
+	planes.concat()
+}
+
+/// Whether `buffer` is one of the frames generated by the synthetic capturer.
+fn is_pattern(buffer: &[u8], width: u32, height: u32, format: BufferFormat) -> bool {
+	(0..64).any(|index| from_rgb(&SyntheticCapturer::pattern(width, height, index), width, height, format).unwrap() == buffer)
+}
+
+#[test]
+fn packetized_frames_can_be_reassembled() {
+	for format in [BufferFormat::Rgb, BufferFormat::Rgba, BufferFormat::Yuv444p] {
+		let sampling = Sampling::for_format(format).unwrap();
+		assert_eq!(sampling.buffer_format(), format);
+
+		// Small packets split lines, large packets hold several lines.
+		for max_packet_size in [100, 1500] {
+			let mut capturer = SyntheticCapturer::new(64, 48);
+			capturer.start_with_options(&SessionOptions::new(format, 100)).unwrap();
+			let mut packetizer = Packetizer::new(sampling, 1234, max_packet_size);
+			let mut depacketizer = Depacketizer::new(64, 48, sampling);
+
+			let mut timestamps = Vec::new();
+			for _ in 0..2 {
+				let frame = capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+				let mut frames = Vec::new();
+				let mut packets = 0;
+				packetizer.packetize(&frame, |packet| {
+					assert!(packet.len() <= max_packet_size);
+					assert!(frames.is_empty(), "the marker bit was set before the last packet");
+					frames.extend(depacketizer.push(packet));
+					packets += 1;
+					Ok(())
+				}).unwrap();
+
+				assert_eq!(frames.len(), 1);
+				let (timestamp, pgroups) = frames.pop().unwrap();
+				assert_eq!(timestamp, packetizer.rtp_timestamp(frame.timestamp_us));
+				assert_eq!(to_buffer(&pgroups, format), frame.buffer);
+				assert!(is_pattern(frame.buffer, 64, 48, format));
+				assert_eq!(packets > 48, max_packet_size == 100);
+				timestamps.push((frame.timestamp_us, timestamp));
+			}
+
+			// The 90 kHz RTP clock follows the grab timestamps.
+			let elapsed_us = timestamps[1].0 - timestamps[0].0;
+			assert_eq!(timestamps[1].1.wrapping_sub(timestamps[0].1) as u64, elapsed_us * 9 / 100);
+		}
+	}
+}
+
+#[test]
+fn packetizer_rejects_other_formats() {
+	let mut capturer = SyntheticCapturer::new(64, 48);
+	capturer.start_with_options(&SessionOptions::new(BufferFormat::Bgra, 100)).unwrap();
+	let frame = capturer.next_frame(CaptureMethod::NoWait, None).unwrap();
+	assert_eq!(Sampling::for_format(BufferFormat::Bgra), None);
+
+	let result = Packetizer::new(Sampling::Rgba, 1, 1400).packetize(&frame, |_| Ok(()));
+	assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidInput);
+
+	capturer.stop().unwrap();
+	capturer.start_with_options(&SessionOptions::new(BufferFormat::Rgba, 100)).unwrap();
+	let frame = capturer.next_frame(CaptureMethod::NoWait, None).unwrap();
+	let result = Packetizer::new(Sampling::Rgba, 1, 20).packetize(&frame, |_| Ok(()));
+	assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidInput);
+
+	assert!(RtspServer::bind("127.0.0.1:0", || Ok(SyntheticCapturer::new(64, 48)), RtspOptions::new(BufferFormat::Nv12, 25)).is_err());
+}
+
+/// A minimal RTSP client.
+struct Client {
+	reader: BufReader<TcpStream>,
+	cseq: u32,
+}
+
+/// An RTSP response.
+struct Response {
+	status: u16,
+	headers: Vec<(String, String)>,
+	body: String,
+}
+
+impl Response {
+	fn header(&self, name: &str) -> &str {
+		self.headers.iter()
+			.find(|(key, _)| key.eq_ignore_ascii_case(name))
+			.map(|(_, value)| value.as_str())
+			.unwrap_or_else(|| panic!("missing {} header", name))
+	}
+}
+
+impl Client {
+	fn connect(addr: SocketAddr) -> Self {
+		let stream = TcpStream::connect(addr).unwrap();
+		stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+		Self { reader: BufReader::new(stream), cseq: 0 }
+	}
+
+	fn request(&mut self, method: &str, uri: &str, headers: &[(&str, &str)]) -> Response {
+		self.cseq += 1;
+		let mut request = format!("{} {} RTSP/1.0\r\nCSeq: {}\r\n", method, uri, self.cseq);
+		for (key, value) in headers {
+			request.push_str(&format!("{}: {}\r\n", key, value));
+		}
+		request.push_str("\r\n");
+		self.reader.get_mut().write_all(request.as_bytes()).unwrap();
+
+		let mut line = String::new();
+		self.reader.read_line(&mut line).unwrap();
+		let status = line.strip_prefix("RTSP/1.0 ").unwrap()[..3].parse().unwrap();
+		let mut headers = Vec::new();
+		loop {
+			line.clear();
+			self.reader.read_line(&mut line).unwrap();
+			let Some((key, value)) = line.trim_end().split_once(':') else {
+				break;
+			};
+			headers.push((key.trim().to_string(), value.trim().to_string()));
+		}
+		let mut response = Response { status, headers, body: String::new() };
+		assert_eq!(response.header("CSeq"), self.cseq.to_string());
+		let length = response.header("Content-Length").parse().unwrap();
+		let mut body = vec![0; length];
+		self.reader.read_exact(&mut body).unwrap();
+		response.body = String::from_utf8(body).unwrap();
+		response
+	}
+}
+
+/// Set up and play the stream, returning the socket receiving RTP packets and the session identifier.
+fn play(client: &mut Client, url: &str) -> (UdpSocket, String) {
+	let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+	socket.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+	let port = socket.local_addr().unwrap().port();
+	let transport = format!("RTP/AVP;unicast;client_port={}-{}", port, port + 1);
+	let response = client.request("SETUP", &format!("{}stream", url), &[("Transport", &transport)]);
+	assert_eq!(response.status, 200);
+	assert!(response.header("Transport").contains(&format!("client_port={}-{}", port, port + 1)));
+	assert!(response.header("Transport").contains("server_port="));
+	let session = response.header("Session").split(';').next().unwrap().to_string();
+
+	let response = client.request("PLAY", url, &[("Session", &session)]);
+	assert_eq!(response.status, 200);
+	assert!(response.header("RTP-Info").contains("seq="));
+	(socket, session)
+}
+
+#[test]
+fn streams_over_rtsp() {
+	for format in [BufferFormat::Rgb, BufferFormat::Rgba, BufferFormat::Yuv444p] {
+		let sampling = Sampling::for_format(format).unwrap();
+		let server = RtspServer::bind("127.0.0.1:0", || Ok(SyntheticCapturer::new(64, 48)), RtspOptions::new(format, 25)).unwrap();
+		let url = format!("rtsp://{}/", server.local_addr());
+		let mut client = Client::connect(server.local_addr());
+
+		let response = client.request("OPTIONS", &url, &[]);
+		assert_eq!(response.status, 200);
+		assert!(response.header("Public").contains("DESCRIBE"));
+
+		let response = client.request("DESCRIBE", &url, &[("Accept", "application/sdp")]);
+		assert_eq!(response.status, 200);
+		assert_eq!(response.header("Content-Type"), "application/sdp");
+		assert_eq!(response.header("Content-Base"), url);
+		assert!(response.body.starts_with("v=0\r\n"));
+		assert!(response.body.contains("a=rtpmap:96 raw/90000\r\n"));
+		assert!(response.body.contains(&format!("sampling={}; width=64; height=48; depth=8;", sampling.name())));
+		assert!(response.body.contains("exactframerate=25"));
+		assert!(response.body.contains("a=control:stream\r\n"));
+
+		let (socket, session) = play(&mut client, &url);
+		assert_eq!(server.sessions(), 1);
+
+		let mut depacketizer = Depacketizer::new(64, 48, sampling);
+		let mut frames = Vec::new();
+		let mut packet = [0; 2048];
+		while frames.len() < 3 {
+			let len = socket.recv(&mut packet).unwrap();
+			frames.extend(depacketizer.push(&packet[..len]));
+		}
+		for (_, pgroups) in &frames {
+			assert!(is_pattern(&to_buffer(pgroups, format), 64, 48, format));
+		}
+		// Frames are grabbed every 40ms, which is 3600 ticks of the RTP clock.
+		for pair in frames.windows(2) {
+			let elapsed = pair[1].0.wrapping_sub(pair[0].0);
+			assert!(elapsed > 0 && elapsed % 3600 == 0, "unexpected timestamp difference {}", elapsed);
+		}
+
+		let response = client.request("TEARDOWN", &url, &[("Session", &session)]);
+		assert_eq!(response.status, 200);
+		assert_eq!(server.sessions(), 0);
+		let response = client.request("PLAY", &url, &[("Session", &session)]);
+		assert_eq!(response.status, 454);
+	}
+}
+
+#[test]
+fn removes_sessions_of_disconnected_clients() {
+	let server = RtspServer::bind("127.0.0.1:0", || Ok(SyntheticCapturer::new(64, 48)), RtspOptions::new(BufferFormat::Rgb, 25)).unwrap();
+	let url = format!("rtsp://{}/", server.local_addr());
+
+	let mut first = Client::connect(server.local_addr());
+	let mut second = Client::connect(server.local_addr());
+	let _first_stream = play(&mut first, &url);
+	let _second_stream = play(&mut second, &url);
+	assert_eq!(server.sessions(), 2);
+	assert_eq!(first.request("GET_PARAMETER", &url, &[]).status, 501);
+
+	drop(first);
+	for _ in 0..50 {
+		if server.sessions() == 1 {
+			break;
+		}
+		std::thread::sleep(Duration::from_millis(20));
+	}
+	assert_eq!(server.sessions(), 1);
+}
+
+/// Starts the session again with a smaller frame size after a number of frames.
+struct Resizing {
+	inner: SyntheticCapturer,
+	options: Option<SessionOptions>,
+	frames: u32,
+}
+
+impl Capture for Resizing {
+	fn status(&self) -> Result<Status, Error> {
+		self.inner.status()
+	}
+
+	fn start_with_options(&mut self, options: &SessionOptions) -> Result<(), Error> {
+		self.options = Some(options.clone());
+		self.inner.start_with_options(options)
+	}
+
+	fn stop(&mut self) -> Result<(), Error> {
+		self.inner.stop()
+	}
+
+	fn next_frame(&mut self, capture_method: CaptureMethod, timeout: Option<Duration>) -> Result<SystemFrameInfo<'_>, Error> {
+		self.frames += 1;
+		if self.frames == 25 {
+			let options = self.options.clone().unwrap();
+			self.inner.stop()?;
+			self.inner.start_with_options(&SessionOptions { frame_size: Some(Size { w: 32, h: 24 }), ..options })?;
+		}
+		self.inner.next_frame(capture_method, timeout)
+	}
+}
+
+#[test]
+fn ends_sessions_when_the_frame_size_changes() {
+	let create = || Ok(Resizing { inner: SyntheticCapturer::new(64, 48), options: None, frames: 0 });
+	let server = RtspServer::bind("127.0.0.1:0", create, RtspOptions::new(BufferFormat::Rgb, 25)).unwrap();
+	let url = format!("rtsp://{}/", server.local_addr());
+	let mut client = Client::connect(server.local_addr());
+
+	let rtp = UdpSocket::bind("127.0.0.1:0").unwrap();
+	let rtcp = UdpSocket::bind("127.0.0.1:0").unwrap();
+	rtcp.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+	let transport = format!("RTP/AVP;unicast;client_port={}-{}", rtp.local_addr().unwrap().port(), rtcp.local_addr().unwrap().port());
+	let response = client.request("SETUP", &format!("{}stream", url), &[("Transport", &transport)]);
+	assert_eq!(response.status, 200);
+	let ssrc = response.header("Transport").split(';').find_map(|parameter| parameter.strip_prefix("ssrc=")).unwrap();
+	let ssrc = u32::from_str_radix(ssrc, 16).unwrap();
+	let session = response.header("Session").split(';').next().unwrap().to_string();
+	assert_eq!(client.request("PLAY", &url, &[("Session", &session)]).status, 200);
+
+	// The session ends with an RTCP BYE once the frames are 32x24 instead of the 64x48 announced in the SDP.
+	let mut packet = [0; 64];
+	let len = rtcp.recv(&mut packet).unwrap();
+	let mut bye = vec![0x81, 203, 0, 1];
+	bye.extend_from_slice(&ssrc.to_be_bytes());
+	assert_eq!(&packet[..len], bye);
+	assert_eq!(server.sessions(), 0);
+	assert_eq!(client.request("PLAY", &url, &[("Session", &session)]).status, 454);
+
+	// A new session streams frames of the new size.
+	let response = client.request("DESCRIBE", &url, &[]);
+	assert!(response.body.contains("width=32; height=24;"));
+	let (socket, _) = play(&mut client, &url);
+	let mut depacketizer = Depacketizer::new(32, 24, Sampling::Rgb);
+	let mut packet = [0; 2048];
+	loop {
+		let len = socket.recv(&mut packet).unwrap();
+		if let Some((_, pgroups)) = depacketizer.push(&packet[..len]) {
+			assert!(is_pattern(&pgroups, 32, 24, BufferFormat::Rgb));
+			break;
+		}
+	}
+}
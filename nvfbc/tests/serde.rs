@@ -0,0 +1,71 @@
+#![cfg(feature = "serde")]
+
+use nvfbc::{Box, BufferFormat, Output, SessionOptions, Size, Status, Tracking, Version};
+use serde_json::json;
+
+#[test]
+fn status_json_shape() {
+	let status = Status {
+		is_capture_possible: true,
+		currently_capturing: false,
+		can_create_now: true,
+		screen_size: Size { w: 3840, h: 1080 },
+		xrandr_available: true,
+		outputs: vec![Output { id: 452, name: "DP-0".to_string(), tracked_box: Box { x: 1920, y: 0, w: 1920, h: 1080 } }],
+		nvfbc_version: Version { major: 1, minor: 8 },
+		in_modeset: false,
+	};
+	let expected = json!({
+		"is_capture_possible": true,
+		"currently_capturing": false,
+		"can_create_now": true,
+		"screen_size": { "w": 3840, "h": 1080 },
+		"xrandr_available": true,
+		"outputs": [{ "id": 452, "name": "DP-0", "tracked_box": { "x": 1920, "y": 0, "w": 1920, "h": 1080 } }],
+		"nvfbc_version": { "major": 1, "minor": 8 },
+		"in_modeset": false,
+	});
+	assert_eq!(serde_json::to_value(&status).unwrap(), expected);
+	assert_eq!(serde_json::from_value::<Status>(expected).unwrap(), status);
+}
+
+#[test]
+fn session_options_json_shape() {
+	let options = SessionOptions {
+		tracking: Tracking::Output(452),
+		capture_box: Some(Box { x: 8, y: 4, w: 640, h: 480 }),
+		frame_size: Some(Size { w: 320, h: 240 }),
+		with_cursor: false,
+		diff_map_scaling_factor: Some(16),
+		..SessionOptions::new(BufferFormat::Yuv444p, 30)
+	};
+	let value = serde_json::to_value(&options).unwrap();
+	assert_eq!(value, json!({
+		"buffer_format": "yuv444p",
+		"fps": 30,
+		"tracking": { "output": 452 },
+		"capture_box": { "x": 8, "y": 4, "w": 640, "h": 480 },
+		"frame_size": { "w": 320, "h": 240 },
+		"with_cursor": false,
+		"diff_map_scaling_factor": 16,
+	}));
+	let parsed: SessionOptions = serde_json::from_value(value.clone()).unwrap();
+	assert_eq!(serde_json::to_value(&parsed).unwrap(), value);
+
+	let defaults = serde_json::to_value(SessionOptions::new(BufferFormat::Nv12, 60)).unwrap();
+	assert_eq!(defaults, json!({
+		"buffer_format": "nv12",
+		"fps": 60,
+		"tracking": "default",
+		"capture_box": null,
+		"frame_size": null,
+		"with_cursor": true,
+		"diff_map_scaling_factor": null,
+	}));
+
+	// Only the buffer format and frame rate are required.
+	let parsed: SessionOptions = serde_json::from_value(json!({ "buffer_format": "nv12", "fps": 60 })).unwrap();
+	assert_eq!(serde_json::to_value(&parsed).unwrap(), defaults);
+	let screen: SessionOptions = serde_json::from_value(json!({ "buffer_format": "bgra", "fps": 1, "tracking": "screen" })).unwrap();
+	assert_eq!(screen.tracking, Tracking::Screen);
+}
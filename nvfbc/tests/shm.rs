@@ -0,0 +1,200 @@
+#![cfg(feature = "shm")]
+
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use nvfbc::convert::from_rgb;
+use nvfbc::shm::{FramePublisher, FrameSubscriber};
+use nvfbc::synthetic::SyntheticCapturer;
+use nvfbc::system::{CaptureMethod, SystemFrameInfo};
+use nvfbc::{BufferFormat, Capture, SessionOptions};
+
+/// Environment variable with the path of the ring opened by the subscribing process.
+const RING_VARIABLE: &str = "NVFBC_SHM_TEST_RING";
+
+/// Line printed by the subscribing process after it received frames of the first layout.
+const RESIZE_LINE: &str = "nvfbc-shm-test: resize";
+
+fn capturer(width: u32, height: u32, format: BufferFormat) -> SyntheticCapturer {
+	let mut capturer = SyntheticCapturer::new(width, height);
+	capturer.start_with_options(&SessionOptions::new(format, 200)).unwrap();
+	capturer
+}
+
+/// Whether `frame` is one of the frames generated by the synthetic capturer.
+fn is_pattern(frame: &SystemFrameInfo) -> bool {
+	(0..64).any(|index| {
+		let rgb = SyntheticCapturer::pattern(frame.width, frame.height, index);
+		from_rgb(&rgb, frame.width, frame.height, frame.buffer_format).unwrap() == frame.buffer
+	})
+}
+
+#[test]
+fn subscriber_reads_the_latest_frame() {
+	let mut publisher = FramePublisher::new("nvfbc-test", 3).unwrap();
+	let mut subscriber = FrameSubscriber::open(publisher.path()).unwrap();
+	assert_eq!(subscriber.slot_count(), 3);
+	assert!(subscriber.latest().unwrap().is_none());
+	assert!(subscriber.wait_for_frame(Some(Duration::from_millis(10))).unwrap().is_none());
+
+	// Publish more frames than there are slots.
+	let mut small = capturer(64, 48, BufferFormat::Rgb);
+	let mut last = None;
+	for _ in 0..5 {
+		let frame = small.next_frame(CaptureMethod::Blocking, None).unwrap();
+		publisher.publish(&frame).unwrap();
+		last = Some((frame.grab_info(), frame.buffer.to_vec()));
+	}
+	assert_eq!(publisher.published(), 5);
+	assert_eq!(subscriber.published(), 5);
+
+	let (info, buffer) = last.unwrap();
+	let frame = subscriber.wait_for_frame(Some(Duration::ZERO)).unwrap().unwrap();
+	assert_eq!(frame.grab_info(), info);
+	assert_eq!(frame.buffer_format, BufferFormat::Rgb);
+	assert_eq!(frame.buffer, buffer);
+	assert!(frame.diff_map.is_none());
+	assert!(subscriber.wait_for_frame(Some(Duration::from_millis(10))).unwrap().is_none());
+	assert!(subscriber.latest().unwrap().is_some());
+
+	// Larger frames in another format grow the ring, smaller frames reuse it.
+	let mut large = capturer(256, 192, BufferFormat::Yuv444p);
+	let frame = large.next_frame(CaptureMethod::Blocking, None).unwrap();
+	publisher.publish(&frame).unwrap();
+	let frame = subscriber.wait_for_frame(Some(Duration::ZERO)).unwrap().unwrap();
+	assert_eq!((frame.width, frame.height, frame.buffer_format), (256, 192, BufferFormat::Yuv444p));
+	assert!(is_pattern(&frame));
+
+	let frame = small.next_frame(CaptureMethod::Blocking, None).unwrap();
+	publisher.publish(&frame).unwrap();
+	let frame = subscriber.latest().unwrap().unwrap();
+	assert_eq!((frame.width, frame.height, frame.buffer_format), (64, 48, BufferFormat::Rgb));
+	assert!(is_pattern(&frame));
+
+	assert!(FramePublisher::new("nvfbc-test", 1).is_err());
+	assert!(FrameSubscriber::open("/dev/null").is_err());
+}
+
+/// A frame of `width` x `height` RGB pixels with the data in `buffer`.
+fn filled_frame(buffer: &[u8], width: u32, height: u32, current_frame: u32) -> SystemFrameInfo<'_> {
+	SystemFrameInfo {
+		buffer,
+		width,
+		height,
+		buffer_format: BufferFormat::Rgb,
+		current_frame,
+		is_new_frame: true,
+		timestamp_us: current_frame as u64,
+		missed_frames: 0,
+		diff_map: None,
+	}
+}
+
+#[test]
+fn shrinking_layout_over_frame_data() {
+	let mut publisher = FramePublisher::new("nvfbc-test", 2).unwrap();
+	let mut subscriber = FrameSubscriber::open(publisher.path()).unwrap();
+
+	// The second slot of the smaller layout starts within the data of the first frame, where every byte is odd.
+	let large = vec![0x01; 40 * 40 * 3];
+	publisher.publish(&filled_frame(&large, 40, 40, 0)).unwrap();
+	let small = vec![0x02; 10 * 10 * 3];
+	publisher.publish(&filled_frame(&small, 10, 10, 1)).unwrap();
+
+	let frame = subscriber.wait_for_frame(Some(Duration::from_millis(100))).unwrap().unwrap();
+	assert_eq!((frame.width, frame.height, frame.current_frame), (10, 10, 1));
+	assert_eq!(frame.buffer, small);
+
+	publisher.publish(&filled_frame(&small, 10, 10, 2)).unwrap();
+	let frame = subscriber.wait_for_frame(Some(Duration::from_millis(100))).unwrap().unwrap();
+	assert_eq!(frame.current_frame, 2);
+}
+
+#[test]
+fn subscriber_in_another_process() {
+	let mut publisher = FramePublisher::new("nvfbc-test", 4).unwrap();
+	let path = publisher.path();
+	let resize = Arc::new(AtomicBool::new(false));
+	let done = Arc::new(AtomicBool::new(false));
+
+	let thread = std::thread::spawn({
+		let (resize, done) = (resize.clone(), done.clone());
+		move || {
+			let mut capturer = capturer(64, 48, BufferFormat::Rgb);
+			let mut resized = false;
+			while !done.load(Ordering::Relaxed) {
+				if !resized && resize.load(Ordering::Relaxed) {
+					capturer = self::capturer(32, 24, BufferFormat::Bgra);
+					resized = true;
+				}
+				let frame = capturer.next_frame(CaptureMethod::Blocking, None).unwrap();
+				publisher.publish(&frame).unwrap();
+			}
+		}
+	});
+
+	let mut child = Command::new(std::env::current_exe().unwrap())
+		.args(["subscriber_process", "--exact", "--nocapture", "--test-threads=1"])
+		.env(RING_VARIABLE, &path)
+		.stdout(Stdio::piped())
+		.spawn()
+		.unwrap();
+	let mut output = String::new();
+	for line in BufReader::new(child.stdout.take().unwrap()).lines() {
+		let line = line.unwrap();
+		// The test harness prints the name of the test on the same line as the first output.
+		if line.ends_with(RESIZE_LINE) {
+			resize.store(true, Ordering::Relaxed);
+		}
+		output.push_str(&line);
+		output.push('\n');
+	}
+	let status = child.wait().unwrap();
+	done.store(true, Ordering::Relaxed);
+	thread.join().unwrap();
+
+	assert!(status.success(), "subscribing process failed:\n{}", output);
+	assert!(output.contains(RESIZE_LINE) && output.contains("1 passed"), "subscribing process did not run:\n{}", output);
+}
+
+/// Runs in the process spawned by `subscriber_in_another_process`.
+#[test]
+fn subscriber_process() {
+	let Some(path) = std::env::var_os(RING_VARIABLE) else {
+		return;
+	};
+	let mut subscriber = FrameSubscriber::open(path).unwrap();
+
+	let mut layouts = [((64, 48), BufferFormat::Rgb), ((32, 24), BufferFormat::Bgra)].into_iter();
+	let mut layout = layouts.next().unwrap();
+	let mut received = 0;
+	let mut current_frame = None;
+	loop {
+		let frame = subscriber.wait_for_frame(Some(Duration::from_secs(5))).unwrap().expect("no frame was published");
+		assert!(is_pattern(&frame), "frame {} is inconsistent", frame.current_frame);
+		if ((frame.width, frame.height), frame.buffer_format) != layout {
+			// Frames of the first layout may still be published shortly after the resize was requested.
+			assert_eq!(((frame.width, frame.height), frame.buffer_format), ((64, 48), BufferFormat::Rgb));
+			continue;
+		}
+		assert!(current_frame.is_none_or(|current| frame.current_frame > current));
+		current_frame = Some(frame.current_frame);
+
+		received += 1;
+		if received < 10 {
+			continue;
+		}
+		match layouts.next() {
+			Some(next) => {
+				println!("{}", RESIZE_LINE);
+				layout = next;
+				received = 0;
+				current_frame = None;
+			},
+			None => break,
+		}
+	}
+}
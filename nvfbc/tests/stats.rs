@@ -0,0 +1,101 @@
+use std::time::{Duration, Instant};
+
+use nvfbc::stats::{GrabOutcome, StatsCapturer, StatsCollector};
+use nvfbc::synthetic::SyntheticCapturer;
+use nvfbc::system::CaptureMethod;
+use nvfbc::{BufferFormat, Capture, SessionOptions};
+
+fn frame(is_new_frame: bool, missed_frames: u32) -> GrabOutcome {
+	GrabOutcome::Frame { is_new_frame, missed_frames, timed_out: false }
+}
+
+#[test]
+fn windowed_statistics() {
+	let start = Instant::now();
+	let mut collector = StatsCollector::starting_at(Duration::from_secs(1), start);
+
+	// 100 grabs over the first second: 60 new frames, 30 repeats, 10 timeouts.
+	for i in 0..100u32 {
+		let at = start + Duration::from_millis(10 * i as u64 + 10);
+		let outcome = match i % 10 {
+			0 => GrabOutcome::Frame { is_new_frame: false, missed_frames: 0, timed_out: true },
+			1..=3 => frame(false, 0),
+			_ => frame(true, 1),
+		};
+		collector.record(at, Duration::from_micros(i as u64 + 1), outcome);
+	}
+
+	let snapshot = collector.snapshot_at(start + Duration::from_secs(1));
+	assert_eq!(snapshot.window, Duration::from_secs(1));
+	assert_eq!(snapshot.grabs, 100);
+	assert_eq!(snapshot.new_frames, 60);
+	assert_eq!(snapshot.repeats, 40);
+	assert_eq!(snapshot.fps, 60.0);
+	assert_eq!(snapshot.new_frame_ratio, 0.6);
+	assert_eq!(snapshot.latency_p50, Duration::from_micros(51));
+	assert_eq!(snapshot.latency_p99, Duration::from_micros(100));
+	assert_eq!(snapshot.missed_frames, 60);
+	assert_eq!(snapshot.timeouts, 10);
+
+	// Half a second later, only the last half of the grabs is in the window, but totals are kept.
+	let snapshot = collector.snapshot_at(start + Duration::from_millis(1505));
+	assert_eq!(snapshot.grabs, 50);
+	assert_eq!(snapshot.new_frames, 30);
+	assert_eq!(snapshot.total_grabs, 100);
+	assert_eq!(snapshot.missed_frames, 60);
+}
+
+#[test]
+fn first_window_uses_elapsed_time() {
+	let start = Instant::now();
+	let mut collector = StatsCollector::starting_at(Duration::from_secs(10), start);
+	for i in 1..=30 {
+		collector.record(start + Duration::from_millis(i * 10), Duration::ZERO, frame(true, 0));
+	}
+
+	let snapshot = collector.snapshot_at(start + Duration::from_millis(500));
+	assert_eq!(snapshot.window, Duration::from_millis(500));
+	assert_eq!(snapshot.fps, 60.0);
+
+	let empty = StatsCollector::starting_at(Duration::from_secs(10), start).snapshot_at(start);
+	assert_eq!(empty.grabs, 0);
+	assert_eq!(empty.fps, 0.0);
+	assert_eq!(empty.new_frame_ratio, 0.0);
+	assert_eq!(empty.latency_p99, Duration::ZERO);
+}
+
+#[test]
+fn errors_are_counted_by_code() {
+	let start = Instant::now();
+	let mut collector = StatsCollector::starting_at(Duration::from_secs(1), start);
+	collector.record(start, Duration::ZERO, GrabOutcome::Error(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_MUST_RECREATE));
+	collector.record(start, Duration::ZERO, GrabOutcome::Error(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_MUST_RECREATE));
+	collector.record(start, Duration::ZERO, GrabOutcome::Error(nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_X));
+
+	let snapshot = collector.snapshot_at(start);
+	assert_eq!(snapshot.errors.get(&nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_MUST_RECREATE), Some(&2));
+	assert_eq!(snapshot.errors.get(&nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_X), Some(&1));
+	assert_eq!(snapshot.grabs, 3);
+	assert_eq!(snapshot.new_frames + snapshot.repeats, 0);
+}
+
+#[test]
+fn wraps_a_capturer() {
+	let mut capturer = StatsCapturer::new(SyntheticCapturer::new(64, 48));
+	let handle = capturer.handle();
+	assert!(capturer.next_frame(CaptureMethod::NoWait, None).is_err());
+
+	capturer.start_with_options(&SessionOptions::new(BufferFormat::Rgb, 100)).unwrap();
+	for _ in 0..3 {
+		assert!(capturer.next_frame(CaptureMethod::Blocking, None).unwrap().is_new_frame);
+	}
+	let frame = capturer.next_frame(CaptureMethod::Blocking, Some(Duration::from_millis(1))).unwrap();
+	assert!(!frame.is_new_frame);
+
+	let snapshot = std::thread::spawn(move || handle.snapshot()).join().unwrap();
+	assert_eq!(snapshot.total_grabs, 5);
+	assert_eq!(snapshot.new_frames, 3);
+	assert_eq!(snapshot.repeats, 1);
+	assert_eq!(snapshot.timeouts, 1);
+	assert_eq!(snapshot.errors.get(&nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_BAD_REQUEST), Some(&1));
+}
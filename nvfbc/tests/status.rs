@@ -0,0 +1,139 @@
+use std::mem::MaybeUninit;
+
+use nvfbc::{Status, StatusError};
+use nvfbc_sys::_NVFBC_GET_STATUS_PARAMS as RawStatus;
+
+/// Small deterministic PRNG, so failures are reproducible.
+struct XorShift(u64);
+
+impl XorShift {
+	fn next(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+}
+
+fn zeroed() -> RawStatus {
+	unsafe { MaybeUninit::zeroed().assume_init() }
+}
+
+fn random_status(rng: &mut XorShift) -> RawStatus {
+	let mut status = zeroed();
+	let bytes = unsafe {
+		std::slice::from_raw_parts_mut(&mut status as *mut RawStatus as *mut u8, std::mem::size_of::<RawStatus>())
+	};
+	for byte in bytes.iter_mut() {
+		*byte = rng.next() as u8;
+	}
+
+	// Make plausible values likely, so that the success path is exercised as well.
+	if rng.next() & 1 == 0 {
+		status.dwOutputNum %= nvfbc_sys::NVFBC_OUTPUT_MAX + 1;
+		status.screenSize.w = u32::MAX;
+		status.screenSize.h = u32::MAX;
+		for output in status.outputs.iter_mut() {
+			output.trackedBox.x %= 1 << 16;
+			output.trackedBox.y %= 1 << 16;
+			output.trackedBox.w %= 1 << 16;
+			output.trackedBox.h %= 1 << 16;
+		}
+	}
+
+	status
+}
+
+fn set_name(status: &mut RawStatus, index: usize, name: &[u8]) {
+	for (dst, &src) in status.outputs[index].name.iter_mut().zip(name) {
+		*dst = src as _;
+	}
+}
+
+#[test]
+fn arbitrary_raw_status_never_panics() {
+	let mut rng = XorShift(0x9E3779B97F4A7C15);
+	let mut accepted = 0;
+	for _ in 0..10_000 {
+		let raw = random_status(&mut rng);
+		match Status::try_from(raw) {
+			Ok(status) => {
+				accepted += 1;
+				assert_eq!(status.outputs.len(), raw.dwOutputNum as usize);
+				for output in &status.outputs {
+					assert!(!output.name.contains('\0'));
+					assert!(output.tracked_box.x as u64 + output.tracked_box.w as u64 <= status.screen_size.w as u64);
+					assert!(output.tracked_box.y as u64 + output.tracked_box.h as u64 <= status.screen_size.h as u64);
+				}
+			},
+			Err(StatusError::TooManyOutputs { count, max }) => assert!(count > max),
+			Err(StatusError::TrackedBoxOutOfBounds { .. }) => {},
+		}
+	}
+	assert!(accepted > 0);
+}
+
+#[test]
+fn too_many_outputs() {
+	let mut raw = zeroed();
+	raw.dwOutputNum = nvfbc_sys::NVFBC_OUTPUT_MAX + 1;
+	assert_eq!(
+		Status::try_from(raw),
+		Err(StatusError::TooManyOutputs { count: nvfbc_sys::NVFBC_OUTPUT_MAX + 1, max: nvfbc_sys::NVFBC_OUTPUT_MAX }),
+	);
+
+	raw.dwOutputNum = u32::MAX;
+	assert!(Status::try_from(raw).is_err());
+}
+
+#[test]
+fn output_names_are_decoded_lossily() {
+	let mut raw = zeroed();
+	raw.screenSize.w = 1920;
+	raw.screenSize.h = 1080;
+	raw.dwOutputNum = 2;
+	set_name(&mut raw, 0, b"DP-\xFF\xFE0");
+	set_name(&mut raw, 1, b"HDMI-0");
+
+	let status = Status::try_from(raw).unwrap();
+	assert_eq!(status.outputs[0].name, "DP-\u{FFFD}\u{FFFD}0");
+	assert_eq!(status.outputs[1].name, "HDMI-0");
+}
+
+#[test]
+fn unterminated_output_names_are_bounded() {
+	let mut raw = zeroed();
+	raw.dwOutputNum = 1;
+	set_name(&mut raw, 0, &[b'A'; nvfbc_sys::NVFBC_OUTPUT_NAME_LEN as usize]);
+	// Make sure reading past the name would find more characters.
+	raw.outputs[0].trackedBox.x = u32::from_ne_bytes(*b"BBBB");
+	raw.screenSize.w = u32::MAX;
+
+	let status = Status::try_from(raw).unwrap();
+	assert_eq!(status.outputs[0].name, "A".repeat(nvfbc_sys::NVFBC_OUTPUT_NAME_LEN as usize));
+}
+
+#[test]
+fn tracked_box_outside_screen() {
+	let mut raw = zeroed();
+	raw.screenSize.w = 1920;
+	raw.screenSize.h = 1080;
+	raw.dwOutputNum = 1;
+	raw.outputs[0].dwId = 7;
+	raw.outputs[0].trackedBox.x = 1920;
+	raw.outputs[0].trackedBox.w = 1;
+	raw.outputs[0].trackedBox.h = 1080;
+	assert!(matches!(
+		Status::try_from(raw),
+		Err(StatusError::TrackedBoxOutOfBounds { output_id: 7, .. }),
+	));
+
+	// Overflowing coordinates must not wrap around.
+	raw.outputs[0].trackedBox.x = u32::MAX;
+	raw.outputs[0].trackedBox.w = 2;
+	assert!(Status::try_from(raw).is_err());
+
+	raw.outputs[0].trackedBox.x = 0;
+	raw.outputs[0].trackedBox.w = 1920;
+	assert!(Status::try_from(raw).is_ok());
+}
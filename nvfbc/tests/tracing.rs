@@ -0,0 +1,153 @@
+#![cfg(feature = "tracing")]
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use nvfbc::system::CaptureMethod;
+use nvfbc::{BufferFormat, SystemCapturer};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing_core::span::Current;
+use tracing::{Event, Metadata, Subscriber};
+
+/// A span and the values recorded on it.
+struct RecordedSpan {
+	metadata: &'static Metadata<'static>,
+	values: HashMap<&'static str, String>,
+}
+
+#[derive(Default)]
+struct Recorded {
+	spans: Vec<RecordedSpan>,
+	/// Identifiers of the entered spans, innermost last.
+	entered: Vec<Id>,
+	/// The values of every event.
+	events: Vec<HashMap<&'static str, String>>,
+}
+
+/// A subscriber that records every span and event, to be used on a single thread.
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Recorded>>);
+
+struct Values<'a>(&'a mut HashMap<&'static str, String>);
+
+impl Visit for Values<'_> {
+	fn record_str(&mut self, field: &Field, value: &str) {
+		self.0.insert(field.name(), value.to_string());
+	}
+
+	fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+		self.0.insert(field.name(), format!("{:?}", value));
+	}
+}
+
+impl Subscriber for Recorder {
+	fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+		true
+	}
+
+	fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+		let mut recorded = self.0.lock().unwrap();
+		let mut values = HashMap::new();
+		attributes.record(&mut Values(&mut values));
+		recorded.spans.push(RecordedSpan { metadata: attributes.metadata(), values });
+		Id::from_u64(recorded.spans.len() as u64)
+	}
+
+	fn record(&self, span: &Id, values: &Record<'_>) {
+		let mut recorded = self.0.lock().unwrap();
+		values.record(&mut Values(&mut recorded.spans[span.into_u64() as usize - 1].values));
+	}
+
+	fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+	fn event(&self, event: &Event<'_>) {
+		let mut values = HashMap::new();
+		event.record(&mut Values(&mut values));
+		self.0.lock().unwrap().events.push(values);
+	}
+
+	fn enter(&self, span: &Id) {
+		self.0.lock().unwrap().entered.push(span.clone());
+	}
+
+	fn exit(&self, span: &Id) {
+		let mut recorded = self.0.lock().unwrap();
+		if let Some(index) = recorded.entered.iter().rposition(|id| id == span) {
+			recorded.entered.remove(index);
+		}
+	}
+
+	fn current_span(&self) -> Current {
+		let recorded = self.0.lock().unwrap();
+		match recorded.entered.last() {
+			Some(id) => Current::new(id.clone(), recorded.spans[id.into_u64() as usize - 1].metadata),
+			None => Current::none(),
+		}
+	}
+}
+
+/// Assert that every NvFBC call recorded its status, and the error message of failed calls.
+fn assert_statuses_recorded(recorded: &Recorded) {
+	for span in &recorded.spans {
+		let status = span.values.get("status").unwrap_or_else(|| panic!("{} did not record a status", span.metadata.name()));
+		let status: u32 = status.parse().unwrap();
+		// A rejected API version is retried with an older version instead of failing.
+		if status != nvfbc_sys::_NVFBCSTATUS_NVFBC_SUCCESS && status != nvfbc_sys::_NVFBCSTATUS_NVFBC_ERR_API_VERSION {
+			assert!(span.values.contains_key("error"), "{} failed without recording an error", span.metadata.name());
+		}
+	}
+}
+
+/// Depending on the driver, creating a capturer fails or succeeds, and the session can be created or not.
+/// Every path must emit the spans of the calls it made.
+#[test]
+fn nvfbc_calls_are_traced() {
+	let recorder = Recorder::default();
+	tracing::subscriber::with_default(recorder.clone(), || {
+		let capturer = SystemCapturer::new();
+		{
+			let recorded = recorder.0.lock().unwrap();
+			let create: Vec<_> = recorded.spans.iter().filter(|span| span.metadata.name() == "NvFBCCreateHandle").collect();
+			assert!(!create.is_empty());
+			// Every attempt names the API version it tried, starting with the compiled version.
+			assert_eq!(create[0].values["api_version"], nvfbc::Version::COMPILED.to_string());
+			assert_statuses_recorded(&recorded);
+			assert!(recorded.entered.is_empty());
+		}
+
+		let Ok(mut capturer) = capturer else {
+			return;
+		};
+		let status = capturer.status();
+		let started = capturer.start(BufferFormat::Rgb, 30);
+		let frame = started.as_ref().ok().map(|_| capturer.next_frame(CaptureMethod::NoWaitIfNewFrame, None).map(|frame| frame.width));
+		drop(capturer);
+
+		let recorded = recorder.0.lock().unwrap();
+		let names: Vec<_> = recorded.spans.iter().map(|span| span.metadata.name()).collect();
+		assert!(names.contains(&"NvFBCGetStatus"));
+		assert!(names.contains(&"NvFBCDestroyHandle"));
+		assert_statuses_recorded(&recorded);
+
+		// Failed calls also emit a warning with the status and error message.
+		let failures = recorded.spans.iter()
+			.filter(|span| span.values.contains_key("error"))
+			.count();
+		let warnings = recorded.events.iter().filter(|event| event.contains_key("error") && event.contains_key("status")).count();
+		assert_eq!(warnings, failures);
+
+		if status.is_ok() && started.is_ok() {
+			assert!(names.contains(&"NvFBCCreateCaptureSession"));
+			assert!(names.contains(&"NvFBCToSysSetUp"));
+		}
+		if let Some(Ok(width)) = frame {
+			let grab = recorded.spans.iter().find(|span| span.metadata.name() == "NvFBCToSysGrabFrame").unwrap();
+			assert_eq!(grab.values["width"], width.to_string());
+			for field in ["height", "byte_size", "current_frame", "is_new_frame", "timestamp_us", "missed_frames"] {
+				assert!(grab.values.contains_key(field), "grab span did not record {}", field);
+			}
+		}
+	});
+}
@@ -0,0 +1,51 @@
+use nvfbc::{Compatibility, Version};
+
+fn version(major: u32, minor: u32) -> Version {
+	Version { major, minor }
+}
+
+#[test]
+fn raw_round_trip() {
+	assert_eq!(Version::from_raw(0x0108), version(1, 8));
+	assert_eq!(version(1, 8).to_raw(), 0x0108);
+	assert_eq!(Version::COMPILED.to_raw(), nvfbc_sys::NVFBC_VERSION);
+	for raw in [0x0000, 0x0106, 0x0107, 0x01ff, 0x0200, 0xffff] {
+		assert_eq!(Version::from_raw(raw).to_raw(), raw);
+	}
+	assert_eq!(version(1, 7).to_string(), "1.7");
+}
+
+#[test]
+fn compatibility_classes() {
+	assert_eq!(Version::COMPILED.compatibility(), Compatibility::Full);
+	assert_eq!(version(1, 9).compatibility(), Compatibility::Full);
+	assert_eq!(version(2, 0).compatibility(), Compatibility::Full);
+	assert_eq!(version(1, 7).compatibility(), Compatibility::Fallback);
+	assert_eq!(version(1, 6).compatibility(), Compatibility::Fallback);
+	assert_eq!(version(1, 5).compatibility(), Compatibility::Unsupported);
+	assert_eq!(version(0, 9).compatibility(), Compatibility::Unsupported);
+}
+
+#[test]
+fn diagnostics() {
+	assert_eq!(Version::COMPILED.diagnostic(), None);
+	assert_eq!(
+		version(1, 7).diagnostic().unwrap(),
+		"NvFBC 1.7 is installed, which is older than NvFBC 1.8; newer features are unavailable. NvFBC 1.8 requires NVIDIA driver 470 or newer",
+	);
+	assert_eq!(
+		version(1, 5).diagnostic().unwrap(),
+		"NvFBC 1.5 is installed, which is not supported. NvFBC 1.8 requires NVIDIA driver 470 or newer",
+	);
+	assert_eq!(version(1, 6).minimum_driver(), Some("410"));
+	assert_eq!(version(1, 5).minimum_driver(), None);
+}
+
+#[test]
+fn fallback_order() {
+	assert_eq!(Version::COMPILED.fallbacks(), [version(1, 8), version(1, 7), version(1, 6)]);
+	// After negotiating an older version, only that version and older versions are tried.
+	assert_eq!(version(1, 7).fallbacks(), [version(1, 7), version(1, 6)]);
+	assert_eq!(version(1, 6).fallbacks(), [version(1, 6)]);
+	assert_eq!(version(1, 5).fallbacks(), []);
+}
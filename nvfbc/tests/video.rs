@@ -0,0 +1,163 @@
+use std::cell::RefCell;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+use nvfbc::video::{numbered_path, y4m_header, Chroma, Container, ResizePolicy, VideoWriter, WriterError, WriterOptions};
+use nvfbc::{BufferFormat, Size};
+
+/// An NV12 frame whose Y samples count up from 0, and whose U and V samples count up from 100 and 200.
+fn nv12(width: u32, height: u32) -> Vec<u8> {
+	let chroma = (width * height / 4) as usize;
+	let mut buffer: Vec<u8> = (0..width * height).map(|i| i as u8).collect();
+	for i in 0..chroma {
+		buffer.push(100 + i as u8);
+		buffer.push(200 + i as u8);
+	}
+	buffer
+}
+
+/// A YUV444P frame with Y, U and V samples filled with 1, 2 and 3.
+fn yuv444p(width: u32, height: u32) -> Vec<u8> {
+	let len = (width * height) as usize;
+	[vec![1; len], vec![2; len], vec![3; len]].concat()
+}
+
+#[test]
+fn header_fields() {
+	assert_eq!(
+		y4m_header(Size { w: 1920, h: 1080 }, Chroma::C420, 60),
+		"YUV4MPEG2 W1920 H1080 F60:1 Ip A1:1 C420jpeg XCOLORRANGE=LIMITED XCOLORMATRIX=BT709\n",
+	);
+	assert_eq!(
+		y4m_header(Size { w: 64, h: 48 }, Chroma::C444, 30),
+		"YUV4MPEG2 W64 H48 F30:1 Ip A1:1 C444 XCOLORRANGE=LIMITED XCOLORMATRIX=BT709\n",
+	);
+
+	let mut writer = VideoWriter::new(Vec::new(), WriterOptions::new(Container::Y4m, 25));
+	writer.write_buffer(&nv12(4, 2), 4, 2, BufferFormat::Nv12).unwrap();
+	writer.write_buffer(&nv12(4, 2), 4, 2, BufferFormat::Nv12).unwrap();
+	let output = writer.into_inner().unwrap();
+	let header = y4m_header(Size { w: 4, h: 2 }, Chroma::C420, 25);
+	assert!(output.starts_with(header.as_bytes()));
+	// The header is only written once, every frame starts with a FRAME line.
+	assert_eq!(output.len(), header.len() + 2 * (6 + 8 + 2 + 2));
+	assert_eq!(&output[header.len()..][..6], b"FRAME\n");
+}
+
+#[test]
+fn nv12_planes_are_split() {
+	let mut writer = VideoWriter::new(Vec::new(), WriterOptions::new(Container::Raw, 25));
+	writer.write_buffer(&nv12(4, 4), 4, 4, BufferFormat::Nv12).unwrap();
+	assert_eq!(writer.stream(), Some((Size { w: 4, h: 4 }, Chroma::C420)));
+	let output = writer.into_inner().unwrap();
+
+	let expected: Vec<u8> = (0..16).chain([100, 101, 102, 103]).chain([200, 201, 202, 203]).collect();
+	assert_eq!(output, expected);
+}
+
+#[test]
+fn nv12_chroma_is_upsampled_to_444() {
+	let options = WriterOptions { chroma: Some(Chroma::C444), ..WriterOptions::new(Container::Raw, 25) };
+	let mut writer = VideoWriter::new(Vec::new(), options);
+	writer.write_buffer(&nv12(4, 2), 4, 2, BufferFormat::Nv12).unwrap();
+	let output = writer.into_inner().unwrap();
+
+	assert_eq!(output[8..16], [100, 100, 101, 101, 100, 100, 101, 101]);
+	assert_eq!(output[16..24], [200, 200, 201, 201, 200, 200, 201, 201]);
+}
+
+#[test]
+fn yuv444p_planes_keep_their_order() {
+	let mut writer = VideoWriter::new(Vec::new(), WriterOptions::new(Container::Raw, 25));
+	writer.write_buffer(&yuv444p(4, 2), 4, 2, BufferFormat::Yuv444p).unwrap();
+	assert_eq!(writer.stream(), Some((Size { w: 4, h: 2 }, Chroma::C444)));
+	assert_eq!(writer.into_inner().unwrap(), yuv444p(4, 2));
+
+	// Subsampling averages every 2x2 block.
+	let options = WriterOptions { chroma: Some(Chroma::C420), ..WriterOptions::new(Container::Raw, 25) };
+	let mut writer = VideoWriter::new(Vec::new(), options);
+	writer.write_buffer(&yuv444p(4, 2), 4, 2, BufferFormat::Yuv444p).unwrap();
+	assert_eq!(writer.into_inner().unwrap(), [vec![1; 8], vec![2; 2], vec![3; 2]].concat());
+}
+
+#[test]
+fn resize_fails_by_default() {
+	let mut writer = VideoWriter::new(Vec::new(), WriterOptions::new(Container::Raw, 25));
+	writer.write_buffer(&nv12(4, 2), 4, 2, BufferFormat::Nv12).unwrap();
+	match writer.write_buffer(&nv12(2, 2), 2, 2, BufferFormat::Nv12) {
+		Err(WriterError::Resized { old, new }) => {
+			assert_eq!(old, Size { w: 4, h: 2 });
+			assert_eq!(new, Size { w: 2, h: 2 });
+		},
+		result => panic!("unexpected result {:?}", result),
+	}
+
+	// The rejected frame is dropped, the stream continues with the old size.
+	writer.write_buffer(&nv12(4, 2), 4, 2, BufferFormat::Nv12).unwrap();
+	assert_eq!(writer.frames(), 2);
+	assert_eq!(writer.into_inner().unwrap().len(), 2 * 12);
+}
+
+#[test]
+fn resize_without_an_opener_fails() {
+	let options = WriterOptions { on_resize: ResizePolicy::NewFile, ..WriterOptions::new(Container::Y4m, 25) };
+	let mut writer = VideoWriter::new(Vec::new(), options);
+	writer.write_buffer(&nv12(4, 2), 4, 2, BufferFormat::Nv12).unwrap();
+	assert!(matches!(writer.write_buffer(&nv12(2, 2), 2, 2, BufferFormat::Nv12), Err(WriterError::Resized { .. })));
+	assert_eq!(writer.file_index(), 0);
+}
+
+/// An output that stays readable after the writer moved on to the next file.
+#[derive(Clone, Default)]
+struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl io::Write for SharedOutput {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.0.borrow_mut().write(buf)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+#[test]
+fn resize_starts_a_new_file() {
+	let files: Rc<RefCell<Vec<SharedOutput>>> = Default::default();
+	let opened = files.clone();
+	let options = WriterOptions { on_resize: ResizePolicy::NewFile, ..WriterOptions::new(Container::Y4m, 25) };
+	let mut writer = VideoWriter::with_opener(options, move |index| {
+		assert_eq!(index, opened.borrow().len());
+		let output = SharedOutput::default();
+		opened.borrow_mut().push(output.clone());
+		Ok(output)
+	}).unwrap();
+
+	writer.write_buffer(&nv12(4, 2), 4, 2, BufferFormat::Nv12).unwrap();
+	writer.write_buffer(&nv12(4, 2), 4, 2, BufferFormat::Nv12).unwrap();
+	writer.write_buffer(&nv12(2, 2), 2, 2, BufferFormat::Nv12).unwrap();
+	assert_eq!(writer.file_index(), 1);
+	assert_eq!(writer.frames(), 1);
+	assert_eq!(writer.stream(), Some((Size { w: 2, h: 2 }, Chroma::C420)));
+
+	let files = files.borrow();
+	assert_eq!(files.len(), 2);
+	let (first, second) = (files[0].0.borrow(), files[1].0.borrow());
+	assert!(first.starts_with(y4m_header(Size { w: 4, h: 2 }, Chroma::C420, 25).as_bytes()));
+	assert!(second.starts_with(y4m_header(Size { w: 2, h: 2 }, Chroma::C420, 25).as_bytes()));
+
+	assert_eq!(numbered_path(Path::new("capture.y4m"), 0), Path::new("capture.y4m"));
+	assert_eq!(numbered_path(Path::new("out/capture.y4m"), 2), Path::new("out/capture-2.y4m"));
+	assert_eq!(numbered_path(Path::new("capture"), 1), Path::new("capture-1"));
+}
+
+#[test]
+fn zero_fps_is_rejected() {
+	let mut writer = VideoWriter::new(Vec::new(), WriterOptions::new(Container::Y4m, 0));
+	match writer.write_buffer(&nv12(4, 2), 4, 2, BufferFormat::Nv12) {
+		Err(WriterError::Io(error)) => assert_eq!(error.kind(), io::ErrorKind::InvalidInput),
+		result => panic!("unexpected result {:?}", result),
+	}
+	assert!(writer.into_inner().unwrap().is_empty());
+}
@@ -0,0 +1,85 @@
+use nvfbc::watcher::{diff, StatusEvent};
+use nvfbc::{Box, Output, Size, Status, Version};
+
+fn output(id: u32, tracked_box: Box) -> Output {
+	Output { id, name: format!("DP-{}", id), tracked_box }
+}
+
+fn status(outputs: Vec<Output>) -> Status {
+	Status {
+		is_capture_possible: true,
+		currently_capturing: false,
+		can_create_now: true,
+		screen_size: Size { w: 3840, h: 1080 },
+		xrandr_available: true,
+		outputs,
+		nvfbc_version: Version::COMPILED,
+		in_modeset: false,
+	}
+}
+
+const LEFT: Box = Box { x: 0, y: 0, w: 1920, h: 1080 };
+const RIGHT: Box = Box { x: 1920, y: 0, w: 1920, h: 1080 };
+
+#[test]
+fn unchanged_status_has_no_events() {
+	let status = status(vec![output(1, LEFT), output(2, RIGHT)]);
+	assert_eq!(diff(&status, &status.clone()), []);
+}
+
+#[test]
+fn outputs_added_and_removed() {
+	let old = status(vec![output(1, LEFT)]);
+	let new = status(vec![output(1, LEFT), output(2, RIGHT)]);
+	assert_eq!(diff(&old, &new), [StatusEvent::OutputAdded(output(2, RIGHT))]);
+	assert_eq!(diff(&new, &old), [StatusEvent::OutputRemoved(output(2, RIGHT))]);
+
+	// Outputs are matched by id, so a different id at the same place replaces the output.
+	let replaced = status(vec![output(3, LEFT)]);
+	assert_eq!(diff(&old, &replaced), [StatusEvent::OutputRemoved(output(1, LEFT)), StatusEvent::OutputAdded(output(3, LEFT))]);
+
+	// The order of the outputs does not matter.
+	let reordered = status(vec![output(2, RIGHT), output(1, LEFT)]);
+	assert_eq!(diff(&new, &reordered), []);
+}
+
+#[test]
+fn outputs_moved_and_resized() {
+	let old = status(vec![output(1, LEFT), output(2, RIGHT)]);
+
+	let moved = Box { x: 0, y: 1080, ..RIGHT };
+	let new = status(vec![output(1, LEFT), output(2, moved)]);
+	assert_eq!(diff(&old, &new), [StatusEvent::TrackedBoxChanged { id: 2, name: "DP-2".to_string(), old: RIGHT, new: moved }]);
+
+	let resized = Box { w: 1280, h: 720, ..LEFT };
+	let new = Status { screen_size: Size { w: 3200, h: 1080 }, ..status(vec![output(1, resized), output(2, Box { x: 1280, ..RIGHT })]) };
+	assert_eq!(diff(&old, &new), [
+		StatusEvent::ScreenResized { old: Size { w: 3840, h: 1080 }, new: Size { w: 3200, h: 1080 } },
+		StatusEvent::TrackedBoxChanged { id: 1, name: "DP-1".to_string(), old: LEFT, new: resized },
+		StatusEvent::TrackedBoxChanged { id: 2, name: "DP-2".to_string(), old: RIGHT, new: Box { x: 1280, ..RIGHT } },
+	]);
+}
+
+#[test]
+fn modeset_transitions() {
+	let idle = status(vec![output(1, LEFT)]);
+	let modeset = Status { in_modeset: true, can_create_now: false, ..idle.clone() };
+	assert_eq!(diff(&idle, &modeset), [StatusEvent::ModesetEntered, StatusEvent::CanCreateNowChanged(false)]);
+	assert_eq!(diff(&modeset, &modeset.clone()), []);
+
+	// A modeset that changed the outputs reports the modeset first.
+	let after = status(vec![output(1, Box { w: 2560, h: 1440, ..LEFT })]);
+	assert_eq!(diff(&modeset, &after), [
+		StatusEvent::ModesetExited,
+		StatusEvent::TrackedBoxChanged { id: 1, name: "DP-1".to_string(), old: LEFT, new: Box { w: 2560, h: 1440, ..LEFT } },
+		StatusEvent::CanCreateNowChanged(true),
+	]);
+}
+
+#[test]
+fn capture_transitions() {
+	let idle = status(vec![output(1, LEFT)]);
+	let capturing = Status { currently_capturing: true, ..idle.clone() };
+	assert_eq!(diff(&idle, &capturing), [StatusEvent::CaptureStarted]);
+	assert_eq!(diff(&capturing, &idle), [StatusEvent::CaptureStopped]);
+}